@@ -58,9 +58,10 @@ fn days_in_month(year: u16, month: u8) -> u8 {
 // Civil date <-> days-since-epoch arithmetic
 // ============================================================================
 //
-// Used only to recompute wall-clock across day boundaries in
-// `DicomDateTime::adjust_to_offset`. Algorithm after Howard Hinnant's
-// `days_from_civil` / `civil_from_days` (public domain), epoch 1970-01-01.
+// Used to recompute wall-clock across day boundaries in
+// `DicomDateTime::adjust_to_offset` and by `DicomDate::add_days`. Algorithm
+// after Howard Hinnant's `days_from_civil` / `civil_from_days` (public
+// domain), epoch 1970-01-01.
 
 fn days_from_civil(y: i32, m: u32, d: u32) -> i64 {
     let y = if m <= 2 { y - 1 } else { y };
@@ -304,6 +305,21 @@ impl DicomDate {
         }
     }
 
+    /// Moves the date `days` days (negative: into the past). A partial date
+    /// is shifted from its first day and keeps its precision. `None` for an
+    /// empty date or a result outside years 1..=9999.
+    pub fn add_days(self, days: i64) -> Option<Self> {
+        let y = self.y?;
+        let base = days_from_civil(
+            i32::from(y),
+            u32::from(self.m.unwrap_or(LIM_MONTH_MIN)),
+            u32::from(self.d.unwrap_or(LIM_DAY_MIN)),
+        );
+        let (ny, nm, nd) = civil_from_days(base.checked_add(days)?);
+        let ny = u16::try_from(ny).ok().filter(|y| (LIM_YEAR_MIN..=LIM_YEAR_MAX).contains(y))?;
+        Some(Self { y: Some(ny), m: self.m.map(|_| nm as u8), d: self.d.map(|_| nd as u8) })
+    }
+
     /// Parse a date with optional partial precision (`YYYY`, `YYYYMM`,
     /// `YYYYMMDD`), used inside `DT` and the range forms.
     ///
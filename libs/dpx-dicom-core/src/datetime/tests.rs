@@ -565,3 +565,16 @@ fn minimized_maximized() {
     assert_eq!(t.minimized(), time(23, 1, 0, 0));
     assert_eq!(t.maximized(), time(23, 1, 59, 999999));
 }
+
+#[test]
+fn date_add_days() {
+    assert_eq!(date(2024, 2, 28).add_days(1), Some(date(2024, 2, 29)));
+    assert_eq!(date(2024, 3, 1).add_days(-366), Some(date(2023, 3, 1)));
+    assert_eq!(date(1999, 12, 31).add_days(1), Some(date(2000, 1, 1)));
+
+    let partial = DicomDate { y: Some(2001), m: Some(2), d: None };
+    assert_eq!(partial.add_days(40), Some(DicomDate { y: Some(2001), m: Some(3), d: None }));
+
+    assert_eq!(DicomDate::default().add_days(1), None);
+    assert_eq!(date(1, 1, 1).add_days(-1), None);
+}
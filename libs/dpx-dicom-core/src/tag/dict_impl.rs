@@ -484,6 +484,25 @@ impl Dictionary {
         best_match
     }
 
+    /// Searches a dictionary for the attribute with the given keyword
+    ///
+    /// Attributes matched through a [Meta::mask] (repeating groups) have no
+    /// single tag, so they are never found. Dynamically added attributes take
+    /// precedence over static ones, and lastly added lists over earlier ones.
+    ///
+    /// The search is always linear, the cache is not involved.
+    pub fn search_by_keyword(&self, keyword: &str) -> Option<&Meta> {
+        if keyword.is_empty() {
+            return None;
+        }
+        let statics = self.statics.iter().rev().flat_map(|ary| ary.0.iter().rev());
+        self.dynamic
+            .iter()
+            .rev()
+            .chain(statics)
+            .find(|meta| meta.mask == 0xFFFFFFFF && meta.keyword == keyword)
+    }
+
     /// Returns some simple metrics
     pub fn metrics(&self) -> DictMetrics {
         DictMetrics {
@@ -924,6 +943,28 @@ mod tests {
         search_tags_in_dict(&dict);
     }
 
+    #[test]
+    fn is_dict_keyword_searchable() {
+        let mut dict = Dictionary::new_empty();
+        dict.add_static_list(&keys::TEST_TAG_LIST);
+        assert_eq!(
+            dict.search_by_keyword("SpecificCharacterSet").unwrap().tag,
+            keys::SpecificCharacterSet
+        );
+        // Masked attributes have no single tag to resolve to
+        assert!(dict.search_by_keyword("OverlayRows").is_none());
+        assert!(dict.search_by_keyword("Unknown").is_none());
+        assert!(dict.search_by_keyword("").is_none());
+
+        // Dynamically added attributes take precedence
+        dict.add_from_memory("(0009,1010)\tCS\tCharset\tSpecificCharacterSet\t1\tPriv".as_bytes())
+            .unwrap();
+        assert_eq!(
+            dict.search_by_keyword("SpecificCharacterSet").unwrap().tag,
+            Tag::new_standard(0x0009, 0x1010)
+        );
+    }
+
     #[test]
     #[cfg(not(miri))]
     #[allow(deprecated)]
@@ -171,6 +171,13 @@ impl Tag {
     pub fn name(&self) -> Option<String> {
         Context::with_current(|ctx| ctx.tag_dict().search_by_tag(self).map(|m| m.name.to_string()))
     }
+
+    /// Searches the attribute with the given keyword in the current [Context]
+    ///
+    /// See also [search_by_keyword](crate::tag::Dictionary::search_by_keyword)
+    pub fn from_keyword(keyword: &str) -> Option<Tag> {
+        Context::with_current(|ctx| ctx.tag_dict().search_by_keyword(keyword).map(|m| m.tag.clone()))
+    }
 }
 
 impl std::fmt::Display for Tag {
@@ -56,6 +56,30 @@ fn adapt_stored(dest: &Shared, src: &Shared, vr: Vr, value: Stored) -> Result<St
     }
 }
 
+/// A copy of `item` free of its context, raw values decoded to
+/// [`Native`](Stored::Native) ones, that can be put under any root.
+pub(crate) fn detach_item(src: &Shared, item: &Item) -> Item {
+    let mut map = ElementMap::with_capacity(item.map.len());
+    for (key, el) in item.map.entries() {
+        map.push_parsed(*key, detach_element(src, item, el));
+    }
+    Item::from_map(map)
+}
+
+/// [`detach_item`] for one attribute of `item`. Values that do not decode are
+/// copied as raw bytes.
+pub(crate) fn detach_element(src: &Shared, item: &Item, el: &Element) -> Element {
+    let value = match &el.value {
+        Stored::Native(v) => Stored::Native(v.clone()),
+        Stored::Items(items) => Stored::Items(items.iter().map(|nested| detach_item(src, nested)).collect()),
+        Stored::Mapped(_) | Stored::Owned(_) => match item.element_value(src, el) {
+            Ok(v) => Stored::Native(v),
+            Err(_) => Stored::Owned(Bytes::copy_from_slice(item.element_bytes(src, el).unwrap_or_default())),
+        },
+    };
+    Element::new(el.vr, value)
+}
+
 fn adapt_raw(dest: &Shared, src: &Shared, vr: Vr, bytes: Bytes) -> Result<Stored> {
     if dest.is_little_endian() == src.is_little_endian()
         && dest.charset().specific_character_set() == src.charset().specific_character_set()
@@ -0,0 +1,278 @@
+//! Secondary Capture Image Storage instances built from PNG files or raw
+//! frames.
+//!
+//! [`SecondaryCapture`] produces a File Meta header and a data set holding the
+//! Patient, General Study, General Series, SC Equipment, General Image, Image
+//! Pixel and SOP Common modules, with every missing UID generated by
+//! [`Uid::generate_unique`]. The pair is a [`ReadOutput`], as [`DcmReader`]
+//! returns, ready for [`DcmWriter::write_file`].
+//!
+//! [`DcmReader`]: crate::DcmReader
+//! [`DcmWriter::write_file`]: crate::DcmWriter::write_file
+
+use bytes::Bytes;
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::uid::DEFAULT_UID_ROOT;
+use dpx_dicom_core::uids::svc_storage::SecondaryCaptureImageStorage;
+use dpx_dicom_core::{Tag, TransferSyntax, Uid, Vr, dicom_err, ensure, tags};
+
+use crate::dataset::DatasetKind;
+use crate::pixels::{PixelBuffer, Samples};
+use crate::{DataSet, PixelData, ReadOutput, Value};
+
+/// Builds Secondary Capture instances, one per image.
+#[derive(Debug, Clone)]
+pub struct SecondaryCapture {
+    uid_root: String,
+    conversion_type: String,
+    patient_name: String,
+    patient_id: String,
+    study_instance_uid: Option<String>,
+    series_instance_uid: Option<String>,
+}
+
+impl Default for SecondaryCapture {
+    fn default() -> Self {
+        SecondaryCapture {
+            uid_root: DEFAULT_UID_ROOT.to_string(),
+            conversion_type: "WSD".to_string(),
+            patient_name: String::new(),
+            patient_id: String::new(),
+            study_instance_uid: None,
+            series_instance_uid: None,
+        }
+    }
+}
+
+impl SecondaryCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Root of the generated UIDs (default [`DEFAULT_UID_ROOT`]).
+    pub fn uid_root(mut self, root: &str) -> Self {
+        self.uid_root = root.to_string();
+        self
+    }
+
+    /// Conversion Type (0008,0064), e.g. `SI` for a scanned image (default
+    /// `WSD`, workstation).
+    pub fn conversion_type(mut self, conversion_type: &str) -> Self {
+        self.conversion_type = conversion_type.to_string();
+        self
+    }
+
+    /// Patient's Name and Patient ID (default empty).
+    pub fn patient(mut self, name: &str, id: &str) -> Self {
+        self.patient_name = name.to_string();
+        self.patient_id = id.to_string();
+        self
+    }
+
+    /// Files the image into an existing study instead of a new one.
+    pub fn study_instance_uid(mut self, uid: &str) -> Self {
+        self.study_instance_uid = Some(uid.to_string());
+        self
+    }
+
+    /// Files the image into an existing series instead of a new one.
+    pub fn series_instance_uid(mut self, uid: &str) -> Self {
+        self.series_instance_uid = Some(uid.to_string());
+        self
+    }
+
+    /// An instance holding the image of a PNG file: gray or RGB, 8 or 16 bits.
+    /// Palette images are expanded to RGB, lower bit depths to 8 bits, and any
+    /// alpha channel is dropped.
+    pub fn image_from_png(&self, png: &[u8]) -> Result<ReadOutput> {
+        let decoding = |e: png::DecodingError| dicom_err!(InvalidData, "decoding PNG: {e}");
+        let mut decoder = png::Decoder::new(std::io::Cursor::new(png));
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(decoding)?;
+        let size = reader.output_buffer_size().ok_or_else(|| dicom_err!(InvalidData, "PNG image is too large"))?;
+        let mut buf = vec![0; size];
+        let info = reader.next_frame(&mut buf).map_err(decoding)?;
+        buf.truncate(info.buffer_size());
+        let (channels, photometric) = match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => (1, "MONOCHROME2"),
+            png::ColorType::Rgb | png::ColorType::Rgba => (3, "RGB"),
+            png::ColorType::Indexed => return Err(dicom_err!(Internal, "PNG palette was not expanded")),
+        };
+        let stride = info.color_type.samples();
+        let wide = info.bit_depth == png::BitDepth::Sixteen;
+        let width = if wide { 2 } else { 1 };
+        let pixels = buf.chunks_exact(stride * width).flat_map(|pixel| pixel[..channels * width].chunks_exact(width));
+        let samples = if wide {
+            Samples::U16(pixels.map(|s| u16::from_be_bytes([s[0], s[1]])).collect())
+        } else {
+            Samples::U8(pixels.map(|s| s[0]).collect())
+        };
+        let dimension =
+            |v: u32| u16::try_from(v).map_err(|_| dicom_err!(UnsupportedFeature, "PNG is {v} pixels wide or high"));
+        self.image_from_pixels(&PixelBuffer {
+            rows: dimension(info.height)?,
+            columns: dimension(info.width)?,
+            samples_per_pixel: channels as u16,
+            planar: false,
+            photometric_interpretation: photometric.to_string(),
+            samples,
+        })
+    }
+
+    /// An instance holding `frame`: MONOCHROME1, MONOCHROME2, RGB or YBR_FULL
+    /// samples of 8 or 16 bits.
+    pub fn image_from_pixels(&self, frame: &PixelBuffer) -> Result<ReadOutput> {
+        let photometric = frame.photometric_interpretation.as_str();
+        let spp = frame.samples_per_pixel;
+        ensure!(
+            matches!((photometric, spp), ("MONOCHROME1" | "MONOCHROME2", 1) | ("RGB" | "YBR_FULL", 3)),
+            UnsupportedFeature,
+            "cannot store {photometric} with {spp} samples per pixel as Secondary Capture"
+        );
+        let expected = frame.rows as usize * frame.columns as usize * spp as usize;
+        ensure!(
+            frame.samples.len() == expected,
+            InvalidData,
+            "frame holds {} of {expected} samples",
+            frame.samples.len()
+        );
+        let (bits, signed, bytes): (u16, bool, Vec<u8>) = match &frame.samples {
+            Samples::U8(v) => (8, false, v.clone()),
+            Samples::I8(v) => (8, true, v.iter().map(|&s| s as u8).collect()),
+            Samples::U16(v) => (16, false, v.iter().flat_map(|s| s.to_le_bytes()).collect()),
+            Samples::I16(v) => (16, true, v.iter().flat_map(|s| s.to_le_bytes()).collect()),
+            _ => return Err(dicom_err!(UnsupportedFeature, "Secondary Capture holds 8- or 16-bit integer samples")),
+        };
+
+        let sop_instance_uid = self.generate();
+        let mut ds = DataSet::new();
+        let text = [
+            (&tags::SOPClassUID, SecondaryCaptureImageStorage),
+            (&tags::SOPInstanceUID, sop_instance_uid.as_str()),
+            (&tags::StudyDate, ""),
+            (&tags::StudyTime, ""),
+            (&tags::AccessionNumber, ""),
+            (&tags::Modality, "OT"),
+            (&tags::ConversionType, &self.conversion_type),
+            (&tags::ReferringPhysicianName, ""),
+            (&tags::PatientName, &self.patient_name),
+            (&tags::PatientID, &self.patient_id),
+            (&tags::PatientBirthDate, ""),
+            (&tags::PatientSex, ""),
+            (&tags::StudyID, ""),
+            (&tags::SeriesNumber, ""),
+            (&tags::InstanceNumber, "1"),
+            (&tags::PatientOrientation, ""),
+            (&tags::PhotometricInterpretation, photometric),
+        ];
+        for (tag, value) in text {
+            ds.set(tag, value)?;
+        }
+        let study = self.study_instance_uid.clone().unwrap_or_else(|| self.generate());
+        let series = self.series_instance_uid.clone().unwrap_or_else(|| self.generate());
+        ds.set(&tags::StudyInstanceUID, study)?;
+        ds.set(&tags::SeriesInstanceUID, series)?;
+        let numbers: [(&Tag, u16); 7] = [
+            (&tags::SamplesPerPixel, spp),
+            (&tags::Rows, frame.rows),
+            (&tags::Columns, frame.columns),
+            (&tags::BitsAllocated, bits),
+            (&tags::BitsStored, bits),
+            (&tags::HighBit, bits - 1),
+            (&tags::PixelRepresentation, u16::from(signed)),
+        ];
+        for (tag, value) in numbers {
+            ds.set(tag, value)?;
+        }
+        if spp > 1 {
+            ds.set(&tags::PlanarConfiguration, u16::from(frame.planar))?;
+        }
+        let vr = if bits > 8 { Vr::OW } else { Vr::OB };
+        ds.set_with_vr(&tags::PixelData, vr, Value::Pixels(Box::new(PixelData::Native(Bytes::from(bytes)))))?;
+
+        let ts = &TransferSyntax::ExplicitVRLittleEndian;
+        let mut header = DataSet::parsed(Bytes::new(), ts, DatasetKind::MetaInfo);
+        header.set_with_vr(&tags::FileMetaInformationVersion, Vr::OB, Value::Bytes(Bytes::from_static(&[0, 1])))?;
+        header.set(&tags::MediaStorageSOPClassUID, SecondaryCaptureImageStorage)?;
+        header.set(&tags::MediaStorageSOPInstanceUID, sop_instance_uid)?;
+        header.set(&tags::TransferSyntaxUID, ts.uid)?;
+        header.set(&tags::ImplementationClassUID, format!("{}.1", self.uid_root))?;
+        header.set(&tags::ImplementationVersionName, concat!("DPX_", env!("CARGO_PKG_VERSION")))?;
+        Ok(ReadOutput { header: Some(header), dataset: Some(ds) })
+    }
+
+    fn generate(&self) -> String {
+        Uid::generate_unique(Some(&self.uid_root)).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DcmReader, DcmWriter, FrameExporter, PixelDecoder};
+
+    fn frame(samples: Samples, spp: u16, photometric: &str) -> PixelBuffer {
+        PixelBuffer {
+            rows: 1,
+            columns: (samples.len() / spp as usize) as u16,
+            samples_per_pixel: spp,
+            planar: false,
+            photometric_interpretation: photometric.into(),
+            samples,
+        }
+    }
+
+    #[test]
+    fn raw_frame_becomes_a_readable_file() {
+        let capture = SecondaryCapture::new().patient("Doe^Jane", "P1").conversion_type("SI");
+        let out = capture.image_from_pixels(&frame(Samples::U16(vec![1, 2, 300]), 1, "MONOCHROME2")).unwrap();
+        let (header, ds) = (out.header.unwrap(), out.dataset.unwrap());
+        let file = DcmWriter::new().to_file_bytes(&header, &ds).unwrap();
+        let read = DcmReader::new().parse_bytes(file).unwrap();
+        let (header, ds) = (read.header.unwrap(), read.dataset.unwrap());
+
+        let sop = ds.get::<String>(&tags::SOPInstanceUID).unwrap();
+        assert!(sop.starts_with(DEFAULT_UID_ROOT));
+        assert_eq!(header.get::<String>(&tags::MediaStorageSOPInstanceUID).unwrap(), sop);
+        assert_eq!(ds.get::<String>(&tags::SOPClassUID).unwrap(), SecondaryCaptureImageStorage);
+        assert_ne!(
+            ds.get::<String>(&tags::StudyInstanceUID).unwrap(),
+            ds.get::<String>(&tags::SeriesInstanceUID).unwrap()
+        );
+        assert_eq!(ds.get::<String>(&tags::ConversionType).unwrap(), "SI");
+        assert_eq!(ds.get::<String>(&tags::PatientID).unwrap(), "P1");
+        assert!(ds.contains(&tags::PatientBirthDate));
+        assert_eq!(ds.get::<u16>(&tags::BitsStored).unwrap(), 16);
+        assert_eq!(PixelDecoder::new(&ds).unwrap().frame(0).unwrap().samples, Samples::U16(vec![1, 2, 300]));
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let capture = SecondaryCapture::new().study_instance_uid("1.2.3.4");
+        let rgb = capture.image_from_pixels(&frame(Samples::U8(vec![10, 20, 30, 40, 50, 60]), 3, "RGB")).unwrap();
+        let rgb = rgb.dataset.unwrap();
+        assert_eq!(rgb.get::<String>(&tags::StudyInstanceUID).unwrap(), "1.2.3.4");
+        let png = FrameExporter::new(&rgb).unwrap().png(0).unwrap();
+
+        let imported = capture.image_from_png(&png).unwrap().dataset.unwrap();
+        assert_eq!(imported.get::<String>(&tags::PhotometricInterpretation).unwrap(), "RGB");
+        assert_eq!(imported.get::<u16>(&tags::PlanarConfiguration).unwrap(), 0);
+        let decoded = PixelDecoder::new(&imported).unwrap().frame(0).unwrap();
+        assert_eq!(decoded.samples, Samples::U8(vec![10, 20, 30, 40, 50, 60]));
+    }
+
+    #[test]
+    fn png_alpha_is_dropped() {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::GrayscaleAlpha);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0x01, 0x02, 0xFF, 0xFF, 0x03, 0x04, 0x00, 0x00]).unwrap();
+        writer.finish().unwrap();
+        let ds = SecondaryCapture::new().image_from_png(&png).unwrap().dataset.unwrap();
+        assert_eq!(ds.get::<String>(&tags::PhotometricInterpretation).unwrap(), "MONOCHROME2");
+        assert_eq!(PixelDecoder::new(&ds).unwrap().frame(0).unwrap().samples, Samples::U16(vec![0x0102, 0x0304]));
+        assert!(SecondaryCapture::new().image_from_pixels(&frame(Samples::F32(vec![0.0]), 1, "MONOCHROME2")).is_err());
+    }
+}
@@ -0,0 +1,623 @@
+//! Decoding of the JPEG processes DICOM uses (ITU-T T.81): sequential DCT
+//! with Huffman coding at 8 or 12 bits (Processes 1, 2 and 4) and lossless
+//! Huffman coding with any predictor (Process 14).
+//!
+//! Scans may be interleaved or one component each, with restart intervals.
+//! Subsampled components are upsampled by replication; no colour transform
+//! is applied, so samples come out in the colour space they were coded in.
+
+use std::sync::LazyLock;
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{dicom_err, ensure};
+
+use crate::codec::PixelInfo;
+
+/// The natural (row-major) index of each zig-zag position.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21,
+    28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54,
+    47, 55, 62, 63,
+];
+
+/// Huffman codes up to this length are decoded with one table lookup.
+const LOOKUP_BITS: u32 = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Process {
+    Dct,
+    Lossless,
+}
+
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    /// Quantization table selector.
+    tq: usize,
+    /// Samples covering whole blocks (DCT) or the component exactly (lossless).
+    plane: Vec<u16>,
+    stride: usize,
+}
+
+struct Frame {
+    process: Process,
+    precision: u8,
+    width: usize,
+    height: usize,
+    h_max: usize,
+    v_max: usize,
+    components: Vec<Component>,
+}
+
+impl Frame {
+    /// Reads a frame header, which must describe the frame `info` expects:
+    /// the planes are allocated from it.
+    fn parse(marker: u8, seg: &[u8], info: &PixelInfo) -> Result<Self> {
+        ensure!(seg.len() >= 6, InvalidData, "truncated JPEG frame header");
+        let process = if marker == 0xC3 { Process::Lossless } else { Process::Dct };
+        let precision = seg[0];
+        let (height, width) = (u16_be(&seg[1..]) as usize, u16_be(&seg[3..]) as usize);
+        let count = seg[5] as usize;
+        match process {
+            Process::Dct => ensure!(
+                precision == 8 || (precision == 12 && marker == 0xC1),
+                UnsupportedFeature,
+                "{precision}-bit samples in a SOF{} frame",
+                marker - 0xC0
+            ),
+            Process::Lossless => {
+                ensure!((2..=16).contains(&precision), InvalidData, "lossless JPEG with {precision}-bit samples")
+            }
+        }
+        ensure!(height > 0, UnsupportedFeature, "JPEG frame height set by a DNL marker");
+        ensure!(width > 0 && count > 0, InvalidData, "empty JPEG frame");
+        ensure!(seg.len() >= 6 + 3 * count, InvalidData, "truncated JPEG frame header");
+        ensure!(
+            width == info.columns as usize && height == info.rows as usize,
+            InvalidData,
+            "JPEG frame is {width}x{height}, the data set says {}x{}",
+            info.columns,
+            info.rows
+        );
+        ensure!(
+            count == info.samples_per_pixel as usize,
+            InvalidData,
+            "JPEG frame has {count} components, the data set says {} samples per pixel",
+            info.samples_per_pixel
+        );
+        ensure!(
+            u16::from(precision) <= info.bits_allocated,
+            InvalidData,
+            "{precision}-bit JPEG samples do not fit Bits Allocated {}",
+            info.bits_allocated
+        );
+        let mut components = Vec::with_capacity(count);
+        for c in seg[6..6 + 3 * count].chunks_exact(3) {
+            let (h, v) = ((c[1] >> 4) as usize, (c[1] & 15) as usize);
+            ensure!((1..=4).contains(&h) && (1..=4).contains(&v), InvalidData, "JPEG sampling factors {h}x{v}");
+            ensure!(
+                process == Process::Dct || (h == 1 && v == 1),
+                UnsupportedFeature,
+                "subsampled lossless JPEG components"
+            );
+            ensure!(c[2] < 4, InvalidData, "JPEG quantization table {} out of range", c[2]);
+            components.push(Component { id: c[0], h, v, tq: c[2] as usize, plane: Vec::new(), stride: 0 });
+        }
+        let h_max = components.iter().map(|c| c.h).max().unwrap_or(1);
+        let v_max = components.iter().map(|c| c.v).max().unwrap_or(1);
+        let mut frame = Frame { process, precision, width, height, h_max, v_max, components };
+        let (mcus_x, mcus_y) = frame.mcus();
+        for c in &mut frame.components {
+            let (w, h) = match process {
+                Process::Dct => (mcus_x * c.h * 8, mcus_y * c.v * 8),
+                Process::Lossless => (width, height),
+            };
+            c.plane = vec![0; w * h];
+            c.stride = w;
+        }
+        Ok(frame)
+    }
+
+    /// MCUs across and down an interleaved scan.
+    fn mcus(&self) -> (usize, usize) {
+        match self.process {
+            Process::Dct => (self.width.div_ceil(8 * self.h_max), self.height.div_ceil(8 * self.v_max)),
+            Process::Lossless => (self.width, self.height),
+        }
+    }
+
+    /// Width and height of component `c` in samples.
+    fn size(&self, c: &Component) -> (usize, usize) {
+        ((self.width * c.h).div_ceil(self.h_max), (self.height * c.v).div_ceil(self.v_max))
+    }
+
+    /// The decoded samples, interleaved at full resolution.
+    fn into_samples(self, point_transform: u8) -> Vec<u16> {
+        let (width, height) = (self.width, self.height);
+        let mut samples = Vec::with_capacity(width * height * self.components.len());
+        for y in 0..height {
+            for x in 0..width {
+                for c in &self.components {
+                    let (cx, cy) = (x * c.h / self.h_max, y * c.v / self.v_max);
+                    samples.push(c.plane[cy * c.stride + cx] << point_transform);
+                }
+            }
+        }
+        samples
+    }
+}
+
+#[derive(Clone)]
+struct Huffman {
+    /// `(length, symbol)` for every `LOOKUP_BITS`-bit prefix; length 0 when
+    /// the code is longer.
+    lookup: Vec<(u8, u8)>,
+    /// Largest code of each length, -1 when there is none.
+    max_code: [i32; 17],
+    /// First code of each length and the index of its symbol.
+    min_code: [i32; 17],
+    first_symbol: [usize; 17],
+    symbols: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8], symbols: &[u8]) -> Result<Self> {
+        let mut table = Huffman {
+            lookup: vec![(0, 0); 1 << LOOKUP_BITS],
+            max_code: [-1; 17],
+            min_code: [0; 17],
+            first_symbol: [0; 17],
+            symbols: symbols.to_vec(),
+        };
+        let (mut code, mut k) = (0i32, 0usize);
+        for len in 1..=16 {
+            let n = counts[len - 1] as usize;
+            ensure!(code + n as i32 <= 1 << len, InvalidData, "over-subscribed JPEG Huffman table");
+            table.min_code[len] = code;
+            table.first_symbol[len] = k;
+            for i in 0..n {
+                if len as u32 <= LOOKUP_BITS {
+                    let shift = LOOKUP_BITS - len as u32;
+                    let first = ((code + i as i32) << shift) as usize;
+                    table.lookup[first..first + (1 << shift)].fill((len as u8, symbols[k + i]));
+                }
+            }
+            if n > 0 {
+                table.max_code[len] = code + n as i32 - 1;
+            }
+            code += n as i32;
+            k += n;
+            code <<= 1;
+        }
+        Ok(table)
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u8> {
+        let (len, symbol) = self.lookup[bits.peek(LOOKUP_BITS) as usize];
+        if len > 0 {
+            bits.consume(u32::from(len));
+            return Ok(symbol);
+        }
+        for len in LOOKUP_BITS as usize + 1..=16 {
+            let code = bits.peek(len as u32) as i32;
+            if code <= self.max_code[len] {
+                bits.consume(len as u32);
+                return Ok(self.symbols[self.first_symbol[len] + (code - self.min_code[len]) as usize]);
+            }
+        }
+        Err(dicom_err!(InvalidData, "invalid JPEG Huffman code"))
+    }
+}
+
+/// Entropy-coded data, with byte stuffing removed. Reading past a marker
+/// yields zero bits, as decoders conventionally do.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    acc: u64,
+    count: u32,
+    at_marker: bool,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Bits { data, pos, acc: 0, count: 0, at_marker: false }
+    }
+
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let byte = match (self.at_marker, self.data.get(self.pos), self.data.get(self.pos + 1)) {
+                (false, Some(0xFF), Some(0)) => {
+                    self.pos += 2;
+                    0xFF
+                }
+                (false, Some(0xFF), _) | (false, None, _) => {
+                    self.at_marker = true;
+                    0
+                }
+                (false, Some(&b), _) => {
+                    self.pos += 1;
+                    b
+                }
+                (true, _, _) => 0,
+            };
+            self.acc |= u64::from(byte) << (56 - self.count);
+            self.count += 8;
+        }
+    }
+
+    fn peek(&mut self, n: u32) -> u32 {
+        if self.count < n {
+            self.fill();
+        }
+        (self.acc >> (64 - n)) as u32
+    }
+
+    fn consume(&mut self, n: u32) {
+        self.acc <<= n;
+        self.count -= n;
+    }
+
+    fn bits(&mut self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        let v = self.peek(n);
+        self.consume(n);
+        v
+    }
+
+    /// A `size`-bit difference, sign-extended (T.81 F.2.2.1 EXTEND).
+    fn receive_extend(&mut self, size: u8) -> i32 {
+        if size == 0 {
+            return 0;
+        }
+        let v = self.bits(u32::from(size)) as i32;
+        if v < 1 << (size - 1) { v - (1 << size) + 1 } else { v }
+    }
+
+    /// Skips to just past the next RSTn marker and drops buffered bits.
+    fn restart(&mut self) -> Result<()> {
+        self.acc = 0;
+        self.count = 0;
+        self.at_marker = false;
+        while self.pos + 1 < self.data.len() && !(self.data[self.pos] == 0xFF && is_rst(self.data[self.pos + 1])) {
+            self.pos += 1;
+        }
+        ensure!(self.pos + 1 < self.data.len(), InvalidData, "JPEG restart marker missing");
+        self.pos += 2;
+        Ok(())
+    }
+
+    /// The position of the marker ending the scan.
+    fn end(&self) -> usize {
+        let mut pos = self.pos;
+        while pos + 1 < self.data.len() {
+            let next = self.data[pos + 1];
+            if self.data[pos] == 0xFF && next != 0 && next != 0xFF && !is_rst(next) {
+                return pos;
+            }
+            pos += 1;
+        }
+        self.data.len()
+    }
+}
+
+fn is_rst(marker: u8) -> bool {
+    (0xD0..=0xD7).contains(&marker)
+}
+
+fn u16_be(b: &[u8]) -> u16 {
+    u16::from_be_bytes([b[0], b[1]])
+}
+
+/// Tables and settings defined so far.
+struct Tables {
+    dc: [Option<Huffman>; 4],
+    ac: [Option<Huffman>; 4],
+    /// Quantization tables in zig-zag order.
+    quant: [[u16; 64]; 4],
+    restart_interval: usize,
+}
+
+impl Tables {
+    fn define_huffman(&mut self, mut seg: &[u8]) -> Result<()> {
+        while !seg.is_empty() {
+            ensure!(seg.len() >= 17, InvalidData, "truncated JPEG Huffman table");
+            let (class, id) = (seg[0] >> 4, (seg[0] & 15) as usize);
+            let total: usize = seg[1..17].iter().map(|&n| n as usize).sum();
+            ensure!(seg.len() >= 17 + total, InvalidData, "truncated JPEG Huffman table");
+            ensure!(class < 2 && id < 4, InvalidData, "JPEG Huffman table {class}/{id} out of range");
+            let table = Some(Huffman::new(&seg[1..17], &seg[17..17 + total])?);
+            if class == 0 {
+                self.dc[id] = table;
+            } else {
+                self.ac[id] = table;
+            }
+            seg = &seg[17 + total..];
+        }
+        Ok(())
+    }
+
+    fn define_quantization(&mut self, mut seg: &[u8]) -> Result<()> {
+        while !seg.is_empty() {
+            let (wide, id) = (seg[0] >> 4 == 1, (seg[0] & 15) as usize);
+            let len = if wide { 128 } else { 64 };
+            ensure!(id < 4 && seg.len() > len, InvalidData, "invalid JPEG quantization table");
+            for (k, q) in self.quant[id].iter_mut().enumerate() {
+                *q = if wide { u16_be(&seg[1 + 2 * k..]) } else { u16::from(seg[1 + k]) };
+            }
+            seg = &seg[1 + len..];
+        }
+        Ok(())
+    }
+}
+
+/// Decodes a complete JPEG stream holding the frame `info` describes, to
+/// samples interleaved at full resolution.
+pub(super) fn decode(data: &[u8], info: &PixelInfo) -> Result<Vec<u16>> {
+    ensure!(data.starts_with(&[0xFF, 0xD8]), InvalidData, "JPEG data does not start with an SOI marker");
+    let mut tables =
+        Tables { dc: Default::default(), ac: Default::default(), quant: [[1; 64]; 4], restart_interval: 0 };
+    let mut frame: Option<Frame> = None;
+    let mut point_transform = 0;
+    let mut pos = 2;
+    loop {
+        while pos < data.len() && data[pos] != 0xFF {
+            pos += 1;
+        }
+        while pos < data.len() && data[pos] == 0xFF {
+            pos += 1;
+        }
+        ensure!(pos < data.len(), InvalidData, "JPEG data ends without an EOI marker");
+        let marker = data[pos];
+        pos += 1;
+        match marker {
+            0xD9 => break,
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+        ensure!(pos + 2 <= data.len(), InvalidData, "truncated JPEG marker segment");
+        let len = u16_be(&data[pos..]) as usize;
+        ensure!(len >= 2 && pos + len <= data.len(), InvalidData, "truncated JPEG marker segment");
+        let seg = &data[pos + 2..pos + len];
+        pos += len;
+        match marker {
+            0xC0 | 0xC1 | 0xC3 => {
+                ensure!(frame.is_none(), UnsupportedFeature, "JPEG stream with several frames");
+                frame = Some(Frame::parse(marker, seg, info)?);
+            }
+            0xC2 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF => {
+                return Err(dicom_err!(UnsupportedFeature, "JPEG process SOF{} is not supported", marker - 0xC0));
+            }
+            0xC4 => tables.define_huffman(seg)?,
+            0xDB => tables.define_quantization(seg)?,
+            0xDD => {
+                ensure!(seg.len() >= 2, InvalidData, "truncated JPEG restart interval");
+                tables.restart_interval = u16_be(seg) as usize;
+            }
+            0xDA => {
+                let frame =
+                    frame.as_mut().ok_or_else(|| dicom_err!(InvalidData, "JPEG scan before the frame header"))?;
+                let scan = Scan::parse(frame, seg)?;
+                point_transform = scan.point_transform;
+                pos = scan.decode(frame, &tables, data, pos)?;
+            }
+            _ => {}
+        }
+    }
+    let frame = frame.ok_or_else(|| dicom_err!(InvalidData, "JPEG data has no frame header"))?;
+    let point_transform = if frame.process == Process::Lossless { point_transform } else { 0 };
+    Ok(frame.into_samples(point_transform))
+}
+
+struct Scan {
+    /// Frame component index with its DC (or lossless) and AC table selectors.
+    components: Vec<(usize, usize, usize)>,
+    predictor: u8,
+    point_transform: u8,
+}
+
+impl Scan {
+    fn parse(frame: &Frame, seg: &[u8]) -> Result<Self> {
+        let count = *seg.first().unwrap_or(&0) as usize;
+        ensure!(count > 0 && seg.len() >= 4 + 2 * count, InvalidData, "truncated JPEG scan header");
+        let mut components = Vec::with_capacity(count);
+        for c in seg[1..1 + 2 * count].chunks_exact(2) {
+            let index = frame
+                .components
+                .iter()
+                .position(|fc| fc.id == c[0])
+                .ok_or_else(|| dicom_err!(InvalidData, "JPEG scan names unknown component {}", c[0]))?;
+            let (dc, ac) = ((c[1] >> 4) as usize, (c[1] & 15) as usize);
+            ensure!(dc < 4 && ac < 4, InvalidData, "JPEG Huffman table selector out of range");
+            components.push((index, dc, ac));
+        }
+        let rest = &seg[1 + 2 * count..];
+        let (ss, se, al) = (rest[0], rest[1], rest[2] & 15);
+        match frame.process {
+            Process::Dct => {
+                ensure!(ss == 0 && se == 63 && rest[2] == 0, UnsupportedFeature, "progressive JPEG scan parameters")
+            }
+            Process::Lossless => {
+                ensure!((1..=7).contains(&ss), InvalidData, "lossless JPEG predictor {ss}");
+                ensure!(al < frame.precision, InvalidData, "JPEG point transform {al} out of range");
+            }
+        }
+        Ok(Scan { components, predictor: ss, point_transform: al })
+    }
+
+    /// Decodes the entropy-coded segment starting at `pos`; returns where the
+    /// next marker starts.
+    fn decode(&self, frame: &mut Frame, tables: &Tables, data: &[u8], pos: usize) -> Result<usize> {
+        let table = |set: &[Option<Huffman>; 4], id: usize| {
+            set[id].clone().ok_or_else(|| dicom_err!(InvalidData, "JPEG scan uses undefined Huffman table {id}"))
+        };
+        let dc: Vec<Huffman> =
+            self.components.iter().map(|&(_, dc, _)| table(&tables.dc, dc)).collect::<Result<_>>()?;
+        let mut bits = Bits::new(data, pos);
+        match frame.process {
+            Process::Dct => {
+                let ac: Vec<Huffman> =
+                    self.components.iter().map(|&(_, _, ac)| table(&tables.ac, ac)).collect::<Result<_>>()?;
+                self.decode_dct(frame, tables, &dc, &ac, &mut bits)?;
+            }
+            Process::Lossless => self.decode_lossless(frame, tables.restart_interval, &dc, &mut bits)?,
+        }
+        Ok(bits.end())
+    }
+
+    fn decode_dct(
+        &self,
+        frame: &mut Frame,
+        tables: &Tables,
+        dc: &[Huffman],
+        ac: &[Huffman],
+        bits: &mut Bits,
+    ) -> Result<()> {
+        // A scan of one component codes its blocks one per MCU, in raster order.
+        let single = self.components.len() == 1;
+        let (mcus_x, mcus_y) = if single {
+            let (w, h) = frame.size(&frame.components[self.components[0].0]);
+            (w.div_ceil(8), h.div_ceil(8))
+        } else {
+            frame.mcus()
+        };
+        let shift = 1i32 << (frame.precision - 1);
+        let max = (1i32 << frame.precision) - 1;
+        let mut predictions = vec![0i32; self.components.len()];
+        let mut coefficients = [0i32; 64];
+        for mcu in 0..mcus_x * mcus_y {
+            if tables.restart_interval > 0 && mcu > 0 && mcu % tables.restart_interval == 0 {
+                bits.restart()?;
+                predictions.fill(0);
+            }
+            let (mx, my) = (mcu % mcus_x, mcu / mcus_x);
+            for (s, &(index, _, _)) in self.components.iter().enumerate() {
+                let c = &mut frame.components[index];
+                let (h, v) = if single { (1, 1) } else { (c.h, c.v) };
+                let quant = &tables.quant[c.tq];
+                for by in 0..v {
+                    for bx in 0..h {
+                        decode_block(bits, &dc[s], &ac[s], quant, &mut predictions[s], &mut coefficients)?;
+                        let (x0, y0) = ((mx * h + bx) * 8, (my * v + by) * 8);
+                        let samples = idct(&coefficients);
+                        for (row, values) in samples.chunks_exact(8).enumerate() {
+                            let start = (y0 + row) * c.stride + x0;
+                            for (out, &value) in c.plane[start..start + 8].iter_mut().zip(values) {
+                                *out = (value.round() as i32 + shift).clamp(0, max) as u16;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn decode_lossless(&self, frame: &mut Frame, restart: usize, dc: &[Huffman], bits: &mut Bits) -> Result<()> {
+        let (width, height) = (frame.width, frame.height);
+        let initial = 1i32 << (frame.precision - self.point_transform - 1);
+        // Each restart interval starts from the initial prediction; its first
+        // line predicts from the left neighbour only.
+        let (mut interval_start, mut first_row) = (0, 0);
+        for mcu in 0..width * height {
+            let (x, y) = (mcu % width, mcu / width);
+            if restart > 0 && mcu > 0 && mcu % restart == 0 {
+                bits.restart()?;
+                (interval_start, first_row) = (mcu, y);
+            }
+            for (s, &(index, _, _)) in self.components.iter().enumerate() {
+                let c = &mut frame.components[index];
+                let at = |dx: usize, dy: usize| i32::from(c.plane[(y - dy) * c.stride + x - dx]);
+                let prediction = if mcu == interval_start {
+                    initial
+                } else if y == first_row {
+                    at(1, 0)
+                } else if x == 0 {
+                    at(0, 1)
+                } else {
+                    let (ra, rb, rc) = (at(1, 0), at(0, 1), at(1, 1));
+                    match self.predictor {
+                        1 => ra,
+                        2 => rb,
+                        3 => rc,
+                        4 => ra + rb - rc,
+                        5 => ra + ((rb - rc) >> 1),
+                        6 => rb + ((ra - rc) >> 1),
+                        _ => (ra + rb) >> 1,
+                    }
+                };
+                let size = dc[s].decode(bits)?;
+                ensure!(size <= 16, InvalidData, "lossless JPEG difference category {size}");
+                let diff = if size == 16 { 32768 } else { bits.receive_extend(size) };
+                c.plane[y * c.stride + x] = (prediction + diff) as u16;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn decode_block(
+    bits: &mut Bits,
+    dc: &Huffman,
+    ac: &Huffman,
+    quant: &[u16; 64],
+    prediction: &mut i32,
+    out: &mut [i32; 64],
+) -> Result<()> {
+    out.fill(0);
+    let size = dc.decode(bits)?;
+    ensure!(size <= 15, InvalidData, "JPEG DC difference category {size}");
+    *prediction += bits.receive_extend(size);
+    out[0] = *prediction * i32::from(quant[0]);
+    let mut k = 1;
+    while k < 64 {
+        let rs = ac.decode(bits)?;
+        let (run, size) = ((rs >> 4) as usize, rs & 15);
+        if size == 0 {
+            if run != 15 {
+                break;
+            }
+            k += 16;
+            continue;
+        }
+        k += run;
+        ensure!(k < 64, InvalidData, "JPEG AC coefficients overrun the block");
+        out[ZIGZAG[k]] = bits.receive_extend(size) * i32::from(quant[k]);
+        k += 1;
+    }
+    Ok(())
+}
+
+/// `COS[x][u]`: the T.81 A.3.3 inverse DCT basis, `C(u)/2 · cos((2x+1)uπ/16)`.
+static COS: LazyLock<[[f32; 8]; 8]> = LazyLock::new(|| {
+    let mut table = [[0f32; 8]; 8];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            let scale = if u == 0 { std::f32::consts::FRAC_1_SQRT_2 } else { 1.0 };
+            *value = scale / 2.0 * (((2 * x + 1) * u) as f32 * std::f32::consts::PI / 16.0).cos();
+        }
+    }
+    table
+});
+
+/// The separable 8×8 inverse DCT of natural-order coefficients, before the
+/// level shift.
+fn idct(coefficients: &[i32; 64]) -> [f32; 64] {
+    let cos = &*COS;
+    let mut rows = [0f32; 64];
+    for v in 0..8 {
+        for x in 0..8 {
+            rows[v * 8 + x] = (0..8).map(|u| cos[x][u] * coefficients[v * 8 + u] as f32).sum();
+        }
+    }
+    let mut out = [0f32; 64];
+    for y in 0..8 {
+        for x in 0..8 {
+            out[y * 8 + x] = (0..8).map(|v| cos[y][v] * rows[v * 8 + x]).sum();
+        }
+    }
+    out
+}
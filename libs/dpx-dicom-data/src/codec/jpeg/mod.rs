@@ -0,0 +1,329 @@
+//! JPEG (ITU-T T.81, PS3.5 Section 8.2.1): Baseline (Process 1) and Extended
+//! (Process 2 & 4) lossy coding, and Lossless (Process 14), decoded by the
+//! in-tree [`decoder`]. Baseline frames are also encoded, through
+//! `jpeg-encoder`.
+//!
+//! Decoded samples keep the colour space they were coded in, so a lossy YCbCr
+//! frame comes out as YBR_FULL whatever its stored subsampling; frames are
+//! always interleaved. The encoder subsamples chroma horizontally, so colour
+//! frames it produces are YBR_FULL_422, as PS3.5 Section 8.2.1 expects of
+//! baseline data.
+
+mod decoder;
+
+use dpx_dicom_core::TransferSyntax;
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{dicom_err, ensure};
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+use super::{CodecEntry, PixelCodec, PixelInfo};
+
+/// Quality of the baseline encoder, on the usual 1–100 scale.
+const QUALITY: u8 = 90;
+
+/// JPEG Baseline: decodes and encodes 8-bit frames.
+struct Baseline;
+
+/// JPEG Extended and Lossless: decode only.
+struct DecodeOnly;
+
+fn decode(frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+    let samples = decoder::decode(frame, info)?;
+    Ok(match info.bytes_per_sample() {
+        1 => samples.iter().map(|&s| s as u8).collect(),
+        _ => samples.iter().flat_map(|s| s.to_le_bytes()).collect(),
+    })
+}
+
+/// Decoded frames are interleaved, and YCbCr ones no longer subsampled.
+fn decoded_info(info: &PixelInfo) -> PixelInfo {
+    let mut out = info.clone();
+    if out.photometric_interpretation == "YBR_FULL_422" {
+        out.photometric_interpretation = "YBR_FULL".to_string();
+    }
+    out.planar_configuration = 0;
+    out
+}
+
+impl PixelCodec for DecodeOnly {
+    fn decode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        decode(frame, info)
+    }
+
+    fn decoded_info(&self, info: &PixelInfo) -> PixelInfo {
+        decoded_info(info)
+    }
+}
+
+impl PixelCodec for Baseline {
+    fn decode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        decode(frame, info)
+    }
+
+    fn decoded_info(&self, info: &PixelInfo) -> PixelInfo {
+        decoded_info(info)
+    }
+
+    // ponytail: Lossy Image Compression (0028,2110) and its ratio/method are
+    // left to the caller; the codec trait cannot add attributes.
+    fn encode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        ensure!(
+            info.bits_allocated == 8 && info.pixel_representation == 0,
+            UnsupportedFeature,
+            "JPEG Baseline encodes unsigned 8-bit samples, not {}-bit",
+            info.bits_allocated
+        );
+        let pixels = info.rows as usize * info.columns as usize;
+        let samples = info.samples_per_pixel as usize;
+        ensure!(frame.len() >= pixels * samples, InvalidData, "frame of {} bytes is truncated", frame.len());
+        let color = match (samples, info.photometric_interpretation.as_str()) {
+            (1, "MONOCHROME1" | "MONOCHROME2") => ColorType::Luma,
+            (3, "RGB") => ColorType::Rgb,
+            (3, "YBR_FULL") => ColorType::Ycbcr,
+            (_, pi) => {
+                return Err(dicom_err!(UnsupportedFeature, "JPEG Baseline cannot encode {samples}-sample {pi} frames"));
+            }
+        };
+        let frame = &frame[..pixels * samples];
+        let interleaved: Vec<u8>;
+        let data = if samples > 1 && info.planar_configuration == 1 {
+            interleaved = (0..pixels * samples).map(|i| frame[(i % samples) * pixels + i / samples]).collect();
+            &interleaved[..]
+        } else {
+            frame
+        };
+        let mut out = Vec::new();
+        let mut encoder = Encoder::new(&mut out, QUALITY);
+        encoder.set_sampling_factor(SamplingFactor::F_2_1);
+        encoder
+            .encode(data, info.columns, info.rows, color)
+            .map_err(|e| dicom_err!(Internal, "encoding JPEG Baseline: {e}"))?;
+        Ok(out)
+    }
+
+    fn encoded_info(&self, info: &PixelInfo) -> PixelInfo {
+        let mut out = info.clone();
+        if info.samples_per_pixel == 3 {
+            out.photometric_interpretation = "YBR_FULL_422".to_string();
+            out.planar_configuration = 0;
+        }
+        out
+    }
+}
+
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEGBaseline8Bit, codec: &Baseline } }
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEGExtended12Bit, codec: &DecodeOnly } }
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEGLossless, codec: &DecodeOnly } }
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEGLosslessSV1, codec: &DecodeOnly } }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::find_codec;
+    use crate::testing::pixel_info;
+
+    /// Entropy-coded bits, padded with ones and byte-stuffed.
+    #[derive(Default)]
+    struct BitWriter {
+        out: Vec<u8>,
+        acc: u32,
+        count: u32,
+    }
+
+    impl BitWriter {
+        fn put(&mut self, value: u32, len: u32) {
+            for i in (0..len).rev() {
+                self.acc = self.acc << 1 | (value >> i & 1);
+                self.count += 1;
+                if self.count == 8 {
+                    self.out.push(self.acc as u8);
+                    if self.acc == 0xFF {
+                        self.out.push(0);
+                    }
+                    (self.acc, self.count) = (0, 0);
+                }
+            }
+        }
+
+        /// A difference in its category's table code (`code_len` bits wide,
+        /// the category itself) followed by its magnitude bits.
+        fn difference(&mut self, diff: i32, code_len: u32) {
+            let size = 32 - diff.unsigned_abs().leading_zeros();
+            self.put(size, code_len);
+            let bits = if diff < 0 { diff + (1 << size) - 1 } else { diff };
+            self.put(bits as u32, size);
+        }
+
+        fn finish(&mut self) -> Vec<u8> {
+            if self.count > 0 {
+                self.put(u32::MAX, 8 - self.count);
+            }
+            std::mem::take(&mut self.out)
+        }
+    }
+
+    fn segment(marker: u8, body: &[u8]) -> Vec<u8> {
+        let mut out = vec![0xFF, marker];
+        out.extend(((body.len() + 2) as u16).to_be_bytes());
+        out.extend(body);
+        out
+    }
+
+    /// A Huffman table whose symbols `0..count` all have 5-bit codes equal to
+    /// the symbol.
+    fn five_bit_table(class_id: u8, count: u8) -> Vec<u8> {
+        let mut body = vec![class_id, 0, 0, 0, 0, count, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        body.extend(0..count);
+        segment(0xC4, &body)
+    }
+
+    #[test]
+    fn baseline_gray_roundtrip() {
+        let info = pixel_info(16, 24, 1, 8, "MONOCHROME2");
+        let frame: Vec<u8> = (0..16 * 24).map(|i| ((i % 24) * 10 + i / 24) as u8).collect();
+        let encoded = Baseline.encode_frame(&frame, &info).expect("encode");
+        assert!(encoded.starts_with(&[0xFF, 0xD8]));
+        assert_eq!(Baseline.encoded_info(&info), info);
+        let decoded = Baseline.decode_frame(&encoded, &info).expect("decode");
+        assert_eq!(decoded.len(), frame.len());
+        assert!(decoded.iter().zip(&frame).all(|(&a, &b)| a.abs_diff(b) <= 6));
+    }
+
+    #[test]
+    fn baseline_colour_is_ybr_full_422() {
+        let mut rgb = pixel_info(8, 16, 3, 8, "RGB");
+        rgb.planar_configuration = 1;
+        // Planar red, green and blue planes of one colour.
+        let frame: Vec<u8> = [200u8, 100, 50].iter().flat_map(|&v| [v; 128]).collect();
+        let encoded = Baseline.encode_frame(&frame, &rgb).expect("encode");
+        let stored = Baseline.encoded_info(&rgb);
+        assert_eq!((stored.photometric_interpretation.as_str(), stored.planar_configuration), ("YBR_FULL_422", 0));
+        assert_eq!(Baseline.decoded_info(&stored).photometric_interpretation, "YBR_FULL");
+
+        let decoded = Baseline.decode_frame(&encoded, &stored).expect("decode");
+        assert_eq!(decoded.len(), 8 * 16 * 3);
+        // The T.871 YCbCr of (200, 100, 50).
+        for ycc in decoded.chunks_exact(3) {
+            assert!(ycc[0].abs_diff(124) <= 2 && ycc[1].abs_diff(86) <= 2 && ycc[2].abs_diff(182) <= 2, "{ycc:?}");
+        }
+        let err = Baseline.encode_frame(&[0; 8], &pixel_info(2, 2, 1, 16, "MONOCHROME2")).unwrap_err();
+        assert_eq!(err.kind, dpx_dicom_core::error::ErrorKind::UnsupportedFeature);
+    }
+
+    #[test]
+    fn extended_twelve_bit_with_restarts() {
+        // Two DC-only blocks of a 12x5 frame, a restart marker between them.
+        let mut data = vec![0xFF, 0xD8];
+        let mut quant = vec![0u8];
+        quant.extend([1u8; 64]);
+        data.extend(segment(0xDB, &quant));
+        data.extend(segment(0xC1, &[12, 0, 5, 0, 12, 1, 1, 0x11, 0]));
+        data.extend(five_bit_table(0x00, 16));
+        data.extend(segment(0xC4, &[0x10, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00]));
+        data.extend(segment(0xDD, &[0, 1]));
+        data.extend(segment(0xDA, &[1, 1, 0x00, 0, 63, 0]));
+        // A DC coefficient of 8·d shifts the whole block by d.
+        let mut bits = BitWriter::default();
+        for d in [100, -300] {
+            bits.difference(8 * d, 5);
+            bits.put(0, 1); // end of block
+            data.extend(bits.finish());
+            data.extend([0xFF, 0xD0]);
+        }
+        data.truncate(data.len() - 2);
+        data.extend([0xFF, 0xD9]);
+
+        let decoded = DecodeOnly.decode_frame(&data, &pixel_info(5, 12, 1, 16, "MONOCHROME2")).expect("decode");
+        let samples: Vec<u16> = decoded.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+        let row: Vec<u16> = [2148; 8].into_iter().chain([1748; 4]).collect();
+        assert_eq!(samples, row.repeat(5));
+        // Twelve-bit samples need two bytes.
+        assert!(DecodeOnly.decode_frame(&data, &pixel_info(5, 12, 1, 8, "MONOCHROME2")).is_err());
+    }
+
+    #[test]
+    fn frame_header_is_checked_before_decoding() {
+        // The planes of a 65535x65535 three-component frame would take gigabytes.
+        let mut data = vec![0xFF, 0xD8];
+        data.extend(segment(0xC0, &[8, 0xFF, 0xFF, 0xFF, 0xFF, 3, 1, 0x11, 0, 2, 0x11, 0, 3, 0x11, 0]));
+        data.extend([0xFF, 0xD9]);
+        let err = Baseline.decode_frame(&data, &pixel_info(2, 2, 3, 8, "RGB")).unwrap_err();
+        assert!(err.to_string().contains("JPEG frame is 65535x65535"), "{err}");
+        let err = Baseline.decode_frame(&data, &pixel_info(65535, 65535, 1, 8, "MONOCHROME2")).unwrap_err();
+        assert!(err.to_string().contains("3 components"), "{err}");
+    }
+
+    #[test]
+    fn hostile_tables_and_scans_are_errors() {
+        let info = pixel_info(2, 3, 1, 8, "MONOCHROME2");
+        let lossless = |table: Vec<u8>, scan: &[u8]| {
+            let mut data = vec![0xFF, 0xD8];
+            data.extend(segment(0xC3, &[8, 0, 2, 0, 3, 1, 1, 0x11, 0]));
+            data.extend(table);
+            data.extend(segment(0xDA, scan));
+            data.extend([0, 0, 0xFF, 0xD9]);
+            DecodeOnly.decode_frame(&data, &info).unwrap_err().to_string()
+        };
+        // Three 1-bit codes.
+        let table = segment(0xC4, &[0x00, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2]);
+        assert!(lossless(table, &[1, 1, 0x00, 1, 0, 0]).contains("over-subscribed"));
+        // A point transform as wide as the samples.
+        let err = lossless(five_bit_table(0x00, 17), &[1, 1, 0x00, 1, 0, 8]);
+        assert!(err.contains("point transform 8"), "{err}");
+    }
+
+    #[test]
+    fn lossless_first_order_prediction() {
+        let samples = [10u8, 12, 9, 20, 20, 255];
+        let mut data = vec![0xFF, 0xD8];
+        data.extend(segment(0xC3, &[8, 0, 2, 0, 3, 1, 1, 0x11, 0]));
+        data.extend(five_bit_table(0x00, 17));
+        data.extend(segment(0xDA, &[1, 1, 0x00, 1, 0, 0]));
+        let mut bits = BitWriter::default();
+        // Predicted from 128, then the left neighbour, then above at the row start.
+        for diff in [-118, 2, -3, 10, 0, 235] {
+            bits.difference(diff, 5);
+        }
+        data.extend(bits.finish());
+        data.extend([0xFF, 0xD9]);
+
+        let info = pixel_info(2, 3, 1, 8, "MONOCHROME2");
+        assert_eq!(DecodeOnly.decode_frame(&data, &info).expect("decode"), samples);
+        assert!(find_codec(&TransferSyntax::JPEGLosslessSV1).is_some());
+        assert!(find_codec(&TransferSyntax::JPEGLossless).is_some());
+        assert!(DecodeOnly.encode_frame(&samples, &info).is_err());
+    }
+
+    #[test]
+    fn multi_frame_through_writer() {
+        use crate::DcmWriter;
+        use crate::testing::Image;
+        use crate::value::{PixelData, Value};
+        use dpx_dicom_core::tags;
+
+        let native: Vec<u8> = (0..2 * 64).flat_map(|i| [i as u8, 128, 255 - i as u8]).collect();
+        let mut ds = Image::new(8, 8, 3, 8).photometric("RGB").frames(2).native(native);
+        ds.set(&tags::PlanarConfiguration, 0u16).unwrap();
+
+        let read = |bytes, ts| {
+            crate::DcmReader::new()
+                .header(crate::HeaderType::NoHeader)
+                .transfer_syntax(ts)
+                .parse_bytes(bytes)
+                .expect("read")
+                .dataset
+                .expect("dataset")
+        };
+        let jpeg = DcmWriter::new().transfer_syntax(&TransferSyntax::JPEGBaseline8Bit).to_bytes(&ds).expect("encode");
+        let ds2 = read(jpeg, &TransferSyntax::JPEGBaseline8Bit);
+        assert_eq!(ds2.get::<String>(&tags::PhotometricInterpretation).unwrap(), "YBR_FULL_422");
+        let Ok(Value::Pixels(px)) = ds2.value(&tags::PixelData) else { panic!("no pixel data") };
+        let PixelData::Encapsulated { fragments, .. } = *px else { panic!("expected encapsulated") };
+        assert_eq!(fragments.len(), 2);
+
+        let back = DcmWriter::new().to_bytes(&ds2).expect("decode");
+        let ds3 = read(back, &TransferSyntax::ExplicitVRLittleEndian);
+        assert_eq!(ds3.get::<String>(&tags::PhotometricInterpretation).unwrap(), "YBR_FULL");
+        assert_eq!(ds3.get_bytes(&tags::PixelData).expect("native").len(), 2 * 64 * 3);
+    }
+}
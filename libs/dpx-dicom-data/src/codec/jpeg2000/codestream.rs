@@ -0,0 +1,585 @@
+//! Codestream syntax (T.800 Annex A): the main header, tile-part headers and
+//! the tile data they delimit, plus the Part 2 multiple component transform
+//! segments (T.801 Annex J). A JP2 file wrapper is stepped over.
+//!
+//! Coding parameters follow the precedence of A.6: tile-part COC over
+//! tile-part COD over main COC over main COD, likewise for quantization.
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{dicom_err, ensure};
+
+const SOC: u16 = 0xFF4F;
+const SOT: u16 = 0xFF90;
+const SOD: u16 = 0xFF93;
+const EOC: u16 = 0xFFD9;
+
+/// Image and tile geometry on the reference grid (SIZ).
+pub(super) struct Siz {
+    pub x1: u32,
+    pub y1: u32,
+    pub x0: u32,
+    pub y0: u32,
+    pub tile_w: u32,
+    pub tile_h: u32,
+    pub tile_x0: u32,
+    pub tile_y0: u32,
+    pub components: Vec<ComponentSiz>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ComponentSiz {
+    pub precision: u8,
+    pub signed: bool,
+    pub dx: u32,
+    pub dy: u32,
+}
+
+impl Siz {
+    pub fn tiles_x(&self) -> u32 {
+        (self.x1 - self.tile_x0).div_ceil(self.tile_w)
+    }
+
+    pub fn tiles_y(&self) -> u32 {
+        (self.y1 - self.tile_y0).div_ceil(self.tile_h)
+    }
+
+    /// The reference grid area `(x0, y0, x1, y1)` of tile `index`.
+    pub fn tile_rect(&self, index: usize) -> (u32, u32, u32, u32) {
+        let (p, q) = (index as u64 % u64::from(self.tiles_x()), index as u64 / u64::from(self.tiles_x()));
+        let (tw, th) = (u64::from(self.tile_w), u64::from(self.tile_h));
+        let (tx0, ty0) = (u64::from(self.tile_x0), u64::from(self.tile_y0));
+        let x0 = (tx0 + p * tw).max(u64::from(self.x0)) as u32;
+        let y0 = (ty0 + q * th).max(u64::from(self.y0)) as u32;
+        let x1 = (tx0 + (p + 1) * tw).min(u64::from(self.x1)) as u32;
+        let y1 = (ty0 + (q + 1) * th).min(u64::from(self.y1)) as u32;
+        (x0, y0, x1, y1)
+    }
+}
+
+/// Per-component coding style (SPcod / SPcoc).
+#[derive(Debug, Clone)]
+pub(super) struct ComponentCoding {
+    pub levels: u8,
+    /// Code-block width and height exponents.
+    pub xcb: u8,
+    pub ycb: u8,
+    /// Code-block style flags (Table A.19).
+    pub style: u8,
+    pub reversible: bool,
+    /// Precinct width and height exponents for each resolution.
+    pub precincts: Vec<(u8, u8)>,
+}
+
+/// Code-block style: arithmetic coding bypass.
+pub(super) const BYPASS: u8 = 0x01;
+/// Code-block style: context reset after each pass.
+pub(super) const RESET: u8 = 0x02;
+/// Code-block style: termination after each pass.
+pub(super) const TERMALL: u8 = 0x04;
+/// Code-block style: vertically causal context.
+pub(super) const CAUSAL: u8 = 0x08;
+/// Code-block style: segmentation symbols.
+pub(super) const SEGMARK: u8 = 0x20;
+
+/// Quantization of one component (SPqcd / SPqcc).
+#[derive(Debug, Clone)]
+pub(super) struct Quantization {
+    /// 0 none, 1 scalar derived, 2 scalar expounded.
+    pub style: u8,
+    pub guard_bits: u8,
+    /// Exponent and mantissa per subband, LL first; one entry when derived.
+    pub steps: Vec<(u8, u16)>,
+}
+
+impl Quantization {
+    /// Exponent and mantissa of subband `band` (0 for LL, then three per
+    /// resolution), whose resolution is `resolution` of `levels`.
+    pub fn step(&self, band: usize, resolution: usize) -> Result<(u8, u16)> {
+        if self.style == 1 {
+            let (e, m) = self.steps[0];
+            let e = i32::from(e) - resolution.saturating_sub(1) as i32;
+            ensure!(e >= 0, InvalidData, "derived JPEG 2000 step exponent below zero");
+            return Ok((e as u8, m));
+        }
+        self.steps
+            .get(band)
+            .copied()
+            .ok_or_else(|| dicom_err!(InvalidData, "JPEG 2000 quantization misses subband {band}"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct ComponentParams {
+    pub coding: ComponentCoding,
+    pub quant: Quantization,
+    /// Maxshift ROI shift (RGN).
+    pub roi_shift: u8,
+    /// Precedence of the marker that set `coding` and `quant` (A.6).
+    coding_level: u8,
+    quant_level: u8,
+}
+
+/// A progression order change (POC) or the single COD progression.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Progression {
+    pub order: u8,
+    pub res_start: usize,
+    pub comp_start: usize,
+    pub layer_end: usize,
+    pub res_end: usize,
+    pub comp_end: usize,
+}
+
+/// A Part 2 transform array (MCT).
+#[derive(Debug, Clone)]
+pub(super) struct McArray {
+    pub index: u8,
+    /// 0 dependency, 1 decorrelation, 2 offset.
+    pub kind: u8,
+    pub values: Vec<f64>,
+}
+
+/// One component collection of a Part 2 MCC stage.
+#[derive(Debug, Clone)]
+pub(super) struct McCollection {
+    pub inputs: Vec<usize>,
+    pub outputs: Vec<usize>,
+    /// Array indices of the decorrelation matrix and the offsets; 0 for none.
+    pub matrix: u8,
+    pub offset: u8,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct McStage {
+    pub index: u8,
+    pub collections: Vec<McCollection>,
+}
+
+/// Coding parameters in force for one tile.
+#[derive(Debug, Clone)]
+pub(super) struct TileCoding {
+    pub order: u8,
+    pub layers: usize,
+    /// 0 none, 1 RCT/ICT on the first three components, 2 Part 2 (MCO).
+    pub mct: u8,
+    pub sop: bool,
+    pub eph: bool,
+    pub components: Vec<ComponentParams>,
+    pub pocs: Vec<Progression>,
+    pub arrays: Vec<McArray>,
+    pub stages: Vec<McStage>,
+    /// MCC stage indices in order of application (MCO).
+    pub stage_order: Vec<u8>,
+}
+
+impl TileCoding {
+    /// The progressions to follow: the POCs, or the COD order over everything.
+    pub fn progressions(&self) -> Vec<Progression> {
+        if !self.pocs.is_empty() {
+            return self.pocs.clone();
+        }
+        let res_end = self.components.iter().map(|c| c.coding.levels as usize + 1).max().unwrap_or(1);
+        vec![Progression {
+            order: self.order,
+            res_start: 0,
+            comp_start: 0,
+            layer_end: self.layers,
+            res_end,
+            comp_end: self.components.len(),
+        }]
+    }
+}
+
+/// The data of one tile: its coding parameters and its tile-parts' bodies.
+pub(super) struct Tile {
+    pub coding: TileCoding,
+    pub data: Vec<u8>,
+}
+
+pub(super) struct Codestream {
+    pub siz: Siz,
+    pub tiles: Vec<Option<Tile>>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        ensure!(self.pos + n <= self.data.len(), InvalidData, "truncated JPEG 2000 marker segment");
+        let out = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    /// A component index: one byte, two with 257 or more components.
+    fn component(&mut self, components: usize) -> Result<usize> {
+        let c = if components > 256 { self.u16()? as usize } else { self.u8()? as usize };
+        ensure!(c < components, InvalidData, "JPEG 2000 component {c} out of range");
+        Ok(c)
+    }
+}
+
+/// The contiguous codestream inside a JP2 file, or `data` itself.
+fn unwrap_jp2(data: &[u8]) -> Result<&[u8]> {
+    if !data.starts_with(&[0, 0, 0, 12, b'j', b'P', b' ', b' ']) {
+        return Ok(data);
+    }
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let len = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as u64;
+        let kind = &data[pos + 4..pos + 8];
+        let (header, len) = match len {
+            0 => (8, (data.len() - pos) as u64),
+            1 if pos + 16 <= data.len() => {
+                (16, u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap_or_default()))
+            }
+            _ => (8, len),
+        };
+        ensure!(len >= header && pos as u64 + len <= data.len() as u64, InvalidData, "truncated JP2 box");
+        if kind == b"jp2c" {
+            return Ok(&data[pos + header as usize..pos + len as usize]);
+        }
+        pos += len as usize;
+    }
+    Err(dicom_err!(InvalidData, "JP2 file without a codestream box"))
+}
+
+/// Parses a codestream into its geometry and per-tile data.
+pub(super) fn parse(data: &[u8]) -> Result<Codestream> {
+    let data = unwrap_jp2(data)?;
+    let mut r = Reader { data, pos: 0 };
+    ensure!(r.u16().ok() == Some(SOC), InvalidData, "JPEG 2000 data does not start with an SOC marker");
+
+    let mut siz = None;
+    let mut main: Option<TileCoding> = None;
+    let mut tiles: Vec<Option<Tile>> = Vec::new();
+    // Markers of the main header, until the first SOT.
+    loop {
+        let marker = r.u16()?;
+        if marker == SOT {
+            break;
+        }
+        ensure!(marker != EOC, InvalidData, "JPEG 2000 codestream has no tiles");
+        let len = r.u16()? as usize;
+        ensure!(len >= 2, InvalidData, "JPEG 2000 marker segment of length {len}");
+        let mut seg = Reader { data: r.take(len - 2)?, pos: 0 };
+        if marker == 0xFF51 {
+            let s = parse_siz(&mut seg)?;
+            let count = (s.tiles_x() as usize).saturating_mul(s.tiles_y() as usize);
+            ensure!(count <= 65535, InvalidData, "JPEG 2000 image of {count} tiles");
+            tiles.resize_with(count, || None);
+            main = Some(TileCoding {
+                order: 0,
+                layers: 1,
+                mct: 0,
+                sop: false,
+                eph: false,
+                components: s.components.iter().map(|_| default_params()).collect(),
+                pocs: Vec::new(),
+                arrays: Vec::new(),
+                stages: Vec::new(),
+                stage_order: Vec::new(),
+            });
+            siz = Some(s);
+            continue;
+        }
+        let coding = main.as_mut().ok_or_else(|| dicom_err!(InvalidData, "JPEG 2000 main header without SIZ"))?;
+        apply_marker(coding, marker, &mut seg, 0)?;
+    }
+    let siz = siz.ok_or_else(|| dicom_err!(InvalidData, "JPEG 2000 main header without SIZ"))?;
+    let main = main.ok_or_else(|| dicom_err!(InvalidData, "JPEG 2000 main header without SIZ"))?;
+
+    // Tile-parts: SOT already read.
+    loop {
+        let start = r.pos - 2;
+        let len = r.u16()?;
+        ensure!(len == 10, InvalidData, "JPEG 2000 SOT of length {len}");
+        let index = r.u16()? as usize;
+        let psot = r.u32()? as usize;
+        let _part = r.u8()?;
+        let _parts = r.u8()?;
+        ensure!(index < tiles.len(), InvalidData, "JPEG 2000 tile {index} out of range");
+        // A Psot of 0 extends the last tile-part to the EOC marker.
+        let end = match psot {
+            0 if data.ends_with(&EOC.to_be_bytes()) => (data.len() - 2).max(r.pos),
+            0 => data.len(),
+            _ => start + psot,
+        };
+        ensure!(end <= data.len() && end >= r.pos, InvalidData, "JPEG 2000 tile-part overruns the codestream");
+        let tile = tiles[index].get_or_insert_with(|| {
+            let mut coding = main.clone();
+            coding.pocs.clear();
+            Tile { coding, data: Vec::new() }
+        });
+        loop {
+            let marker = r.u16()?;
+            if marker == SOD {
+                break;
+            }
+            let len = r.u16()? as usize;
+            ensure!(len >= 2, InvalidData, "JPEG 2000 marker segment of length {len}");
+            let mut seg = Reader { data: r.take(len - 2)?, pos: 0 };
+            apply_marker(&mut tile.coding, marker, &mut seg, 2)?;
+        }
+        ensure!(r.pos <= end, InvalidData, "JPEG 2000 tile-part header overruns the tile-part");
+        tile.data.extend_from_slice(&data[r.pos..end]);
+        r.pos = end;
+        match r.u16() {
+            Ok(SOT) => continue,
+            _ => break,
+        }
+    }
+    // Tiles without a POC of their own follow the main header's.
+    for tile in tiles.iter_mut().flatten() {
+        if tile.coding.pocs.is_empty() {
+            tile.coding.pocs = main.pocs.clone();
+        }
+    }
+    Ok(Codestream { siz, tiles })
+}
+
+fn parse_siz(r: &mut Reader) -> Result<Siz> {
+    let _rsiz = r.u16()?;
+    let (x1, y1, x0, y0) = (r.u32()?, r.u32()?, r.u32()?, r.u32()?);
+    let (tile_w, tile_h, tile_x0, tile_y0) = (r.u32()?, r.u32()?, r.u32()?, r.u32()?);
+    let count = r.u16()? as usize;
+    ensure!(x1 > x0 && y1 > y0, InvalidData, "empty JPEG 2000 image");
+    ensure!(tile_w > 0 && tile_h > 0, InvalidData, "JPEG 2000 tiles of zero size");
+    ensure!(
+        tile_x0 <= x0 && tile_y0 <= y0 && tile_x0 + tile_w > x0 && tile_y0 + tile_h > y0,
+        InvalidData,
+        "JPEG 2000 tile grid does not cover the image origin"
+    );
+    ensure!(count > 0, InvalidData, "JPEG 2000 image without components");
+    let mut components = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (ssiz, dx, dy) = (r.u8()?, r.u8()?, r.u8()?);
+        let precision = (ssiz & 0x7F) + 1;
+        ensure!(precision <= 16, UnsupportedFeature, "{precision}-bit JPEG 2000 samples");
+        ensure!(dx > 0 && dy > 0, InvalidData, "JPEG 2000 component subsampling of zero");
+        components.push(ComponentSiz { precision, signed: ssiz & 0x80 != 0, dx: dx.into(), dy: dy.into() });
+    }
+    Ok(Siz { x1, y1, x0, y0, tile_w, tile_h, tile_x0, tile_y0, components })
+}
+
+fn default_params() -> ComponentParams {
+    ComponentParams {
+        coding: ComponentCoding { levels: 5, xcb: 6, ycb: 6, style: 0, reversible: true, precincts: vec![(15, 15); 6] },
+        quant: Quantization { style: 0, guard_bits: 2, steps: Vec::new() },
+        roi_shift: 0,
+        coding_level: 0,
+        quant_level: 0,
+    }
+}
+
+/// Applies one marker segment of a header at precedence `base` (0 main, 2
+/// tile-part); COC and QCC rank one above their COD and QCD.
+fn apply_marker(coding: &mut TileCoding, marker: u16, r: &mut Reader, base: u8) -> Result<()> {
+    let count = coding.components.len();
+    match marker {
+        // COD
+        0xFF52 => {
+            let scod = r.u8()?;
+            coding.order = r.u8()?;
+            coding.layers = r.u16()? as usize;
+            coding.mct = r.u8()?;
+            coding.sop = scod & 2 != 0;
+            coding.eph = scod & 4 != 0;
+            ensure!(coding.order <= 4, InvalidData, "JPEG 2000 progression order {}", coding.order);
+            ensure!(coding.layers > 0, InvalidData, "JPEG 2000 coding with no layers");
+            let style = parse_coding(r, scod & 1 != 0)?;
+            for c in &mut coding.components {
+                if c.coding_level <= base {
+                    c.coding = style.clone();
+                    c.coding_level = base;
+                }
+            }
+        }
+        // COC
+        0xFF53 => {
+            let c = r.component(count)?;
+            let scoc = r.u8()?;
+            let style = parse_coding(r, scoc & 1 != 0)?;
+            let params = &mut coding.components[c];
+            if params.coding_level <= base + 1 {
+                params.coding = style;
+                params.coding_level = base + 1;
+            }
+        }
+        // QCD
+        0xFF5C => {
+            let quant = parse_quantization(r)?;
+            for c in &mut coding.components {
+                if c.quant_level <= base {
+                    c.quant = quant.clone();
+                    c.quant_level = base;
+                }
+            }
+        }
+        // QCC
+        0xFF5D => {
+            let c = r.component(count)?;
+            let quant = parse_quantization(r)?;
+            let params = &mut coding.components[c];
+            if params.quant_level <= base + 1 {
+                params.quant = quant;
+                params.quant_level = base + 1;
+            }
+        }
+        // RGN
+        0xFF5E => {
+            let c = r.component(count)?;
+            let style = r.u8()?;
+            ensure!(style == 0, UnsupportedFeature, "JPEG 2000 ROI style {style}");
+            coding.components[c].roi_shift = r.u8()?;
+        }
+        // POC
+        0xFF5F => {
+            let wide = count > 256;
+            let entry = if wide { 9 } else { 7 };
+            while r.rest().len() >= entry {
+                let res_start = r.u8()? as usize;
+                let comp_start = if wide { r.u16()? as usize } else { r.u8()? as usize };
+                let layer_end = r.u16()? as usize;
+                let res_end = r.u8()? as usize;
+                let comp_end = if wide { r.u16()? as usize } else { r.u8()? as usize };
+                let order = r.u8()?;
+                ensure!(order <= 4, InvalidData, "JPEG 2000 progression order {order}");
+                // A CEpoc of 0 stands for 256.
+                let comp_end = if comp_end == 0 && !wide { 256 } else { comp_end };
+                coding.pocs.push(Progression { order, res_start, comp_start, layer_end, res_end, comp_end });
+            }
+        }
+        // PPM, PPT
+        0xFF60 | 0xFF61 => {
+            return Err(dicom_err!(UnsupportedFeature, "JPEG 2000 packed packet headers"));
+        }
+        // MCT
+        0xFF74 => {
+            let z = r.u16()?;
+            ensure!(z == 0, UnsupportedFeature, "JPEG 2000 MCT arrays split over several segments");
+            let i = r.u16()?;
+            let _y = r.u16()?;
+            let (index, kind, element) = ((i & 0xFF) as u8, ((i >> 8) & 3) as u8, (i >> 10) & 3);
+            let bytes = r.rest();
+            let values = match element {
+                0 => bytes.chunks_exact(2).map(|b| f64::from(i16::from_be_bytes([b[0], b[1]]))).collect(),
+                1 => bytes.chunks_exact(4).map(|b| f64::from(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))).collect(),
+                2 => bytes.chunks_exact(4).map(|b| f64::from(f32::from_be_bytes([b[0], b[1], b[2], b[3]]))).collect(),
+                _ => bytes.chunks_exact(8).map(|b| f64::from_be_bytes(b.try_into().unwrap_or_default())).collect(),
+            };
+            coding.arrays.retain(|a| a.index != index);
+            coding.arrays.push(McArray { index, kind, values });
+        }
+        // MCC
+        0xFF75 => {
+            let z = r.u16()?;
+            ensure!(z == 0, UnsupportedFeature, "JPEG 2000 MCC stages split over several segments");
+            let index = r.u8()?;
+            let _y = r.u16()?;
+            let collections = r.u16()? as usize;
+            let mut stage = McStage { index, collections: Vec::with_capacity(collections.min(16)) };
+            for _ in 0..collections {
+                let kind = r.u8()? & 3;
+                ensure!(
+                    kind == 1,
+                    UnsupportedFeature,
+                    "JPEG 2000 multiple component transform type {kind}; only array-based decorrelation is supported"
+                );
+                let inputs = parse_component_list(r, count)?;
+                let outputs = parse_component_list(r, count)?;
+                let t = r.take(3)?;
+                stage.collections.push(McCollection { inputs, outputs, matrix: t[2], offset: t[1] });
+            }
+            coding.stages.retain(|s| s.index != index);
+            coding.stages.push(stage);
+        }
+        // MCO
+        0xFF77 => {
+            let n = r.u8()? as usize;
+            coding.stage_order = r.take(n)?.to_vec();
+        }
+        // CBD, NLT, ATK, DFS, ADS: Part 2 tools this decoder does not implement.
+        0xFF78 | 0xFF76 | 0xFF79 | 0xFF72 | 0xFF73 => {
+            return Err(dicom_err!(UnsupportedFeature, "JPEG 2000 Part 2 marker {marker:04X}"));
+        }
+        // CAP, TLM, PLM, PLT, CRG, COM and anything else: nothing to apply.
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_coding(r: &mut Reader, precincts: bool) -> Result<ComponentCoding> {
+    let levels = r.u8()?;
+    let (xcb, ycb) = (r.u8()? + 2, r.u8()? + 2);
+    let style = r.u8()?;
+    let transform = r.u8()?;
+    ensure!(levels <= 32, InvalidData, "JPEG 2000 with {levels} decomposition levels");
+    ensure!(xcb <= 10 && ycb <= 10 && xcb + ycb <= 12, InvalidData, "JPEG 2000 code-blocks of 2^{xcb} x 2^{ycb}");
+    ensure!(style & 0xC0 == 0, UnsupportedFeature, "JPEG 2000 code-block style {style:#04X}");
+    ensure!(transform <= 1, UnsupportedFeature, "JPEG 2000 wavelet transform {transform}");
+    let precincts = if precincts {
+        let sizes = r.take(levels as usize + 1)?;
+        sizes.iter().map(|&b| (b & 15, b >> 4)).collect()
+    } else {
+        vec![(15, 15); levels as usize + 1]
+    };
+    ensure!(
+        precincts.iter().skip(1).all(|&(x, y)| x > 0 && y > 0),
+        InvalidData,
+        "JPEG 2000 precincts of one sample above the lowest resolution"
+    );
+    Ok(ComponentCoding { levels, xcb, ycb, style, reversible: transform == 1, precincts })
+}
+
+fn parse_quantization(r: &mut Reader) -> Result<Quantization> {
+    let sq = r.u8()?;
+    let (style, guard_bits) = (sq & 0x1F, sq >> 5);
+    let steps: Vec<(u8, u16)> = match style {
+        0 => r.rest().iter().map(|&b| (b >> 3, 0)).collect(),
+        1 | 2 => r
+            .rest()
+            .chunks_exact(2)
+            .map(|b| {
+                let v = u16::from_be_bytes([b[0], b[1]]);
+                ((v >> 11) as u8, v & 0x7FF)
+            })
+            .collect(),
+        _ => return Err(dicom_err!(InvalidData, "JPEG 2000 quantization style {style}")),
+    };
+    ensure!(!steps.is_empty(), InvalidData, "JPEG 2000 quantization without step sizes");
+    Ok(Quantization { style, guard_bits, steps })
+}
+
+fn parse_component_list(r: &mut Reader, count: usize) -> Result<Vec<usize>> {
+    let n = r.u16()?;
+    let wide = n & 0x8000 != 0;
+    let mut out = Vec::with_capacity(((n & 0x7FFF) as usize).min(count));
+    for _ in 0..n & 0x7FFF {
+        let c = if wide { r.u16()? as usize } else { r.u8()? as usize };
+        ensure!(c < count, InvalidData, "JPEG 2000 transform names component {c}");
+        out.push(c);
+    }
+    Ok(out)
+}
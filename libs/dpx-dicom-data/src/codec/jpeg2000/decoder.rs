@@ -0,0 +1,334 @@
+//! Decoding of a whole codestream: each tile's packets, code-blocks and
+//! wavelet levels, then the component transform, DC level shift (Annex G)
+//! and clipping to each component's range.
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{dicom_err, ensure};
+
+use crate::codec::PixelInfo;
+
+use super::codestream::{self, ComponentSiz, McCollection, Siz, TileCoding};
+use super::dwt::{self, Plane, Sample};
+use super::tier1;
+use super::tier2::{Band, Orientation, TileComponent, read_packets};
+
+/// A reconstructed tile-component, before the component transform.
+enum Samples {
+    Int(Plane<i32>),
+    Float(Plane<f32>),
+}
+
+impl Samples {
+    fn to_float(&self) -> Vec<f32> {
+        match self {
+            Samples::Int(p) => p.data.iter().map(|&v| v as f32).collect(),
+            Samples::Float(p) => p.data.clone(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Samples::Int(p) => p.data.len(),
+            Samples::Float(p) => p.data.len(),
+        }
+    }
+}
+
+/// Decodes a JPEG 2000 codestream, optionally wrapped in a JP2 file, into
+/// interleaved samples, each component at full resolution. The image must be
+/// the frame `info` expects: the samples are allocated from its size.
+pub(super) fn decode(data: &[u8], info: &PixelInfo) -> Result<Vec<i32>> {
+    let codestream = codestream::parse(data)?;
+    let siz = &codestream.siz;
+    ensure!(
+        siz.components.iter().all(|c| c.dx == 1 && c.dy == 1),
+        UnsupportedFeature,
+        "subsampled JPEG 2000 components"
+    );
+    let (width, height) = ((siz.x1 - siz.x0) as usize, (siz.y1 - siz.y0) as usize);
+    let count = siz.components.len();
+    ensure!(
+        width == info.columns as usize && height == info.rows as usize,
+        InvalidData,
+        "JPEG 2000 frame is {width}x{height}, the data set says {}x{}",
+        info.columns,
+        info.rows
+    );
+    ensure!(
+        count == info.samples_per_pixel as usize,
+        InvalidData,
+        "JPEG 2000 frame has {count} components, the data set says {} samples per pixel",
+        info.samples_per_pixel
+    );
+    let precision = siz.components.iter().map(|c| c.precision).max().unwrap_or(0);
+    ensure!(
+        u16::from(precision) <= info.bits_allocated,
+        InvalidData,
+        "{precision}-bit JPEG 2000 samples do not fit Bits Allocated {}",
+        info.bits_allocated
+    );
+    ensure!(
+        width.checked_mul(height).and_then(|n| n.checked_mul(count)).is_some_and(|n| n <= 1 << 30),
+        UnsupportedFeature,
+        "JPEG 2000 image of {width}x{height}x{count} samples"
+    );
+    let mut samples = vec![0i32; width * height * count];
+    for (index, tile) in codestream.tiles.iter().enumerate() {
+        let tile = tile.as_ref().ok_or_else(|| dicom_err!(InvalidData, "JPEG 2000 tile {index} is missing"))?;
+        let rect = siz.tile_rect(index);
+        let planes = decode_tile(siz, &tile.coding, rect, &tile.data)?;
+        let (tw, th) = ((rect.2 - rect.0) as usize, (rect.3 - rect.1) as usize);
+        let (ox, oy) = ((rect.0 - siz.x0) as usize, (rect.1 - siz.y0) as usize);
+        for (c, plane) in planes.iter().enumerate() {
+            for y in 0..th {
+                for x in 0..tw {
+                    samples[((oy + y) * width + ox + x) * count + c] = plane[y * tw + x];
+                }
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// The final samples of each component of one tile.
+fn decode_tile(siz: &Siz, coding: &TileCoding, rect: (u32, u32, u32, u32), data: &[u8]) -> Result<Vec<Vec<i32>>> {
+    let mut tile = siz
+        .components
+        .iter()
+        .enumerate()
+        .map(|(c, comp)| TileComponent::new(rect, comp, coding, c))
+        .collect::<Result<Vec<_>>>()?;
+    read_packets(&mut tile, coding, rect, data)?;
+    let mut planes = tile
+        .iter()
+        .enumerate()
+        .map(|(c, tc)| reconstruct(tc, coding, siz.components[c], c))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Offsets a Part 2 transform adds take the place of the DC level shift.
+    let mut shifted = vec![false; planes.len()];
+    match coding.mct {
+        1 if planes.len() >= 3 => {
+            ensure!(
+                planes[..3].windows(2).all(|p| p[0].len() == p[1].len()),
+                InvalidData,
+                "JPEG 2000 component transform over components of different sizes"
+            );
+            inverse_rct_ict(&mut planes);
+        }
+        2 => shifted = inverse_part2(&mut planes, coding)?,
+        _ => {}
+    }
+
+    Ok(planes
+        .into_iter()
+        .zip(&siz.components)
+        .zip(shifted)
+        .map(|((plane, comp), shifted)| {
+            let shift = if comp.signed || shifted { 0 } else { 1i32 << (comp.precision - 1) };
+            let (min, max) = if comp.signed {
+                (-(1i32 << (comp.precision - 1)), (1i32 << (comp.precision - 1)) - 1)
+            } else {
+                (0, (1i32 << comp.precision) - 1)
+            };
+            match plane {
+                Samples::Int(p) => p.data.iter().map(|&v| (v + shift).clamp(min, max)).collect(),
+                Samples::Float(p) => p.data.iter().map(|&v| (v.round() as i32 + shift).clamp(min, max)).collect(),
+            }
+        })
+        .collect())
+}
+
+/// Decodes the code-blocks of a tile-component and inverts its wavelet
+/// transform.
+fn reconstruct(tc: &TileComponent, coding: &TileCoding, comp: ComponentSiz, c: usize) -> Result<Samples> {
+    let params = &coding.components[c];
+    let reversible = params.coding.reversible;
+    let mut bands_int = Vec::new();
+    let mut bands_float = Vec::new();
+    for (r, res) in tc.resolutions.iter().enumerate() {
+        for (k, band) in res.bands.iter().enumerate() {
+            let index = if r == 0 { 0 } else { 1 + 3 * (r - 1) + k };
+            let (exponent, mantissa) = params.quant.step(index, r)?;
+            let gain = match band.orientation {
+                Orientation::Ll => 0,
+                Orientation::Hl | Orientation::Lh => 1,
+                Orientation::Hh => 2,
+            };
+            // Magnitude bit-planes (E-2), and the step size (E-3).
+            let planes = (u32::from(params.quant.guard_bits) + u32::from(exponent)).saturating_sub(1);
+            let step = if reversible {
+                1.0
+            } else {
+                let range = i32::from(comp.precision) + gain - i32::from(exponent);
+                2f32.powi(range) * (1.0 + f32::from(mantissa) / 2048.0)
+            };
+            let coefficients = decode_band(band, params.coding.style, planes, params.roi_shift, !reversible)?;
+            if reversible {
+                bands_int.push(band_plane(band, coefficients.into_iter().map(|v| v as i32).collect()));
+            } else {
+                bands_float.push(band_plane(band, coefficients.into_iter().map(|v| v as f32 * step).collect()));
+            }
+        }
+    }
+    let rects: Vec<_> = tc.resolutions.iter().map(|r| (r.x0, r.y0, r.x1, r.y1)).collect();
+    Ok(if reversible {
+        Samples::Int(inverse_dwt(bands_int, &rects))
+    } else {
+        Samples::Float(inverse_dwt(bands_float, &rects))
+    })
+}
+
+fn band_plane<T>(band: &Band, data: Vec<T>) -> Plane<T> {
+    Plane { x0: band.x0, y0: band.y0, w: band.x1 - band.x0, h: band.y1 - band.y0, data }
+}
+
+/// The coefficients of one subband, as quantization indices with the
+/// reconstruction offset of E.1.1.2 (half the lowest unknown bit) applied
+/// to those of an irreversible transform or truncated code-blocks.
+fn decode_band(band: &Band, style: u8, planes: u32, roi_shift: u8, irreversible: bool) -> Result<Vec<f64>> {
+    let (w, h) = (band.x1 - band.x0, band.y1 - band.y0);
+    let mut out = vec![0f64; w * h];
+    for block in band.blocks() {
+        if block.segments.is_empty() {
+            continue;
+        }
+        let (bw, bh) = (block.x1 - block.x0, block.y1 - block.y0);
+        let planes = (planes + u32::from(roi_shift)).saturating_sub(block.zero_bitplanes);
+        let (values, known) = tier1::decode(bw, bh, band.orientation, style, planes, &block.segments)?;
+        let half = if known > 0 {
+            f64::from(1u32 << (known - 1))
+        } else if irreversible {
+            0.5
+        } else {
+            0.0
+        };
+        for (i, &v) in values.iter().enumerate() {
+            let mut m = v.unsigned_abs();
+            if m == 0 {
+                continue;
+            }
+            if roi_shift > 0 && m >> roi_shift > 0 {
+                m >>= roi_shift;
+            }
+            let magnitude = f64::from(m) + half;
+            let (x, y) = (block.x0 - band.x0 + i % bw, block.y0 - band.y0 + i / bw);
+            out[y * w + x] = if v < 0 { -magnitude } else { magnitude };
+        }
+    }
+    Ok(out)
+}
+
+/// Synthesizes the full-resolution tile-component from its subbands, given
+/// in codestream order, and the area of each resolution.
+fn inverse_dwt<T: Sample>(bands: Vec<Plane<T>>, rects: &[(usize, usize, usize, usize)]) -> Plane<T> {
+    let mut bands = bands.into_iter();
+    let mut image = bands.next().unwrap_or(Plane { x0: 0, y0: 0, w: 0, h: 0, data: Vec::new() });
+    for &rect in &rects[1..] {
+        let (Some(hl), Some(lh), Some(hh)) = (bands.next(), bands.next(), bands.next()) else { break };
+        image = dwt::synthesize(rect, &image, &hl, &lh, &hh);
+    }
+    image
+}
+
+/// The reversible (RCT) or irreversible (ICT) colour transform of G.2 and
+/// G.3, back from the first three components.
+fn inverse_rct_ict(planes: &mut [Samples]) {
+    if let [Samples::Int(y), Samples::Int(cb), Samples::Int(cr), ..] = planes {
+        for i in 0..y.data.len() {
+            let (y0, y1, y2) = (y.data[i], cb.data[i], cr.data[i]);
+            let g = y0 - ((y1 + y2) >> 2);
+            (y.data[i], cb.data[i], cr.data[i]) = (y2 + g, g, y1 + g);
+        }
+        return;
+    }
+    let (y, cb, cr) = (planes[0].to_float(), planes[1].to_float(), planes[2].to_float());
+    let rect = |p: &Samples| match p {
+        Samples::Int(p) => (p.x0, p.y0, p.w, p.h),
+        Samples::Float(p) => (p.x0, p.y0, p.w, p.h),
+    };
+    let (x0, y0, w, h) = rect(&planes[0]);
+    let channel =
+        |f: &dyn Fn(usize) -> f32| Samples::Float(Plane { x0, y0, w, h, data: (0..y.len()).map(f).collect() });
+    let r = channel(&|i| y[i] + 1.402 * cr[i]);
+    let g = channel(&|i| y[i] - 0.344_13 * cb[i] - 0.714_14 * cr[i]);
+    let b = channel(&|i| y[i] + 1.772 * cb[i]);
+    (planes[0], planes[1], planes[2]) = (r, g, b);
+}
+
+/// Applies the Part 2 transform stages named by MCO, in order. Returns which
+/// components received an offset.
+fn inverse_part2(planes: &mut [Samples], coding: &TileCoding) -> Result<Vec<bool>> {
+    let mut shifted = vec![false; planes.len()];
+    for index in &coding.stage_order {
+        let stage = coding
+            .stages
+            .iter()
+            .find(|s| s.index == *index)
+            .ok_or_else(|| dicom_err!(InvalidData, "JPEG 2000 MCO names undefined stage {index}"))?;
+        for collection in &stage.collections {
+            apply_collection(planes, coding, collection, &mut shifted)?;
+        }
+    }
+    Ok(shifted)
+}
+
+// ponytail: reversible array-based transforms are applied in floating point
+// and rounded, not with the integer rounding of T.801 J.3.
+fn apply_collection(
+    planes: &mut [Samples],
+    coding: &TileCoding,
+    collection: &McCollection,
+    shifted: &mut [bool],
+) -> Result<()> {
+    let array = |index: u8, kind: u8| {
+        coding
+            .arrays
+            .iter()
+            .find(|a| a.index == index)
+            .filter(|a| a.kind == kind)
+            .ok_or_else(|| dicom_err!(InvalidData, "JPEG 2000 MCC names undefined array {index}"))
+    };
+    let (n_in, n_out) = (collection.inputs.len(), collection.outputs.len());
+    let len = collection.inputs.first().map_or(0, |&c| planes[c].len());
+    ensure!(
+        collection.inputs.iter().chain(&collection.outputs).all(|&c| planes[c].len() == len),
+        InvalidData,
+        "JPEG 2000 component transform over components of different sizes"
+    );
+    let inputs: Vec<Vec<f32>> = collection.inputs.iter().map(|&c| planes[c].to_float()).collect();
+    let matrix = match collection.matrix {
+        0 => None,
+        i => {
+            let m = array(i, 1)?;
+            ensure!(m.values.len() >= n_in * n_out, InvalidData, "JPEG 2000 decorrelation array {i} is too small");
+            Some(&m.values)
+        }
+    };
+    let offsets = match collection.offset {
+        0 => None,
+        i => {
+            let o = array(i, 2)?;
+            ensure!(o.values.len() >= n_out, InvalidData, "JPEG 2000 offset array {i} is too small");
+            Some(&o.values)
+        }
+    };
+    let (x0, y0, w, h) = match &planes[collection.outputs.first().copied().unwrap_or(0)] {
+        Samples::Int(p) => (p.x0, p.y0, p.w, p.h),
+        Samples::Float(p) => (p.x0, p.y0, p.w, p.h),
+    };
+    for (o, &c) in collection.outputs.iter().enumerate() {
+        let data = (0..len)
+            .map(|i| {
+                let value = match matrix {
+                    Some(m) => (0..n_in).map(|j| m[o * n_in + j] * f64::from(inputs[j][i])).sum(),
+                    None => inputs.get(o).map_or(0.0, |v| f64::from(v[i])),
+                };
+                (value + offsets.map_or(0.0, |v| v[o])) as f32
+            })
+            .collect();
+        planes[c] = Samples::Float(Plane { x0, y0, w, h, data });
+        shifted[c] |= offsets.is_some();
+    }
+    Ok(())
+}
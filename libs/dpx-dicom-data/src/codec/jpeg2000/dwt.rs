@@ -0,0 +1,215 @@
+//! Inverse discrete wavelet transforms (T.800 Annex F): the reversible 5/3
+//! filter on integers and the irreversible 9/7 filter on floats, by lifting
+//! with whole-sample symmetric extension. Each level interleaves four
+//! subbands into the next resolution, then filters rows, then columns.
+
+/// A rectangle of samples with its origin, whose parity decides which
+/// positions hold low-pass samples.
+pub(super) struct Plane<T> {
+    pub x0: usize,
+    pub y0: usize,
+    pub w: usize,
+    pub h: usize,
+    pub data: Vec<T>,
+}
+
+/// The samples of one dimension of a wavelet transform.
+pub(super) trait Sample: Copy + Default {
+    /// Inverse-filters `line`, whose first sample sits at an odd position
+    /// when `odd`.
+    fn synthesize(line: &mut [Self], odd: bool);
+}
+
+/// Mirrors `i` into `0..n` without repeating the edge sample (F-4).
+fn mirror(i: isize, n: usize) -> usize {
+    let n = n as isize;
+    let period = 2 * (n - 1);
+    let mut i = i.rem_euclid(period.max(1));
+    if i >= n {
+        i = period - i;
+    }
+    i as usize
+}
+
+/// Applies `update(neighbour_sum)` to every sample of parity `parity`
+/// (relative to the absolute position), as one lifting step.
+fn lift<T: Copy>(line: &mut [T], odd: bool, parity: usize, mut update: impl FnMut(T, T, T) -> T) {
+    let n = line.len();
+    let first = (parity + usize::from(odd)) % 2;
+    for i in (first..n).step_by(2) {
+        let left = line[mirror(i as isize - 1, n)];
+        let right = line[mirror(i as isize + 1, n)];
+        line[i] = update(line[i], left, right);
+    }
+}
+
+impl Sample for i32 {
+    fn synthesize(line: &mut [i32], odd: bool) {
+        if line.len() == 1 {
+            if odd {
+                line[0] /= 2;
+            }
+            return;
+        }
+        lift(line, odd, 0, |x, l, r| x - ((l + r + 2) >> 2));
+        lift(line, odd, 1, |x, l, r| x + ((l + r) >> 1));
+    }
+}
+
+const ALPHA: f32 = -1.586_134_3;
+const BETA: f32 = -0.052_980_117;
+const GAMMA: f32 = 0.882_911_1;
+const DELTA: f32 = 0.443_506_87;
+const K: f32 = 1.230_174_1;
+
+impl Sample for f32 {
+    fn synthesize(line: &mut [f32], odd: bool) {
+        if line.len() == 1 {
+            if odd {
+                line[0] /= 2.0;
+            }
+            return;
+        }
+        for (i, v) in line.iter_mut().enumerate() {
+            *v *= if (i + usize::from(odd)) % 2 == 0 { K } else { 1.0 / K };
+        }
+        lift(line, odd, 0, |x, l, r| x - DELTA * (l + r));
+        lift(line, odd, 1, |x, l, r| x - GAMMA * (l + r));
+        lift(line, odd, 0, |x, l, r| x - BETA * (l + r));
+        lift(line, odd, 1, |x, l, r| x - ALPHA * (l + r));
+    }
+}
+
+/// Reassembles the next resolution, covering `x0..x1` by `y0..y1`, from its
+/// lower resolution `ll` and the `hl`, `lh` and `hh` subbands.
+pub(super) fn synthesize<T: Sample>(
+    rect: (usize, usize, usize, usize),
+    ll: &Plane<T>,
+    hl: &Plane<T>,
+    lh: &Plane<T>,
+    hh: &Plane<T>,
+) -> Plane<T> {
+    let (x0, y0, x1, y1) = rect;
+    let (w, h) = (x1 - x0, y1 - y0);
+    let mut data = vec![T::default(); w * h];
+    let at = |band: &Plane<T>, u: usize, v: usize| match (u.checked_sub(band.x0), v.checked_sub(band.y0)) {
+        (Some(u), Some(v)) if u < band.w && v < band.h => band.data[v * band.w + u],
+        _ => T::default(),
+    };
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let band = match (x % 2, y % 2) {
+                (0, 0) => ll,
+                (1, 0) => hl,
+                (0, _) => lh,
+                _ => hh,
+            };
+            data[(y - y0) * w + x - x0] = at(band, x / 2, y / 2);
+        }
+    }
+    if w > 0 && h > 0 {
+        for row in data.chunks_exact_mut(w) {
+            T::synthesize(row, x0 % 2 == 1);
+        }
+        let mut column = vec![T::default(); h];
+        for x in 0..w {
+            for (y, v) in column.iter_mut().enumerate() {
+                *v = data[y * w + x];
+            }
+            T::synthesize(&mut column, y0 % 2 == 1);
+            for (y, &v) in column.iter().enumerate() {
+                data[y * w + x] = v;
+            }
+        }
+    }
+    Plane { x0, y0, w, h, data }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    pub(crate) fn analyze_i32(line: &mut [i32], odd: bool) {
+        if line.len() == 1 {
+            if odd {
+                line[0] *= 2;
+            }
+            return;
+        }
+        lift(line, odd, 1, |x, l, r| x - ((l + r) >> 1));
+        lift(line, odd, 0, |x, l, r| x + ((l + r + 2) >> 2));
+    }
+
+    pub(crate) fn analyze_f32(line: &mut [f32], odd: bool) {
+        if line.len() == 1 {
+            if odd {
+                line[0] *= 2.0;
+            }
+            return;
+        }
+        lift(line, odd, 1, |x, l, r| x + ALPHA * (l + r));
+        lift(line, odd, 0, |x, l, r| x + BETA * (l + r));
+        lift(line, odd, 1, |x, l, r| x + GAMMA * (l + r));
+        lift(line, odd, 0, |x, l, r| x + DELTA * (l + r));
+        for (i, v) in line.iter_mut().enumerate() {
+            *v /= if (i + usize::from(odd)) % 2 == 0 { K } else { 1.0 / K };
+        }
+    }
+
+    /// One level of the forward transform: columns, then rows, then split
+    /// into LL, HL, LH and HH by absolute parity.
+    pub(crate) fn analyze<T: Sample>(plane: &Plane<T>, filter: fn(&mut [T], bool)) -> [Plane<T>; 4] {
+        let (x0, y0, w, h) = (plane.x0, plane.y0, plane.w, plane.h);
+        let mut data = plane.data.clone();
+        for x in 0..w {
+            let mut column: Vec<T> = (0..h).map(|y| data[y * w + x]).collect();
+            filter(&mut column, y0 % 2 == 1);
+            for (y, v) in column.into_iter().enumerate() {
+                data[y * w + x] = v;
+            }
+        }
+        for row in data.chunks_exact_mut(w) {
+            filter(row, x0 % 2 == 1);
+        }
+        let band = |px: usize, py: usize| {
+            let (bx0, bx1) = ((x0 + 1 - px) / 2, (x0 + w + 1 - px) / 2);
+            let (by0, by1) = ((y0 + 1 - py) / 2, (y0 + h + 1 - py) / 2);
+            let data = (by0..by1)
+                .flat_map(|v| (bx0..bx1).map(move |u| (2 * u + px, 2 * v + py)))
+                .map(|(x, y)| data[(y - y0) * w + x - x0])
+                .collect();
+            Plane { x0: bx0, y0: by0, w: bx1 - bx0, h: by1 - by0, data }
+        };
+        [band(0, 0), band(1, 0), band(0, 1), band(1, 1)]
+    }
+
+    fn round_trip<T: Sample>(
+        rect: (usize, usize, usize, usize),
+        value: fn(usize) -> T,
+        filter: fn(&mut [T], bool),
+    ) -> (Vec<T>, Vec<T>) {
+        let (x0, y0, x1, y1) = rect;
+        let (w, h) = (x1 - x0, y1 - y0);
+        let plane = Plane { x0, y0, w, h, data: (0..w * h).map(value).collect() };
+        let [ll, hl, lh, hh] = analyze(&plane, filter);
+        (plane.data, synthesize(rect, &ll, &hl, &lh, &hh).data)
+    }
+
+    #[test]
+    fn reversible_round_trip() {
+        for rect in [(0, 0, 8, 6), (3, 2, 12, 9), (5, 1, 6, 4), (2, 7, 9, 8)] {
+            let (original, restored) = round_trip(rect, |i| (i * 73 % 251) as i32 - 128, analyze_i32);
+            assert_eq!(restored, original, "{rect:?}");
+        }
+    }
+
+    #[test]
+    fn irreversible_round_trip() {
+        for rect in [(0, 0, 8, 6), (3, 2, 12, 9), (5, 1, 6, 4)] {
+            let (original, restored) = round_trip(rect, |i| (i * 73 % 251) as f32 - 128.0, analyze_f32);
+            for (a, b) in original.iter().zip(&restored) {
+                assert!((a - b).abs() < 1e-3, "{rect:?}: {a} vs {b}");
+            }
+        }
+    }
+}
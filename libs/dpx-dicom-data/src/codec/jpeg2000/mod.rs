@@ -0,0 +1,512 @@
+//! JPEG 2000 (ITU-T T.800 / ISO 15444-1, PS3.5 Section 8.2.4), and the Part 2
+//! multi-component transform syntaxes (ISO 15444-2, PS3.5 Section 8.2.5),
+//! decoded in-tree: the [`codestream`] is split into tiles, [`tier2`] reads
+//! the packets, [`tier1`] decodes each code-block and [`dwt`] inverts the
+//! wavelet transform, all driven by [`decoder`].
+//!
+//! Signed and unsigned components up to 16 bits are supported, with any
+//! tiling, progression order, precincts and code-block style. Subsampled
+//! components, packed packet headers, and the Part 2 extensions other than
+//! array-based component decorrelation are rejected. A colour frame coded
+//! with the RCT or ICT comes out as RGB; frames are always interleaved.
+
+mod codestream;
+mod decoder;
+mod dwt;
+mod tier1;
+mod tier2;
+
+use dpx_dicom_core::TransferSyntax;
+use dpx_dicom_core::error::Result;
+
+use super::{CodecEntry, PixelCodec, PixelInfo};
+
+/// JPEG 2000 and JPEG 2000 Part 2: decode only.
+struct Jpeg2000;
+
+impl PixelCodec for Jpeg2000 {
+    fn decode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        let samples = decoder::decode(frame, info)?;
+        Ok(match info.bytes_per_sample() {
+            1 => samples.iter().map(|&s| s as u8).collect(),
+            _ => samples.iter().flat_map(|&s| (s as u16).to_le_bytes()).collect(),
+        })
+    }
+
+    /// Decoded frames are interleaved, and those coded with a component
+    /// transform are back in RGB.
+    fn decoded_info(&self, info: &PixelInfo) -> PixelInfo {
+        let mut out = info.clone();
+        if matches!(out.photometric_interpretation.as_str(), "YBR_RCT" | "YBR_ICT") {
+            out.photometric_interpretation = "RGB".to_string();
+        }
+        out.planar_configuration = 0;
+        out
+    }
+}
+
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEG2000Lossless, codec: &Jpeg2000 } }
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEG2000, codec: &Jpeg2000 } }
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEG2000MCLossless, codec: &Jpeg2000 } }
+inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEG2000MC, codec: &Jpeg2000 } }
+
+#[cfg(test)]
+mod tests {
+    use super::dwt::Plane;
+    use super::dwt::tests::{analyze, analyze_f32, analyze_i32};
+    use super::tier1::tests::encode_block;
+    use super::tier2::{Orientation, Segment};
+    use super::*;
+    use crate::codec::find_codec;
+    use crate::testing::pixel_info;
+
+    /// Packet header bits, with the 0xFF stuffing of B.10.1.
+    #[derive(Default)]
+    struct HeaderWriter {
+        out: Vec<u8>,
+        acc: u8,
+        count: u8,
+    }
+
+    impl HeaderWriter {
+        fn capacity(&self) -> u8 {
+            if self.out.last() == Some(&0xFF) { 7 } else { 8 }
+        }
+
+        fn bit(&mut self, bit: u32) {
+            self.acc = self.acc << 1 | bit as u8;
+            self.count += 1;
+            if self.count == self.capacity() {
+                self.out.push(self.acc);
+                (self.acc, self.count) = (0, 0);
+            }
+        }
+
+        fn bits(&mut self, value: u32, n: u32) {
+            for i in (0..n).rev() {
+                self.bit(value >> i & 1);
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            if self.count > 0 {
+                self.acc <<= self.capacity() - self.count;
+                self.out.push(self.acc);
+            }
+            if self.out.last() == Some(&0xFF) {
+                self.out.push(0);
+            }
+            self.out
+        }
+    }
+
+    /// A tag tree node: its value, the lowest value not yet ruled out, and
+    /// whether it has been signalled.
+    type Node = (u32, u32, bool);
+
+    /// A tag tree encoder (B.10.2) over one band's code-blocks.
+    struct TagTree {
+        /// Width and nodes of each level, leaves first.
+        levels: Vec<(usize, Vec<Node>)>,
+    }
+
+    impl TagTree {
+        fn new(mut w: usize, mut h: usize, values: Vec<u32>) -> Self {
+            let mut levels = vec![(w, values.into_iter().map(|v| (v, 0, false)).collect::<Vec<_>>())];
+            while w > 1 || h > 1 {
+                let (pw, ph) = (w.div_ceil(2), h.div_ceil(2));
+                let below = &levels[levels.len() - 1].1;
+                let nodes = (0..pw * ph)
+                    .map(|i| {
+                        let (x, y) = (i % pw * 2, i / pw * 2);
+                        let children = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+                        let value = children.iter().filter(|&&(x, y)| x < w && y < h).map(|&(x, y)| below[y * w + x].0);
+                        (value.min().unwrap_or(0), 0, false)
+                    })
+                    .collect();
+                levels.push((pw, nodes));
+                (w, h) = (pw, ph);
+            }
+            TagTree { levels }
+        }
+
+        fn encode(&mut self, out: &mut HeaderWriter, x: usize, y: usize, threshold: u32) {
+            let mut low = 0;
+            for (level, (w, nodes)) in self.levels.iter_mut().enumerate().rev() {
+                let (value, node_low, known) = &mut nodes[(y >> level) * *w + (x >> level)];
+                low = low.max(*node_low);
+                while low < threshold {
+                    if low >= *value {
+                        if !*known {
+                            out.bit(1);
+                            *known = true;
+                        }
+                        break;
+                    }
+                    out.bit(0);
+                    low += 1;
+                }
+                *node_low = low;
+            }
+        }
+    }
+
+    /// A subband of quantization indices, at `x0, y0` in its own
+    /// coordinates.
+    struct Subband {
+        orientation: Orientation,
+        x0: usize,
+        y0: usize,
+        w: usize,
+        h: usize,
+        values: Vec<i32>,
+        exponent: u8,
+    }
+
+    /// The code-blocks of 2^`cb` squared covering a band, in raster order,
+    /// and how many there are across.
+    fn code_blocks(band: &Subband, cb: u8) -> (usize, Vec<Option<(u32, Segment)>>) {
+        let size = 1 << cb;
+        let (bx0, by0) = (band.x0 / size, band.y0 / size);
+        let (bx1, by1) = ((band.x0 + band.w).div_ceil(size), (band.y0 + band.h).div_ceil(size));
+        let mut blocks = Vec::new();
+        for by in by0..by1 {
+            for bx in bx0..bx1 {
+                let (x0, y0) = ((bx * size).max(band.x0), (by * size).max(band.y0));
+                let (x1, y1) = (((bx + 1) * size).min(band.x0 + band.w), ((by + 1) * size).min(band.y0 + band.h));
+                let values: Vec<i32> = (y0..y1)
+                    .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                    .map(|(x, y)| band.values[(y - band.y0) * band.w + x - band.x0])
+                    .collect();
+                let max = values.iter().map(|v| v.unsigned_abs()).max().unwrap_or(0);
+                let planes = 32 - max.leading_zeros();
+                // Mb of E-2, with two guard bits.
+                let zero = u32::from(band.exponent) + 1 - planes;
+                blocks
+                    .push((max > 0).then(|| (zero, encode_block(&values, x1 - x0, y1 - y0, band.orientation, planes))));
+            }
+        }
+        (bx1 - bx0, blocks)
+    }
+
+    /// The only layer's packet for a precinct holding the whole of `bands`.
+    fn packet(bands: &[Subband], cb: u8) -> Vec<u8> {
+        let mut header = HeaderWriter::default();
+        let mut body = Vec::new();
+        let bands: Vec<_> = bands.iter().filter(|b| b.w * b.h > 0).map(|b| (b, code_blocks(b, cb))).collect();
+        if bands.iter().all(|(_, (_, blocks))| blocks.iter().all(Option::is_none)) {
+            header.bit(0);
+            return header.finish();
+        }
+        header.bit(1);
+        for (band, (across, blocks)) in bands {
+            let down = blocks.len() / across;
+            let mut inclusion = TagTree::new(across, down, blocks.iter().map(|b| u32::from(b.is_none())).collect());
+            let zeros = blocks.iter().map(|b| b.as_ref().map_or(u32::from(band.exponent) + 1, |b| b.0)).collect();
+            let mut zero_bitplanes = TagTree::new(across, down, zeros);
+            for (k, block) in blocks.into_iter().enumerate() {
+                let (x, y) = (k % across, k / across);
+                inclusion.encode(&mut header, x, y, 1);
+                let Some((zero, segment)) = block else { continue };
+                zero_bitplanes.encode(&mut header, x, y, zero + 1);
+                match segment.passes {
+                    1 => header.bit(0),
+                    2 => header.bits(0b10, 2),
+                    n @ 3..=5 => header.bits(0b1100 | (n - 3), 4),
+                    n @ 6..=36 => header.bits(0b1111 << 5 | (n - 6), 9),
+                    n => header.bits(0x1FF << 7 | (n - 37), 16),
+                }
+                let (len, extra) = (segment.data.len() as u32, segment.passes.ilog2());
+                let mut lblock = 3;
+                while len >> (lblock + extra) != 0 {
+                    header.bit(1);
+                    lblock += 1;
+                }
+                header.bit(0);
+                header.bits(len, lblock + extra);
+                body.extend(segment.data);
+            }
+        }
+        let mut out = header.finish();
+        out.extend(body);
+        out
+    }
+
+    fn subband<T>(plane: Plane<T>, orientation: Orientation, exponent: u8, quantize: impl Fn(T) -> i32) -> Subband {
+        let values = plane.data.into_iter().map(quantize).collect();
+        Subband { orientation, x0: plane.x0, y0: plane.y0, w: plane.w, h: plane.h, values, exponent }
+    }
+
+    /// The subbands of `levels` decompositions, LL first; `exponent` gives
+    /// each one's step exponent from its gain.
+    fn decompose<T: dwt::Sample>(
+        mut plane: Plane<T>,
+        levels: u8,
+        filter: fn(&mut [T], bool),
+        exponent: impl Fn(u8) -> u8,
+        quantize: impl Fn(T) -> i32 + Copy,
+    ) -> Vec<Subband> {
+        let mut details = Vec::new();
+        for _ in 0..levels {
+            let [ll, hl, lh, hh] = analyze(&plane, filter);
+            details.push([hl, lh, hh]);
+            plane = ll;
+        }
+        let mut out = vec![subband(plane, Orientation::Ll, exponent(0), quantize)];
+        for [hl, lh, hh] in details.into_iter().rev() {
+            out.push(subband(hl, Orientation::Hl, exponent(1), quantize));
+            out.push(subband(lh, Orientation::Lh, exponent(1), quantize));
+            out.push(subband(hh, Orientation::Hh, exponent(2), quantize));
+        }
+        out
+    }
+
+    /// Lossless subbands of a tile-component already level-shifted and
+    /// transformed.
+    fn reversible(plane: Plane<i32>, levels: u8, precision: u8) -> Vec<Subband> {
+        decompose(plane, levels, analyze_i32, |gain| precision + gain + 1, |v| v)
+    }
+
+    /// Subbands quantized with a step of 1/4 (an exponent two above the
+    /// nominal range, and no mantissa).
+    fn irreversible(plane: Plane<f32>, levels: u8, precision: u8) -> Vec<Subband> {
+        decompose(plane, levels, analyze_f32, |gain| precision + gain + 2, |v| (v * 4.0) as i32)
+    }
+
+    fn plane<T>(w: usize, h: usize, data: Vec<T>) -> Plane<T> {
+        Plane { x0: 0, y0: 0, w, h, data }
+    }
+
+    /// The coding of a single-layer LRCP test codestream.
+    struct Layout<'a> {
+        w: usize,
+        h: usize,
+        /// Tile width and height.
+        tile: usize,
+        /// Precision and signedness of each component.
+        components: &'a [(u8, bool)],
+        levels: u8,
+        /// Code-block width and height exponent.
+        cb: u8,
+        mct: u8,
+        reversible: bool,
+        /// Further marker segments for the main header.
+        extra: &'a [u8],
+    }
+
+    impl Layout<'_> {
+        fn new(w: usize, h: usize, components: &[(u8, bool)], levels: u8) -> Layout<'_> {
+            Layout { w, h, tile: w.max(h), components, levels, cb: 6, mct: 0, reversible: true, extra: &[] }
+        }
+
+        /// The codestream of `tiles`, each holding every component's subbands.
+        fn codestream(&self, tiles: &[Vec<Vec<Subband>>]) -> Vec<u8> {
+            let mut out = vec![0xFF, 0x4F, 0xFF, 0x51];
+            out.extend((38 + 3 * self.components.len() as u16).to_be_bytes());
+            out.extend([0, 0]);
+            let (w, h, tile) = (self.w as u32, self.h as u32, self.tile as u32);
+            for v in [w, h, 0, 0, tile, tile, 0, 0] {
+                out.extend(v.to_be_bytes());
+            }
+            out.extend((self.components.len() as u16).to_be_bytes());
+            for &(precision, signed) in self.components {
+                out.extend([u8::from(signed) << 7 | (precision - 1), 1, 1]);
+            }
+            let cb = self.cb - 2;
+            out.extend([0xFF, 0x52, 0, 12, 0, 0, 0, 1, self.mct, self.levels, cb, cb, 0, u8::from(self.reversible)]);
+            let steps: Vec<u8> = if self.reversible {
+                tiles[0][0].iter().map(|b| b.exponent << 3).collect()
+            } else {
+                tiles[0][0].iter().flat_map(|b| (u16::from(b.exponent) << 11).to_be_bytes()).collect()
+            };
+            out.extend([0xFF, 0x5C]);
+            out.extend((3 + steps.len() as u16).to_be_bytes());
+            out.push(2 << 5 | if self.reversible { 0 } else { 2 });
+            out.extend(steps);
+            out.extend(self.extra);
+            for (index, bands) in tiles.iter().enumerate() {
+                let mut data = Vec::new();
+                for r in 0..=self.levels as usize {
+                    let range = if r == 0 { 0..1 } else { 3 * r - 2..3 * r + 1 };
+                    for component in bands {
+                        // An empty resolution has no precincts, so no packet.
+                        let bands = &component[range.clone()];
+                        if bands.iter().any(|b| b.w * b.h > 0) {
+                            data.extend(packet(bands, self.cb));
+                        }
+                    }
+                }
+                out.extend([0xFF, 0x90, 0, 10]);
+                out.extend((index as u16).to_be_bytes());
+                out.extend((14 + data.len() as u32).to_be_bytes());
+                out.extend([0, 1, 0xFF, 0x93]);
+                out.extend(data);
+            }
+            out.extend([0xFF, 0xD9]);
+            out
+        }
+    }
+
+    fn gradient(w: usize, h: usize, seed: usize) -> Vec<i32> {
+        (0..w * h).map(|i| ((i % w) * 9 + (i / w) * 5 + (i * seed) % 23) as i32 % 256).collect()
+    }
+
+    #[test]
+    fn lossless_gray_round_trip() {
+        let (w, h) = (13, 9);
+        let pixels = gradient(w, h, 7);
+        let bands = reversible(plane(w, h, pixels.iter().map(|p| p - 128).collect()), 2, 8);
+        let data = Layout::new(w, h, &[(8, false)], 2).codestream(&[vec![bands]]);
+        let codec = find_codec(&TransferSyntax::JPEG2000Lossless).expect("codec");
+        let decoded = codec.decode_frame(&data, &pixel_info(h as u16, w as u16, 1, 8, "MONOCHROME2")).unwrap();
+        assert_eq!(decoded, pixels.iter().map(|&p| p as u8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn lossless_rct_decodes_to_rgb() {
+        let (w, h) = (10, 7);
+        let (r, g, b) = (gradient(w, h, 3), gradient(w, h, 11), gradient(w, h, 17));
+        let shift = |v: &Vec<i32>| v.iter().map(|p| p - 128).collect::<Vec<_>>();
+        let (rs, gs, bs) = (shift(&r), shift(&g), shift(&b));
+        let y0 = (0..w * h).map(|i| (rs[i] + 2 * gs[i] + bs[i]) >> 2).collect();
+        let y1 = (0..w * h).map(|i| bs[i] - gs[i]).collect();
+        let y2 = (0..w * h).map(|i| rs[i] - gs[i]).collect();
+        // One QCD serves all three, so every component gets the chroma range.
+        let bands = [y0, y1, y2].map(|c| reversible(plane(w, h, c), 1, 9)).into();
+        let data = Layout { mct: 1, ..Layout::new(w, h, &[(8, false); 3], 1) }.codestream(&[bands]);
+        let codec = find_codec(&TransferSyntax::JPEG2000Lossless).expect("codec");
+        let frame_info = pixel_info(h as u16, w as u16, 3, 8, "YBR_RCT");
+        assert_eq!(codec.decoded_info(&frame_info).photometric_interpretation, "RGB");
+        let decoded = codec.decode_frame(&data, &frame_info).unwrap();
+        let expected: Vec<u8> = (0..w * h).flat_map(|i| [r[i] as u8, g[i] as u8, b[i] as u8]).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn signed_12_bit_round_trip() {
+        let (w, h) = (16, 11);
+        let pixels: Vec<i32> = (0..w * h).map(|i| ((i * 997) % 4096) as i32 - 2048).collect();
+        let bands = reversible(plane(w, h, pixels.clone()), 3, 12);
+        let data = Layout::new(w, h, &[(12, true)], 3).codestream(&[vec![bands]]);
+        let codec = find_codec(&TransferSyntax::JPEG2000Lossless).expect("codec");
+        let mut frame_info = pixel_info(h as u16, w as u16, 1, 16, "MONOCHROME2");
+        (frame_info.bits_stored, frame_info.high_bit, frame_info.pixel_representation) = (12, 11, 1);
+        let decoded = codec.decode_frame(&data, &frame_info).unwrap();
+        let expected: Vec<u8> = pixels.iter().flat_map(|&p| (p as i16).to_le_bytes()).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn irreversible_ict_is_close() {
+        let (w, h) = (12, 10);
+        let (r, g, b) = (gradient(w, h, 5), gradient(w, h, 13), gradient(w, h, 19));
+        let (rf, gf, bf): (Vec<f32>, Vec<f32>, Vec<f32>) = (
+            r.iter().map(|&v| v as f32 - 128.0).collect(),
+            g.iter().map(|&v| v as f32 - 128.0).collect(),
+            b.iter().map(|&v| v as f32 - 128.0).collect(),
+        );
+        let y = (0..w * h).map(|i| 0.299 * rf[i] + 0.587 * gf[i] + 0.114 * bf[i]).collect();
+        let cb = (0..w * h).map(|i| -0.168_75 * rf[i] - 0.331_26 * gf[i] + 0.5 * bf[i]).collect();
+        let cr = (0..w * h).map(|i| 0.5 * rf[i] - 0.418_69 * gf[i] - 0.081_31 * bf[i]).collect();
+        let bands = [y, cb, cr].map(|c| irreversible(plane(w, h, c), 2, 8)).into();
+        let layout = Layout { mct: 1, reversible: false, ..Layout::new(w, h, &[(8, false); 3], 2) };
+        let data = layout.codestream(&[bands]);
+        let codec = find_codec(&TransferSyntax::JPEG2000).expect("codec");
+        let decoded = codec.decode_frame(&data, &pixel_info(h as u16, w as u16, 3, 8, "YBR_ICT")).unwrap();
+        let expected = (0..w * h).flat_map(|i| [r[i], g[i], b[i]]);
+        for (i, (got, want)) in decoded.iter().zip(expected).enumerate() {
+            assert!((i32::from(*got) - want).abs() <= 2, "sample {i}: {got} vs {want}");
+        }
+    }
+
+    #[test]
+    fn part2_decorrelation_with_offsets() {
+        let (w, h) = (8, 6);
+        // Coded as the first component and differences from it; the offsets
+        // replace the level shift.
+        let base = gradient(w, h, 7);
+        let coded = [
+            base.iter().map(|p| p - 128).collect::<Vec<_>>(),
+            (0..w * h).map(|i| (i % 5) as i32 - 2).collect(),
+            (0..w * h).map(|i| 3 - (i % 7) as i32).collect(),
+        ];
+        let mut extra = Vec::new();
+        // MCT: decorrelation array 1 and offset array 2, 16-bit integers.
+        for (imct, values) in [(0x0101u16, [1i16, 0, 0, 1, 1, 0, 1, 0, 1].as_slice()), (0x0202, &[128, 128, 128])] {
+            extra.extend([0xFF, 0x74]);
+            extra.extend((8 + 2 * values.len() as u16).to_be_bytes());
+            extra.extend([0, 0]);
+            extra.extend(imct.to_be_bytes());
+            extra.extend([0, 0]);
+            extra.extend(values.iter().flat_map(|v| v.to_be_bytes()));
+        }
+        // MCC stage 1: one decorrelation collection over all components.
+        extra.extend([0xFF, 0x75, 0, 23, 0, 0, 1, 0, 0, 0, 1, 1, 0, 3, 0, 1, 2, 0, 3, 0, 1, 2, 1, 2, 1]);
+        // MCO: stage 1.
+        extra.extend([0xFF, 0x77, 0, 4, 1, 1]);
+        let bands = coded.iter().map(|c| reversible(plane(w, h, c.clone()), 1, 9)).collect();
+        let data = Layout { mct: 2, extra: &extra, ..Layout::new(w, h, &[(8, false); 3], 1) }.codestream(&[bands]);
+        let codec = find_codec(&TransferSyntax::JPEG2000MCLossless).expect("codec");
+        let decoded = codec.decode_frame(&data, &pixel_info(h as u16, w as u16, 3, 8, "RGB")).unwrap();
+        let expected: Vec<u8> = (0..w * h)
+            .flat_map(|i| {
+                let c0 = coded[0][i];
+                [c0, c0 + coded[1][i], c0 + coded[2][i]].map(|v| (v + 128).clamp(0, 255) as u8)
+            })
+            .collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn empty_code_blocks_decode_to_the_level_shift() {
+        let (w, h) = (5, 4);
+        let bands = reversible(plane(w, h, vec![0; w * h]), 1, 8);
+        let data = Layout::new(w, h, &[(8, false)], 1).codestream(&[vec![bands]]);
+        let codec = find_codec(&TransferSyntax::JPEG2000Lossless).expect("codec");
+        let decoded = codec.decode_frame(&data, &pixel_info(h as u16, w as u16, 1, 8, "MONOCHROME2")).unwrap();
+        assert_eq!(decoded, vec![128; w * h]);
+    }
+
+    #[test]
+    fn tiles_and_code_blocks() {
+        // Tiles of 11 put odd origins on the wavelet grid, and code-blocks
+        // of 4x4 give each band several, with tag trees of a few levels.
+        let (w, h, tile) = (29, 24, 11);
+        let pixels = gradient(w, h, 5);
+        let layout = Layout { tile, cb: 2, ..Layout::new(w, h, &[(8, false)], 2) };
+        let mut tiles = Vec::new();
+        for (ty, tx) in (0..h.div_ceil(tile)).flat_map(|y| (0..w.div_ceil(tile)).map(move |x| (y, x))) {
+            let (x0, y0) = (tx * tile, ty * tile);
+            let (tw, th) = ((w - x0).min(tile), (h - y0).min(tile));
+            let data = (0..tw * th).map(|i| pixels[(y0 + i / tw) * w + x0 + i % tw] - 128).collect();
+            tiles.push(vec![reversible(Plane { x0, y0, w: tw, h: th, data }, 2, 8)]);
+        }
+        let data = layout.codestream(&tiles);
+        let codec = find_codec(&TransferSyntax::JPEG2000Lossless).expect("codec");
+        let decoded = codec.decode_frame(&data, &pixel_info(h as u16, w as u16, 1, 8, "MONOCHROME2")).unwrap();
+        assert_eq!(decoded, pixels.iter().map(|&p| p as u8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rejects_mismatched_geometry() {
+        let bands = reversible(plane(4, 4, vec![0; 16]), 0, 8);
+        let data = Layout::new(4, 4, &[(8, false)], 0).codestream(&[vec![bands]]);
+        let codec = find_codec(&TransferSyntax::JPEG2000Lossless).expect("codec");
+        assert!(codec.decode_frame(&data, &pixel_info(4, 5, 1, 8, "MONOCHROME2")).is_err());
+        assert!(codec.decode_frame(&data, &pixel_info(4, 4, 3, 8, "RGB")).is_err());
+        assert!(codec.decode_frame(&data[..20], &pixel_info(4, 4, 1, 8, "MONOCHROME2")).is_err());
+    }
+
+    #[test]
+    fn siz_is_checked_before_decoding() {
+        let bands = reversible(plane(4, 4, vec![0; 16]), 0, 8);
+        let mut data = Layout::new(4, 4, &[(8, false)], 0).codestream(&[vec![bands]]);
+        // One 30000x30000 tile: its samples would take gigabytes.
+        for offset in [8, 12, 24, 28] {
+            data[offset..offset + 4].copy_from_slice(&30000u32.to_be_bytes());
+        }
+        let codec = find_codec(&TransferSyntax::JPEG2000Lossless).expect("codec");
+        let err = codec.decode_frame(&data, &pixel_info(4, 4, 1, 8, "MONOCHROME2")).unwrap_err();
+        assert!(err.to_string().contains("JPEG 2000 frame is 30000x30000"), "{err}");
+    }
+}
@@ -0,0 +1,701 @@
+//! Code-block decoding (T.800 Annex D): the MQ arithmetic decoder (Annex C),
+//! raw bypass segments, and the significance propagation, magnitude
+//! refinement and cleanup passes over each bit-plane.
+
+use dpx_dicom_core::ensure;
+use dpx_dicom_core::error::Result;
+
+use super::codestream::{BYPASS, CAUSAL, RESET, SEGMARK};
+use super::tier2::{Orientation, Segment};
+
+/// The MQ coder's probability states (Table C.2): Qe, next index after an
+/// MPS, next after an LPS, and whether an LPS swaps the MPS sense.
+const STATES: [(u32, u8, u8, bool); 47] = [
+    (0x5601, 1, 1, true),
+    (0x3401, 2, 6, false),
+    (0x1801, 3, 9, false),
+    (0x0AC1, 4, 12, false),
+    (0x0521, 5, 29, false),
+    (0x0221, 38, 33, false),
+    (0x5601, 7, 6, true),
+    (0x5401, 8, 14, false),
+    (0x4801, 9, 14, false),
+    (0x3801, 10, 14, false),
+    (0x3001, 11, 17, false),
+    (0x2401, 12, 18, false),
+    (0x1C01, 13, 20, false),
+    (0x1601, 29, 21, false),
+    (0x5601, 15, 14, true),
+    (0x5401, 16, 14, false),
+    (0x5101, 17, 15, false),
+    (0x4801, 18, 16, false),
+    (0x3801, 19, 17, false),
+    (0x3401, 20, 18, false),
+    (0x3001, 21, 19, false),
+    (0x2801, 22, 19, false),
+    (0x2401, 23, 20, false),
+    (0x2201, 24, 21, false),
+    (0x1C01, 25, 22, false),
+    (0x1801, 26, 23, false),
+    (0x1601, 27, 24, false),
+    (0x1401, 28, 25, false),
+    (0x1201, 29, 26, false),
+    (0x1101, 30, 27, false),
+    (0x0AC1, 31, 28, false),
+    (0x09C1, 32, 29, false),
+    (0x08A1, 33, 30, false),
+    (0x0521, 34, 31, false),
+    (0x0441, 35, 32, false),
+    (0x02A1, 36, 33, false),
+    (0x0221, 37, 34, false),
+    (0x0141, 38, 35, false),
+    (0x0111, 39, 36, false),
+    (0x0085, 40, 37, false),
+    (0x0049, 41, 38, false),
+    (0x0025, 42, 39, false),
+    (0x0015, 43, 40, false),
+    (0x0009, 44, 41, false),
+    (0x0005, 45, 42, false),
+    (0x0001, 45, 43, false),
+    (0x5601, 46, 46, false),
+];
+
+/// Context labels: 0–8 zero coding, 9–13 sign, 14–16 refinement, then
+/// run-length and uniform.
+const SIGN: usize = 9;
+const REFINE: usize = 14;
+const RUN: usize = 17;
+const UNIFORM: usize = 18;
+const CONTEXTS: usize = 19;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    state: u8,
+    mps: u32,
+}
+
+/// Contexts in their initial states (Table D.7).
+fn initial_contexts() -> [Context; CONTEXTS] {
+    let mut contexts = [Context::default(); CONTEXTS];
+    contexts[0].state = 4;
+    contexts[RUN].state = 3;
+    contexts[UNIFORM].state = 46;
+    contexts
+}
+
+/// The MQ decoder of C.3, over one codeword segment. Reading past the end
+/// yields 0xFF bytes, which act as a marker.
+struct Mq<'a> {
+    data: &'a [u8],
+    pos: usize,
+    a: u32,
+    c: u32,
+    count: u32,
+}
+
+impl<'a> Mq<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        let mut mq = Mq { data, pos: 0, a: 0x8000, c: 0, count: 0 };
+        mq.c = mq.byte(0) << 16;
+        mq.byte_in();
+        mq.c <<= 7;
+        mq.count -= 7;
+        mq
+    }
+
+    fn byte(&self, pos: usize) -> u32 {
+        u32::from(self.data.get(pos).copied().unwrap_or(0xFF))
+    }
+
+    fn byte_in(&mut self) {
+        if self.byte(self.pos) == 0xFF {
+            let next = self.byte(self.pos + 1);
+            if next > 0x8F {
+                self.c = self.c.wrapping_add(0xFF00);
+                self.count = 8;
+            } else {
+                self.pos += 1;
+                self.c = self.c.wrapping_add(next << 9);
+                self.count = 7;
+            }
+        } else {
+            self.pos += 1;
+            self.c = self.c.wrapping_add(self.byte(self.pos) << 8);
+            self.count = 8;
+        }
+    }
+
+    fn decode(&mut self, cx: &mut Context) -> u32 {
+        let (qe, nmps, nlps, switch) = STATES[cx.state as usize];
+        self.a -= qe;
+        let d;
+        if (self.c >> 16) < qe {
+            // LPS exchange
+            if self.a < qe {
+                d = cx.mps;
+                cx.state = nmps;
+            } else {
+                d = 1 - cx.mps;
+                if switch {
+                    cx.mps = 1 - cx.mps;
+                }
+                cx.state = nlps;
+            }
+            self.a = qe;
+            self.renormalize();
+        } else {
+            self.c -= qe << 16;
+            if self.a & 0x8000 != 0 {
+                return cx.mps;
+            }
+            // MPS exchange
+            if self.a < qe {
+                d = 1 - cx.mps;
+                if switch {
+                    cx.mps = 1 - cx.mps;
+                }
+                cx.state = nlps;
+            } else {
+                d = cx.mps;
+                cx.state = nmps;
+            }
+            self.renormalize();
+        }
+        d
+    }
+
+    fn renormalize(&mut self) {
+        loop {
+            if self.count == 0 {
+                self.byte_in();
+            }
+            self.a <<= 1;
+            self.c <<= 1;
+            self.count -= 1;
+            if self.a & 0x8000 != 0 {
+                break;
+            }
+        }
+    }
+}
+
+/// Bits of a raw (bypass) segment, with the same 0xFF stuffing as packet
+/// headers.
+struct Raw<'a> {
+    data: &'a [u8],
+    pos: usize,
+    c: u32,
+    count: u32,
+}
+
+impl Raw<'_> {
+    fn bit(&mut self) -> u32 {
+        if self.count == 0 {
+            let next = u32::from(self.data.get(self.pos).copied().unwrap_or(0xFF));
+            if self.c == 0xFF {
+                if next > 0x8F {
+                    self.count = 8;
+                } else {
+                    self.c = next;
+                    self.pos += 1;
+                    self.count = 7;
+                }
+            } else {
+                self.c = next;
+                self.pos += 1;
+                self.count = 8;
+            }
+        }
+        self.count -= 1;
+        (self.c >> self.count) & 1
+    }
+}
+
+enum Coder<'a> {
+    Mq(Mq<'a>),
+    Raw(Raw<'a>),
+}
+
+/// Coefficient state flags.
+const SIG: u8 = 1;
+const VISITED: u8 = 2;
+const REFINED: u8 = 4;
+const NEGATIVE: u8 = 8;
+
+/// The significance state of a code-block, with a one-coefficient border so
+/// neighbours need no bounds checks.
+struct Block {
+    w: usize,
+    h: usize,
+    flags: Vec<u8>,
+    magnitude: Vec<u32>,
+    orientation: Orientation,
+    causal: bool,
+}
+
+impl Block {
+    fn new(w: usize, h: usize, orientation: Orientation, causal: bool) -> Self {
+        Block { w, h, flags: vec![0; (w + 2) * (h + 2)], magnitude: vec![0; w * h], orientation, causal }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y + 1) * (self.w + 2) + x + 1
+    }
+
+    /// Whether the row below `y` may be looked at: not across a stripe in
+    /// vertically causal mode.
+    fn below(&self, y: usize) -> bool {
+        !(self.causal && y % 4 == 3)
+    }
+
+    /// Significant horizontal, vertical and diagonal neighbours.
+    fn neighbours(&self, x: usize, y: usize) -> (u32, u32, u32) {
+        let (i, s) = (self.index(x, y), self.w + 2);
+        let f = |j: usize| u32::from(self.flags[j] & SIG);
+        let h = f(i - 1) + f(i + 1);
+        let (mut v, mut d) = (f(i - s), f(i - s - 1) + f(i - s + 1));
+        if self.below(y) {
+            v += f(i + s);
+            d += f(i + s - 1) + f(i + s + 1);
+        }
+        (h, v, d)
+    }
+
+    /// The zero coding context (Table D.1).
+    fn zero_context(&self, x: usize, y: usize) -> usize {
+        let (h, v, d) = self.neighbours(x, y);
+        match self.orientation {
+            Orientation::Hh => match (d, h + v) {
+                (0, hv) => hv.min(2) as usize,
+                (1, hv) => 3 + hv.min(2) as usize,
+                (2, 0) => 6,
+                (2, _) => 7,
+                _ => 8,
+            },
+            orientation => {
+                // The HL subband looks at its neighbours transposed.
+                let (h, v) = if orientation == Orientation::Hl { (v, h) } else { (h, v) };
+                match (h, v, d) {
+                    (2, _, _) => 8,
+                    (1, 1.., _) => 7,
+                    (1, 0, 1..) => 6,
+                    (1, 0, 0) => 5,
+                    (0, 2, _) => 4,
+                    (0, 1, _) => 3,
+                    (0, 0, 2..) => 2,
+                    (0, 0, 1) => 1,
+                    _ => 0,
+                }
+            }
+        }
+    }
+
+    /// The sign coding context and the bit it is XORed with (Table D.3).
+    fn sign_context(&self, x: usize, y: usize) -> (usize, u32) {
+        let (i, s) = (self.index(x, y), self.w + 2);
+        let sign = |j: usize| match self.flags[j] & (SIG | NEGATIVE) {
+            SIG => 1,
+            f if f & SIG != 0 => -1,
+            _ => 0,
+        };
+        let h = (sign(i - 1) + sign(i + 1)).clamp(-1, 1);
+        let below = if self.below(y) { sign(i + s) } else { 0 };
+        let v = (sign(i - s) + below).clamp(-1, 1);
+        match (h, v) {
+            (1, 1) => (SIGN + 4, 0),
+            (1, 0) => (SIGN + 3, 0),
+            (1, _) => (SIGN + 2, 0),
+            (0, 1) => (SIGN + 1, 0),
+            (0, 0) => (SIGN, 0),
+            (0, _) => (SIGN + 1, 1),
+            (_, 1) => (SIGN + 2, 1),
+            (_, 0) => (SIGN + 3, 1),
+            _ => (SIGN + 4, 1),
+        }
+    }
+
+    /// The magnitude refinement context (Table D.4).
+    fn refine_context(&self, x: usize, y: usize) -> usize {
+        if self.flags[self.index(x, y)] & REFINED != 0 {
+            return REFINE + 2;
+        }
+        let (h, v, d) = self.neighbours(x, y);
+        if h + v + d == 0 { REFINE } else { REFINE + 1 }
+    }
+
+    fn set_significant(&mut self, x: usize, y: usize, negative: bool, plane: u32) {
+        let i = self.index(x, y);
+        self.flags[i] |= SIG | if negative { NEGATIVE } else { 0 };
+        self.magnitude[y * self.w + x] |= 1 << plane;
+    }
+
+    /// Whether the column of four at `(x, y)` can be run-length coded.
+    fn run_eligible(&self, x: usize, y: usize) -> bool {
+        y + 4 <= self.h
+            && (y..y + 4).all(|yy| {
+                let (h, v, d) = self.neighbours(x, yy);
+                self.flags[self.index(x, yy)] & (SIG | VISITED) == 0 && h + v + d == 0
+            })
+    }
+
+    fn clear_visited(&mut self) {
+        for f in &mut self.flags {
+            *f &= !VISITED;
+        }
+    }
+
+    /// The coefficients in stripe order: four rows at a time, column by
+    /// column.
+    fn scan(&self) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let (w, h) = (self.w, self.h);
+        (0..h).step_by(4).flat_map(move |y0| (0..w).flat_map(move |x| (y0..(y0 + 4).min(h)).map(move |y| (x, y))))
+    }
+}
+
+/// Decodes the passes of one code-block into signed coefficients. `planes`
+/// is the number of magnitude bit-planes, zero bit-planes and ROI shift
+/// accounted for. Returns the coefficients and the lowest bit-plane every
+/// coefficient is known to, for reconstruction.
+pub(super) fn decode(
+    w: usize,
+    h: usize,
+    orientation: Orientation,
+    style: u8,
+    planes: u32,
+    segments: &[Segment],
+) -> Result<(Vec<i32>, u32)> {
+    ensure!(planes <= 31, UnsupportedFeature, "JPEG 2000 code-block of {planes} bit-planes");
+    let mut block = Block::new(w, h, orientation, style & CAUSAL != 0);
+    let mut contexts = initial_contexts();
+    let mut pass = 0u32;
+    let total: u32 = segments.iter().map(|s| s.passes).sum();
+    // Passes beyond the last bit-plane carry nothing to decode.
+    let total = total.min(if planes == 0 { 0 } else { 3 * planes - 2 });
+    for segment in segments {
+        if pass >= total {
+            break;
+        }
+        let raw = style & BYPASS != 0 && pass >= 10 && !pass.is_multiple_of(3);
+        let mut coder = if raw {
+            Coder::Raw(Raw { data: &segment.data, pos: 0, c: 0, count: 0 })
+        } else {
+            Coder::Mq(Mq::new(&segment.data))
+        };
+        for _ in 0..segment.passes.min(total - pass) {
+            let plane = planes - 1 - pass.div_ceil(3);
+            let mut bit = |cx: usize| match &mut coder {
+                Coder::Mq(mq) => mq.decode(&mut contexts[cx]),
+                Coder::Raw(raw) => raw.bit(),
+            };
+            match pass % 3 {
+                0 => cleanup(&mut block, plane, &mut bit, style & SEGMARK != 0),
+                1 => significance(&mut block, plane, &mut bit),
+                _ => refinement(&mut block, plane, &mut bit),
+            }
+            if style & RESET != 0 {
+                contexts = initial_contexts();
+            }
+            pass += 1;
+        }
+    }
+    // The next pass would have been at `plane`; what came before is exact.
+    let known = match pass {
+        0 => planes,
+        _ => {
+            let plane = planes as i64 - 1 - i64::from(pass.div_ceil(3));
+            (plane + i64::from(!pass.is_multiple_of(3))).max(0) as u32
+        }
+    };
+    let values = block
+        .magnitude
+        .iter()
+        .enumerate()
+        .map(|(i, &m)| {
+            let negative = block.flags[block.index(i % w.max(1), i / w.max(1))] & NEGATIVE != 0;
+            if negative { -(m as i32) } else { m as i32 }
+        })
+        .collect();
+    Ok((values, known))
+}
+
+fn decode_sign(block: &Block, x: usize, y: usize, bit: &mut impl FnMut(usize) -> u32) -> bool {
+    let (cx, xor) = block.sign_context(x, y);
+    bit(cx) ^ xor == 1
+}
+
+fn significance(block: &mut Block, plane: u32, bit: &mut impl FnMut(usize) -> u32) {
+    for (x, y) in block.scan() {
+        let i = block.index(x, y);
+        if block.flags[i] & SIG != 0 {
+            continue;
+        }
+        let (h, v, d) = block.neighbours(x, y);
+        if h + v + d == 0 {
+            continue;
+        }
+        if bit(block.zero_context(x, y)) == 1 {
+            let negative = decode_sign(block, x, y, bit);
+            block.set_significant(x, y, negative, plane);
+        }
+        block.flags[i] |= VISITED;
+    }
+}
+
+fn refinement(block: &mut Block, plane: u32, bit: &mut impl FnMut(usize) -> u32) {
+    for (x, y) in block.scan() {
+        let i = block.index(x, y);
+        if block.flags[i] & (SIG | VISITED) != SIG {
+            continue;
+        }
+        if bit(block.refine_context(x, y)) == 1 {
+            block.magnitude[y * block.w + x] |= 1 << plane;
+        }
+        block.flags[i] |= REFINED;
+    }
+}
+
+fn cleanup(block: &mut Block, plane: u32, bit: &mut impl FnMut(usize) -> u32, segmentation: bool) {
+    for y0 in (0..block.h).step_by(4) {
+        let y1 = (y0 + 4).min(block.h);
+        for x in 0..block.w {
+            let mut y = y0;
+            if block.run_eligible(x, y0) {
+                if bit(RUN) == 0 {
+                    continue;
+                }
+                y = y0 + (bit(UNIFORM) << 1 | bit(UNIFORM)) as usize;
+                let negative = decode_sign(block, x, y, bit);
+                block.set_significant(x, y, negative, plane);
+                y += 1;
+            }
+            for y in y..y1 {
+                if block.flags[block.index(x, y)] & (SIG | VISITED) != 0 {
+                    continue;
+                }
+                if bit(block.zero_context(x, y)) == 1 {
+                    let negative = decode_sign(block, x, y, bit);
+                    block.set_significant(x, y, negative, plane);
+                }
+            }
+        }
+    }
+    block.clear_visited();
+    if segmentation {
+        for _ in 0..4 {
+            bit(UNIFORM);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// The MQ encoder of C.2, for building test code-blocks.
+    struct MqEncoder {
+        out: Vec<u8>,
+        a: u32,
+        c: u32,
+        count: u32,
+    }
+
+    impl MqEncoder {
+        fn new() -> Self {
+            // The first byte stands for the one before the codeword.
+            MqEncoder { out: vec![0], a: 0x8000, c: 0, count: 12 }
+        }
+
+        fn encode(&mut self, cx: &mut Context, d: u32) {
+            let (qe, nmps, nlps, switch) = STATES[cx.state as usize];
+            self.a -= qe;
+            if d == cx.mps {
+                if self.a & 0x8000 != 0 {
+                    self.c += qe;
+                    return;
+                }
+                if self.a < qe {
+                    self.a = qe;
+                } else {
+                    self.c += qe;
+                }
+                cx.state = nmps;
+            } else {
+                if self.a < qe {
+                    self.c += qe;
+                } else {
+                    self.a = qe;
+                }
+                if switch {
+                    cx.mps = 1 - cx.mps;
+                }
+                cx.state = nlps;
+            }
+            loop {
+                self.a <<= 1;
+                self.c <<= 1;
+                self.count -= 1;
+                if self.count == 0 {
+                    self.byte_out();
+                }
+                if self.a & 0x8000 != 0 {
+                    break;
+                }
+            }
+        }
+
+        fn byte_out(&mut self) {
+            let last = self.out.len() - 1;
+            if self.out[last] == 0xFF {
+                self.out.push((self.c >> 20) as u8);
+                self.c &= 0xF_FFFF;
+                self.count = 7;
+            } else if self.c & 0x800_0000 == 0 {
+                self.out.push((self.c >> 19) as u8);
+                self.c &= 0x7_FFFF;
+                self.count = 8;
+            } else {
+                self.out[last] += 1;
+                if self.out[last] == 0xFF {
+                    self.c &= 0x7FF_FFFF;
+                    self.out.push((self.c >> 20) as u8);
+                    self.c &= 0xF_FFFF;
+                    self.count = 7;
+                } else {
+                    self.out.push((self.c >> 19) as u8);
+                    self.c &= 0x7_FFFF;
+                    self.count = 8;
+                }
+            }
+        }
+
+        fn finish(mut self) -> Vec<u8> {
+            let top = self.c + self.a;
+            self.c |= 0xFFFF;
+            if self.c >= top {
+                self.c -= 0x8000;
+            }
+            self.c <<= self.count;
+            self.byte_out();
+            self.c <<= self.count;
+            self.byte_out();
+            if self.out.last() == Some(&0xFF) {
+                self.out.pop();
+            }
+            self.out.remove(0);
+            self.out
+        }
+    }
+
+    /// Codes `values` over `planes` bit-planes in a single segment of all
+    /// passes, mirroring the decoder's scan.
+    pub(crate) fn encode_block(values: &[i32], w: usize, h: usize, orientation: Orientation, planes: u32) -> Segment {
+        let mut block = Block::new(w, h, orientation, false);
+        let mut contexts = initial_contexts();
+        let mut mq = MqEncoder::new();
+        let bit_of = |x: usize, y: usize, plane: u32| (values[y * w + x].unsigned_abs() >> plane) & 1;
+        let mut passes = 0;
+        for plane in (0..planes).rev() {
+            let mut put = |cx: usize, d: u32| mq.encode(&mut contexts[cx], d);
+            let sign = |block: &mut Block, x: usize, y: usize, put: &mut dyn FnMut(usize, u32)| {
+                let (cx, xor) = block.sign_context(x, y);
+                let negative = values[y * w + x] < 0;
+                put(cx, u32::from(negative) ^ xor);
+                block.set_significant(x, y, negative, plane);
+            };
+            if plane + 1 < planes {
+                for (x, y) in block.scan() {
+                    let i = block.index(x, y);
+                    let (nh, nv, nd) = block.neighbours(x, y);
+                    if block.flags[i] & SIG != 0 || nh + nv + nd == 0 {
+                        continue;
+                    }
+                    let b = bit_of(x, y, plane);
+                    put(block.zero_context(x, y), b);
+                    if b == 1 {
+                        sign(&mut block, x, y, &mut put);
+                    }
+                    block.flags[i] |= VISITED;
+                }
+                for (x, y) in block.scan() {
+                    let i = block.index(x, y);
+                    if block.flags[i] & (SIG | VISITED) != SIG {
+                        continue;
+                    }
+                    put(block.refine_context(x, y), bit_of(x, y, plane));
+                    block.magnitude[y * w + x] |= bit_of(x, y, plane) << plane;
+                    block.flags[i] |= REFINED;
+                }
+                passes += 2;
+            }
+            for y0 in (0..h).step_by(4) {
+                let y1 = (y0 + 4).min(h);
+                for x in 0..w {
+                    let mut y = y0;
+                    if block.run_eligible(x, y0) {
+                        let Some(first) = (y0..y1).find(|&y| bit_of(x, y, plane) == 1) else {
+                            put(RUN, 0);
+                            continue;
+                        };
+                        put(RUN, 1);
+                        put(UNIFORM, ((first - y0) >> 1) as u32);
+                        put(UNIFORM, ((first - y0) & 1) as u32);
+                        sign(&mut block, x, first, &mut put);
+                        y = first + 1;
+                    }
+                    for y in y..y1 {
+                        if block.flags[block.index(x, y)] & (SIG | VISITED) != 0 {
+                            continue;
+                        }
+                        let b = bit_of(x, y, plane);
+                        put(block.zero_context(x, y), b);
+                        if b == 1 {
+                            sign(&mut block, x, y, &mut put);
+                        }
+                    }
+                }
+            }
+            block.clear_visited();
+            passes += 1;
+        }
+        Segment { data: mq.finish(), passes }
+    }
+
+    #[test]
+    fn mq_round_trip() {
+        let bits: Vec<u32> = (0..2000u32).map(|i| u32::from(i % 7 == 0 || i % 13 < 3)).collect();
+        let mut contexts = initial_contexts();
+        let mut mq = MqEncoder::new();
+        for (i, &b) in bits.iter().enumerate() {
+            mq.encode(&mut contexts[i % 3], b);
+        }
+        let data = mq.finish();
+        let mut contexts = initial_contexts();
+        let mut mq = Mq::new(&data);
+        let decoded: Vec<u32> = (0..bits.len()).map(|i| mq.decode(&mut contexts[i % 3])).collect();
+        assert_eq!(decoded, bits);
+    }
+
+    #[test]
+    fn code_block_round_trip() {
+        let (w, h) = (9, 6);
+        let values: Vec<i32> =
+            (0..w * h).map(|i| ((i * 37 % 41) as i32 - 20) * if i % 5 == 0 { 0 } else { 3 }).collect();
+        for orientation in [Orientation::Ll, Orientation::Hl, Orientation::Lh, Orientation::Hh] {
+            let segment = encode_block(&values, w, h, orientation, 7);
+            let (decoded, known) = decode(w, h, orientation, 0, 7, &[segment]).unwrap();
+            assert_eq!(decoded, values);
+            assert_eq!(known, 0);
+        }
+    }
+
+    #[test]
+    fn truncated_code_block_keeps_upper_planes() {
+        let values = [100, -64, 3, 0];
+        let mut segment = encode_block(&values, 2, 2, Orientation::Ll, 7);
+        // The cleanup pass of the top plane, then all three of the next.
+        segment.passes = 4;
+        let (decoded, known) = decode(2, 2, Orientation::Ll, 0, 7, &[segment]).unwrap();
+        assert_eq!(decoded, [96, -64, 0, 0]);
+        assert_eq!(known, 5);
+    }
+}
@@ -0,0 +1,574 @@
+//! Tile structure and packet decoding (T.800 Annex B): resolutions, subbands,
+//! precincts and code-blocks of each tile-component, the five progression
+//! orders with their POC changes, and packet headers with their tag trees.
+//! Packets collect the code-block segments tier-1 decodes.
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{dicom_err, ensure};
+
+use super::codestream::{BYPASS, ComponentSiz, TERMALL, TileCoding};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Orientation {
+    Ll,
+    Hl,
+    Lh,
+    Hh,
+}
+
+/// A codeword segment of a code-block: bytes that one arithmetic (or raw)
+/// decoder runs over, and the coding passes they hold.
+#[derive(Default)]
+pub(super) struct Segment {
+    pub data: Vec<u8>,
+    pub passes: u32,
+}
+
+pub(super) struct CodeBlock {
+    /// Area in subband coordinates.
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+    pub zero_bitplanes: u32,
+    pub segments: Vec<Segment>,
+    included: bool,
+    lblock: u32,
+    passes: u32,
+}
+
+/// The code-blocks of one subband that fall in one precinct.
+struct PrecinctBand {
+    blocks_w: usize,
+    inclusion: TagTree,
+    zero_bitplanes: TagTree,
+    blocks: Vec<CodeBlock>,
+}
+
+pub(super) struct Band {
+    pub orientation: Orientation,
+    /// Area in subband coordinates.
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+    precincts: Vec<PrecinctBand>,
+}
+
+impl Band {
+    pub fn blocks(&self) -> impl Iterator<Item = &CodeBlock> {
+        self.precincts.iter().flat_map(|p| &p.blocks)
+    }
+}
+
+pub(super) struct Resolution {
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+    pub bands: Vec<Band>,
+    /// Precinct exponents, and precincts across and down.
+    ppx: u8,
+    ppy: u8,
+    pw: usize,
+    ph: usize,
+}
+
+pub(super) struct TileComponent {
+    pub resolutions: Vec<Resolution>,
+    dx: usize,
+    dy: usize,
+}
+
+fn ceil_div(a: usize, b: usize) -> usize {
+    a.div_ceil(b)
+}
+
+/// `ceil((a - offset) / 2^shift)` where `a - offset` may be negative.
+fn ceil_shift_signed(a: usize, offset: usize, shift: u32) -> usize {
+    let v = a as i64 - offset as i64;
+    let d = 1i64 << shift;
+    (v + d - 1).div_euclid(d).max(0) as usize
+}
+
+impl TileComponent {
+    /// Lays out component `c` of the tile covering `rect` on the reference grid.
+    pub fn new(rect: (u32, u32, u32, u32), siz: &ComponentSiz, coding: &TileCoding, c: usize) -> Result<Self> {
+        let params = &coding.components[c].coding;
+        let (dx, dy) = (siz.dx as usize, siz.dy as usize);
+        let x0 = ceil_div(rect.0 as usize, dx);
+        let y0 = ceil_div(rect.1 as usize, dy);
+        let x1 = ceil_div(rect.2 as usize, dx);
+        let y1 = ceil_div(rect.3 as usize, dy);
+        let levels = params.levels as u32;
+        let mut resolutions = Vec::with_capacity(levels as usize + 1);
+        for r in 0..=levels {
+            let shift = levels - r;
+            let (rx0, ry0) = (ceil_shift_signed(x0, 0, shift), ceil_shift_signed(y0, 0, shift));
+            let (rx1, ry1) = (ceil_shift_signed(x1, 0, shift), ceil_shift_signed(y1, 0, shift));
+            let (ppx, ppy) = params.precincts.get(r as usize).copied().unwrap_or((15, 15));
+            let (pw, ph) = if rx1 > rx0 && ry1 > ry0 {
+                (ceil_div(rx1, 1 << ppx) - (rx0 >> ppx), ceil_div(ry1, 1 << ppy) - (ry0 >> ppy))
+            } else {
+                (0, 0)
+            };
+            ensure!(pw.saturating_mul(ph) <= 1 << 24, InvalidData, "JPEG 2000 resolution with {pw}x{ph} precincts");
+            let orientations: &[Orientation] =
+                if r == 0 { &[Orientation::Ll] } else { &[Orientation::Hl, Orientation::Lh, Orientation::Hh] };
+            // Code-blocks never straddle precincts.
+            let (xcb, ycb) = if r == 0 {
+                (params.xcb.min(ppx), params.ycb.min(ppy))
+            } else {
+                (params.xcb.min(ppx - 1), params.ycb.min(ppy - 1))
+            };
+            let mut bands = Vec::with_capacity(orientations.len());
+            for &orientation in orientations {
+                let (xo, yo) = match orientation {
+                    Orientation::Ll => (0, 0),
+                    Orientation::Hl => (1, 0),
+                    Orientation::Lh => (0, 1),
+                    Orientation::Hh => (1, 1),
+                };
+                // Decomposition level of the band (B-15).
+                let nb = if r == 0 { levels } else { levels - r + 1 };
+                let half = if nb == 0 { 0 } else { 1usize << (nb - 1) };
+                let bx0 = ceil_shift_signed(x0, half * xo, nb);
+                let by0 = ceil_shift_signed(y0, half * yo, nb);
+                let bx1 = ceil_shift_signed(x1, half * xo, nb);
+                let by1 = ceil_shift_signed(y1, half * yo, nb);
+                let mut precincts = Vec::with_capacity(pw * ph);
+                for p in 0..pw * ph {
+                    // The precinct's area in resolution, then subband, coordinates.
+                    let px = ((rx0 >> ppx) + p % pw) << ppx;
+                    let py = ((ry0 >> ppy) + p / pw) << ppy;
+                    let (px0, py0, px1, py1) = if r == 0 {
+                        (px, py, px + (1 << ppx), py + (1 << ppy))
+                    } else {
+                        (px / 2, py / 2, (px + (1 << ppx)) / 2, (py + (1 << ppy)) / 2)
+                    };
+                    let (cx0, cy0) = (px0.max(bx0), py0.max(by0));
+                    let (cx1, cy1) = (px1.min(bx1), py1.min(by1));
+                    precincts.push(PrecinctBand::new(cx0, cy0, cx1, cy1, xcb, ycb));
+                }
+                bands.push(Band { orientation, x0: bx0, y0: by0, x1: bx1, y1: by1, precincts });
+            }
+            resolutions.push(Resolution { x0: rx0, y0: ry0, x1: rx1, y1: ry1, bands, ppx, ppy, pw, ph });
+        }
+        Ok(TileComponent { resolutions, dx, dy })
+    }
+}
+
+impl PrecinctBand {
+    fn new(x0: usize, y0: usize, x1: usize, y1: usize, xcb: u8, ycb: u8) -> Self {
+        if x1 <= x0 || y1 <= y0 {
+            return PrecinctBand {
+                blocks_w: 0,
+                inclusion: TagTree::new(0, 0),
+                zero_bitplanes: TagTree::new(0, 0),
+                blocks: Vec::new(),
+            };
+        }
+        let (first_x, first_y) = (x0 >> xcb, y0 >> ycb);
+        let blocks_w = ceil_div(x1, 1 << xcb) - first_x;
+        let blocks_h = ceil_div(y1, 1 << ycb) - first_y;
+        let mut blocks = Vec::with_capacity(blocks_w * blocks_h);
+        for j in 0..blocks_h {
+            for i in 0..blocks_w {
+                let (bx, by) = ((first_x + i) << xcb, (first_y + j) << ycb);
+                blocks.push(CodeBlock {
+                    x0: bx.max(x0),
+                    y0: by.max(y0),
+                    x1: (bx + (1 << xcb)).min(x1),
+                    y1: (by + (1 << ycb)).min(y1),
+                    zero_bitplanes: 0,
+                    segments: Vec::new(),
+                    included: false,
+                    lblock: 3,
+                    passes: 0,
+                });
+            }
+        }
+        PrecinctBand {
+            blocks_w,
+            inclusion: TagTree::new(blocks_w, blocks_h),
+            zero_bitplanes: TagTree::new(blocks_w, blocks_h),
+            blocks,
+        }
+    }
+}
+
+/// A tag tree (B.10.2): each node holds the minimum of its children, coded
+/// incrementally against rising thresholds.
+struct TagTree {
+    /// Width of each level, leaves first, and where its nodes start.
+    levels: Vec<(usize, usize)>,
+    value: Vec<u32>,
+    low: Vec<u32>,
+}
+
+impl TagTree {
+    fn new(mut w: usize, mut h: usize) -> Self {
+        let mut levels = Vec::new();
+        let mut count = 0;
+        while w > 0 && h > 0 {
+            levels.push((w, count));
+            count += w * h;
+            if w == 1 && h == 1 {
+                break;
+            }
+            (w, h) = (w.div_ceil(2), h.div_ceil(2));
+        }
+        TagTree { levels, value: vec![u32::MAX; count], low: vec![0; count] }
+    }
+
+    /// Reads bits until it is known whether leaf `(x, y)` is below
+    /// `threshold`.
+    fn decode(&mut self, bits: &mut HeaderBits, x: usize, y: usize, threshold: u32) -> Result<bool> {
+        let mut low = 0;
+        let mut leaf = 0;
+        for (level, &(w, start)) in self.levels.iter().enumerate().rev() {
+            let i = start + (y >> level) * w + (x >> level);
+            if low > self.low[i] {
+                self.low[i] = low;
+            } else {
+                low = self.low[i];
+            }
+            while low < threshold && low < self.value[i] {
+                if bits.bit()? == 1 {
+                    self.value[i] = low;
+                } else {
+                    low += 1;
+                }
+            }
+            self.low[i] = low;
+            leaf = i;
+        }
+        Ok(self.value[leaf] < threshold)
+    }
+}
+
+/// Packet header bits: after an 0xFF byte, the next holds only seven.
+struct HeaderBits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl<'a> HeaderBits<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        HeaderBits { data, pos, buf: 0, count: 0 }
+    }
+
+    fn bit(&mut self) -> Result<u32> {
+        if self.count == 0 {
+            let byte =
+                *self.data.get(self.pos).ok_or_else(|| dicom_err!(InvalidData, "truncated JPEG 2000 packet header"))?;
+            self.pos += 1;
+            self.count = if self.buf == 0xFF { 7 } else { 8 };
+            self.buf = u32::from(byte);
+        }
+        self.count -= 1;
+        Ok((self.buf >> self.count) & 1)
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32> {
+        let mut v = 0;
+        for _ in 0..n {
+            v = v << 1 | self.bit()?;
+        }
+        Ok(v)
+    }
+
+    /// Where the packet body starts: past the byte stuffed after a final 0xFF.
+    fn end(&self) -> usize {
+        if self.buf == 0xFF { self.pos + 1 } else { self.pos }
+    }
+}
+
+/// Passes a codeword segment can hold from its first pass `first` (D.4.1).
+fn segment_passes(style: u8, first: u32) -> u32 {
+    if style & TERMALL != 0 {
+        1
+    } else if style & BYPASS != 0 {
+        match first {
+            0..10 => 10 - first,
+            // Raw significance and refinement passes share a segment.
+            _ if first % 3 == 1 => 2,
+            _ => 1,
+        }
+    } else {
+        u32::MAX
+    }
+}
+
+/// Number of coding passes (Table B.4).
+fn read_passes(bits: &mut HeaderBits) -> Result<u32> {
+    if bits.bit()? == 0 {
+        return Ok(1);
+    }
+    if bits.bit()? == 0 {
+        return Ok(2);
+    }
+    let n = bits.bits(2)?;
+    if n != 3 {
+        return Ok(3 + n);
+    }
+    let n = bits.bits(5)?;
+    if n != 31 {
+        return Ok(6 + n);
+    }
+    Ok(37 + bits.bits(7)?)
+}
+
+/// A packet: layer, resolution, component, precinct.
+type Packet = (usize, usize, usize, usize);
+
+/// The tile's packets in codestream order (B.12), honouring POCs.
+fn packet_order(tile: &[TileComponent], coding: &TileCoding, rect: (u32, u32, u32, u32)) -> Vec<Packet> {
+    let mut next_layer: Vec<Vec<Vec<usize>>> =
+        tile.iter().map(|tc| tc.resolutions.iter().map(|r| vec![0; r.pw * r.ph]).collect()).collect();
+    let mut order = Vec::new();
+    let mut emit = |next: &mut Vec<Vec<Vec<usize>>>, l: usize, r: usize, c: usize, p: usize| {
+        let slot = &mut next[c][r][p];
+        if *slot == l {
+            *slot += 1;
+            order.push((l, r, c, p));
+        }
+    };
+    for prog in coding.progressions() {
+        let layers = prog.layer_end.min(coding.layers);
+        let comps = prog.comp_start..prog.comp_end.min(tile.len());
+        let res_end = prog.res_end.min(tile.iter().map(|tc| tc.resolutions.len()).max().unwrap_or(0));
+        let resolutions = prog.res_start..res_end;
+        let precincts = |c: usize, r: usize| tile[c].resolutions.get(r).map_or(0, |res| res.pw * res.ph);
+        match prog.order {
+            // LRCP
+            0 => {
+                for l in 0..layers {
+                    for r in resolutions.clone() {
+                        for c in comps.clone() {
+                            for p in 0..precincts(c, r) {
+                                emit(&mut next_layer, l, r, c, p);
+                            }
+                        }
+                    }
+                }
+            }
+            // RLCP
+            1 => {
+                for r in resolutions.clone() {
+                    for l in 0..layers {
+                        for c in comps.clone() {
+                            for p in 0..precincts(c, r) {
+                                emit(&mut next_layer, l, r, c, p);
+                            }
+                        }
+                    }
+                }
+            }
+            // RPCL, PCRL, CPRL: precincts visited by position.
+            order_kind => {
+                let (step_x, step_y) = position_steps(tile, comps.clone(), resolutions.clone());
+                let positions = positions(rect, step_x, step_y);
+                match order_kind {
+                    2 => {
+                        for r in resolutions.clone() {
+                            for &(x, y) in &positions {
+                                for c in comps.clone() {
+                                    if let Some(p) = precinct_at(&tile[c], r, x, y, rect) {
+                                        (0..layers).for_each(|l| emit(&mut next_layer, l, r, c, p));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    3 => {
+                        for &(x, y) in &positions {
+                            for c in comps.clone() {
+                                for r in resolutions.clone() {
+                                    if let Some(p) = precinct_at(&tile[c], r, x, y, rect) {
+                                        (0..layers).for_each(|l| emit(&mut next_layer, l, r, c, p));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    _ => {
+                        for c in comps.clone() {
+                            for &(x, y) in &positions {
+                                for r in resolutions.clone() {
+                                    if let Some(p) = precinct_at(&tile[c], r, x, y, rect) {
+                                        (0..layers).for_each(|l| emit(&mut next_layer, l, r, c, p));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    order
+}
+
+/// The smallest precinct spacing on the reference grid over the components
+/// and resolutions of a progression.
+fn position_steps(
+    tile: &[TileComponent],
+    comps: std::ops::Range<usize>,
+    resolutions: std::ops::Range<usize>,
+) -> (u64, u64) {
+    let (mut sx, mut sy) = (u64::MAX, u64::MAX);
+    for c in comps {
+        let tc = &tile[c];
+        let levels = tc.resolutions.len() - 1;
+        for r in resolutions.clone().filter(|&r| r <= levels) {
+            let res = &tc.resolutions[r];
+            let shift_x = u32::from(res.ppx) + (levels - r) as u32;
+            let shift_y = u32::from(res.ppy) + (levels - r) as u32;
+            sx = sx.min((tc.dx as u64).checked_shl(shift_x).unwrap_or(u64::MAX));
+            sy = sy.min((tc.dy as u64).checked_shl(shift_y).unwrap_or(u64::MAX));
+        }
+    }
+    (sx, sy)
+}
+
+/// The reference grid positions a position-driven progression visits.
+fn positions(rect: (u32, u32, u32, u32), step_x: u64, step_y: u64) -> Vec<(u64, u64)> {
+    let mut out = Vec::new();
+    if step_x == u64::MAX || step_y == u64::MAX {
+        return out;
+    }
+    let (x0, y0, x1, y1) = (u64::from(rect.0), u64::from(rect.1), u64::from(rect.2), u64::from(rect.3));
+    let mut y = y0;
+    while y < y1 {
+        let mut x = x0;
+        while x < x1 {
+            out.push((x, y));
+            x += step_x - x % step_x;
+        }
+        y += step_y - y % step_y;
+    }
+    out
+}
+
+/// The precinct of resolution `r` that starts at reference grid position
+/// `(x, y)`, if one does.
+fn precinct_at(tc: &TileComponent, r: usize, x: u64, y: u64, rect: (u32, u32, u32, u32)) -> Option<usize> {
+    let res = tc.resolutions.get(r)?;
+    if res.pw == 0 || res.ph == 0 {
+        return None;
+    }
+    let level = (tc.resolutions.len() - 1 - r) as u32;
+    let (dx, dy) = (tc.dx as u64, tc.dy as u64);
+    let rpx = u32::from(res.ppx) + level;
+    let rpy = u32::from(res.ppy) + level;
+    let (rx0, ry0) = (res.x0 as u64, res.y0 as u64);
+    let aligned = |v: u64, d: u64, shift: u32, origin: u64, first: u64| {
+        d.checked_shl(shift).is_some_and(|s| v.is_multiple_of(s))
+            || (v == origin && !(first << level).is_multiple_of(1u64.checked_shl(shift).unwrap_or(u64::MAX)))
+    };
+    if !aligned(y, dy, rpy, u64::from(rect.1), ry0) || !aligned(x, dx, rpx, u64::from(rect.0), rx0) {
+        return None;
+    }
+    let px = (x.div_ceil(dx << level) >> res.ppx) - (rx0 >> res.ppx);
+    let py = (y.div_ceil(dy << level) >> res.ppy) - (ry0 >> res.ppy);
+    let p = py as usize * res.pw + px as usize;
+    (px < res.pw as u64 && py < res.ph as u64).then_some(p)
+}
+
+/// Reads the tile's packets from `data`, filling the code-blocks' segments.
+/// Decoding stops cleanly where the data runs out between packets.
+pub(super) fn read_packets(
+    tile: &mut [TileComponent],
+    coding: &TileCoding,
+    rect: (u32, u32, u32, u32),
+    data: &[u8],
+) -> Result<()> {
+    let mut pos = 0;
+    for (layer, r, c, p) in packet_order(tile, coding, rect) {
+        if pos >= data.len() {
+            break;
+        }
+        let style = coding.components[c].coding.style;
+        pos = read_packet(&mut tile[c].resolutions[r], layer, p, style, coding, data, pos)?;
+    }
+    Ok(())
+}
+
+fn read_packet(
+    res: &mut Resolution,
+    layer: usize,
+    p: usize,
+    style: u8,
+    coding: &TileCoding,
+    data: &[u8],
+    mut pos: usize,
+) -> Result<usize> {
+    if coding.sop && data[pos..].starts_with(&[0xFF, 0x91]) {
+        pos += 6;
+    }
+    let mut bits = HeaderBits::new(data, pos);
+    // (band, block, segment, length) of each contribution, in body order.
+    let mut contributions = Vec::new();
+    if bits.bit()? == 1 {
+        for (b, band) in res.bands.iter_mut().enumerate() {
+            let precinct = &mut band.precincts[p];
+            for (k, block) in precinct.blocks.iter_mut().enumerate() {
+                let (x, y) = (k % precinct.blocks_w, k / precinct.blocks_w);
+                let included = if block.included {
+                    bits.bit()? == 1
+                } else {
+                    precinct.inclusion.decode(&mut bits, x, y, layer as u32 + 1)?
+                };
+                if !included {
+                    continue;
+                }
+                if !block.included {
+                    let mut threshold = 1;
+                    while !precinct.zero_bitplanes.decode(&mut bits, x, y, threshold)? {
+                        threshold += 1;
+                        ensure!(threshold <= 64, InvalidData, "JPEG 2000 code-block with over 63 zero bit-planes");
+                    }
+                    block.zero_bitplanes = threshold - 1;
+                    block.included = true;
+                }
+                let mut remaining = read_passes(&mut bits)?;
+                while bits.bit()? == 1 {
+                    block.lblock += 1;
+                }
+                while remaining > 0 {
+                    let open = block
+                        .segments
+                        .last()
+                        .is_some_and(|s| s.passes < segment_passes(style, block.passes - s.passes));
+                    if !open {
+                        block.segments.push(Segment::default());
+                    }
+                    let s = block.segments.len() - 1;
+                    let first = block.passes - block.segments[s].passes;
+                    let n = remaining.min(segment_passes(style, first) - block.segments[s].passes);
+                    let width = block.lblock + n.ilog2();
+                    ensure!(width <= 32, InvalidData, "JPEG 2000 codeword segment length of {width} bits");
+                    let len = bits.bits(width)? as usize;
+                    block.segments[s].passes += n;
+                    block.passes += n;
+                    remaining -= n;
+                    contributions.push((b, k, s, len));
+                }
+            }
+        }
+    }
+    pos = bits.end();
+    if coding.eph && data[pos.min(data.len())..].starts_with(&[0xFF, 0x92]) {
+        pos += 2;
+    }
+    for (b, k, s, len) in contributions {
+        ensure!(pos + len <= data.len(), InvalidData, "truncated JPEG 2000 packet body");
+        let block = &mut res.bands[b].precincts[p].blocks[k];
+        block.segments[s].data.extend_from_slice(&data[pos..pos + len]);
+        pos += len;
+    }
+    Ok(pos)
+}
@@ -0,0 +1,269 @@
+//! Pixel data codecs: compression and decompression of encapsulated Pixel Data.
+//!
+//! A [`PixelCodec`] works one frame at a time on native little-endian pixels
+//! described by a [`PixelInfo`]. Codecs are registered per encapsulated
+//! [`TransferSyntax`] with a [`CodecEntry`] and discovered at link time through
+//! [`inventory`], like the tag and UID dictionaries; [`find_codec`] looks one
+//! up. [`DcmWriter`](crate::DcmWriter) uses the registry to decode and
+//! re-encode Pixel Data when the target transfer syntax differs from the data
+//! set's.
+//!
+//! ```ignore
+//! struct MyCodec;
+//! impl PixelCodec for MyCodec { /* decode_frame / encode_frame */ }
+//! inventory::submit! { CodecEntry { ts: &TransferSyntax::JPEGLSLossless, codec: &MyCodec } }
+//! ```
+
+#[cfg(feature = "jpeg")]
+mod jpeg;
+#[cfg(feature = "jpeg2000")]
+mod jpeg2000;
+mod rle;
+mod uncompressed;
+
+use std::borrow::Cow;
+
+use bytes::Bytes;
+use dpx_dicom_core::error::{ErrContext, Result};
+use dpx_dicom_core::{TagKey, TransferSyntax, Vr, dicom_err, ensure, tags};
+
+use crate::DataSet;
+use crate::frames::Frames;
+use crate::value::{Element, OneOrMany, PixelData, Stored, Value};
+
+/// The Image Pixel module attributes a codec needs to interpret a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PixelInfo {
+    pub rows: u16,
+    pub columns: u16,
+    pub samples_per_pixel: u16,
+    pub bits_allocated: u16,
+    pub bits_stored: u16,
+    pub high_bit: u16,
+    /// 0 unsigned, 1 two's complement.
+    pub pixel_representation: u16,
+    /// 0 interleaved (`RGBRGB…`), 1 planar (`RR…GG…BB…`).
+    pub planar_configuration: u16,
+    pub number_of_frames: u32,
+    pub photometric_interpretation: String,
+}
+
+impl PixelInfo {
+    /// Reads the Image Pixel attributes of `ds`. Rows, Columns and Bits
+    /// Allocated are required; the rest default as for a single-frame,
+    /// single-sample, unsigned MONOCHROME2 image.
+    pub fn from_dataset(ds: &DataSet) -> Result<Self> {
+        let rows = ds.get::<u16>(&tags::Rows).err_context("reading the Image Pixel module")?;
+        let columns = ds.get::<u16>(&tags::Columns).err_context("reading the Image Pixel module")?;
+        let bits_allocated = ds.get::<u16>(&tags::BitsAllocated).err_context("reading the Image Pixel module")?;
+        let bits_stored = ds.get_some::<u16>(&tags::BitsStored).unwrap_or(bits_allocated);
+        Ok(PixelInfo {
+            rows,
+            columns,
+            samples_per_pixel: ds.get_some::<u16>(&tags::SamplesPerPixel).unwrap_or(1),
+            bits_allocated,
+            bits_stored,
+            high_bit: ds.get_some::<u16>(&tags::HighBit).unwrap_or(bits_stored.saturating_sub(1)),
+            pixel_representation: ds.get_some::<u16>(&tags::PixelRepresentation).unwrap_or(0),
+            planar_configuration: ds.get_some::<u16>(&tags::PlanarConfiguration).unwrap_or(0),
+            number_of_frames: ds.get_some::<u32>(&tags::NumberOfFrames).unwrap_or(1).max(1),
+            photometric_interpretation: ds
+                .get_some::<String>(&tags::PhotometricInterpretation)
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|| "MONOCHROME2".to_string()),
+        })
+    }
+
+    /// Bytes per sample: Bits Allocated rounded up to whole bytes.
+    pub fn bytes_per_sample(&self) -> usize {
+        (self.bits_allocated as usize).div_ceil(8)
+    }
+
+    /// Samples stored for one native frame: YBR_FULL_422 stores two per pixel
+    /// (each pair of pixels shares its chroma).
+    pub fn frame_samples(&self) -> usize {
+        let per_pixel = if self.photometric_interpretation == "YBR_FULL_422" { 2 } else { self.samples_per_pixel };
+        self.rows as usize * self.columns as usize * per_pixel as usize
+    }
+
+    /// Bytes of one native frame (1-bit data packed, rounded up to a byte).
+    pub fn frame_size(&self) -> usize {
+        (self.frame_samples() * self.bits_allocated as usize).div_ceil(8)
+    }
+}
+
+/// Compresses and decompresses Pixel Data for one encapsulated transfer syntax.
+///
+/// Frames are passed in native form as little-endian samples laid out per
+/// [`PixelInfo`]; a codec whose output layout differs from its input (e.g. a
+/// colour transform or planar output) says so through
+/// [`decoded_info`](Self::decoded_info) / [`encoded_info`](Self::encoded_info).
+/// Both directions default to [`UnsupportedFeature`](dpx_dicom_core::error::ErrorKind::UnsupportedFeature),
+/// so a decode-only codec implements just one.
+pub trait PixelCodec: Sync {
+    /// Decompresses one frame: its fragments, concatenated.
+    fn decode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        let _ = (frame, info);
+        Err(dicom_err!(UnsupportedFeature, "this codec cannot decode"))
+    }
+
+    /// Compresses one native frame into the bytes of a single fragment.
+    fn encode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        let _ = (frame, info);
+        Err(dicom_err!(UnsupportedFeature, "this codec cannot encode"))
+    }
+
+    /// How frames produced by [`decode_frame`](Self::decode_frame) are laid out,
+    /// given the attributes of the encoded data set.
+    fn decoded_info(&self, info: &PixelInfo) -> PixelInfo {
+        info.clone()
+    }
+
+    /// The attributes describing frames produced by
+    /// [`encode_frame`](Self::encode_frame), given those of the native input.
+    fn encoded_info(&self, info: &PixelInfo) -> PixelInfo {
+        info.clone()
+    }
+}
+
+/// Registers `codec` for the encapsulated transfer syntax `ts`; submit it with
+/// [`inventory::submit!`]. When several entries name the same syntax, the one
+/// found first wins.
+pub struct CodecEntry {
+    pub ts: &'static TransferSyntax,
+    pub codec: &'static dyn PixelCodec,
+}
+inventory::collect!(CodecEntry);
+
+/// The codec registered for `ts`, if any.
+pub fn find_codec(ts: &TransferSyntax) -> Option<&'static dyn PixelCodec> {
+    inventory::iter::<CodecEntry>.into_iter().find(|e| e.ts.uid == ts.uid).map(|e| e.codec)
+}
+
+fn require_codec(ts: &TransferSyntax) -> Result<&'static dyn PixelCodec> {
+    find_codec(ts).ok_or_else(|| dicom_err!(UnsupportedFeature, "no pixel data codec registered for {}", ts.uid))
+}
+
+/// Swaps each `width`-byte word, for moving samples between byte orders.
+pub(crate) fn swap_words(data: &mut [u8], width: usize) {
+    if width > 1 {
+        for word in data.chunks_exact_mut(width) {
+            word.reverse();
+        }
+    }
+}
+
+/// The root elements [`DcmWriter`](crate::DcmWriter) writes in place of those
+/// in `ds` so its Pixel Data is encoded for `target`: the converted Pixel Data
+/// plus any Image Pixel attributes the codecs changed. Empty when the syntaxes
+/// match or neither side is encapsulated.
+pub(crate) fn transcode(ds: &DataSet, target: &'static TransferSyntax) -> Result<Vec<(TagKey, Element)>> {
+    let source = ds.transfer_syntax();
+    if source.uid == target.uid || !(source.is_encapsulated || target.is_encapsulated) {
+        return Ok(Vec::new());
+    }
+    let (shared, root) = ds.context();
+    let Some(el) = root.map.get(tags::PixelData.key) else { return Ok(Vec::new()) };
+    let info = PixelInfo::from_dataset(ds)?;
+    let width = info.bytes_per_sample();
+    let frames = info.number_of_frames as usize;
+
+    // Native little-endian frames and their layout.
+    let (native, native_info): (Cow<[u8]>, PixelInfo) = match &el.value {
+        Stored::Native(Value::Pixels(px)) if matches!(**px, PixelData::Encapsulated { .. }) => {
+            ensure!(
+                source.is_encapsulated,
+                InvalidData,
+                "encapsulated Pixel Data under the native transfer syntax {}",
+                source.uid
+            );
+            let codec = require_codec(source)?;
+            let mut out = Vec::with_capacity(info.frame_size() * frames);
+            for frame in Frames::new(ds)?.iter() {
+                out.extend(codec.decode_frame(&frame?, &info)?);
+            }
+            (Cow::Owned(out), codec.decoded_info(&info))
+        }
+        Stored::Native(Value::Pixels(px)) => {
+            let PixelData::Native(b) = &**px else { unreachable!("encapsulated matched above") };
+            (Cow::Borrowed(&b[..]), info.clone())
+        }
+        Stored::Mapped(range) => {
+            let raw = shared.master().get(range.clone()).unwrap_or_default();
+            (Cow::Borrowed(raw), info.clone())
+        }
+        Stored::Owned(b) => (Cow::Borrowed(&b[..]), info.clone()),
+        _ => return Err(dicom_err!(InvalidData, "Pixel Data holds no pixel value")),
+    };
+    let native = match native {
+        // Stored samples follow the data set's byte order; codecs see little endian.
+        Cow::Borrowed(raw) if !shared.is_little_endian() && width > 1 => {
+            let mut v = raw.to_vec();
+            swap_words(&mut v, width);
+            Cow::Owned(v)
+        }
+        other => other,
+    };
+
+    let (pixels, vr, out_info) = if target.is_encapsulated {
+        let codec = require_codec(target)?;
+        let frame_size = native_info.frame_size();
+        ensure!(
+            native.len() >= frame_size * frames,
+            InvalidData,
+            "Pixel Data holds {} bytes, {frames} frames need {}",
+            native.len(),
+            frame_size * frames
+        );
+        let mut bot = Vec::with_capacity(frames);
+        let mut fragments = Vec::with_capacity(frames);
+        let mut offset = 0u32;
+        for frame in native.chunks_exact(frame_size.max(1)).take(frames) {
+            let mut fragment = codec.encode_frame(frame, &native_info)?;
+            if fragment.len() % 2 == 1 {
+                fragment.push(0);
+            }
+            bot.push(offset);
+            offset = offset.saturating_add(8 + fragment.len() as u32);
+            fragments.push(Bytes::from(fragment));
+        }
+        // A single frame's table carries no information; leave it empty.
+        if frames == 1 {
+            bot.clear();
+        }
+        (PixelData::Encapsulated { bot, fragments, eot: Vec::new() }, Vr::OB, codec.encoded_info(&native_info))
+    } else {
+        // Native values are kept in the data set's byte order; the serializer
+        // converts them to the target's.
+        let mut bytes = native.into_owned();
+        if !shared.is_little_endian() {
+            swap_words(&mut bytes, width);
+        }
+        let vr = if width > 1 { Vr::OW } else { Vr::OB };
+        (PixelData::Native(Bytes::from(bytes)), vr, native_info)
+    };
+
+    let mut overrides = Vec::new();
+    if out_info.photometric_interpretation != info.photometric_interpretation {
+        let pi = Value::Str(out_info.photometric_interpretation.clone());
+        overrides.push((tags::PhotometricInterpretation.key, Element::new(Vr::CS, Stored::Native(pi))));
+    }
+    if out_info.planar_configuration != info.planar_configuration && out_info.samples_per_pixel > 1 {
+        let pc = Value::UInt(OneOrMany::One(u64::from(out_info.planar_configuration)));
+        overrides.push((tags::PlanarConfiguration.key, Element::new(Vr::US, Stored::Native(pc))));
+    }
+    overrides.push((tags::PixelData.key, Element::new(vr, Stored::Native(Value::Pixels(Box::new(pixels))))));
+    Ok(overrides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_finds_built_in_codecs() {
+        assert!(find_codec(&TransferSyntax::EncapsulatedUncompressedExplicitVRLittleEndian).is_some());
+        assert!(find_codec(&TransferSyntax::RLELossless).is_some());
+        assert!(find_codec(&TransferSyntax::MPEG2MPML).is_none());
+    }
+}
@@ -0,0 +1,301 @@
+//! RLE Lossless (PS3.5 Annex G): each frame is split into byte planes — one
+//! segment per byte of each sample, most significant byte first, sample by
+//! sample — and each segment is PackBits-compressed row by row. A 64-byte
+//! header of little-endian `u32`s holds the segment count and offsets.
+
+use dpx_dicom_core::TransferSyntax;
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::ensure;
+
+use super::{CodecEntry, PixelCodec, PixelInfo};
+
+const HEADER_LEN: usize = 64;
+const MAX_SEGMENTS: usize = 15;
+
+struct Rle;
+
+/// Where byte `byte` (0 = least significant) of sample `sample` of pixel
+/// `pixel` sits in a native little-endian frame.
+struct Layout {
+    pixels: usize,
+    samples: usize,
+    width: usize,
+    planar: bool,
+}
+
+impl Layout {
+    fn new(info: &PixelInfo) -> Result<Self> {
+        ensure!(
+            info.bits_allocated >= 8 && info.bits_allocated.is_multiple_of(8),
+            UnsupportedFeature,
+            "RLE Lossless cannot carry {}-bit samples",
+            info.bits_allocated
+        );
+        let layout = Layout {
+            pixels: info.rows as usize * info.columns as usize,
+            samples: info.samples_per_pixel.max(1) as usize,
+            width: info.bytes_per_sample(),
+            planar: info.planar_configuration == 1,
+        };
+        ensure!(
+            layout.segments() <= MAX_SEGMENTS,
+            UnsupportedFeature,
+            "RLE Lossless allows {MAX_SEGMENTS} segments, {} samples of {} bytes need {}",
+            layout.samples,
+            layout.width,
+            layout.segments()
+        );
+        Ok(layout)
+    }
+
+    fn segments(&self) -> usize {
+        self.samples * self.width
+    }
+
+    /// The sample and byte carried by `segment`.
+    fn plane(&self, segment: usize) -> (usize, usize) {
+        (segment / self.width, self.width - 1 - segment % self.width)
+    }
+
+    fn index(&self, pixel: usize, sample: usize, byte: usize) -> usize {
+        let sample_index = if self.planar { sample * self.pixels + pixel } else { pixel * self.samples + sample };
+        sample_index * self.width + byte
+    }
+}
+
+impl PixelCodec for Rle {
+    fn decode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        let layout = Layout::new(info)?;
+        ensure!(frame.len() >= HEADER_LEN, InvalidData, "RLE frame of {} bytes has no header", frame.len());
+        let header: Vec<usize> = frame[..HEADER_LEN].chunks_exact(4).map(|c| u32_le(c) as usize).collect();
+        let count = header[0];
+        ensure!(
+            count == layout.segments(),
+            InvalidData,
+            "RLE frame has {count} segments, {} samples of {} bytes need {}",
+            layout.samples,
+            layout.width,
+            layout.segments()
+        );
+        let mut out = vec![0u8; layout.pixels * layout.segments()];
+        let mut plane = Vec::with_capacity(layout.pixels);
+        for segment in 0..count {
+            let start = header[1 + segment];
+            let end = if segment + 1 < count { header[2 + segment] } else { frame.len() };
+            ensure!(
+                HEADER_LEN <= start && start <= end && end <= frame.len(),
+                InvalidData,
+                "RLE segment {segment} spans {start}..{end} of a {}-byte frame",
+                frame.len()
+            );
+            plane.clear();
+            unpack_bits(&frame[start..end], layout.pixels, &mut plane);
+            ensure!(
+                plane.len() == layout.pixels,
+                InvalidData,
+                "RLE segment {segment} decodes to {} of {} bytes",
+                plane.len(),
+                layout.pixels
+            );
+            let (sample, byte) = layout.plane(segment);
+            for (pixel, &b) in plane.iter().enumerate() {
+                out[layout.index(pixel, sample, byte)] = b;
+            }
+        }
+        Ok(out)
+    }
+
+    fn encode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        let layout = Layout::new(info)?;
+        let size = layout.pixels * layout.segments();
+        ensure!(frame.len() >= size, InvalidData, "frame holds {} of {size} bytes", frame.len());
+        let columns = (info.columns as usize).max(1);
+        let mut out = vec![0u8; HEADER_LEN];
+        out[..4].copy_from_slice(&(layout.segments() as u32).to_le_bytes());
+        let mut plane = Vec::with_capacity(layout.pixels);
+        for segment in 0..layout.segments() {
+            let start = out.len();
+            out[4 + segment * 4..8 + segment * 4].copy_from_slice(&(start as u32).to_le_bytes());
+            let (sample, byte) = layout.plane(segment);
+            plane.clear();
+            plane.extend((0..layout.pixels).map(|pixel| frame[layout.index(pixel, sample, byte)]));
+            // Runs must not cross a row boundary (PS3.5 G.3.1).
+            for row in plane.chunks(columns) {
+                pack_bits(row, &mut out);
+            }
+            if (out.len() - start) % 2 == 1 {
+                out.push(0);
+            }
+        }
+        Ok(out)
+    }
+}
+
+inventory::submit! { CodecEntry { ts: &TransferSyntax::RLELossless, codec: &Rle } }
+
+fn u32_le(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// PackBits decoding of `input` into `out`, stopping once `limit` bytes are
+/// produced (a segment may carry a trailing pad byte).
+fn unpack_bits(input: &[u8], limit: usize, out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < input.len() && out.len() < limit {
+        let n = input[i] as i8;
+        i += 1;
+        match n {
+            0.. => {
+                let end = (i + n as usize + 1).min(input.len());
+                out.extend_from_slice(&input[i..end]);
+                i = end;
+            }
+            -127..=-1 => {
+                if let Some(&b) = input.get(i) {
+                    out.resize(out.len() + (1 - n as isize) as usize, b);
+                }
+                i += 1;
+            }
+            // -128 is a no-op.
+            _ => {}
+        }
+    }
+    out.truncate(limit);
+}
+
+/// PackBits encoding of `input`, appended to `out`: runs of 2 to 128 equal
+/// bytes become a replicate run, everything else literal runs of up to 128.
+fn pack_bits(input: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+    let mut literal = 0; // start of the pending literal run
+    while i < input.len() {
+        let run = input[i..].iter().take(128).take_while(|&&b| b == input[i]).count();
+        // A 2-byte run inside a literal run is cheaper kept literal.
+        if run >= 3 || (run == 2 && i == literal) {
+            flush_literal(&input[literal..i], out);
+            out.push((1 - run as isize) as i8 as u8);
+            out.push(input[i]);
+            i += run;
+            literal = i;
+        } else {
+            i += run;
+        }
+    }
+    flush_literal(&input[literal..], out);
+}
+
+fn flush_literal(bytes: &[u8], out: &mut Vec<u8>) {
+    for chunk in bytes.chunks(128) {
+        out.push((chunk.len() - 1) as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pixel_info;
+
+    #[test]
+    fn packbits_roundtrip() {
+        let mut input = vec![7u8; 300];
+        input.extend(0..=255u8);
+        input.extend([1, 1, 2, 3, 3, 3, 4]);
+        let mut packed = Vec::new();
+        pack_bits(&input, &mut packed);
+        assert!(packed.len() < input.len());
+        let mut unpacked = Vec::new();
+        unpack_bits(&packed, input.len(), &mut unpacked);
+        assert_eq!(unpacked, input);
+    }
+
+    #[test]
+    fn decodes_reference_segment() {
+        // The Apple PackBits sample, with a -128 no-op spliced in.
+        let packed = [0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A, 0x80, 0xFD, 0xAA, 0x03, 0x80, 0x00, 0x2A, 0x22, 0xF7, 0xAA];
+        let mut out = Vec::new();
+        unpack_bits(&packed, 24, &mut out);
+        assert_eq!(
+            out,
+            [
+                0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0xAA, 0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0x22, 0xAA, 0xAA, 0xAA,
+                0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA
+            ]
+        );
+    }
+
+    #[test]
+    fn sixteen_bit_segments_are_most_significant_first() {
+        let info = pixel_info(1, 2, 1, 16, "MONOCHROME2");
+        let frame = [0x34, 0x12, 0x78, 0x56]; // 0x1234, 0x5678 little endian
+        let encoded = Rle.encode_frame(&frame, &info).expect("encode");
+        assert_eq!(u32_le(&encoded[0..]), 2);
+        assert_eq!(u32_le(&encoded[4..]), 64);
+        assert_eq!(&encoded[64..67], [0x01, 0x12, 0x56]);
+        assert_eq!(Rle.decode_frame(&encoded, &info).expect("decode"), frame);
+    }
+
+    #[test]
+    fn rgb_planes_roundtrip_in_both_configurations() {
+        let mut info = pixel_info(3, 5, 3, 8, "RGB");
+        let frame: Vec<u8> = (0..45u8).map(|v| v / 4).collect();
+        let encoded = Rle.encode_frame(&frame, &info).expect("encode");
+        assert_eq!(u32_le(&encoded), 3);
+        assert_eq!(Rle.decode_frame(&encoded, &info).expect("decode"), frame);
+
+        info.planar_configuration = 1;
+        let encoded = Rle.encode_frame(&frame, &info).expect("encode planar");
+        // The red plane is the first 15 bytes of a planar frame.
+        let mut red = Vec::new();
+        unpack_bits(&encoded[64..], 15, &mut red);
+        assert_eq!(red, frame[..15]);
+        assert_eq!(Rle.decode_frame(&encoded, &info).expect("decode planar"), frame);
+    }
+
+    #[test]
+    fn thirty_two_bit_and_limits() {
+        let info32 = pixel_info(2, 2, 1, 32, "MONOCHROME2");
+        let frame: Vec<u8> = (0..16u8).collect();
+        let encoded = Rle.encode_frame(&frame, &info32).expect("encode");
+        assert_eq!(u32_le(&encoded), 4);
+        assert_eq!(Rle.decode_frame(&encoded, &info32).expect("decode"), frame);
+
+        let err = Rle.encode_frame(&[0; 4], &pixel_info(2, 2, 1, 1, "MONOCHROME2")).unwrap_err();
+        assert_eq!(err.kind, dpx_dicom_core::error::ErrorKind::UnsupportedFeature);
+        assert!(Rle.decode_frame(&encoded[..HEADER_LEN], &info32).is_err());
+    }
+
+    #[test]
+    fn multi_frame_through_writer() {
+        use crate::DcmWriter;
+        use crate::testing::Image;
+        use crate::value::{PixelData, Value};
+        use dpx_dicom_core::tags;
+
+        let native: Vec<u8> = (0..48u16).flat_map(|v| (v / 5 * 300).to_le_bytes()).collect();
+        let ds = Image::new(4, 4, 1, 16).photometric("MONOCHROME2").frames(3).native(native.clone());
+
+        let rle = DcmWriter::new().transfer_syntax(&TransferSyntax::RLELossless).to_bytes(&ds).expect("encode");
+        let ds2 = crate::DcmReader::new()
+            .header(crate::HeaderType::NoHeader)
+            .transfer_syntax(&TransferSyntax::RLELossless)
+            .parse_bytes(rle)
+            .expect("read")
+            .dataset
+            .expect("dataset");
+        let Ok(Value::Pixels(px)) = ds2.value(&tags::PixelData) else { panic!("no pixel data") };
+        let PixelData::Encapsulated { bot, fragments, .. } = *px else { panic!("expected encapsulated") };
+        assert_eq!(fragments.len(), 3);
+        assert_eq!(bot.len(), 3);
+
+        let back = DcmWriter::new().to_bytes(&ds2).expect("decode");
+        let ds3 = crate::DcmReader::new()
+            .header(crate::HeaderType::NoHeader)
+            .transfer_syntax(&TransferSyntax::ExplicitVRLittleEndian)
+            .parse_bytes(back)
+            .expect("read")
+            .dataset
+            .expect("dataset");
+        assert_eq!(ds3.get_bytes(&tags::PixelData).expect("native"), native);
+    }
+}
@@ -0,0 +1,25 @@
+//! Encapsulated Uncompressed Explicit VR Little Endian: native frames carried
+//! one per fragment, so single frames can be addressed without a codec.
+
+use dpx_dicom_core::TransferSyntax;
+use dpx_dicom_core::ensure;
+use dpx_dicom_core::error::Result;
+
+use super::{CodecEntry, PixelCodec, PixelInfo};
+
+struct Uncompressed;
+
+impl PixelCodec for Uncompressed {
+    fn decode_frame(&self, frame: &[u8], info: &PixelInfo) -> Result<Vec<u8>> {
+        let size = info.frame_size();
+        ensure!(frame.len() >= size, InvalidData, "uncompressed frame holds {} of {size} bytes", frame.len());
+        // Drop the fragment's even-length padding.
+        Ok(frame[..size].to_vec())
+    }
+
+    fn encode_frame(&self, frame: &[u8], _: &PixelInfo) -> Result<Vec<u8>> {
+        Ok(frame.to_vec())
+    }
+}
+
+inventory::submit! { CodecEntry { ts: &TransferSyntax::EncapsulatedUncompressedExplicitVRLittleEndian, codec: &Uncompressed } }
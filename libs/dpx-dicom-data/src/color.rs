@@ -0,0 +1,250 @@
+//! Colour conversion of decoded frames to RGB (PS3.3 C.7.6.3.1.2).
+//!
+//! [`ColorConverter`] turns YBR_FULL frames — including YBR_FULL_422 frames,
+//! whose chroma [`PixelDecoder`](crate::PixelDecoder) already upsamples — and
+//! PALETTE COLOR frames, through the Red/Green/Blue Palette Color Lookup Tables
+//! (plain or segmented), into RGB [`PixelBuffer`]s laid out interleaved or
+//! planar as asked.
+
+use dpx_dicom_core::error::{ErrContext, Result};
+use dpx_dicom_core::{Tag, dicom_err, ensure, tags};
+
+use crate::DataSet;
+use crate::codec::PixelInfo;
+use crate::lut::Lut;
+use crate::pixels::{PixelBuffer, Samples};
+
+/// The Red, Green and Blue Palette Color Lookup Table attributes: descriptor,
+/// data and segmented data.
+const PALETTE_TAGS: [(&Tag, &Tag, &Tag); 3] = [
+    (
+        &tags::RedPaletteColorLookupTableDescriptor,
+        &tags::RedPaletteColorLookupTableData,
+        &tags::SegmentedRedPaletteColorLookupTableData,
+    ),
+    (
+        &tags::GreenPaletteColorLookupTableDescriptor,
+        &tags::GreenPaletteColorLookupTableData,
+        &tags::SegmentedGreenPaletteColorLookupTableData,
+    ),
+    (
+        &tags::BluePaletteColorLookupTableDescriptor,
+        &tags::BluePaletteColorLookupTableData,
+        &tags::SegmentedBluePaletteColorLookupTableData,
+    ),
+];
+
+/// Converts frames of one data set to RGB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorConverter {
+    /// Red, green and blue tables of a PALETTE COLOR image.
+    palette: Option<[Lut; 3]>,
+    bits_stored: u16,
+    planar: bool,
+}
+
+impl ColorConverter {
+    /// Reads the Image Pixel attributes of `ds`, and its palette when the
+    /// Photometric Interpretation is PALETTE COLOR. Output is interleaved.
+    pub fn from_dataset(ds: &DataSet) -> Result<Self> {
+        let info = PixelInfo::from_dataset(ds)?;
+        let palette = if info.photometric_interpretation == "PALETTE COLOR" {
+            let signed = info.pixel_representation == 1;
+            let [red, green, blue] = PALETTE_TAGS.map(|tags| read_palette(ds, tags, signed));
+            Some([red?, green?, blue?])
+        } else {
+            None
+        };
+        Ok(ColorConverter { palette, bits_stored: info.bits_stored, planar: false })
+    }
+
+    /// Whether converted frames are laid out `RR…GG…BB…` rather than `RGBRGB…`.
+    pub fn planar(mut self, planar: bool) -> Self {
+        self.planar = planar;
+        self
+    }
+
+    /// `frame` as RGB samples. RGB frames only change layout; YBR_FULL frames
+    /// keep their sample type; PALETTE COLOR frames take 8- or 16-bit samples
+    /// from the palette's entry size.
+    pub fn to_rgb(&self, frame: PixelBuffer) -> Result<PixelBuffer> {
+        let rgb = match frame.photometric_interpretation.as_str() {
+            "RGB" => {
+                ensure!(
+                    frame.samples_per_pixel == 3,
+                    InvalidData,
+                    "RGB frame has {} samples per pixel",
+                    frame.samples_per_pixel
+                );
+                frame
+            }
+            "YBR_FULL" => self.ybr_full_to_rgb(frame)?,
+            "PALETTE COLOR" => self.palette_to_rgb(frame)?,
+            other => return Err(dicom_err!(UnsupportedFeature, "cannot convert {other} to RGB")),
+        };
+        Ok(if self.planar { rgb.into_planar() } else { rgb.into_interleaved() })
+    }
+
+    fn ybr_full_to_rgb(&self, frame: PixelBuffer) -> Result<PixelBuffer> {
+        ensure!(
+            frame.samples_per_pixel == 3,
+            InvalidData,
+            "YBR_FULL frame has {} samples per pixel",
+            frame.samples_per_pixel
+        );
+        let frame = frame.into_interleaved();
+        let bits = u32::from(self.bits_stored.clamp(1, 32));
+        let max = ((1u64 << bits) - 1) as f64;
+        let half = (1u64 << (bits - 1)) as f64;
+        let mut rgb = Vec::with_capacity(frame.samples.len());
+        for pixel in 0..frame.samples.len() / 3 {
+            let sample = |s: usize| frame.samples.get(pixel * 3 + s).unwrap_or_default();
+            let (y, cb, cr) = (sample(0), sample(1) - half, sample(2) - half);
+            rgb.extend(
+                [y + 1.402 * cr, y - 0.344136 * cb - 0.714136 * cr, y + 1.772 * cb].map(|v| v.round().clamp(0.0, max)),
+            );
+        }
+        let samples = retyped(&frame.samples, rgb);
+        Ok(PixelBuffer { photometric_interpretation: "RGB".to_string(), samples, ..frame })
+    }
+
+    fn palette_to_rgb(&self, frame: PixelBuffer) -> Result<PixelBuffer> {
+        let palette = self
+            .palette
+            .as_ref()
+            .ok_or_else(|| dicom_err!(InvalidData, "the data set's Photometric Interpretation is not PALETTE COLOR"))?;
+        ensure!(
+            frame.samples_per_pixel == 1,
+            InvalidData,
+            "PALETTE COLOR frame has {} samples per pixel",
+            frame.samples_per_pixel
+        );
+        let indices = (0..frame.samples.len()).map(|i| frame.samples.get(i).unwrap_or_default());
+        let rgb = indices.flat_map(|index| palette.each_ref().map(|lut| lut.lookup(index)));
+        let samples = if palette.iter().any(|lut| lut.bits > 8) {
+            Samples::U16(rgb.collect())
+        } else {
+            Samples::U8(rgb.map(|v| v as u8).collect())
+        };
+        Ok(PixelBuffer {
+            samples_per_pixel: 3,
+            planar: false,
+            photometric_interpretation: "RGB".to_string(),
+            samples,
+            ..frame
+        })
+    }
+}
+
+/// One palette table from its descriptor and either its data or its segmented
+/// data.
+fn read_palette(ds: &DataSet, (descriptor, data, segmented): (&Tag, &Tag, &Tag), signed: bool) -> Result<Lut> {
+    let values: Vec<i64> = ds.get_iter::<i64>(descriptor)?.collect();
+    let le = ds.is_little_endian();
+    let lut = match ds.value_some(data) {
+        Some(data) => Lut::from_values(&values, &data, le, signed),
+        None => Lut::from_segmented(&values, &ds.value(segmented)?, le, signed),
+    };
+    lut.err_context("reading the Palette Color Lookup Tables")
+}
+
+/// `values` in the sample type of `like`.
+fn retyped(like: &Samples, values: Vec<f64>) -> Samples {
+    let cast = values.into_iter();
+    match like {
+        Samples::U8(_) => Samples::U8(cast.map(|v| v as u8).collect()),
+        Samples::I8(_) => Samples::I8(cast.map(|v| v as i8).collect()),
+        Samples::U16(_) => Samples::U16(cast.map(|v| v as u16).collect()),
+        Samples::I16(_) => Samples::I16(cast.map(|v| v as i16).collect()),
+        Samples::U32(_) => Samples::U32(cast.map(|v| v as u32).collect()),
+        Samples::I32(_) => Samples::I32(cast.map(|v| v as i32).collect()),
+        Samples::F32(_) => Samples::F32(cast.map(|v| v as f32).collect()),
+        Samples::F64(_) => Samples::F64(cast.collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Image, palette, words};
+    use crate::{OneOrMany, PixelDecoder, Value};
+    use dpx_dicom_core::Vr;
+
+    fn convert(ds: &DataSet, planar: bool) -> PixelBuffer {
+        let frame = PixelDecoder::new(ds).unwrap().frame(0).unwrap();
+        ColorConverter::from_dataset(ds).unwrap().planar(planar).to_rgb(frame).unwrap()
+    }
+
+    #[test]
+    fn ybr_full_to_rgb() {
+        let mut ds = Image::new(1, 2, 3, 8).photometric("YBR_FULL").native(vec![76, 128, 85, 128, 255, 128]);
+        ds.set(&tags::PlanarConfiguration, 1u16).unwrap();
+        let rgb = convert(&ds, false);
+        assert_eq!(rgb.photometric_interpretation, "RGB");
+        assert_eq!(rgb.samples, Samples::U8(vec![254, 0, 0, 128, 128, 128]));
+        let planar = convert(&ds, true);
+        assert!(planar.planar);
+        assert_eq!(planar.samples, Samples::U8(vec![254, 128, 0, 128, 0, 128]));
+    }
+
+    #[test]
+    fn ybr_full_422_is_upsampled_then_converted() {
+        // Y1 Y2 Cb Cr: both pixels share neutral chroma.
+        let ds = Image::new(1, 2, 3, 8).photometric("YBR_FULL_422").native(vec![76, 200, 128, 128]);
+        assert_eq!(convert(&ds, false).samples, Samples::U8(vec![76, 76, 76, 200, 200, 200]));
+    }
+
+    #[test]
+    fn palette_color_expands() {
+        let mut ds = Image::new(1, 3, 1, 8).photometric("PALETTE COLOR").native(vec![10, 11, 200]);
+        palette(&mut ds, [2, 10, 16], [&[0xFFFF, 0], &[0, 0x8000], &[0, 0xFFFF]]);
+        let rgb = convert(&ds, false);
+        assert_eq!(rgb.samples_per_pixel, 3);
+        assert_eq!(rgb.samples, Samples::U16(vec![0xFFFF, 0, 0, 0, 0x8000, 0xFFFF, 0, 0x8000, 0xFFFF]));
+
+        // 8-bit entries, one per word.
+        palette(&mut ds, [2, 10, 8], [&[1, 255]; 3]);
+        assert_eq!(convert(&ds, false).samples, Samples::U8(vec![1, 1, 1, 255, 255, 255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn segmented_palette_expands() {
+        let mut ds = Image::new(1, 6, 1, 8).photometric("PALETTE COLOR").native(vec![0, 1, 2, 3, 4, 5]);
+        // Discrete 0, 100; linear to 200 over 2; indirect copy of the first segment.
+        let segments = [0, 2, 0, 100, 1, 2, 200, 2, 1, 0, 0];
+        for (descriptor, _, segmented) in PALETTE_TAGS {
+            ds.set_with_vr(descriptor, Vr::US, Value::UInt(OneOrMany::Many(vec![6, 0, 16]))).unwrap();
+            ds.set_with_vr(segmented, Vr::OW, words(&segments)).unwrap();
+        }
+        let Samples::U16(rgb) = convert(&ds, false).samples else { panic!("16-bit palette") };
+        let red: Vec<u16> = rgb.iter().step_by(3).copied().collect();
+        assert_eq!(red, vec![0, 100, 150, 200, 0, 100]);
+
+        let forward = Value::UInt(OneOrMany::Many(vec![0, 1, 5, 2, 1, 9, 0]));
+        assert!(Lut::from_segmented(&[2, 0, 16], &forward, true, false).is_err());
+
+        // Six levels of ten indirect segments, each repeating the ten of the
+        // level before: 30001 entries times 10^6, of which only the 65535 of
+        // the descriptor are expanded.
+        let mut nested = vec![0, 1, 5, 1, 30000, 9];
+        let (mut offset, mut count) = (0, 2);
+        for _ in 0..6 {
+            let start = nested.len() as u64;
+            for _ in 0..10 {
+                nested.extend([2, count, offset, 0]);
+            }
+            (offset, count) = (start, 10);
+        }
+        let lut = Lut::from_segmented(&[65535, 0, 16], &Value::UInt(OneOrMany::Many(nested)), true, false).unwrap();
+        assert_eq!([0.0, 30000.0, 30001.0, 60002.0].map(|i| lut.lookup(i)), [5, 9, 5, 5]);
+        let empty = Value::UInt(OneOrMany::Many(vec![0, 1, 5, 0, 0, 0, 1, 6]));
+        assert!(Lut::from_segmented(&[2, 0, 16], &empty, true, false).is_err());
+    }
+
+    #[test]
+    fn grayscale_is_not_converted() {
+        let ds = Image::new(1, 1, 1, 8).photometric("MONOCHROME2").native(vec![0]);
+        let frame = PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+        assert!(ColorConverter::from_dataset(&ds).unwrap().to_rgb(frame).is_err());
+    }
+}
@@ -7,7 +7,7 @@
 
 use bytes::Bytes;
 use dpx_dicom_core::vr::Kind;
-use dpx_dicom_core::{DicomDate, DicomDateTime, DicomTime, Tag, Vr, dicom_err, ensure};
+use dpx_dicom_core::{DicomDate, DicomDateTime, DicomTime, Tag, TagKey, Vr, dicom_err, ensure};
 use dpx_dicom_core::error::Result;
 
 use crate::dataset::{DatasetRole, Shared};
@@ -33,13 +33,47 @@ fn rtrim(b: &[u8]) -> &[u8] {
     &b[..end]
 }
 
+/// Whether a text VR may hold several `'\'`-separated values; in `LT ST UR UT`
+/// the backslash is an ordinary character.
+pub(crate) fn is_multi_valued_text(vr: Vr) -> bool {
+    !matches!(vr, Vr::LT | Vr::ST | Vr::UR | Vr::UT)
+}
+
 fn text_context(vr: Vr) -> dpx_dicom_charset::Context {
-    dpx_dicom_charset::Context {
-        is_multi_valued: !matches!(vr, Vr::LT | Vr::ST | Vr::UR | Vr::UT),
-        is_pn: vr == Vr::PN,
+    dpx_dicom_charset::Context { is_multi_valued: is_multi_valued_text(vr), is_pn: vr == Vr::PN }
+}
+
+/// Trims the padding and, where the VR deems them insignificant, the leading
+/// spaces of one text value.
+pub(crate) fn trim_text(vr: Vr, s: &str) -> &str {
+    let s = s.trim_end_matches([' ', '\0']);
+    match vr.info().kind {
+        Kind::Text { leading_spaces_important: false, .. } => s.trim_start_matches(' '),
+        _ => s,
     }
 }
 
+/// The path of attribute `key` within the Item at `parent`, by keyword:
+/// `ReferencedImageSequence[0].ReferencedSOPClassUID`.
+pub(crate) fn keyword_path(parent: &str, key: TagKey) -> String {
+    let meta = Tag::new(key, None).meta();
+    let name = meta.map(|m| m.keyword.to_string()).unwrap_or_else(|| key.to_string());
+    if parent.is_empty() { name } else { format!("{parent}.{name}") }
+}
+
+/// `"GGGGEEEE"`: the tag form of the DICOM JSON and XML models (attribute keys
+/// and `AT` values).
+pub(crate) fn tag_hex(key: TagKey) -> String {
+    format!("{:04X}{:04X}", key.group(), key.element())
+}
+
+/// Parses the `"GGGGEEEE"` tag form.
+pub(crate) fn parse_tag_hex(s: &str) -> Result<TagKey> {
+    ensure!(s.len() == 8 && s.bytes().all(|b| b.is_ascii_hexdigit()), InvalidData, "invalid tag {s:?}");
+    let v = u32::from_str_radix(s, 16).map_err(|_| dicom_err!(InvalidData, "invalid tag {s:?}"))?;
+    Ok(TagKey(v))
+}
+
 fn ints_value(v: Vec<i64>) -> Result<Value> {
     ensure!(!v.is_empty(), InvalidData, "empty numeric value");
     Ok(Value::Int(if v.len() == 1 {
@@ -360,6 +394,9 @@ impl<T: FromNumber> Iterator for OwnedNumbers<T> {
 /// for binary numerics, appended to `out`. The inverse of [`decode`]; `Pixels`
 /// are written by the serializer.
 pub(crate) fn encode(shared: &Shared, little_endian: bool, vr: Vr, value: &Value, out: &mut Vec<u8>) -> Result<()> {
+    if let Value::BulkData(uri) = value {
+        return Err(dicom_err!(InvalidData, "unresolved bulk data reference {uri} cannot be encoded"));
+    }
     let start = out.len();
     match vr {
         Vr::DA | Vr::TM | Vr::DT => match value {
@@ -371,6 +408,8 @@ pub(crate) fn encode(shared: &Shared, little_endian: bool, vr: Vr, value: &Value
                 false,
                 Some(shared.effective_tz()),
             )?,
+            // Already in DICOM syntax, e.g. set from text.
+            Value::Str(s) => out.extend_from_slice(s.as_bytes()),
             _ => {}
         },
         Vr::IS => write_numbers_text(out, numbers::<i64>(value)),
@@ -9,7 +9,8 @@ use dpx_dicom_core::TransferSyntax;
 
 use crate::convert::{FromValue, IntoValue};
 use crate::item::{Item, read_accessors, write_accessors};
-use crate::sequence::{Sequence, SequenceRef};
+use crate::path::AttributePath;
+use crate::sequence::{ItemMut, ItemRef, Sequence, SequenceRef};
 use crate::value::{TagHeader, Value};
 
 /// What top-level data the [`DataSet`] represents.
@@ -34,6 +35,7 @@ pub enum DatasetRole {
 
 /// Root-only context shared across the whole data set tree. Nested
 /// [`Item`]s do not carry it; they are interpreted under their owning root.
+#[derive(Clone)]
 pub(crate) struct Shared {
     kind: DatasetKind,
     /// Backing buffer for `Stored::Mapped` slices (a memory-mapped file, or
@@ -46,6 +48,7 @@ pub(crate) struct Shared {
     resolve_private: bool,
     /// Suppresses TRACE element-discovery events even when the subscriber is
     /// active. ponytail: a stub for the future `dicom.dataset.*` config option.
+    #[allow(dead_code)]
     disable_tracing: bool,
     /// Fallback timezone when (0008,0201) is absent (from configuration).
     default_tz: DicomTimeZoneOffset,
@@ -98,6 +101,10 @@ impl Shared {
 }
 
 /// An in-memory DICOM data set: root context plus the top-level attribute map.
+///
+/// Cloning copies the attribute tree; values read from a buffer or file keep
+/// sharing it.
+#[derive(Clone)]
 pub struct DataSet {
     shared: Shared,
     root: Item,
@@ -159,7 +166,8 @@ impl DataSet {
     pub(crate) fn context(&self) -> (&Shared, &Item) {
         (&self.shared, &self.root)
     }
-    fn ctx_mut(&mut self) -> (&Shared, &mut Item) {
+    /// Root context and mutable attributes, for in-place rewrites.
+    pub(crate) fn ctx_mut(&mut self) -> (&Shared, &mut Item) {
         (&self.shared, &mut self.root)
     }
 
@@ -177,7 +185,17 @@ impl DataSet {
             crate::item::vr_for_write(tag).map(|vr| vr.info().kind),
             Ok(dpx_dicom_core::vr::Kind::Text { translatable: true, .. })
         );
-        if !translatable {
+        if translatable {
+            self.stamp_charset_for(s);
+        }
+    }
+
+    /// The charset half of [`before_set`](Self::before_set): records the
+    /// configured `default_charset` in (0008,0005) when translatable text `s`
+    /// needs more than ASCII and no charset is established yet. Also used by the
+    /// JSON/XML readers, whose text arrives as Unicode.
+    pub(crate) fn stamp_charset_for(&mut self, s: &str) {
+        if self.shared.has_charset() {
             return;
         }
         let codec = self.shared.default_charset();
@@ -255,6 +273,106 @@ impl DataSet {
         self.shared.effective_tz()
     }
 
+    /// Rebuilds Private Data Element Characteristics Sequence (0008,0300)
+    /// from the private tag dictionary: one item per private block at the
+    /// root, with its Block Identifying Information Status, Nonidentifying
+    /// Private Elements and Deidentification Action Sequence. Removes the
+    /// attribute when there are no private blocks.
+    pub fn update_private_characteristics(&mut self) -> Result<()> {
+        crate::private_blocks::update_characteristics(self)
+    }
+
+    /// The Items holding the attribute at `path`, the data set itself for a
+    /// root attribute, whether or not the attribute is present in them.
+    pub fn items_at(&self, path: &AttributePath) -> Vec<ItemRef<'_>> {
+        let shared = &self.shared;
+        crate::path::items(shared, &self.root, path).into_iter().map(|item| ItemRef { shared, item }).collect()
+    }
+
+    /// The Items that hold the attribute at `path`, for editing, creating the
+    /// sequences and Items leading to them. An index may name the next Item
+    /// of a sequence but not one past it. `[*]` takes the existing Items only,
+    /// so a path through one creates nothing past it.
+    pub fn items_mut_at(&mut self, path: &AttributePath) -> Result<Vec<ItemMut<'_>>> {
+        let shared = &self.shared;
+        let items = crate::path::items_mut(shared, &mut self.root, path, true)?;
+        Ok(items.into_iter().map(|item| ItemMut { shared, item }).collect())
+    }
+
+    /// Decodes every attribute matched by `path`, in Item order.
+    pub fn get_at(&self, path: &AttributePath) -> Result<Vec<Value>> {
+        let tag = path.tag();
+        self.items_at(path).iter().filter(|item| item.contains(tag)).map(|item| item.value(tag)).collect()
+    }
+
+    /// Stores `value` in every Item matched by `path`, as
+    /// [`items_mut_at`](Self::items_mut_at) finds or creates them. Returns
+    /// the number of attributes stored.
+    pub fn set_value_at(&mut self, path: &AttributePath, value: Value) -> Result<usize> {
+        self.before_set(path.tag(), &value);
+        let mut items = self.items_mut_at(path)?;
+        for item in &mut items {
+            item.set_value(path.tag(), value.clone())?;
+        }
+        Ok(items.len())
+    }
+
+    /// Stores a typed value at `path`; see [`set_value_at`](Self::set_value_at).
+    pub fn set_at<T: IntoValue>(&mut self, path: &AttributePath, value: T) -> Result<usize> {
+        self.set_value_at(path, value.into_value())
+    }
+
+    /// Stores `value` in place of every attribute matched by `path`, keeping
+    /// its VR. Unlike [`set_value_at`](Self::set_value_at), creates nothing.
+    /// Returns the number of attributes replaced.
+    pub fn replace_value_at(&mut self, path: &AttributePath, value: Value) -> Result<usize> {
+        if !self.contains_at(path) {
+            return Ok(0);
+        }
+        self.before_set(path.tag(), &value);
+        let (shared, tag) = (&self.shared, path.tag());
+        let mut replaced = 0;
+        for item in crate::path::items_mut(shared, &mut self.root, path, false)? {
+            let Some(vr) = item.vr_of(shared, tag) else { continue };
+            item.set_with_vr(shared, tag, vr, value.clone())?;
+            replaced += 1;
+        }
+        Ok(replaced)
+    }
+
+    /// Removes every attribute matched by `path`, returning how many were
+    /// present.
+    pub fn remove_at(&mut self, path: &AttributePath) -> usize {
+        if !self.contains_at(path) {
+            return 0;
+        }
+        let shared = &self.shared;
+        let Ok(items) = crate::path::items_mut(shared, &mut self.root, path, false) else { return 0 };
+        items.into_iter().map(|item| item.remove(shared, path.tag())).filter(|removed| *removed).count()
+    }
+
+    /// Whether any attribute matches `path`. Looked up before editing, which
+    /// would otherwise resolve private blocks for writing even when nothing
+    /// matches.
+    fn contains_at(&self, path: &AttributePath) -> bool {
+        let shared = &self.shared;
+        crate::path::items(shared, &self.root, path).iter().any(|item| item.contains(shared, path.tag()))
+    }
+
+    /// Checks this data set against the built-in definition of the IOD of its
+    /// SOP Class UID, which only covers CT Image and Secondary Capture Image;
+    /// see [`IodDictionary`](crate::IodDictionary) to use other definitions.
+    pub fn validate(&self) -> crate::ValidationReport {
+        crate::validate::validate(self)
+    }
+
+    /// Checks every value of this data set, nested ones included, against the
+    /// VR and VM of its attribute: multiplicity, length, characters and, for
+    /// `AS`, `DA`, `DS`, `DT`, `IS`, `TM` and `UI`, syntax.
+    pub fn validate_values(&self) -> crate::ValidationReport {
+        crate::validate::validate_values(self)
+    }
+
     /// Rebuilds the charset/timezone cache from the (0008,0005)/(0008,0201)
     /// attributes. Needed only after editing those through a low-level path
     /// that bypasses the maintaining mutators.
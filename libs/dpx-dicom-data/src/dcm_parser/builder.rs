@@ -0,0 +1,224 @@
+//! The [`DcmVisitor`] that materializes parse events into an [`ElementMap`]:
+//! values stay zero-copy ranges into a mapped master buffer (or owned copies
+//! otherwise), pixel fragments are sliced from it, and sequences nest as
+//! [`Item`]s. Under `file_offsets` it also records every on-disk header.
+
+use bytes::Bytes;
+use dpx_dicom_core::{TagKey, Vr, tags};
+
+use super::core::note;
+use super::input::Source;
+use super::visitor::{DcmVisitor, ElementHeader, Visit};
+use crate::item::{ElementMap, Item, PushNote};
+use crate::value::{Element, PixelData, Stored, TagHeader, Value};
+
+/// The on-disk header of an element, as kept under `file_offsets`.
+#[cfg(feature = "file_offsets")]
+fn tag_header(h: &ElementHeader, size: Option<usize>) -> TagHeader {
+    TagHeader { offset: h.offset as i64, tag: h.tag, vr: h.vr_code, length: h.length, size }
+}
+
+/// Encapsulated pixel data being collected between its start and end events.
+#[derive(Default)]
+struct Pixels {
+    bot: Vec<u32>,
+    fragments: Vec<Bytes>,
+    /// Offset Table, fragment and delimiter headers, in file order.
+    #[cfg(feature = "file_offsets")]
+    spans: Vec<TagHeader>,
+}
+
+pub(crate) struct Builder<'a> {
+    /// The parsed stream, sliced zero-copy for pixel fragments (shares the
+    /// mmap/in-memory Arc).
+    master: &'a Bytes,
+    /// Values become ranges into `master` rather than owned copies.
+    mapped: bool,
+    disable_tracing: bool,
+    /// The data set and the items being filled, innermost last.
+    maps: Vec<ElementMap>,
+    /// Items collected so far by each open sequence, innermost last.
+    sequences: Vec<Vec<Item>>,
+    pixels: Option<Pixels>,
+}
+
+impl<'a> Builder<'a> {
+    pub(crate) fn new(source: &'a Source, disable_tracing: bool) -> Self {
+        Builder {
+            master: &source.data,
+            mapped: source.mapped,
+            disable_tracing,
+            maps: vec![ElementMap::default()],
+            sequences: Vec::new(),
+            pixels: None,
+        }
+    }
+
+    /// The top-level map; anything left open by a stopped walk is dropped.
+    pub(crate) fn finish(mut self) -> ElementMap {
+        self.maps.truncate(1);
+        let mut map = self.maps.pop().unwrap_or_default();
+        extended_offsets(self.master, &mut map);
+        map
+    }
+
+    fn push(&mut self, offset: usize, key: TagKey, el: Element) {
+        let Some(map) = self.maps.last_mut() else { return };
+        if let Some(pushed) = map.push_parsed(key, el) {
+            let what = match pushed {
+                PushNote::Duplicate => "duplicate",
+                PushNote::OutOfOrder => "out-of-order",
+            };
+            note(
+                self.disable_tracing,
+                format_args!("{what} tag ({:04X},{:04X}) at offset {offset}", key.group(), key.element()),
+            );
+        }
+    }
+}
+
+impl DcmVisitor for Builder<'_> {
+    fn value(&mut self, h: &ElementHeader, value: &[u8]) -> Visit {
+        let end = h.value_offset + value.len();
+        let stored = if self.mapped {
+            Stored::Mapped(h.value_offset..end)
+        } else {
+            Stored::Owned(Bytes::copy_from_slice(value))
+        };
+        #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
+        let mut el = Element::new(h.vr, stored);
+        #[cfg(feature = "file_offsets")]
+        el.header.push(tag_header(h, Some(end - h.offset)));
+        self.push(h.offset, h.tag, el);
+        Visit::Continue
+    }
+
+    fn sequence_start(&mut self, _: &ElementHeader) -> Visit {
+        self.sequences.push(Vec::new());
+        Visit::Continue
+    }
+
+    fn item_start(&mut self, _: usize, _: &TagHeader) -> Visit {
+        self.maps.push(ElementMap::default());
+        Visit::Continue
+    }
+
+    #[cfg_attr(not(feature = "file_offsets"), allow(unused_variables))]
+    fn item_end(&mut self, _: usize, item: &TagHeader, delimiter: Option<&TagHeader>) -> Visit {
+        let map = self.maps.pop().unwrap_or_default();
+        #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
+        let mut built = Item::from_map(map);
+        #[cfg(feature = "file_offsets")]
+        {
+            built.header.push(item.clone());
+            built.header.extend(delimiter.cloned());
+        }
+        if let Some(items) = self.sequences.last_mut() {
+            items.push(built);
+        }
+        Visit::Continue
+    }
+
+    #[cfg_attr(not(feature = "file_offsets"), allow(unused_variables))]
+    fn sequence_end(&mut self, h: &ElementHeader, end: usize) -> Visit {
+        let items = self.sequences.pop().unwrap_or_default();
+        #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
+        let mut el = Element::new(Vr::SQ, Stored::Items(items));
+        #[cfg(feature = "file_offsets")]
+        {
+            el.header.push(tag_header(h, Some(end - h.offset)));
+            if h.is_undefined_length() {
+                el.header.push(TagHeader {
+                    offset: (end - 8) as i64,
+                    tag: tags::SequenceDelimitationItem.key,
+                    vr: None,
+                    length: (0, 4),
+                    size: None,
+                });
+            }
+        }
+        self.push(h.offset, h.tag, el);
+        Visit::Continue
+    }
+
+    fn pixel_data_start(&mut self, _: &ElementHeader) -> Visit {
+        self.pixels = Some(Pixels::default());
+        Visit::Continue
+    }
+
+    #[cfg_attr(not(feature = "file_offsets"), allow(unused_variables))]
+    fn pixel_offset_table(&mut self, item: &TagHeader, offsets: &[u32]) -> Visit {
+        if let Some(px) = &mut self.pixels {
+            px.bot = offsets.to_vec();
+            #[cfg(feature = "file_offsets")]
+            px.spans.push(item.clone());
+        }
+        Visit::Continue
+    }
+
+    fn pixel_fragment(&mut self, _: usize, item: &TagHeader, fragment: &[u8]) -> Visit {
+        if let Some(px) = &mut self.pixels {
+            let start = item.offset as usize + 8;
+            px.fragments.push(self.master.slice(start..start + fragment.len()));
+            #[cfg(feature = "file_offsets")]
+            px.spans.push(item.clone());
+        }
+        Visit::Continue
+    }
+
+    #[cfg_attr(not(feature = "file_offsets"), allow(unused_variables))]
+    fn pixel_data_end(&mut self, h: &ElementHeader, end: usize, delimiter: Option<&TagHeader>) -> Visit {
+        let px = self.pixels.take().unwrap_or_default();
+        let value =
+            Value::Pixels(Box::new(PixelData::Encapsulated { bot: px.bot, fragments: px.fragments, eot: Vec::new() }));
+        #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
+        let mut el = Element::new(h.vr, Stored::Native(value));
+        #[cfg(feature = "file_offsets")]
+        {
+            el.header.push(tag_header(h, Some(end - h.offset)));
+            el.header.extend(px.spans);
+            el.header.extend(delimiter.cloned());
+        }
+        self.push(h.offset, h.tag, el);
+        Visit::Continue
+    }
+}
+
+/// Moves a root (7FE0,0001) Extended Offset Table and its (7FE0,0002) Lengths
+/// into the encapsulated Pixel Data they describe. A missing or mismatched
+/// Lengths table is made up from the fragment each offset points at.
+fn extended_offsets(master: &Bytes, map: &mut ElementMap) {
+    if !map.contains_key(tags::ExtendedOffsetTable.key) {
+        return;
+    }
+    let Some(Element { value: Stored::Native(Value::Pixels(px)), .. }) = map.get(tags::PixelData.key) else { return };
+    let PixelData::Encapsulated { fragments, .. } = &**px else { return };
+    let mut position = 0u64;
+    let sizes: Vec<(u64, u64)> = fragments
+        .iter()
+        .map(|f| {
+            let at = position;
+            position += 8 + f.len() as u64;
+            (at, f.len() as u64)
+        })
+        .collect();
+    // Encapsulated transfer syntaxes are little endian.
+    let words = |el: Element| -> Vec<u64> {
+        let bytes = match &el.value {
+            Stored::Mapped(range) => master.get(range.clone()).unwrap_or_default(),
+            Stored::Owned(b) => &b[..],
+            _ => &[],
+        };
+        bytes.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().expect("8-byte chunk"))).collect()
+    };
+    let offsets = map.remove(tags::ExtendedOffsetTable.key).map(words).unwrap_or_default();
+    let lengths = map.remove(tags::ExtendedOffsetTableLengths.key).map(words).filter(|l| l.len() == offsets.len());
+    let lengths = lengths.unwrap_or_else(|| {
+        offsets.iter().map(|o| sizes.iter().find(|(at, _)| at == o).map_or(0, |&(_, len)| len)).collect()
+    });
+    if let Some(Element { value: Stored::Native(Value::Pixels(px)), .. }) = map.get_mut(tags::PixelData.key)
+        && let PixelData::Encapsulated { eot, .. } = &mut **px
+    {
+        *eot = offsets.into_iter().zip(lengths).collect();
+    }
+}
@@ -3,6 +3,10 @@
 //! buffer (mmap or read-into-memory); a future async wrapper feeds the same
 //! core.
 //!
+//! The walk reports to a [`DcmVisitor`]; a [`DataSet`] is assembled by the
+//! [`builder`](super::builder) visitor, while [`DcmReader`](super::DcmReader)'s
+//! `visit_*` entry points hand the events to the caller.
+//!
 //! Handles the File Meta header (preamble + group 0002), Explicit/Implicit VR
 //! (LE/BE), defined and undefined-length sequences with Item / Item Delimitation
 //! / Sequence Delimitation special attributes, encapsulated pixel data (Basic
@@ -34,16 +38,16 @@ use dpx_dicom_core::error::Result;
 use dpx_dicom_core::{Tag, TagKey, TransferSyntax, Vr, ensure, tags};
 use tracing::{info, trace};
 
+use super::builder::Builder;
 use super::input::Source;
+use super::visitor::{DcmVisitor, ElementHeader, Mute, Visit};
 use crate::dataset::{DataSet, DatasetKind};
-use crate::item::{ElementMap, Item, PushNote};
-use crate::value::{Element, PixelData, Stored, Value};
-#[cfg(feature = "file_offsets")]
-use crate::value::TagHeader;
+use crate::item::{ElementMap, Item};
+use crate::value::{Stored, TagHeader};
 
-const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+pub(crate) const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
 const PREAMBLE_LEN: usize = 128;
-const META_START: usize = 132; // 128-byte preamble + "DICM"
+pub(super) const META_START: usize = 132; // 128-byte preamble + "DICM"
 
 /// Explicit-VR elements whose header uses 2 reserved bytes plus a 32-bit length.
 fn is_long_form(vr: Vr) -> bool {
@@ -66,12 +70,12 @@ fn read_u16(buf: &[u8], at: usize, little_endian: bool) -> u16 {
     if little_endian { u16::from_le_bytes(a) } else { u16::from_be_bytes(a) }
 }
 
-fn read_u32(buf: &[u8], at: usize, little_endian: bool) -> u32 {
+pub(super) fn read_u32(buf: &[u8], at: usize, little_endian: bool) -> u32 {
     let a = [buf[at], buf[at + 1], buf[at + 2], buf[at + 3]];
     if little_endian { u32::from_le_bytes(a) } else { u32::from_be_bytes(a) }
 }
 
-fn read_tag(buf: &[u8], at: usize, little_endian: bool) -> TagKey {
+pub(super) fn read_tag(buf: &[u8], at: usize, little_endian: bool) -> TagKey {
     TagKey::new(read_u16(buf, at, little_endian), read_u16(buf, at + 2, little_endian))
 }
 
@@ -93,19 +97,24 @@ impl Decoder {
 /// Decoding state for the File Meta group (0002), always Explicit VR Little Endian.
 const META_LE: Decoder = Decoder { little_endian: true, explicit_vr: true };
 
-/// Per-item file offsets collected while parsing encapsulated pixel data;
-/// a zero-size `()` when `file_offsets` is disabled.
-#[cfg(feature = "file_offsets")]
-type PixelSpans = Vec<TagHeader>;
-#[cfg(not(feature = "file_offsets"))]
-type PixelSpans = ();
-
 /// A [`TagHeader`] for an Item (FFFE,E000) carrying `content_len` content bytes.
-#[cfg(feature = "file_offsets")]
 fn item_header(offset: usize, content_len: u32) -> TagHeader {
     TagHeader { offset: offset as i64, tag: tags::Item.key, vr: None, length: (content_len, 4), size: Some(content_len as usize) }
 }
 
+/// A [`TagHeader`] for a sequence Item whose content size is not yet known.
+fn item_header_open(offset: usize, length: u32) -> TagHeader {
+    TagHeader { offset: offset as i64, tag: tags::Item.key, vr: None, length: (length, 4), size: None }
+}
+
+/// Reports a recoverable parsing anomaly (a file deviating from the standard)
+/// at INFO unless tracing is disabled.
+pub(super) fn note(disable_tracing: bool, args: std::fmt::Arguments) {
+    if !disable_tracing {
+        info!(target: "dpx_dicom::parse", "{args}");
+    }
+}
+
 /// Sibling values that disambiguate a dictionary VR, gathered per data-set level
 /// and inherited by nested items (a nested element falls back to its ancestors).
 #[derive(Default, Clone, Copy)]
@@ -118,25 +127,10 @@ struct AmbiguityCtx {
     waveform_bits: Option<u16>,
 }
 
-/// A parsed element header (tag, VR, length), without the value content.
-struct Header {
-    tag: TagKey,
-    vr: Vr,
-    // Only consumed under `file_offsets` (recorded in `TagHeader`).
-    #[cfg_attr(not(feature = "file_offsets"), allow(dead_code))]
-    vr_code: Option<[u8; 2]>,
-    length: (u32, usize),
-    value_start: usize,
-    undefined: bool,
-}
-
-/// Recursive-descent builder over a fully-buffered stream.
+/// Recursive-descent walker over a fully-buffered stream, reporting to a
+/// [`DcmVisitor`].
 struct Parser<'a> {
     buf: &'a [u8],
-    /// The same bytes as `buf`, kept as `Bytes` so encapsulated pixel-data
-    /// fragments can be sliced zero-copy (shares the mmap/in-memory Arc).
-    master: &'a Bytes,
-    mapped: bool,
     /// Sorted whitelist; `None` keeps every (top-level) tag.
     whitelist: Option<&'a [TagKey]>,
     /// Stop once a top-level tag exceeds this; `TagKey::MAX` = no limit.
@@ -145,14 +139,35 @@ struct Parser<'a> {
     /// Set when at least one element got an ambiguous (`Vr::Undefined`) VR that a
     /// post-read pass must resolve. Interior mutability keeps parsing on `&self`.
     ambiguous: Cell<bool>,
+    /// Set once a visitor returns [`Visit::Stop`]; every loop unwinds on it.
+    stopped: Cell<bool>,
 }
 
-impl Parser<'_> {
+impl<'a> Parser<'a> {
+    fn new(buf: &'a [u8], whitelist: Option<&'a [TagKey]>, stop_after: TagKey, disable_tracing: bool) -> Self {
+        Parser {
+            buf,
+            whitelist,
+            stop_after,
+            disable_tracing,
+            ambiguous: Cell::new(false),
+            stopped: Cell::new(false),
+        }
+    }
+
+    /// Records a [`Visit::Stop`] and hands the decision back to the caller.
+    fn steer(&self, visit: Visit) -> Visit {
+        if visit == Visit::Stop {
+            self.stopped.set(true);
+        }
+        visit
+    }
+
     fn kept(&self, tag: TagKey) -> bool {
         self.whitelist.is_none_or(|wl| wl.binary_search_by(|k| k.0.cmp(&tag.0)).is_ok())
     }
 
-    fn trace(&self, pos: usize, h: &Header) {
+    fn trace(&self, pos: usize, h: &ElementHeader) {
         if !self.disable_tracing {
             trace!(
                 target: "dpx_dicom::parse",
@@ -166,17 +181,7 @@ impl Parser<'_> {
     /// Reports a recoverable parsing anomaly (a file deviating from the standard)
     /// at INFO, gated by the same flag as element tracing.
     fn note(&self, args: std::fmt::Arguments) {
-        if !self.disable_tracing {
-            info!(target: "dpx_dicom::parse", "{args}");
-        }
-    }
-
-    fn note_push(&self, tag: TagKey, pos: usize, note: PushNote) {
-        let what = match note {
-            PushNote::Duplicate => "duplicate",
-            PushNote::OutOfOrder => "out-of-order",
-        };
-        self.note(format_args!("{what} tag ({:04X},{:04X}) at offset {pos}", tag.group(), tag.element()));
+        note(self.disable_tracing, args);
     }
 
     /// Parses the element header at `pos` under `ts`. Lenient about real-world
@@ -184,7 +189,7 @@ impl Parser<'_> {
     /// Explicit VR; an Explicit-VR element whose "VR" bytes are not a valid VR
     /// is re-read as Implicit VR Little Endian (mixed encoding, as produced by
     /// some converters). Buffer bounds for the *value* are checked by the caller.
-    fn read_header(&self, pos: usize, dec: Decoder) -> Result<Header> {
+    fn read_header(&self, pos: usize, dec: Decoder) -> Result<ElementHeader> {
         let buf = self.buf;
         let order = dec.little_endian;
         ensure!(pos + 4 <= buf.len(), InvalidData, "truncated tag at offset {pos}");
@@ -239,8 +244,7 @@ impl Parser<'_> {
             (vr, None, len, 4usize)
         };
 
-        let undefined = length_value == UNDEFINED_LENGTH;
-        Ok(Header { tag, vr, vr_code, length: (length_value, length_size), value_start: p, undefined })
+        Ok(ElementHeader { offset: pos, tag, vr, vr_code, length: (length_value, length_size), value_offset: p })
     }
 
     /// Scans the File Meta group (0002) from [`META_START`], returning the offset
@@ -250,10 +254,10 @@ impl Parser<'_> {
         let mut pos = META_START;
         loop {
             let Ok(h) = self.read_header(pos, META_LE) else { break pos };
-            if h.tag.group() != 0x0002 || h.undefined {
+            if h.tag.group() != 0x0002 || h.is_undefined_length() {
                 break pos;
             }
-            let end = h.value_start + h.length.0 as usize;
+            let end = h.value_offset + h.length.0 as usize;
             if end <= pos || end > self.buf.len() {
                 break pos;
             }
@@ -342,22 +346,18 @@ impl Parser<'_> {
     }
 
     /// Top-level (or File Meta) data set in `[start, end)`: applies the
-    /// whitelist filter and the early-stop tag.
-    fn dataset(&self, start: usize, end: usize, dec: Decoder) -> Result<ElementMap> {
-        let mut map = ElementMap::default();
+    /// whitelist filter and the early-stop tag. Elements filtered out are still
+    /// walked (silently) to find where they end.
+    fn dataset<V: DcmVisitor>(&self, start: usize, end: usize, dec: Decoder, v: &mut V) {
         let mut pos = start;
-        while pos + 4 <= end {
+        while pos + 4 <= end && !self.stopped.get() {
             let tag = read_tag(self.buf, pos, dec.little_endian);
             if tag.0 > self.stop_after.0 {
                 break;
             }
-            match self.element(pos, dec) {
-                Ok((etag, el, next)) => {
-                    if self.kept(etag)
-                        && let Some(note) = map.push_parsed(etag, el)
-                    {
-                        self.note_push(etag, pos, note);
-                    }
+            let walked = if self.kept(tag) { self.element(pos, dec, v) } else { self.element(pos, dec, &mut Mute) };
+            match walked {
+                Ok(next) => {
                     if next <= pos {
                         break;
                     }
@@ -369,12 +369,11 @@ impl Parser<'_> {
                 }
             }
         }
-        Ok(map)
     }
 
-    /// Parses one data element at `pos`, recursing into sequences. Returns the
-    /// tag, the built element, and the offset just past it.
-    fn element(&self, pos: usize, dec: Decoder) -> Result<(TagKey, Element, usize)> {
+    /// Parses one data element at `pos`, reporting it to `v` and recursing into
+    /// sequences. Returns the offset just past it.
+    fn element<V: DcmVisitor>(&self, pos: usize, dec: Decoder, v: &mut V) -> Result<usize> {
         let h = self.read_header(pos, dec)?;
         self.trace(pos, &h);
 
@@ -382,54 +381,30 @@ impl Parser<'_> {
         // PixelData (7FE0,0010). Anything else with undefined length is, per
         // PS3.5 6.2.2, an implicitly-encoded sequence (commonly a private SQ
         // read as VR UN) — parse it as one rather than as pixel data.
-        let is_encapsulated_pixels = h.undefined && h.tag == tags::PixelData.key;
-        if h.vr == Vr::SQ || (h.undefined && !is_encapsulated_pixels) {
+        let is_encapsulated_pixels = h.is_undefined_length() && h.tag == tags::PixelData.key;
+        if h.vr == Vr::SQ || (h.is_undefined_length() && !is_encapsulated_pixels) {
             if h.vr != Vr::SQ {
                 self.note(format_args!(
                     "undefined-length ({:04X},{:04X}) VR={:?} at offset {pos}; reading as a sequence",
                     h.tag.group(), h.tag.element(), h.vr
                 ));
             }
-            let (items, next) = self.sequence(&h, dec);
-            #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
-            let mut el = Element::new(Vr::SQ, Stored::Items(items));
-            #[cfg(feature = "file_offsets")]
-            {
-                el.header.push(TagHeader {
-                    offset: pos as i64,
-                    tag: h.tag,
-                    vr: h.vr_code,
-                    length: h.length,
-                    size: Some(next - pos),
-                });
-                if h.undefined {
-                    el.header.push(TagHeader {
-                        offset: (next - 8) as i64,
-                        tag: tags::SequenceDelimitationItem.key,
-                        vr: None,
-                        length: (0, 4),
-                        size: None,
-                    });
-                }
-            }
-            return Ok((h.tag, el, next));
+            return Ok(match self.steer(v.sequence_start(&h)) {
+                Visit::Continue => self.sequence(&h, dec, v),
+                Visit::Skip => self.sequence(&h, dec, &mut Mute),
+                Visit::Stop => pos,
+            });
         }
 
         if is_encapsulated_pixels {
-            #[cfg(feature = "file_offsets")]
-            let (px, next, spans) = self.encapsulated(&h, pos, dec);
-            #[cfg(not(feature = "file_offsets"))]
-            let (px, next, _spans) = self.encapsulated(&h, pos, dec);
-            #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
-            let mut el = Element::new(h.vr, Stored::Native(Value::Pixels(Box::new(px))));
-            #[cfg(feature = "file_offsets")]
-            {
-                el.header = spans;
-            }
-            return Ok((h.tag, el, next));
+            return Ok(match self.steer(v.pixel_data_start(&h)) {
+                Visit::Continue => self.encapsulated(&h, dec, v),
+                Visit::Skip => self.encapsulated(&h, dec, &mut Mute),
+                Visit::Stop => pos,
+            });
         }
 
-        let value_end = h.value_start + h.length.0 as usize;
+        let value_end = h.value_offset + h.length.0 as usize;
         ensure!(
             value_end <= self.buf.len(),
             InvalidData,
@@ -438,38 +413,21 @@ impl Parser<'_> {
             h.tag.group(),
             h.tag.element()
         );
-        let value = if self.mapped {
-            Stored::Mapped(h.value_start..value_end)
-        } else {
-            Stored::Owned(Bytes::copy_from_slice(&self.buf[h.value_start..value_end]))
-        };
-        #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
-        let mut el = Element::new(h.vr, value);
-        #[cfg(feature = "file_offsets")]
-        el.header.push(TagHeader {
-            offset: pos as i64,
-            tag: h.tag,
-            vr: h.vr_code,
-            length: h.length,
-            size: Some(value_end - pos),
-        });
-        Ok((h.tag, el, value_end))
+        if self.steer(v.element(&h)) == Visit::Continue {
+            self.steer(v.value(&h, &self.buf[h.value_offset..value_end]));
+        }
+        Ok(value_end)
     }
 
     /// Parses encapsulated (compressed) pixel data: an undefined-length element
-    /// at `el_offset` whose value is a Basic Offset Table item followed by
-    /// fragment items, terminated by a Sequence Delimitation Item. Fragments are
-    /// sliced zero-copy from the master buffer. Returns the pixel data, the
-    /// offset just past the delimiter, and (under `file_offsets`) one
-    /// [`TagHeader`] per discovered item: the pixel-data element, the Basic
-    /// Offset Table, each fragment, and the Sequence Delimitation Item.
-    fn encapsulated(&self, h: &Header, el_offset: usize, dec: Decoder) -> (PixelData, usize, PixelSpans) {
+    /// whose value is a Basic Offset Table item followed by fragment items,
+    /// terminated by a Sequence Delimitation Item. Returns the offset just past
+    /// the delimiter. Once a fragment callback skips, the rest are walked
+    /// without being reported.
+    fn encapsulated<V: DcmVisitor>(&self, h: &ElementHeader, dec: Decoder, v: &mut V) -> usize {
         let order = dec.little_endian;
-        let mut pos = h.value_start;
-        let mut bot = Vec::new();
-        let mut fragments: Vec<Bytes> = Vec::new();
-        #[cfg(feature = "file_offsets")]
-        let mut spans: Vec<TagHeader> = Vec::new();
+        let mut pos = h.value_offset;
+        let mut reporting = true;
 
         // First item is the Basic Offset Table (it may be empty). Some files
         // omit it; tolerate that and treat what follows as the first fragment.
@@ -477,15 +435,15 @@ impl Parser<'_> {
             let bot_len = read_u32(self.buf, pos + 4, order);
             let bot_end = pos + 8 + bot_len as usize;
             if bot_len != UNDEFINED_LENGTH && bot_end <= self.buf.len() {
-                #[cfg(feature = "file_offsets")]
-                spans.push(item_header(pos, bot_len));
+                let item = item_header(pos, bot_len);
                 pos += 8;
-                bot.reserve(bot_len as usize / 4);
+                let mut bot = Vec::with_capacity(bot_len as usize / 4);
                 while pos + 4 <= bot_end {
                     bot.push(read_u32(self.buf, pos, order));
                     pos += 4;
                 }
                 pos = bot_end;
+                reporting = self.steer(v.pixel_offset_table(&item, &bot)) == Visit::Continue;
             } else {
                 self.note(format_args!("malformed Basic Offset Table at offset {pos}; treating as fragments"));
             }
@@ -493,73 +451,67 @@ impl Parser<'_> {
             self.note(format_args!("missing Basic Offset Table at offset {pos}"));
         }
 
-        loop {
+        let mut index = 0;
+        let delimiter = loop {
+            if self.stopped.get() {
+                return pos;
+            }
             if pos + 8 > self.buf.len() {
                 self.note(format_args!("encapsulated PixelData truncated, missing delimiter at offset {pos}"));
-                break;
+                break None;
             }
             let tag = read_tag(self.buf, pos, order);
             let len = read_u32(self.buf, pos + 4, order);
             if tag == tags::SequenceDelimitationItem.key {
-                #[cfg(feature = "file_offsets")]
-                spans.push(TagHeader { offset: pos as i64, tag, vr: None, length: (0, 4), size: None });
+                let delimiter = TagHeader { offset: pos as i64, tag, vr: None, length: (0, 4), size: None };
                 pos += 8;
-                break;
+                break Some(delimiter);
             }
             if tag != tags::Item.key || len == UNDEFINED_LENGTH {
                 self.note(format_args!(
                     "expected a fragment item (FFFE,E000) but found ({:04X},{:04X}) at offset {pos}; ending PixelData",
                     tag.group(), tag.element()
                 ));
-                break;
+                break None;
             }
             let start = pos + 8;
             let end = start + len as usize;
             if end > self.buf.len() {
                 self.note(format_args!("pixel-data fragment exceeds the buffer at offset {pos}; truncating"));
-                break;
+                break None;
+            }
+            if reporting {
+                let visit = v.pixel_fragment(index, &item_header(pos, len), &self.buf[start..end]);
+                reporting = self.steer(visit) == Visit::Continue;
             }
-            #[cfg(feature = "file_offsets")]
-            spans.push(item_header(pos, len));
-            fragments.push(self.master.slice(start..end));
+            index += 1;
             pos = end;
+        };
+        if !self.stopped.get() {
+            self.steer(v.pixel_data_end(h, pos, delimiter.as_ref()));
         }
-
-        #[cfg(feature = "file_offsets")]
-        {
-            let mut all = Vec::with_capacity(spans.len() + 1);
-            all.push(TagHeader {
-                offset: el_offset as i64,
-                tag: h.tag,
-                vr: h.vr_code,
-                length: h.length,
-                size: Some(pos - el_offset),
-            });
-            all.append(&mut spans);
-            (PixelData::Encapsulated { bot, fragments }, pos, all)
-        }
-        #[cfg(not(feature = "file_offsets"))]
-        {
-            let _ = el_offset;
-            (PixelData::Encapsulated { bot, fragments }, pos, ())
-        }
+        pos
     }
 
-    /// Parses the items of the sequence whose header is `h`. Returns the items
-    /// and the offset just past the sequence (including any delimiter). Lenient:
-    /// a missing delimiter, a truncated item header, or an unexpected tag ends
-    /// the sequence so the parent can carry on.
-    fn sequence(&self, h: &Header, dec: Decoder) -> (Vec<Item>, usize) {
+    /// Parses the items of the sequence whose header is `h`. Returns the offset
+    /// just past the sequence (including any delimiter). Lenient: a missing
+    /// delimiter, a truncated item header, or an unexpected tag ends the
+    /// sequence so the parent can carry on.
+    fn sequence<V: DcmVisitor>(&self, h: &ElementHeader, dec: Decoder, v: &mut V) -> usize {
         let order = dec.little_endian;
-        let defined_end = (h.value_start + h.length.0 as usize).min(self.buf.len());
-        let mut items = Vec::new();
-        let mut pos = h.value_start;
+        let undefined = h.is_undefined_length();
+        let defined_end = (h.value_offset + h.length.0 as usize).min(self.buf.len());
+        let mut index = 0;
+        let mut pos = h.value_offset;
         loop {
-            if !h.undefined && pos >= defined_end {
+            if self.stopped.get() {
+                return pos;
+            }
+            if !undefined && pos >= defined_end {
                 break;
             }
             if pos + 8 > self.buf.len() {
-                if h.undefined {
+                if undefined {
                     self.note(format_args!("sequence truncated, missing delimiter at offset {pos}"));
                 }
                 break;
@@ -577,29 +529,36 @@ impl Parser<'_> {
                 ));
                 break;
             }
-            let (item, next) = self.item(pos, item_len, dec);
-            items.push(item);
+            let next = match self.steer(v.item_start(index, &item_header_open(pos, item_len))) {
+                Visit::Continue => self.item(pos, item_len, index, dec, v),
+                Visit::Skip => self.item(pos, item_len, index, dec, &mut Mute),
+                Visit::Stop => return pos,
+            };
+            index += 1;
             if next <= pos {
                 break;
             }
             pos = next;
         }
-        (items, pos)
+        self.steer(v.sequence_end(h, pos));
+        pos
     }
 
-    /// Parses one sequence item starting at `item_start` (its Item tag), whose
-    /// declared length is `item_len`. Returns the item and the offset past it.
+    /// Parses item `index` of a sequence starting at `item_start` (its Item
+    /// tag), whose declared length is `item_len`. Returns the offset past it.
     /// Lenient: errors inside the item content stop it without aborting the
     /// surrounding sequence.
-    fn item(&self, item_start: usize, item_len: u32, dec: Decoder) -> (Item, usize) {
+    fn item<V: DcmVisitor>(&self, item_start: usize, item_len: u32, index: usize, dec: Decoder, v: &mut V) -> usize {
         let undefined = item_len == UNDEFINED_LENGTH;
         let content_start = item_start + 8;
-        let mut map = ElementMap::default();
         let mut pos = content_start;
         let defined_end = (content_start + item_len as usize).min(self.buf.len());
 
         let mut delim_consumed = false;
         let content_end = loop {
+            if self.stopped.get() {
+                return pos;
+            }
             if !undefined && pos >= defined_end {
                 break pos;
             }
@@ -610,11 +569,8 @@ impl Parser<'_> {
                 delim_consumed = true;
                 break pos;
             }
-            match self.element(pos, dec) {
-                Ok((etag, el, next)) => {
-                    if let Some(note) = map.push_parsed(etag, el) {
-                        self.note_push(etag, pos, note);
-                    }
+            match self.element(pos, dec, v) {
+                Ok(next) => {
                     if next <= pos {
                         break pos;
                     }
@@ -634,28 +590,16 @@ impl Parser<'_> {
             defined_end
         };
 
-        #[cfg_attr(not(feature = "file_offsets"), allow(unused_mut))]
-        let mut item = Item::from_map(map);
-        #[cfg(feature = "file_offsets")]
-        {
-            item.header.push(TagHeader {
-                offset: item_start as i64,
-                tag: tags::Item.key,
-                vr: None,
-                length: (item_len, 4),
-                size: Some(content_end - content_start),
-            });
-            if delim_consumed {
-                item.header.push(TagHeader {
-                    offset: content_end as i64,
-                    tag: tags::ItemDelimitationItem.key,
-                    vr: None,
-                    length: (0, 4),
-                    size: None,
-                });
-            }
-        }
-        (item, next)
+        let item = TagHeader { size: Some(content_end - content_start), ..item_header_open(item_start, item_len) };
+        let delimiter = delim_consumed.then_some(TagHeader {
+            offset: content_end as i64,
+            tag: tags::ItemDelimitationItem.key,
+            vr: None,
+            length: (0, 4),
+            size: None,
+        });
+        self.steer(v.item_end(index, &item, delimiter.as_ref()));
+        next
     }
 }
 
@@ -680,9 +624,11 @@ pub(crate) fn build_dataset(
     whitelist: Option<&[TagKey]>,
     disable_tracing: bool,
 ) -> Result<DataSet> {
-    let parser = Parser { buf: &source.data, master: &source.data, mapped: source.mapped, whitelist, stop_after, disable_tracing, ambiguous: Cell::new(false) };
+    let parser = Parser::new(&source.data, whitelist, stop_after, disable_tracing);
     let dec = Decoder::from_ts(ts);
-    let mut map = parser.dataset(start, end, dec)?;
+    let mut builder = Builder::new(source, disable_tracing);
+    parser.dataset(start, end, dec, &mut builder);
+    let mut map = builder.finish();
     if parser.ambiguous.get() {
         parser.resolve_ambiguous(&mut map, dec, &AmbiguityCtx::default());
     }
@@ -699,19 +645,58 @@ pub(crate) fn build_flat(
     whitelist: Option<&[TagKey]>,
     disable_tracing: bool,
 ) -> Result<DataSet> {
-    let parser = Parser { buf: &source.data, master: &source.data, mapped: source.mapped, whitelist, stop_after, disable_tracing, ambiguous: Cell::new(false) };
+    let parser = Parser::new(&source.data, whitelist, stop_after, disable_tracing);
     let dec = Decoder::from_ts(ts);
-    let mut map = parser.dataset(META_START, meta_end, META_LE)?;
+    let mut builder = Builder::new(source, disable_tracing);
+    parser.dataset(META_START, meta_end, META_LE, &mut builder);
     // Main data set tags all sort after the (0002,xxxx) meta tags.
-    for (key, el) in parser.dataset(meta_end, source.data.len(), dec)?.into_entries() {
-        map.push_parsed(key, el);
-    }
+    parser.dataset(meta_end, source.data.len(), dec, &mut builder);
+    let mut map = builder.finish();
     if parser.ambiguous.get() {
         parser.resolve_ambiguous(&mut map, dec, &AmbiguityCtx::default());
     }
     assemble(source, map, ts, DatasetKind::Dataset)
 }
 
+/// Walks a stream for a [`DcmVisitor`]: the File Meta group in
+/// `[META_START, meta_end)` when there is one (unfiltered, as for a data set
+/// read), then the main data set from `start` under `ts`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn visit<V: DcmVisitor>(
+    source: &Source,
+    meta_end: Option<usize>,
+    start: Option<usize>,
+    ts: &'static TransferSyntax,
+    stop_after: TagKey,
+    whitelist: Option<&[TagKey]>,
+    disable_tracing: bool,
+    v: &mut V,
+) {
+    if let Some(meta_end) = meta_end {
+        let parser = Parser::new(&source.data, None, TagKey(u32::MAX), disable_tracing);
+        match parser.steer(v.dataset_start(DatasetKind::MetaInfo, &TransferSyntax::ExplicitVRLittleEndian)) {
+            Visit::Continue => parser.dataset(META_START, meta_end, META_LE, v),
+            Visit::Skip => {}
+            Visit::Stop => return,
+        }
+        if parser.stopped.get() {
+            return;
+        }
+    }
+    if let Some(start) = start {
+        let parser = Parser::new(&source.data, whitelist, stop_after, disable_tracing);
+        if parser.steer(v.dataset_start(DatasetKind::Dataset, ts)) == Visit::Continue {
+            parser.dataset(start, source.data.len(), Decoder::from_ts(ts), v);
+        }
+    }
+}
+
+/// Reads the element header at the start of `buf` under `ts`, as leniently as a
+/// full parse, for the incremental [`stream`](super::stream) reader.
+pub(crate) fn peek_header(buf: &[u8], ts: &TransferSyntax) -> Result<ElementHeader> {
+    Parser::new(buf, None, TagKey(u32::MAX), true).read_header(0, Decoder::from_ts(ts))
+}
+
 /// Whether `data` begins with a DICOM File Meta preamble + `DICM` magic.
 pub(crate) fn has_dicm(data: &[u8]) -> bool {
     data.len() >= META_START && &data[PREAMBLE_LEN..META_START] == b"DICM"
@@ -734,15 +719,15 @@ pub(crate) fn meta(source: &Source, disable_tracing: bool) -> Result<(DataSet, u
     let buf = &source.data;
     ensure!(has_dicm(buf), InvalidData, "missing DICM File Meta preamble");
 
-    let parser = Parser { buf, master: &source.data, mapped: source.mapped, whitelist: None, stop_after: TagKey(u32::MAX), disable_tracing, ambiguous: Cell::new(false) };
+    let parser = Parser::new(&source.data, None, TagKey(u32::MAX), disable_tracing);
 
     // Prefer the File Meta group length (0002,0000) when present and sane;
     // otherwise scan group 0002 to find where the main data set begins. Many
     // older/converted files omit the group length element entirely.
     let meta_end = match parser.read_header(META_START, META_LE) {
         Ok(gl) if gl.tag == tags::FileMetaInformationGroupLength.key && gl.length.0 == 4 => {
-            let group_length = read_u32(buf, gl.value_start, true) as usize;
-            let end = gl.value_start + 4 + group_length;
+            let group_length = read_u32(buf, gl.value_offset, true) as usize;
+            let end = gl.value_offset + 4 + group_length;
             if end <= buf.len() {
                 end
             } else {
@@ -756,12 +741,9 @@ pub(crate) fn meta(source: &Source, disable_tracing: bool) -> Result<(DataSet, u
         }
     };
 
-    let header = assemble(
-        source,
-        parser.dataset(META_START, meta_end, META_LE)?,
-        &TransferSyntax::ExplicitVRLittleEndian,
-        DatasetKind::MetaInfo,
-    )?;
+    let mut builder = Builder::new(source, disable_tracing);
+    parser.dataset(META_START, meta_end, META_LE, &mut builder);
+    let header = assemble(source, builder.finish(), &TransferSyntax::ExplicitVRLittleEndian, DatasetKind::MetaInfo)?;
     let ts = match header.get::<String>(&tags::TransferSyntaxUID) {
         Ok(uid) => TransferSyntax::from_uid(uid.trim()).unwrap_or_else(|| {
             parser.note(format_args!("unknown TransferSyntaxUID (0002,0010) {:?}; assuming Explicit VR LE", uid.trim()));
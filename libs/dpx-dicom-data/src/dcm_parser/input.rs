@@ -1,16 +1,14 @@
 use std::fs::File;
-use std::io::{self, Read};
+use std::io;
 use std::path::Path;
 
 use bytes::Bytes;
 
 /// Fully-buffered input for the sans-io parser: the whole stream is available as
 /// one [`Bytes`] buffer. A memory-mapped file yields it zero-copy (`mapped =
-/// true`, so the data set keeps it as its master buffer); other readers are read
-/// into memory.
-///
-// ponytail: a non-mmap reader is buffered whole. Incremental, memory-bounded
-// push-streaming for huge non-mmap inputs is a later layer over the same core.
+/// true`, so the data set keeps it as its master buffer); other readers are
+/// pulled incrementally by [`stream`](super::stream), which hands the core only
+/// the bytes it kept.
 pub(crate) struct Source {
     pub(crate) data: Bytes,
     pub(crate) mapped: bool,
@@ -26,13 +24,6 @@ impl Source {
         Ok(Self { data: Bytes::from_owner(mmap), mapped: true })
     }
 
-    /// Reads a reader fully into memory.
-    pub(crate) fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-        Ok(Self { data: Bytes::from(buf), mapped: false })
-    }
-
     pub(crate) fn from_bytes(data: Bytes, mapped: bool) -> Self {
         Self { data, mapped }
     }
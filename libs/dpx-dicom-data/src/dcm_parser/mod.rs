@@ -1,11 +1,20 @@
 //! DICOM binary stream parser.
 //!
-//! Sibling parsers for XML and JSON representations will live alongside this
-//! module. The core is sans-io ([`core`]); [`input`] provides the byte sources
-//! (mmap / read-into-memory); [`reader`] is the configurable [`DcmReader`] facade.
+//! The DICOM JSON ([`json_parser`](crate::json_parser)) and Native DICOM Model
+//! XML ([`xml_parser`](crate::xml_parser)) parsers live alongside this module.
+//! The core is sans-io ([`core`]) and reports to a [`DcmVisitor`]
+//! ([`visitor`]); [`builder`] is the visitor that materializes a data set;
+//! [`input`] provides the byte sources (mmap / in-memory) and [`stream`] the
+//! incremental pull from any reader; [`reader`] is the configurable
+//! [`DcmReader`] facade.
 
-mod core;
+mod builder;
+pub(crate) mod core;
 mod input;
 mod reader;
+mod stream;
+mod visitor;
 
-pub use reader::{DcmReader, HeaderType, ReadMode, ReadOutput};
+pub use reader::{DcmReader, HeaderType, ReadMode, ReadOutput, StreamOutput};
+pub use stream::LazyValue;
+pub use visitor::{DcmVisitor, ElementHeader, Visit};
@@ -4,12 +4,16 @@ use std::path::Path;
 
 use bytes::Bytes;
 use flate2::read::DeflateDecoder;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 use dpx_dicom_core::error::{IntoDicomErr, Result};
 use dpx_dicom_core::{TagKey, TransferSyntax, ensure};
 
-use super::core;
+use super::core::{self, META_START};
 use super::input::Source;
+use super::stream::{Body, LazyValue, Pull};
+use super::visitor::DcmVisitor;
 use crate::DataSet;
 use crate::dataset::DatasetKind;
 
@@ -46,6 +50,28 @@ pub struct ReadOutput {
     pub dataset: Option<DataSet>,
 }
 
+/// Result of [`DcmReader::parse_stream`]: a [`ReadOutput`] plus the value left
+/// unread in the stream, if [`lazy_threshold`](DcmReader::lazy_threshold) cut
+/// the read short.
+pub struct StreamOutput<R> {
+    pub header: Option<DataSet>,
+    pub dataset: Option<DataSet>,
+    pub lazy: Option<LazyValue<R>>,
+}
+
+/// What an incremental read retained, laid out like a buffered stream: the
+/// preamble and File Meta group, then the kept data-set bytes (inflated).
+struct Pulled<R> {
+    source: Source,
+    header: Option<DataSet>,
+    /// End of the File Meta group in `source`, when there is one.
+    meta_end: Option<usize>,
+    /// Where the data set starts in `source` and its (inflated) transfer
+    /// syntax; `None` when only the header was read.
+    body: Option<(usize, &'static TransferSyntax)>,
+    lazy: Option<LazyValue<R>>,
+}
+
 /// Configurable DICOM stream reader. Set parameters with the builder methods,
 /// then call one of the `parse_*` entry points.
 #[derive(Debug, Clone, Default)]
@@ -56,6 +82,7 @@ pub struct DcmReader {
     tag_max: Option<TagKey>,
     /// Sorted, de-duplicated whitelist; only these tags are kept.
     tag_whitelist: Option<Vec<TagKey>>,
+    lazy_threshold: Option<u32>,
 }
 
 impl DcmReader {
@@ -88,6 +115,13 @@ impl DcmReader {
         self.tag_whitelist = Some(tags);
         self
     }
+    /// For [`parse_stream`](Self::parse_stream): the first top-level value
+    /// longer than `bytes`, or encapsulated Pixel Data, ends the read and is
+    /// left in the stream as a [`LazyValue`].
+    pub fn lazy_threshold(mut self, bytes: u32) -> Self {
+        self.lazy_threshold = Some(bytes);
+        self
+    }
 
     // --- Entry points ------------------------------------------------------
 
@@ -96,10 +130,21 @@ impl DcmReader {
         self.run(Source::from_bytes(data, true))
     }
 
-    /// Reads fully from any reader.
+    /// Reads incrementally from any reader, pulling one top-level element at a
+    /// time: reading ends once `tag_max` or the whitelist is satisfied, and
+    /// elements the whitelist drops are never buffered.
     pub fn parse_bufreader<R: Read>(&self, reader: R) -> Result<ReadOutput> {
-        let source = Source::from_reader(reader).to_dicom_err_with(|| "reading stream".to_string())?;
-        self.run(source)
+        let pulled = self.pull(reader, None)?;
+        self.pulled_output(pulled).map(|out| ReadOutput { header: out.header, dataset: out.dataset })
+    }
+
+    /// Like [`parse_bufreader`](Self::parse_bufreader), but hands off a large
+    /// value (typically Pixel Data) unread, per
+    /// [`lazy_threshold`](Self::lazy_threshold). Nothing past that value's
+    /// header has been read when this returns.
+    pub fn parse_stream<R: Read>(&self, reader: R) -> Result<StreamOutput<R>> {
+        let pulled = self.pull(reader, self.lazy_threshold)?;
+        self.pulled_output(pulled)
     }
 
     /// Opens a file and reads it through a buffered reader (no mmap).
@@ -119,6 +164,44 @@ impl DcmReader {
         }
     }
 
+    /// Walks an in-memory buffer, reporting to `visitor` instead of building a
+    /// data set. The File Meta group (if any) is reported first; `tag_max` and
+    /// the whitelist filter the main data set, and [`ReadMode::HeaderOnly`]
+    /// stops after the header. Offsets in a Deflated stream refer to the
+    /// inflated bytes, which keep the File Meta header in place.
+    pub fn visit_bytes<V: DcmVisitor>(&self, data: Bytes, visitor: &mut V) -> Result<()> {
+        self.run_visitor(Source::from_bytes(data, true), visitor)
+    }
+
+    /// Reads incrementally from any reader, like
+    /// [`parse_bufreader`](Self::parse_bufreader), and walks what was kept.
+    /// Offsets refer to the kept (inflated) bytes.
+    pub fn visit_bufreader<R: Read, V: DcmVisitor>(&self, reader: R, visitor: &mut V) -> Result<()> {
+        let pulled = self.pull(reader, None)?;
+        let (start, ts) = pulled.body.unzip();
+        let ts = ts.unwrap_or(&TransferSyntax::ExplicitVRLittleEndian);
+        let wl = self.tag_whitelist.as_deref();
+        core::visit(&pulled.source, pulled.meta_end, start, ts, self.stop_after(), wl, false, visitor);
+        Ok(())
+    }
+
+    /// Opens a file and walks it through a buffered reader (no mmap).
+    pub fn visit_file<V: DcmVisitor>(&self, path: impl AsRef<Path>, visitor: &mut V) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::open(path).to_dicom_err_with(|| format!("opening {}", path.display()))?;
+        self.visit_bufreader(BufReader::new(file), visitor)
+    }
+
+    /// Memory-maps a file and walks it, falling back to
+    /// [`visit_file`](Self::visit_file) when mapping is unavailable.
+    pub fn visit_mmap<V: DcmVisitor>(&self, path: impl AsRef<Path>, visitor: &mut V) -> Result<()> {
+        let path = path.as_ref();
+        match Source::mmap(path) {
+            Ok(source) => self.run_visitor(source, visitor),
+            Err(_) => self.visit_file(path, visitor),
+        }
+    }
+
     // --- Orchestration -----------------------------------------------------
 
     /// Effective stop tag: the *smaller* of `tag_max` and the whitelist
@@ -134,44 +217,33 @@ impl DcmReader {
         }
     }
 
-    fn run(&self, source: Source) -> Result<ReadOutput> {
-        let has_header = match self.header {
+    /// Whether the stream starting with `data` has a File Meta header.
+    fn has_header(&self, data: &[u8]) -> Result<bool> {
+        Ok(match self.header {
             HeaderType::NoHeader => false,
-            HeaderType::Auto => core::has_dicm(&source.data),
+            HeaderType::Auto => core::has_dicm(data),
             HeaderType::WithHeader => {
-                ensure!(core::has_dicm(&source.data), InvalidData, "expected a DICOM File Meta header (DICM)");
+                ensure!(core::has_dicm(data), InvalidData, "expected a DICOM File Meta header (DICM)");
                 true
             }
-        };
+        })
+    }
 
-        let stop = self.stop_after();
-        let wl = self.tag_whitelist.as_deref();
+    fn run(&self, source: Source) -> Result<ReadOutput> {
+        let has_header = self.has_header(&source.data)?;
 
         if has_header {
+            let (header, dataset_start, meta_ts) = core::meta(&source, false)?;
             if let ReadMode::HeaderOnly = self.mode {
-                let (header, _, _) = core::meta(&source, false)?;
                 return Ok(ReadOutput { header: Some(header), dataset: None });
             }
-            let (header, dataset_start, meta_ts) = core::meta(&source, false)?;
             let ts = self.xfer.unwrap_or(meta_ts);
             // For a Deflated transfer syntax the body is inflated into its own
             // buffer (with the uncompressed meta prefix kept in place) so the
             // parser core sees a plain Explicit VR LE data set.
             let (inflated, body_ts) = inflate_if_deflated(&source, dataset_start, ts)?;
             let body = inflated.as_ref().unwrap_or(&source);
-            let body_end = body.data.len();
-            return match self.mode {
-                ReadMode::Normal => {
-                    let dataset =
-                        core::build_dataset(body, dataset_start, body_end, body_ts, DatasetKind::Dataset, stop, wl, false)?;
-                    Ok(ReadOutput { header: Some(header), dataset: Some(dataset) })
-                }
-                ReadMode::Flat => {
-                    let dataset = core::build_flat(body, dataset_start, body_ts, stop, wl, false)?;
-                    Ok(ReadOutput { header: None, dataset: Some(dataset) })
-                }
-                ReadMode::HeaderOnly => unreachable!("handled above"),
-            };
+            return self.output(body, Some(header), dataset_start, body_ts);
         }
 
         match self.mode {
@@ -188,12 +260,117 @@ impl DcmReader {
                 let ts = self.xfer.unwrap_or_else(|| core::detect_transfer_syntax(&source.data));
                 let (inflated, body_ts) = inflate_if_deflated(&source, 0, ts)?;
                 let body = inflated.as_ref().unwrap_or(&source);
-                let dataset =
-                    core::build_dataset(body, 0, body.data.len(), body_ts, DatasetKind::Dataset, stop, wl, false)?;
-                Ok(ReadOutput { header: None, dataset: Some(dataset) })
+                self.output(body, None, 0, body_ts)
             }
         }
     }
+
+    /// Parses the data set of an (inflated) `body` starting at `dataset_start`,
+    /// merging it with the header in [`ReadMode::Flat`].
+    fn output(
+        &self,
+        body: &Source,
+        header: Option<DataSet>,
+        dataset_start: usize,
+        ts: &'static TransferSyntax,
+    ) -> Result<ReadOutput> {
+        let stop = self.stop_after();
+        let wl = self.tag_whitelist.as_deref();
+        if header.is_some() && self.mode == ReadMode::Flat {
+            let dataset = core::build_flat(body, dataset_start, ts, stop, wl, false)?;
+            return Ok(ReadOutput { header: None, dataset: Some(dataset) });
+        }
+        let end = body.data.len();
+        let dataset = core::build_dataset(body, dataset_start, end, ts, DatasetKind::Dataset, stop, wl, false)?;
+        Ok(ReadOutput { header, dataset: Some(dataset) })
+    }
+
+    /// Pulls what the read needs from `reader`, one top-level element at a
+    /// time (see [`stream`](super::stream)).
+    fn pull<R: Read>(&self, reader: R, lazy: Option<u32>) -> Result<Pulled<R>> {
+        let ctx = || "reading stream".to_string();
+        let mut input = Pull::new(reader);
+        let has_header = self.has_header(input.peek(META_START).to_dicom_err_with(ctx)?)?;
+
+        let mut data = Vec::new();
+        let (header, meta_end, detected) = if has_header {
+            input.take(META_START, Some(&mut data)).to_dicom_err_with(ctx)?;
+            input.meta_group(&mut data).to_dicom_err_with(ctx)?;
+            let prefix = Source::from_bytes(Bytes::from(data), true);
+            let (header, meta_end, meta_ts) = core::meta(&prefix, false)?;
+            if self.mode == ReadMode::HeaderOnly {
+                return Ok(Pulled {
+                    source: prefix,
+                    header: Some(header),
+                    meta_end: Some(meta_end),
+                    body: None,
+                    lazy: None,
+                });
+            }
+            // Bytes past a short group length belong to the data set.
+            input.unread(&prefix.data[meta_end..]);
+            data = prefix.data[..meta_end].to_vec();
+            (Some(header), Some(meta_end), meta_ts)
+        } else {
+            if self.mode == ReadMode::HeaderOnly {
+                ensure!(
+                    !matches!(self.header, HeaderType::NoHeader),
+                    InvalidData,
+                    "HeaderOnly mode requires a header"
+                );
+                let source = Source::from_bytes(Bytes::new(), true);
+                return Ok(Pulled { source, header: None, meta_end: None, body: None, lazy: None });
+            }
+            (None, None, core::detect_transfer_syntax(input.peek(6).to_dicom_err_with(ctx)?))
+        };
+
+        let ts = self.xfer.unwrap_or(detected);
+        let start = data.len();
+        // A Deflated body is inflated on the fly; what follows is plain
+        // Explicit VR Little Endian.
+        let (body, body_ts) = if ts.is_compressed {
+            (Body::Deflated(DeflateDecoder::new(input.into_reader())), &TransferSyntax::ExplicitVRLittleEndian)
+        } else {
+            (Body::Plain(input.into_reader()), ts)
+        };
+        let mut body = Pull::new(body);
+        let wl = self.tag_whitelist.as_deref();
+        let lazy_header = body.dataset(body_ts, self.stop_after(), wl, lazy, &mut data).to_dicom_err_with(ctx)?;
+        let lazy = lazy_header.map(|h| LazyValue::new(h, body_ts.is_little_endian, body.into_reader()));
+        let source = Source::from_bytes(Bytes::from(data), true);
+        Ok(Pulled { source, header, meta_end, body: Some((start, body_ts)), lazy })
+    }
+
+    fn pulled_output<R>(&self, pulled: Pulled<R>) -> Result<StreamOutput<R>> {
+        let Some((start, ts)) = pulled.body else {
+            return Ok(StreamOutput { header: pulled.header, dataset: None, lazy: None });
+        };
+        let out = self.output(&pulled.source, pulled.header, start, ts)?;
+        Ok(StreamOutput { header: out.header, dataset: out.dataset, lazy: pulled.lazy })
+    }
+
+    fn run_visitor<V: DcmVisitor>(&self, source: Source, visitor: &mut V) -> Result<()> {
+        let stop = self.stop_after();
+        let wl = self.tag_whitelist.as_deref();
+        if self.has_header(&source.data)? {
+            let (_, dataset_start, meta_ts) = core::meta(&source, false)?;
+            let ts = self.xfer.unwrap_or(meta_ts);
+            let (inflated, body_ts) = inflate_if_deflated(&source, dataset_start, ts)?;
+            let body = inflated.as_ref().unwrap_or(&source);
+            let start = (self.mode != ReadMode::HeaderOnly).then_some(dataset_start);
+            core::visit(body, Some(dataset_start), start, body_ts, stop, wl, false, visitor);
+            return Ok(());
+        }
+        if self.mode == ReadMode::HeaderOnly {
+            ensure!(!matches!(self.header, HeaderType::NoHeader), InvalidData, "HeaderOnly mode requires a header");
+            return Ok(());
+        }
+        let ts = self.xfer.unwrap_or_else(|| core::detect_transfer_syntax(&source.data));
+        let (inflated, body_ts) = inflate_if_deflated(&source, 0, ts)?;
+        let body = inflated.as_ref().unwrap_or(&source);
+        core::visit(body, None, Some(0), body_ts, stop, wl, false, visitor);
+        Ok(())
+    }
 }
 
 /// If `ts` is a Deflated transfer syntax, inflates the raw-DEFLATE body at
@@ -218,10 +395,47 @@ fn inflate_if_deflated(
     Ok((Some(inflated), &TransferSyntax::ExplicitVRLittleEndian))
 }
 
+/// Async entry point. The incremental parse (deflate included) runs on tokio's
+/// blocking pool, pulling from the [`AsyncRead`] as it goes, so a large stream
+/// never stalls the runtime's worker threads.
+#[cfg(feature = "tokio")]
+impl DcmReader {
+    /// Async counterpart of [`parse_bufreader`](Self::parse_bufreader). The
+    /// caller's [`Context`](dpx_dicom_core::Context) (e.g. one installed by
+    /// [`ContextScope`](dpx_dicom_core::ContextScope)) is carried over for
+    /// dictionary lookups.
+    pub async fn parse_async<R: AsyncRead + Unpin + Send + 'static>(&self, reader: R) -> Result<ReadOutput> {
+        let this = self.clone();
+        let ctx = dpx_dicom_core::Context::extend();
+        let rt = tokio::runtime::Handle::current();
+        let input = BufReader::new(Blocking { reader, rt });
+        match tokio::task::spawn_blocking(move || ctx.provide(|| this.parse_bufreader(input))).await {
+            Ok(out) => out,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => Err(dpx_dicom_core::dicom_err!(Internal, "parse task did not complete: {e}")),
+        }
+    }
+}
+
+/// Reads an [`AsyncRead`] from a blocking-pool thread.
+#[cfg(feature = "tokio")]
+struct Blocking<R> {
+    reader: R,
+    rt: tokio::runtime::Handle,
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncRead + Unpin> Read for Blocking<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.rt.block_on(self.reader.read(buf))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::value::{PixelData, Value};
+    use crate::dcm_parser::{ElementHeader, Visit};
+    use crate::value::{PixelData, TagHeader, Value};
     use dpx_dicom_core::{Tag, TagKey, tags};
 
     fn sample() -> Bytes {
@@ -340,10 +554,7 @@ mod tests {
         let out = DcmReader::new().parse_bytes(sample_with_header()).expect("read");
         let header = out.header.expect("header");
         assert_eq!(header.kind(), DatasetKind::MetaInfo);
-        assert_eq!(
-            header.get::<String>(&tags::TransferSyntaxUID).expect("TS uid"),
-            "1.2.840.10008.1.2.1"
-        );
+        assert_eq!(header.get::<String>(&tags::TransferSyntaxUID).expect("TS uid"), "1.2.840.10008.1.2.1");
         let ds = out.dataset.expect("dataset");
         assert_eq!(ds.get::<String>(&tags::PatientName).expect("PN"), "Doe^John");
         assert_eq!(ds.get::<u16>(&tags::Rows).expect("Rows"), 512);
@@ -467,7 +678,7 @@ mod tests {
         let ds = out.dataset.expect("dataset");
         match ds.value(&tags::PixelData).expect("pixel data") {
             Value::Pixels(px) => match *px {
-                PixelData::Encapsulated { bot, fragments } => {
+                PixelData::Encapsulated { bot, fragments, .. } => {
                     assert!(bot.is_empty());
                     assert_eq!(fragments.len(), 2);
                     assert_eq!(&fragments[0][..], b"ABCD");
@@ -634,4 +845,252 @@ mod tests {
         assert_eq!(ds.get::<String>(&tags::PatientName).expect("PN"), "Doe^John");
         assert_eq!(ds.get::<u16>(&tags::Rows).expect("Rows"), 512);
     }
+
+    /// Records every event as a short string.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        skip: Option<TagKey>,
+        stop: Option<TagKey>,
+    }
+
+    impl Recorder {
+        fn steer(&self, tag: TagKey) -> Visit {
+            if self.stop == Some(tag) {
+                Visit::Stop
+            } else if self.skip == Some(tag) {
+                Visit::Skip
+            } else {
+                Visit::Continue
+            }
+        }
+    }
+
+    impl DcmVisitor for Recorder {
+        fn dataset_start(&mut self, kind: DatasetKind, ts: &'static TransferSyntax) -> Visit {
+            self.events.push(format!("{kind:?} {}", ts.uid));
+            Visit::Continue
+        }
+        fn element(&mut self, h: &ElementHeader) -> Visit {
+            self.events.push(format!("element {:08X} {}", h.tag.0, h.vr));
+            self.steer(h.tag)
+        }
+        fn value(&mut self, _: &ElementHeader, value: &[u8]) -> Visit {
+            self.events.push(format!("value {}", String::from_utf8_lossy(value)));
+            Visit::Continue
+        }
+        fn sequence_start(&mut self, h: &ElementHeader) -> Visit {
+            self.events.push(format!("sequence {:08X}", h.tag.0));
+            self.steer(h.tag)
+        }
+        fn item_start(&mut self, index: usize, _: &TagHeader) -> Visit {
+            self.events.push(format!("item {index}"));
+            Visit::Continue
+        }
+        fn item_end(&mut self, index: usize, item: &TagHeader, delimiter: Option<&TagHeader>) -> Visit {
+            self.events.push(format!("item end {index} {:?} {}", item.size, delimiter.is_some()));
+            Visit::Continue
+        }
+        fn sequence_end(&mut self, h: &ElementHeader, end: usize) -> Visit {
+            self.events.push(format!("sequence end {:08X} {end}", h.tag.0));
+            Visit::Continue
+        }
+        fn pixel_data_start(&mut self, _: &ElementHeader) -> Visit {
+            self.events.push("pixels".into());
+            Visit::Continue
+        }
+        fn pixel_offset_table(&mut self, _: &TagHeader, offsets: &[u32]) -> Visit {
+            self.events.push(format!("offsets {offsets:?}"));
+            Visit::Continue
+        }
+        fn pixel_fragment(&mut self, index: usize, _: &TagHeader, fragment: &[u8]) -> Visit {
+            self.events.push(format!("fragment {index} {}", String::from_utf8_lossy(fragment)));
+            Visit::Continue
+        }
+        fn pixel_data_end(&mut self, _: &ElementHeader, end: usize, delimiter: Option<&TagHeader>) -> Visit {
+            self.events.push(format!("pixels end {end} {}", delimiter.is_some()));
+            Visit::Continue
+        }
+    }
+
+    fn visit(reader: DcmReader, data: Bytes, recorder: &mut Recorder) -> Vec<String> {
+        reader.visit_bytes(data, recorder).expect("visit");
+        std::mem::take(&mut recorder.events)
+    }
+
+    #[test]
+    fn visitor_reports_sequences_and_values() {
+        let events = visit(DcmReader::new(), sample_sequence(true), &mut Recorder::default());
+        assert_eq!(
+            events,
+            [
+                "Dataset 1.2.840.10008.1.2.1",
+                "sequence 00081115",
+                "item 0",
+                "element 0020000E UI",
+                "value 1.2\0",
+                "item end 0 Some(12) true",
+                "sequence end 00081115 48",
+            ]
+        );
+    }
+
+    #[test]
+    fn visitor_reports_header_and_fragments() {
+        let events = visit(DcmReader::new(), sample_with_header(), &mut Recorder::default());
+        assert_eq!(events[0], "MetaInfo 1.2.840.10008.1.2.1");
+        assert!(events.contains(&"value 1.2.840.10008.1.2.1\0".to_string()));
+        assert!(events.contains(&"Dataset 1.2.840.10008.1.2.1".to_string()));
+        assert_eq!(events.last().map(String::as_str), Some("value \0\u{2}"));
+
+        let events = visit(DcmReader::new(), sample_encapsulated(), &mut Recorder::default());
+        assert_eq!(events[1..], ["pixels", "offsets []", "fragment 0 ABCD", "fragment 1 EF", "pixels end 50 true"]);
+    }
+
+    #[test]
+    fn visitor_skips_and_stops() {
+        let mut recorder = Recorder { skip: Some(tags::ReferencedSeriesSequence.key), ..Default::default() };
+        let events = visit(DcmReader::new(), sample_sequence(true), &mut recorder);
+        assert_eq!(events, ["Dataset 1.2.840.10008.1.2.1", "sequence 00081115"]);
+
+        let mut recorder = Recorder { skip: Some(tags::PatientName.key), ..Default::default() };
+        let events = visit(DcmReader::new(), sample(), &mut recorder);
+        assert_eq!(events[1..], ["element 00100010 PN", "element 00280010 US", "value \0\u{2}"]);
+
+        let mut recorder = Recorder { stop: Some(tags::PatientName.key), ..Default::default() };
+        let events = visit(DcmReader::new(), sample(), &mut recorder);
+        assert_eq!(events[1..], ["element 00100010 PN"]);
+
+        // The whitelist applies before any callback.
+        let mut recorder = Recorder::default();
+        let events = visit(DcmReader::new().tag_whitelist(vec![tags::Rows.key]), sample(), &mut recorder);
+        assert_eq!(events[1..], ["element 00280010 US", "value \0\u{2}"]);
+    }
+
+    /// Hands out one byte per read and counts what was consumed.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        read: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some(&b) = self.data.get(self.read) else { return Ok(0) };
+            let Some(first) = buf.first_mut() else { return Ok(0) };
+            *first = b;
+            self.read += 1;
+            Ok(1)
+        }
+    }
+
+    /// `data` followed by a 64-byte native (7FE0,0010) OW value and a trailing
+    /// padding element.
+    fn with_pixels(data: Bytes) -> Vec<u8> {
+        let mut data = data.to_vec();
+        data.extend_from_slice(&[0xE0, 0x7F, 0x10, 0x00]);
+        data.extend_from_slice(b"OW");
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&64u32.to_le_bytes());
+        data.extend_from_slice(&[7; 64]);
+        data.extend_from_slice(&[0xFC, 0xFF, 0xFC, 0xFF, b'O', b'B', 0, 0, 0, 0, 0, 0]);
+        data
+    }
+
+    #[test]
+    fn stream_matches_buffered_read() {
+        for data in [sample_with_header(), sample_deflated(), sample_sequence(true), sample_encapsulated()] {
+            let buffered = DcmReader::new().parse_bytes(data.clone()).expect("buffered");
+            let mut input = Trickle { data: &data, read: 0 };
+            let streamed = DcmReader::new().parse_bufreader(&mut input).expect("streamed");
+            assert_eq!(streamed.header.is_some(), buffered.header.is_some());
+            assert_eq!(streamed.dataset.expect("dataset").len(), buffered.dataset.expect("dataset").len());
+        }
+    }
+
+    #[test]
+    fn stream_stops_reading_after_tag_max() {
+        let data = with_pixels(sample_with_header());
+        let mut input = Trickle { data: &data, read: 0 };
+        let out = DcmReader::new().tag_max(tags::Rows.key).parse_bufreader(&mut input).expect("read");
+        let ds = out.dataset.expect("dataset");
+        assert_eq!(ds.get::<u16>(&tags::Rows).expect("Rows"), 512);
+        assert!(!ds.contains(&tags::PixelData));
+        // Only the Pixel Data header was peeked; its value stayed in the stream.
+        assert_eq!(input.read, data.len() - 64 - 12);
+
+        let mut input = Trickle { data: &data, read: 0 };
+        let out = DcmReader::new().mode(ReadMode::HeaderOnly).parse_bufreader(&mut input).expect("read");
+        assert!(out.header.is_some() && out.dataset.is_none());
+        assert!(input.read <= sample_with_header().len() - sample().len() + 12);
+    }
+
+    #[test]
+    fn stream_drops_unlisted_elements() {
+        let out = DcmReader::new()
+            .tag_whitelist(vec![tags::Rows.key])
+            .parse_bufreader(&sample_sequence(true)[..])
+            .expect("read");
+        assert!(out.dataset.expect("dataset").is_empty());
+        let mut recorder = Recorder::default();
+        DcmReader::new()
+            .tag_whitelist(vec![tags::Rows.key])
+            .visit_bufreader(&sample()[..], &mut recorder)
+            .expect("visit");
+        assert_eq!(recorder.events[1..], ["element 00280010 US", "value \0\u{2}"]);
+    }
+
+    #[test]
+    fn stream_hands_off_large_values() {
+        let data = with_pixels(sample_with_header());
+        let mut input = Trickle { data: &data, read: 0 };
+        let out = DcmReader::new().lazy_threshold(32).parse_stream(&mut input).expect("read");
+        assert_eq!(out.dataset.expect("dataset").len(), 2);
+        let lazy = out.lazy.expect("lazy pixel data");
+        assert_eq!(lazy.header().tag, tags::PixelData.key);
+        assert_eq!(lazy.header().length.0, 64);
+        match lazy.read_pixel_data().expect("pixels") {
+            PixelData::Native(bytes) => assert_eq!(&bytes[..], &[7; 64]),
+            PixelData::Encapsulated { .. } => panic!("expected native pixel data"),
+        }
+        assert_eq!(input.read, data.len() - 12);
+
+        let data = sample_encapsulated();
+        let out = DcmReader::new().lazy_threshold(1 << 20).parse_stream(&data[..]).expect("read");
+        assert!(out.dataset.expect("dataset").is_empty());
+        match out.lazy.expect("lazy pixel data").read_pixel_data().expect("pixels") {
+            PixelData::Encapsulated { bot, fragments, .. } => {
+                assert!(bot.is_empty());
+                assert_eq!(fragments, [&b"ABCD"[..], &b"EF"[..]]);
+            }
+            PixelData::Native(_) => panic!("expected encapsulated pixel data"),
+        }
+    }
+
+    #[test]
+    fn lazy_value_rejects_truncated_streams() {
+        let data = with_pixels(sample_with_header());
+        let value_end = data.len() - 12;
+        // A value cut short, then one claiming nearly 4 GiB.
+        let truncated = &data[..value_end - 32];
+        let mut huge = data[..value_end].to_vec();
+        huge[value_end - 68..value_end - 64].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        for input in [truncated, &huge[..]] {
+            let out = DcmReader::new().lazy_threshold(32).parse_stream(input).expect("read");
+            let err = out.lazy.expect("lazy pixel data").read_pixel_data().unwrap_err();
+            assert!(err.to_string().contains("bytes short"), "{err}");
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn parse_async_inflates_and_stops() {
+        let out = DcmReader::new().parse_async(std::io::Cursor::new(sample_deflated())).await.expect("read");
+        let ds = out.dataset.expect("dataset");
+        assert_eq!(ds.get::<String>(&tags::PatientName).expect("PN"), "Doe^John");
+        assert_eq!(ds.get::<u16>(&tags::Rows).expect("Rows"), 512);
+
+        let reader = DcmReader::new().tag_max(tags::PatientName.key);
+        let out = reader.parse_async(std::io::Cursor::new(sample_with_header())).await.expect("read");
+        assert!(!out.dataset.expect("dataset").contains(&tags::Rows));
+    }
 }
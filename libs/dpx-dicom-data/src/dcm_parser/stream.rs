@@ -0,0 +1,285 @@
+//! Incremental input for non-mmappable streams (pipes, sockets, huge files).
+//!
+//! Instead of buffering the whole stream, the reader pulls it one top-level
+//! element at a time: each header is peeked, the element is moved into the
+//! retained buffer (or discarded when the whitelist drops it), undefined-length
+//! contents are walked item by item, and reading ends as soon as a tag passes
+//! `stop_after`. The retained bytes — memory bounded by what is kept — are then
+//! parsed by the same [`core`](super::core). A large value can be left in the
+//! stream as a [`LazyValue`] for the caller to read on demand.
+//!
+//! Offsets reported for a streamed read (visitor events, `file_offsets`) refer
+//! to the retained bytes, which omit discarded elements and are inflated for a
+//! Deflated transfer syntax.
+
+use std::io::{self, Chain, Cursor, Read};
+
+use bytes::Bytes;
+use flate2::read::DeflateDecoder;
+
+use dpx_dicom_core::error::{IntoDicomErr, Result};
+use dpx_dicom_core::{TagKey, TransferSyntax, Vr, ensure, tags};
+
+use super::core::{self, UNDEFINED_LENGTH, read_tag, read_u32};
+use super::visitor::ElementHeader;
+use crate::value::PixelData;
+
+/// Longest element header: tag, VR, 2 reserved bytes and a 32-bit length.
+const MAX_HEADER: usize = 12;
+
+/// A reader with a small lookahead window for peeking element headers.
+pub(crate) struct Pull<R> {
+    reader: R,
+    window: Vec<u8>,
+}
+
+impl<R: Read> Pull<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Pull { reader, window: Vec::new() }
+    }
+
+    /// Buffers until `n` bytes are available or the stream ends; returns what
+    /// is available, at most `n` bytes.
+    pub(crate) fn peek(&mut self, n: usize) -> io::Result<&[u8]> {
+        while self.window.len() < n {
+            let have = self.window.len();
+            self.window.resize(n, 0);
+            let got = loop {
+                match self.reader.read(&mut self.window[have..]) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    other => break other,
+                }
+            };
+            let got = got.inspect_err(|_| self.window.truncate(have))?;
+            self.window.truncate(have + got);
+            if got == 0 {
+                break;
+            }
+        }
+        Ok(&self.window[..n.min(self.window.len())])
+    }
+
+    /// Moves the next `n` bytes to `out`, or discards them when `out` is
+    /// `None`. Returns how many were available (short at the end of stream).
+    pub(crate) fn take(&mut self, n: usize, out: Option<&mut Vec<u8>>) -> io::Result<usize> {
+        let buffered = n.min(self.window.len());
+        let rest = (n - buffered) as u64;
+        let streamed = match out {
+            Some(out) => {
+                out.extend_from_slice(&self.window[..buffered]);
+                self.window.drain(..buffered);
+                (&mut self.reader).take(rest).read_to_end(out)?
+            }
+            None => {
+                self.window.drain(..buffered);
+                io::copy(&mut (&mut self.reader).take(rest), &mut io::sink())? as usize
+            }
+        };
+        Ok(buffered + streamed)
+    }
+
+    /// Puts bytes back in front of the stream.
+    pub(crate) fn unread(&mut self, bytes: &[u8]) {
+        self.window.splice(0..0, bytes.iter().copied());
+    }
+
+    /// The rest of the stream, lookahead included.
+    pub(crate) fn into_reader(self) -> Chain<Cursor<Vec<u8>>, R> {
+        Cursor::new(self.window).chain(self.reader)
+    }
+
+    /// Peeks and parses the next element header; `None` at the end of the
+    /// stream or when the remaining bytes do not form a header.
+    fn header(&mut self, ts: &TransferSyntax) -> io::Result<Option<ElementHeader>> {
+        let head = self.peek(MAX_HEADER)?;
+        Ok(if head.len() < 8 { None } else { core::peek_header(head, ts).ok() })
+    }
+
+    /// Moves the element whose header `h` was just peeked to `out` (or
+    /// discards it), walking undefined-length contents to find their end.
+    fn element(&mut self, h: &ElementHeader, ts: &TransferSyntax, mut out: Option<&mut Vec<u8>>) -> io::Result<()> {
+        self.take(h.value_offset, out.as_deref_mut())?;
+        if h.is_undefined_length() { self.undefined(ts, out) } else { self.take(h.length.0 as usize, out).map(drop) }
+    }
+
+    /// Undefined-length contents: sequence items or pixel-data fragments up to
+    /// the Sequence Delimitation Item. A malformed or truncated structure ends
+    /// the walk; the core parser reports it when parsing the retained bytes.
+    fn undefined(&mut self, ts: &TransferSyntax, mut out: Option<&mut Vec<u8>>) -> io::Result<()> {
+        let le = ts.is_little_endian;
+        loop {
+            let head = self.peek(8)?;
+            if head.len() < 8 {
+                return Ok(());
+            }
+            let (tag, len) = (read_tag(head, 0, le), read_u32(head, 4, le));
+            if tag != tags::Item.key {
+                if tag == tags::SequenceDelimitationItem.key {
+                    self.take(8, out)?;
+                }
+                return Ok(());
+            }
+            self.take(8, out.as_deref_mut())?;
+            if len != UNDEFINED_LENGTH {
+                self.take(len as usize, out.as_deref_mut())?;
+                continue;
+            }
+            loop {
+                let head = self.peek(8)?;
+                if head.len() >= 4 && read_tag(head, 0, le) == tags::ItemDelimitationItem.key {
+                    self.take(8, out.as_deref_mut())?;
+                    break;
+                }
+                let Some(h) = self.header(ts)? else { return Ok(()) };
+                self.element(&h, ts, out.as_deref_mut())?;
+            }
+        }
+    }
+
+    /// Moves the File Meta group that follows the preamble to `out`: the
+    /// length its (0002,0000) group length declares, else every group 0002
+    /// element.
+    pub(crate) fn meta_group(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        let le = &TransferSyntax::ExplicitVRLittleEndian;
+        if let Some(h) = self.header(le)?
+            && h.tag == tags::FileMetaInformationGroupLength.key
+            && h.length.0 == 4
+        {
+            let start = out.len();
+            self.take(h.value_offset + 4, Some(out))?;
+            if out.len() == start + h.value_offset + 4 {
+                let group_length = read_u32(out, start + h.value_offset, true);
+                self.take(group_length as usize, Some(out))?;
+                return Ok(());
+            }
+        }
+        while let Some(h) = self.header(le)? {
+            if h.tag.group() != 0x0002 || h.is_undefined_length() {
+                break;
+            }
+            self.element(&h, le, Some(out))?;
+        }
+        Ok(())
+    }
+
+    /// Moves the top-level elements of a data set encoded in `ts` to `out`,
+    /// dropping those outside `whitelist` and stopping before the first tag
+    /// past `stop_after`. With a `lazy` threshold, the first kept value longer
+    /// than it (or encapsulated Pixel Data) ends the walk with its header
+    /// consumed; its header is returned.
+    pub(crate) fn dataset(
+        &mut self,
+        ts: &TransferSyntax,
+        stop_after: TagKey,
+        whitelist: Option<&[TagKey]>,
+        lazy: Option<u32>,
+        out: &mut Vec<u8>,
+    ) -> io::Result<Option<ElementHeader>> {
+        while let Some(h) = self.header(ts)? {
+            if h.tag.0 > stop_after.0 {
+                break;
+            }
+            let kept = whitelist.is_none_or(|wl| wl.binary_search_by(|k| k.0.cmp(&h.tag.0)).is_ok());
+            if kept && lazy.is_some_and(|threshold| is_large(&h, threshold)) {
+                self.take(h.value_offset, None)?;
+                return Ok(Some(h));
+            }
+            self.element(&h, ts, kept.then_some(&mut *out))?;
+        }
+        Ok(None)
+    }
+}
+
+/// Whether the value announced by `h` is handed off rather than read.
+fn is_large(h: &ElementHeader, threshold: u32) -> bool {
+    if h.is_undefined_length() { h.tag == tags::PixelData.key } else { h.vr != Vr::SQ && h.length.0 > threshold }
+}
+
+/// The data-set part of a stream: plain, or inflated on the fly for the
+/// Deflated Explicit VR Little Endian transfer syntax.
+pub(crate) enum Body<R> {
+    Plain(R),
+    Deflated(DeflateDecoder<R>),
+}
+
+impl<R: Read> Read for Body<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Body::Plain(r) => r.read(buf),
+            Body::Deflated(r) => r.read(buf),
+        }
+    }
+}
+
+/// The stream behind a [`LazyValue`]: the data set body following the File
+/// Meta header, itself preceded by whatever lookahead was buffered.
+type Rest<R> = Chain<Cursor<Vec<u8>>, Body<Chain<Cursor<Vec<u8>>, R>>>;
+
+/// A value left unread by [`DcmReader::parse_stream`](super::DcmReader::parse_stream)
+/// (see [`lazy_threshold`](super::DcmReader::lazy_threshold)): its header, and
+/// the stream positioned at its first value byte. Nothing after it was read.
+pub struct LazyValue<R> {
+    header: ElementHeader,
+    little_endian: bool,
+    rest: Rest<R>,
+}
+
+impl<R: Read> LazyValue<R> {
+    pub(crate) fn new(header: ElementHeader, little_endian: bool, rest: Rest<R>) -> Self {
+        LazyValue { header, little_endian, rest }
+    }
+
+    /// The element header. Offsets refer to the retained (inflated) bytes.
+    pub fn header(&self) -> &ElementHeader {
+        &self.header
+    }
+
+    /// Reads the value as pixel data: its bytes for a defined length, else the
+    /// Basic Offset Table and fragments of encapsulated Pixel Data.
+    pub fn read_pixel_data(mut self) -> Result<PixelData> {
+        let ctx =
+            || format!("reading the value of ({:04X},{:04X})", self.header.tag.group(), self.header.tag.element());
+        if !self.header.is_undefined_length() {
+            let value = read_value(&mut self.rest, self.header.length.0, ctx)?;
+            return Ok(PixelData::Native(Bytes::from(value)));
+        }
+        let le = self.little_endian;
+        let mut items = Vec::new();
+        loop {
+            let mut head = [0u8; 8];
+            self.rest.read_exact(&mut head).to_dicom_err_with(ctx)?;
+            let (tag, len) = (read_tag(&head, 0, le), read_u32(&head, 4, le));
+            if tag == tags::SequenceDelimitationItem.key {
+                break;
+            }
+            ensure!(
+                tag == tags::Item.key && len != UNDEFINED_LENGTH,
+                InvalidData,
+                "expected a fragment item (FFFE,E000) but found ({:04X},{:04X})",
+                tag.group(),
+                tag.element()
+            );
+            items.push(Bytes::from(read_value(&mut self.rest, len, ctx)?));
+        }
+        // The first item is the Basic Offset Table; the rest are fragments.
+        let mut items = items.into_iter();
+        let bot = items.next().map(|t| t.chunks_exact(4).map(|c| read_u32(c, 0, le)).collect()).unwrap_or_default();
+        Ok(PixelData::Encapsulated { bot, fragments: items.collect(), eot: Vec::new() })
+    }
+
+    /// The stream positioned at the first value byte, for reading it (and
+    /// anything after it) directly.
+    pub fn into_reader(self) -> impl Read {
+        self.rest
+    }
+}
+
+/// Reads `len` bytes, failing on a short read. The buffer grows with the bytes
+/// actually read, so a corrupt length can not force a huge allocation.
+fn read_value(rest: &mut impl Read, len: u32, ctx: impl Fn() -> String) -> Result<Vec<u8>> {
+    let mut value = Vec::new();
+    rest.take(u64::from(len)).read_to_end(&mut value).to_dicom_err_with(&ctx)?;
+    let short = len as usize - value.len();
+    ensure!(short == 0, InvalidData, "{}: stream ends {short} bytes short", ctx());
+    Ok(value)
+}
@@ -0,0 +1,158 @@
+//! Event-driven (SAX-style) access to the parser core.
+//!
+//! [`DcmReader::visit_bytes`](super::DcmReader::visit_bytes) and its siblings
+//! walk a stream and report what they find to a [`DcmVisitor`] instead of
+//! materializing a [`DataSet`](crate::DataSet): element headers, raw value
+//! slices, sequence and item boundaries, and encapsulated pixel fragments.
+//! Every callback steers the walk through its returned [`Visit`]. Building a
+//! `DataSet` is itself one such visitor, so both paths share the same lenient
+//! parsing.
+
+use dpx_dicom_core::{TagKey, TransferSyntax, Vr};
+
+use crate::dataset::DatasetKind;
+use crate::value::TagHeader;
+
+/// What the parser does after a [`DcmVisitor`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Visit {
+    /// Carry on.
+    #[default]
+    Continue,
+    /// Skip what the callback opened: a data set, an element's value, a whole
+    /// sequence or item, or the remaining pixel fragments. From a callback
+    /// that opens nothing (values, ends) it acts as [`Continue`](Self::Continue).
+    Skip,
+    /// End the walk; no further callbacks are made.
+    Stop,
+}
+
+/// A data element header as read from the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementHeader {
+    /// Offset of the tag from the start of the (inflated) stream.
+    pub offset: usize,
+    pub tag: TagKey,
+    /// The VR the value is encoded with: the wire VR under Explicit VR, else the
+    /// dictionary VR. `Vr::Undefined` when an Implicit VR tag has several
+    /// dictionary VRs (Pixel Data "OB or OW", "US or SS", …); siblings decide.
+    pub vr: Vr,
+    /// VR bytes read from the stream; `None` under Implicit VR.
+    pub vr_code: Option<[u8; 2]>,
+    /// The length field as `(value, size_in_bytes)`, as in [`TagHeader::length`].
+    pub length: (u32, usize),
+    /// Offset of the first value byte.
+    pub value_offset: usize,
+}
+
+impl ElementHeader {
+    /// Whether the length field holds the undefined length `0xFFFF_FFFF`.
+    pub fn is_undefined_length(&self) -> bool {
+        self.length.0 == u32::MAX
+    }
+}
+
+/// Receives parse events from [`DcmReader`](super::DcmReader). Every method
+/// has a no-op default, so an implementation overrides only what it needs.
+///
+/// Each data set is bracketed by [`dataset_start`](Self::dataset_start) — the
+/// File Meta group first when the stream has one, then the main data set.
+/// Within it every element opens with exactly one of:
+///
+/// * [`element`](Self::element), followed by [`value`](Self::value);
+/// * [`sequence_start`](Self::sequence_start), followed by
+///   [`item_start`](Self::item_start) … [`item_end`](Self::item_end) per item
+///   (nesting the same events) and [`sequence_end`](Self::sequence_end);
+/// * [`pixel_data_start`](Self::pixel_data_start) for encapsulated Pixel Data,
+///   followed by [`pixel_offset_table`](Self::pixel_offset_table), one
+///   [`pixel_fragment`](Self::pixel_fragment) per fragment and
+///   [`pixel_data_end`](Self::pixel_data_end).
+///
+/// Returning [`Visit::Skip`] from an opening callback skips everything up to
+/// and including its matching end; the reader's tag whitelist and `tag_max`
+/// apply before any callback is made.
+pub trait DcmVisitor {
+    /// A data set begins; `ts` is the transfer syntax its values are encoded in.
+    fn dataset_start(&mut self, kind: DatasetKind, ts: &'static TransferSyntax) -> Visit {
+        let _ = (kind, ts);
+        Visit::Continue
+    }
+
+    /// A non-sequence element header; [`Visit::Skip`] omits its value.
+    fn element(&mut self, header: &ElementHeader) -> Visit {
+        let _ = header;
+        Visit::Continue
+    }
+
+    /// The raw value bytes of the element just announced, in the stream's
+    /// byte order and character set.
+    fn value(&mut self, header: &ElementHeader, value: &[u8]) -> Visit {
+        let _ = (header, value);
+        Visit::Continue
+    }
+
+    /// A sequence begins. Also reported for an undefined-length non-SQ element,
+    /// which the parser reads as a sequence.
+    fn sequence_start(&mut self, header: &ElementHeader) -> Visit {
+        let _ = header;
+        Visit::Continue
+    }
+
+    /// Item `index` of the current sequence begins; `item.size` is not yet known.
+    fn item_start(&mut self, index: usize, item: &TagHeader) -> Visit {
+        let _ = (index, item);
+        Visit::Continue
+    }
+
+    /// Item `index` ends; `item.size` is the byte length of its data set and
+    /// `delimiter` the Item Delimitation Item, if one closed it.
+    fn item_end(&mut self, index: usize, item: &TagHeader, delimiter: Option<&TagHeader>) -> Visit {
+        let _ = (index, item, delimiter);
+        Visit::Continue
+    }
+
+    /// The sequence ends at `end`, just past any Sequence Delimitation Item.
+    fn sequence_end(&mut self, header: &ElementHeader, end: usize) -> Visit {
+        let _ = (header, end);
+        Visit::Continue
+    }
+
+    /// Encapsulated (undefined-length) Pixel Data begins.
+    fn pixel_data_start(&mut self, header: &ElementHeader) -> Visit {
+        let _ = header;
+        Visit::Continue
+    }
+
+    /// The Basic Offset Table item and its (possibly empty) offsets. Not
+    /// reported when the table is missing or malformed.
+    fn pixel_offset_table(&mut self, item: &TagHeader, offsets: &[u32]) -> Visit {
+        let _ = (item, offsets);
+        Visit::Continue
+    }
+
+    /// Fragment `index` (from 0, after the offset table) and its bytes.
+    fn pixel_fragment(&mut self, index: usize, item: &TagHeader, fragment: &[u8]) -> Visit {
+        let _ = (index, item, fragment);
+        Visit::Continue
+    }
+
+    /// Encapsulated Pixel Data ends at `end`; `delimiter` is the Sequence
+    /// Delimitation Item unless the stream was truncated.
+    fn pixel_data_end(&mut self, header: &ElementHeader, end: usize, delimiter: Option<&TagHeader>) -> Visit {
+        let _ = (header, end, delimiter);
+        Visit::Continue
+    }
+}
+
+/// Walks structure without reporting it: skipped undefined-length sequences
+/// must still be traversed to find where they end.
+pub(crate) struct Mute;
+
+impl DcmVisitor for Mute {
+    fn element(&mut self, _: &ElementHeader) -> Visit {
+        Visit::Skip
+    }
+    fn pixel_data_start(&mut self, _: &ElementHeader) -> Visit {
+        Visit::Skip
+    }
+}
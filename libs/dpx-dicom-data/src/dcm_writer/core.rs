@@ -45,11 +45,16 @@ fn pad_byte(vr: Vr) -> u8 {
 /// Byte width of one stored word for `vr` (for byte-order transcoding of raw
 /// values). 1 means an opaque byte stream that never needs swapping.
 fn word_width(vr: Vr) -> usize {
-    match vr.info().kind {
-        Kind::U16 | Kind::I16 => 2,
-        Kind::U32 | Kind::I32 | Kind::F32 => 4,
-        Kind::U64 | Kind::I64 | Kind::F64 => 8,
-        _ => 1,
+    match vr {
+        Vr::OW => 2,
+        Vr::OF | Vr::OL => 4,
+        Vr::OD | Vr::OV => 8,
+        _ => match vr.info().kind {
+            Kind::U16 | Kind::I16 => 2,
+            Kind::U32 | Kind::I32 | Kind::F32 => 4,
+            Kind::U64 | Kind::I64 | Kind::F64 => 8,
+            _ => 1,
+        },
     }
 }
 
@@ -74,11 +79,22 @@ pub(crate) struct Serializer<'a, W: Write> {
     target: &'static TransferSyntax,
     /// Write SQ and items with undefined length + delimiters (else defined length).
     undefined_sq: bool,
+    /// Root elements written in place of (or in addition to) the stored ones,
+    /// sorted by tag; `None` drops the stored element.
+    overrides: &'a [(TagKey, Option<Element>)],
 }
 
 impl<'a, W: Write> Serializer<'a, W> {
     pub(crate) fn new(out: W, shared: &'a Shared, target: &'static TransferSyntax, undefined_sq: bool) -> Self {
-        Self { out, shared, target, undefined_sq }
+        Self { out, shared, target, undefined_sq, overrides: &[] }
+    }
+
+    /// Writes `overrides` (sorted by tag) at the root in place of the stored
+    /// elements with the same tags, inserting any that are absent; a `None`
+    /// override omits the stored element.
+    pub(crate) fn with_overrides(mut self, overrides: &'a [(TagKey, Option<Element>)]) -> Self {
+        self.overrides = overrides;
+        self
     }
 
     fn put(&mut self, bytes: &[u8]) -> Result<()> {
@@ -145,16 +161,32 @@ impl<'a, W: Write> Serializer<'a, W> {
         let tz = self.shared.default_tz();
         let stamp = !self.shared.has_root_tz() && !matches!(tz, DicomTimeZoneOffset::Local);
         let mut done = !stamp;
+        let overrides = self.overrides;
+        let mut added = overrides
+            .iter()
+            .filter(|(key, _)| !item.map.contains_key(*key))
+            .filter_map(|(key, el)| Some((key, el.as_ref()?)))
+            .peekable();
         for (key, el) in item.map.entries() {
+            while let Some((k, e)) = added.next_if(|(k, _)| k.0 < key.0) {
+                self.element(*k, e)?;
+            }
             if !done && key.0 > tags::TimezoneOffsetFromUTC.key.0 {
                 self.write_timezone(tz)?;
                 done = true;
             }
-            self.element(*key, el)?;
+            match overrides.iter().find(|(k, _)| k == key) {
+                Some((_, Some(e))) => self.element(*key, e)?,
+                Some((_, None)) => {}
+                None => self.element(*key, el)?,
+            }
         }
         if !done {
             self.write_timezone(tz)?;
         }
+        for (k, e) in added {
+            self.element(*k, e)?;
+        }
         Ok(())
     }
 
@@ -229,10 +261,11 @@ impl<'a, W: Write> Serializer<'a, W> {
     fn pixels(&mut self, tag: TagKey, vr: Vr, px: &PixelData) -> Result<()> {
         match px {
             PixelData::Native(b) => {
-                self.write_header(tag, vr, b.len() as u32)?;
-                self.put(b)
+                let body = transcode(b, vr, self.shared.is_little_endian(), self.target.is_little_endian);
+                self.write_header(tag, vr, body.len() as u32)?;
+                self.put(&body)
             }
-            PixelData::Encapsulated { bot, fragments } => {
+            PixelData::Encapsulated { bot, fragments, .. } => {
                 self.write_header(tag, vr, UNDEFINED_LENGTH)?;
                 self.put_delimiter(tags::Item.key, (bot.len() * 4) as u32)?;
                 for &offset in bot {
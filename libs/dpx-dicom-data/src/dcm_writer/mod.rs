@@ -2,7 +2,9 @@
 //!
 //! The counterpart of [`dcm_parser`](crate::dcm_parser): the core is sans-io
 //! ([`core`]); [`writer`] is the configurable [`DcmWriter`] facade that adds the
-//! File Meta header and deflation. Sibling XML/JSON writers will live alongside.
+//! File Meta header and deflation. The DICOM JSON
+//! ([`json_writer`](crate::json_writer)) and Native DICOM Model XML
+//! ([`xml_writer`](crate::xml_writer)) writers live alongside.
 
 mod core;
 mod writer;
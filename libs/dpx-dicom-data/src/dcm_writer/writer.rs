@@ -4,34 +4,49 @@ use std::io::Write;
 
 use bytes::Bytes;
 use flate2::{Compression, write::DeflateEncoder};
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use dpx_dicom_core::error::{IntoDicomErr, Result};
-use dpx_dicom_core::tags;
+use dpx_dicom_core::{TagKey, ensure, tags};
 
 use dpx_dicom_core::TransferSyntax;
 
 use super::core::Serializer;
 use crate::DataSet;
+use crate::value::Element;
+use crate::{codec, frames};
 
 const PREAMBLE: [u8; 128] = [0u8; 128];
 
 /// Serializes a [`DataSet`] back to the DICOM binary stream form.
 ///
-/// The target transfer syntax controls byte order, Explicit/Implicit VR and
-/// deflation; raw values stored in a different byte order are transcoded, others
-/// pass through unchanged. The File Meta header (when writing a full file) is
-/// always Explicit VR Little Endian and its group length is recomputed; its
-/// (0002,0010) Transfer Syntax UID is written verbatim from the supplied header,
-/// so transcoding to a different syntax means updating that element first.
+/// The target transfer syntax controls byte order, Explicit/Implicit VR,
+/// deflation and the Pixel Data encoding; raw values stored in a different byte
+/// order are transcoded, others pass through unchanged. When the target and the
+/// data set's transfer syntaxes differ and either is encapsulated, Pixel Data is
+/// decoded and re-encoded by the registered [`PixelCodec`](crate::PixelCodec)s,
+/// failing with `UnsupportedFeature` when no codec is registered for one of
+/// them. The File Meta header (when writing a full file) is always Explicit VR
+/// Little Endian and its group length is recomputed; its (0002,0010) Transfer
+/// Syntax UID is written verbatim from the supplied header, so transcoding to a
+/// different syntax means updating that element first.
 #[derive(Debug, Clone)]
 pub struct DcmWriter {
     xfer: &'static TransferSyntax,
     undefined_sq: bool,
+    extended_offsets: bool,
+    strict: bool,
 }
 
 impl Default for DcmWriter {
     fn default() -> Self {
-        Self { xfer: &TransferSyntax::ExplicitVRLittleEndian, undefined_sq: true }
+        Self {
+            xfer: &TransferSyntax::ExplicitVRLittleEndian,
+            undefined_sq: true,
+            extended_offsets: false,
+            strict: false,
+        }
     }
 }
 
@@ -53,6 +68,39 @@ impl DcmWriter {
         self
     }
 
+    /// Whether encapsulated Pixel Data always gets a (7FE0,0001) Extended Offset
+    /// Table and (7FE0,0002) Lengths, with an empty Basic Offset Table and one
+    /// fragment per frame (default `false`: only when the data set has one or
+    /// the offsets overflow the 32-bit Basic Offset Table).
+    pub fn extended_offset_table(mut self, always: bool) -> Self {
+        self.extended_offsets = always;
+        self
+    }
+
+    /// Whether to refuse writing a data set, or File Meta header, with values
+    /// failing [`DataSet::validate_values`] (default `false`). The error lists
+    /// the findings.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn check(&self, ds: &DataSet) -> Result<()> {
+        if self.strict {
+            let report = ds.validate_values();
+            ensure!(report.is_valid(), InvalidData, "data set has invalid values:\n{report}");
+        }
+        Ok(())
+    }
+
+    /// The root elements written in place of the stored ones: transcoded Pixel
+    /// Data and the offset tables.
+    fn overrides(&self, ds: &DataSet) -> Result<Vec<(TagKey, Option<Element>)>> {
+        let mut overrides: Vec<_> = codec::transcode(ds, self.xfer)?.into_iter().map(|(k, el)| (k, Some(el))).collect();
+        frames::offset_tables(ds, &mut overrides, self.extended_offsets)?;
+        Ok(overrides)
+    }
+
     /// Transfer syntax used for the body bytes (deflation is applied by the
     /// stream wrapper, so the inner framing is plain Explicit VR LE).
     fn body_ts(&self) -> &'static TransferSyntax {
@@ -67,21 +115,9 @@ impl DcmWriter {
     /// Writes a full file: 128-byte preamble, `DICM`, the File Meta header (from
     /// `header`, group length recomputed) and the data set body.
     pub fn write_file<W: Write>(&self, header: &DataSet, ds: &DataSet, mut w: W) -> Result<()> {
-        let (meta_shared, meta_root) = header.context();
-        let mut meta_body = Vec::new();
-        Serializer::new(&mut meta_body, meta_shared, &TransferSyntax::ExplicitVRLittleEndian, false)
-            .elements_skipping(meta_root, tags::FileMetaInformationGroupLength.key)?;
-
-        let io = |r: std::io::Result<()>| r.to_dicom_err_with(|| "writing File Meta header".to_string());
-        io(w.write_all(&PREAMBLE))?;
-        io(w.write_all(b"DICM"))?;
-        // (0002,0000) UL group length, Explicit VR LE.
-        io(w.write_all(&[0x02, 0x00, 0x00, 0x00]))?;
-        io(w.write_all(b"UL"))?;
-        io(w.write_all(&4u16.to_le_bytes()))?;
-        io(w.write_all(&(meta_body.len() as u32).to_le_bytes()))?;
-        io(w.write_all(&meta_body))?;
-
+        self.check(header)?;
+        let prefix = file_prefix(header)?;
+        w.write_all(&prefix).to_dicom_err_with(|| "writing File Meta header".to_string())?;
         self.write_body(ds, w)
     }
 
@@ -100,22 +136,138 @@ impl DcmWriter {
     }
 
     fn write_body<W: Write>(&self, ds: &DataSet, w: W) -> Result<()> {
+        self.check(ds)?;
         let (shared, root) = ds.context();
         let ts = self.body_ts();
+        let overrides = self.overrides(ds)?;
         if self.xfer.is_compressed {
             let mut enc = DeflateEncoder::new(w, Compression::default());
-            Serializer::new(&mut enc, shared, ts, self.undefined_sq).root(root)?;
+            Serializer::new(&mut enc, shared, ts, self.undefined_sq).with_overrides(&overrides).root(root)?;
             enc.finish().to_dicom_err_with(|| "deflating data set".to_string())?;
             Ok(())
         } else {
-            Serializer::new(w, shared, ts, self.undefined_sq).root(root)
+            Serializer::new(w, shared, ts, self.undefined_sq).with_overrides(&overrides).root(root)
+        }
+    }
+}
+
+/// The preamble, `DICM` and the File Meta group of `header`, with its group
+/// length recomputed.
+fn file_prefix(header: &DataSet) -> Result<Vec<u8>> {
+    let (meta_shared, meta_root) = header.context();
+    let mut meta_body = Vec::new();
+    Serializer::new(&mut meta_body, meta_shared, &TransferSyntax::ExplicitVRLittleEndian, false)
+        .elements_skipping(meta_root, tags::FileMetaInformationGroupLength.key)?;
+
+    let mut prefix = Vec::with_capacity(PREAMBLE.len() + 16 + meta_body.len());
+    prefix.extend_from_slice(&PREAMBLE);
+    prefix.extend_from_slice(b"DICM");
+    // (0002,0000) UL group length, Explicit VR LE.
+    prefix.extend_from_slice(&[0x02, 0x00, 0x00, 0x00]);
+    prefix.extend_from_slice(b"UL");
+    prefix.extend_from_slice(&4u16.to_le_bytes());
+    prefix.extend_from_slice(&(meta_body.len() as u32).to_le_bytes());
+    prefix.extend_from_slice(&meta_body);
+    Ok(prefix)
+}
+
+/// Async entry points. The serializer (Pixel Data transcoding and deflation
+/// included) runs on tokio's blocking pool, like
+/// [`DcmReader::parse_async`](crate::DcmReader::parse_async), on a clone of the
+/// data set. Its output reaches the [`AsyncWrite`] in chunks through a bounded
+/// channel, so the runtime's worker threads only move bytes and memory stays
+/// bounded by a few chunks.
+#[cfg(feature = "tokio")]
+impl DcmWriter {
+    /// Async counterpart of [`write_dataset`](Self::write_dataset).
+    pub async fn write_async<W: AsyncWrite + Unpin>(&self, ds: &DataSet, w: &mut W) -> Result<()> {
+        self.write_body_async(ds, w).await
+    }
+
+    /// Async counterpart of [`write_file`](Self::write_file).
+    pub async fn write_file_async<W: AsyncWrite + Unpin>(
+        &self,
+        header: &DataSet,
+        ds: &DataSet,
+        w: &mut W,
+    ) -> Result<()> {
+        self.check(header)?;
+        let prefix = file_prefix(header)?;
+        w.write_all(&prefix).await.to_dicom_err_with(|| "writing File Meta header".to_string())?;
+        self.write_body_async(ds, w).await
+    }
+
+    async fn write_body_async<W: AsyncWrite + Unpin>(&self, ds: &DataSet, w: &mut W) -> Result<()> {
+        let (this, ds) = (self.clone(), ds.clone());
+        let ctx = dpx_dicom_core::Context::extend();
+        let (tx, mut rx) = tokio::sync::mpsc::channel(CHUNKS_IN_FLIGHT);
+        let task = tokio::task::spawn_blocking(move || {
+            ctx.provide(|| {
+                let mut out = Chunks { tx, buf: Vec::with_capacity(CHUNK_SIZE) };
+                this.write_body(&ds, &mut out)?;
+                out.flush().to_dicom_err_with(|| "writing data set".to_string())
+            })
+        });
+        // Returning early drops the receiver, which fails the task's next send.
+        let io = |r: std::io::Result<()>| r.to_dicom_err_with(|| "writing data set".to_string());
+        while let Some(chunk) = rx.recv().await {
+            io(w.write_all(&chunk).await)?;
         }
+        match task.await {
+            Ok(written) => written?,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => return Err(dpx_dicom_core::dicom_err!(Internal, "write task did not complete: {e}")),
+        }
+        io(w.flush().await)
+    }
+}
+
+/// Bytes per chunk sent from the blocking pool to the [`AsyncWrite`].
+#[cfg(feature = "tokio")]
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Chunks queued ahead of the [`AsyncWrite`] before the serializer waits.
+#[cfg(feature = "tokio")]
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+/// Cuts the serializer output into chunks for the async side of
+/// [`DcmWriter::write_async`].
+#[cfg(feature = "tokio")]
+struct Chunks {
+    tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "tokio")]
+impl Chunks {
+    fn send(&mut self) -> std::io::Result<()> {
+        let chunk = std::mem::replace(&mut self.buf, Vec::with_capacity(CHUNK_SIZE));
+        self.tx.blocking_send(chunk).map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "writer gone"))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Write for Chunks {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        let n = bytes.len().min(CHUNK_SIZE - self.buf.len());
+        self.buf.extend_from_slice(&bytes[..n]);
+        if self.buf.len() == CHUNK_SIZE {
+            self.send()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.buf.is_empty() {
+            self.send()?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::Image;
     use crate::{DcmReader, HeaderType};
     use dpx_dicom_core::tags;
 
@@ -165,6 +317,16 @@ mod tests {
         assert_eq!(ds2.get::<u16>(&tags::Rows).unwrap(), 512);
     }
 
+    #[test]
+    fn strict_refuses_invalid_values() {
+        let mut ds = read_le(Bytes::from(sample()));
+        assert!(DcmWriter::new().strict(true).to_bytes(&ds).is_ok());
+        ds.set(&tags::StudyDate, "2024-02-29").unwrap();
+        assert!(DcmWriter::new().to_bytes(&ds).is_ok());
+        let err = DcmWriter::new().strict(true).to_bytes(&ds).unwrap_err();
+        assert!(err.to_string().contains("StudyDate"), "{err}");
+    }
+
     #[test]
     fn roundtrip_full_file_preserves_transfer_syntax() {
         // Build a file with a File Meta header (Explicit VR LE).
@@ -296,6 +458,7 @@ mod tests {
         ds.set(&tags::InstanceNumber, 7.0_f64).unwrap(); // Float -> IS (integer text)
         ds.set(&tags::DiffusionBValue, "1.25").unwrap(); // Str -> FD (binary f64)
         ds.set(&tags::ExaminedBodyThickness, "3").unwrap(); // Str -> FL (binary f32)
+        ds.set(&tags::StudyDate, "20240229").unwrap(); // Str -> DA (text as is)
 
         let bytes = DcmWriter::new().to_bytes(&ds).unwrap();
         let ds2 = read_le(bytes);
@@ -304,6 +467,7 @@ mod tests {
         assert_eq!(ds2.get::<i32>(&tags::InstanceNumber).unwrap(), 7);
         assert!((ds2.get::<f64>(&tags::DiffusionBValue).unwrap() - 1.25).abs() < 1e-9);
         assert!((ds2.get::<f32>(&tags::ExaminedBodyThickness).unwrap() - 3.0).abs() < 1e-6);
+        assert_eq!(ds2.get_str_some(&tags::StudyDate).as_deref(), Some("20240229"));
     }
 
     #[test]
@@ -319,4 +483,119 @@ mod tests {
         assert_eq!(ds2.get::<u16>(&tags::Rows).unwrap(), 512);
         assert_eq!(ds2.get::<String>(&tags::PatientName).unwrap(), "Doe^John");
     }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn write_async_matches_sync() {
+        let mut ds = read_le(Bytes::from(sample()));
+        // Several chunks' worth of value.
+        let big = Bytes::from((0..200_000u32).map(|i| i as u8).collect::<Vec<_>>());
+        ds.set_with_vr(&tags::EncapsulatedDocument, dpx_dicom_core::Vr::OB, crate::Value::Bytes(big)).unwrap();
+        for ts in [&TransferSyntax::ExplicitVRLittleEndian, &TransferSyntax::DeflatedExplicitVRLittleEndian] {
+            let writer = DcmWriter::new().transfer_syntax(ts);
+            let expected = writer.to_bytes(&ds).expect("write");
+            let mut written = Vec::new();
+            writer.write_async(&ds, &mut written).await.expect("write async");
+            assert_eq!(written, expected);
+        }
+        // The future is Send, so it can run on a spawned task.
+        let written = tokio::spawn(async move {
+            let mut out = Vec::new();
+            DcmWriter::new().write_async(&ds, &mut out).await.map(|()| out)
+        });
+        assert!(!written.await.expect("join").expect("write").is_empty());
+    }
+
+    /// Two 2x2 frames of 16-bit MONOCHROME2 pixels, native Explicit VR LE.
+    fn sample_image() -> Vec<u8> {
+        let mut b = Vec::new();
+        el(&mut b, 0x0028, 0x0002, b"US", &1u16.to_le_bytes());
+        el(&mut b, 0x0028, 0x0004, b"CS", b"MONOCHROME2 ");
+        el(&mut b, 0x0028, 0x0008, b"IS", b"2 ");
+        el(&mut b, 0x0028, 0x0010, b"US", &2u16.to_le_bytes());
+        el(&mut b, 0x0028, 0x0011, b"US", &2u16.to_le_bytes());
+        el(&mut b, 0x0028, 0x0100, b"US", &16u16.to_le_bytes());
+        let pixels: Vec<u8> = (0u16..8).flat_map(|v| (v * 257).to_le_bytes()).collect();
+        el(&mut b, 0x7FE0, 0x0010, b"OW", &pixels);
+        b
+    }
+
+    #[test]
+    fn transcode_through_codec_registry() {
+        let ds = read_le(Bytes::from(sample_image()));
+        let native = ds.get_bytes(&tags::PixelData).expect("native").to_vec();
+
+        let encapsulated = &TransferSyntax::EncapsulatedUncompressedExplicitVRLittleEndian;
+        let out = DcmWriter::new().transfer_syntax(encapsulated).to_bytes(&ds).expect("encode");
+        let ds2 = read(out, encapsulated);
+        match ds2.value(&tags::PixelData).expect("pixels") {
+            crate::Value::Pixels(px) => match *px {
+                crate::PixelData::Encapsulated { bot, fragments, .. } => {
+                    assert_eq!(bot, [0, 16]);
+                    assert_eq!(fragments.concat(), native);
+                }
+                crate::PixelData::Native(_) => panic!("expected encapsulated"),
+            },
+            other => panic!("expected pixels, got {other:?}"),
+        }
+
+        let back = DcmWriter::new().to_bytes(&ds2).expect("decode");
+        let ds3 = read_le(back);
+        assert_eq!(ds3.get_bytes(&tags::PixelData).expect("native"), native);
+        assert_eq!(ds3.get::<u16>(&tags::Rows).expect("Rows"), 2);
+
+        let err = DcmWriter::new().transfer_syntax(&TransferSyntax::MPEG2MPML).to_bytes(&ds).unwrap_err();
+        assert_eq!(err.kind, dpx_dicom_core::error::ErrorKind::UnsupportedFeature);
+    }
+
+    fn pixel_tables(ds: &DataSet) -> (Vec<u32>, Vec<Bytes>, Vec<(u64, u64)>) {
+        match ds.value(&tags::PixelData).expect("pixels") {
+            crate::Value::Pixels(px) => match *px {
+                crate::PixelData::Encapsulated { bot, fragments, eot } => (bot, fragments, eot),
+                crate::PixelData::Native(_) => panic!("expected encapsulated"),
+            },
+            other => panic!("expected pixels, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn extended_offset_table_roundtrip() {
+        let rle = &TransferSyntax::RLELossless;
+        // Frame 0 spans the first two fragments.
+        let ds = Image::new(1, 1, 1, 8).frames(2).encapsulated(vec![0, 20], &[b"AB", b"CD", b"EF"]);
+        let ds = read(DcmWriter::new().to_bytes(&ds).expect("write"), rle);
+
+        let out = DcmWriter::new().transfer_syntax(rle).extended_offset_table(true).to_bytes(&ds).expect("write");
+        let ds2 = read(out, rle);
+        // The parser moves the tables into the pixel data model.
+        assert!(!ds2.contains(&tags::ExtendedOffsetTable));
+        assert!(!ds2.contains(&tags::ExtendedOffsetTableLengths));
+        let (bot, fragments, eot) = pixel_tables(&ds2);
+        assert!(bot.is_empty());
+        assert_eq!(fragments, [&b"ABCD"[..], b"EF"]);
+        assert_eq!(eot, [(0, 4), (12, 2)]);
+        let frames = crate::Frames::new(&ds2).expect("frames");
+        assert_eq!(frames.frame(1).expect("frame"), &b"EF"[..]);
+
+        // Once present it is kept without being asked for.
+        let ds3 = read(DcmWriter::new().transfer_syntax(rle).to_bytes(&ds2).expect("write"), rle);
+        assert_eq!(pixel_tables(&ds3).2, eot);
+    }
+
+    #[test]
+    fn stale_extended_offset_table_is_dropped() {
+        let uncompressed = &TransferSyntax::EncapsulatedUncompressedExplicitVRLittleEndian;
+        let mut ds = Image::new(1, 2, 1, 8).frames(2).encapsulated(vec![], &[b"AB", b"CD"]);
+        // As a hand-built or JSON-read data set carries it: plain elements.
+        let words = |v: [u64; 2]| crate::Value::Bytes(v.iter().flat_map(|w| w.to_le_bytes()).collect());
+        ds.set_with_vr(&tags::ExtendedOffsetTable, dpx_dicom_core::Vr::OV, words([0, 10])).unwrap();
+        ds.set_with_vr(&tags::ExtendedOffsetTableLengths, dpx_dicom_core::Vr::OV, words([2, 2])).unwrap();
+        let ds = read(DcmWriter::new().to_bytes(&ds).expect("write"), uncompressed);
+        assert_eq!(pixel_tables(&ds).2, [(0, 2), (10, 2)]);
+
+        let native = read_le(DcmWriter::new().to_bytes(&ds).expect("decode"));
+        assert!(!native.contains(&tags::ExtendedOffsetTable));
+        assert!(!native.contains(&tags::ExtendedOffsetTableLengths));
+        assert_eq!(native.get_bytes(&tags::PixelData).expect("native"), b"ABCD");
+    }
 }
@@ -0,0 +1,852 @@
+//! Attribute-level de-identification after the PS3.15 Annex E Basic
+//! Application Level Confidentiality Profile.
+//!
+//! [`Deidentifier`] applies the Table E.1-1 action of every attribute it
+//! knows, as modified by the enabled [`DeidentifyOption`]s, recursing into
+//! sequence items. Attributes the table does not list are kept. Private
+//! attributes are removed unless Retain Safe Private is enabled, in which case
+//! the action recorded for them in the private dictionary
+//! ([`PrivateIdentificationAction`]) is applied, and the private blocks kept
+//! are described in (0008,0300). The result records Patient Identity Removed,
+//! De-identification Method and its Code Sequence.
+//!
+//! Table E.1-1 allows several actions for some attributes (`X/Z`, `Z/D`,
+//! `X/Z/D`, ...), leaving the choice to what the IOD requires. Without the IOD
+//! at hand the engine picks the least destructive of them, so a conformant
+//! input stays conformant: `X/Z` empties, `Z/D`, `X/D` and `X/Z/D` replace with
+//! a dummy, and `X/Z/U*` keeps the sequence and remaps the UIDs within.
+//!
+//! Pixel data, overlays and other binary content are not inspected: the Clean
+//! Pixel Data, Clean Recognizable Visual Features and Clean Graphics options
+//! record that the caller has cleaned them.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::tag::{PrivateIdentificationAction, Source};
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{DicomDate, Tag, TagKey, Vr, tags};
+
+use crate::dataset::Shared;
+use crate::value::{Element, Stored};
+use crate::{DataSet, Item, OneOrMany, ReadOutput, UidMapper, Value};
+
+/// A Table E.1-1 action on one attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeidentifyAction {
+    /// Replace with a non-zero length dummy value consistent with the VR.
+    D,
+    /// Replace with a zero length value.
+    Z,
+    /// Remove.
+    X,
+    /// Keep.
+    K,
+    /// Clean: replace text with a value of similar meaning known not to be
+    /// identifying (see [`Deidentifier::cleaner`]), shift dates.
+    C,
+    /// Replace a UID with one that is consistent within the set of instances.
+    U,
+}
+
+/// A Profile Option of PS3.15 Annex E, identified by its CID 7050 code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeidentifyOption {
+    CleanPixelData,
+    CleanRecognizableVisualFeatures,
+    CleanGraphics,
+    CleanStructuredContent,
+    CleanDescriptors,
+    RetainLongitudinalFullDates,
+    RetainLongitudinalModifiedDates,
+    RetainPatientCharacteristics,
+    RetainDeviceIdentity,
+    RetainUids,
+    RetainSafePrivate,
+    RetainInstitutionIdentity,
+}
+
+impl DeidentifyOption {
+    /// Code Value of the option in CID 7050 (coding scheme `DCM`).
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::CleanPixelData => "113101",
+            Self::CleanRecognizableVisualFeatures => "113102",
+            Self::CleanGraphics => "113103",
+            Self::CleanStructuredContent => "113104",
+            Self::CleanDescriptors => "113105",
+            Self::RetainLongitudinalFullDates => "113106",
+            Self::RetainLongitudinalModifiedDates => "113107",
+            Self::RetainPatientCharacteristics => "113108",
+            Self::RetainDeviceIdentity => "113109",
+            Self::RetainUids => "113110",
+            Self::RetainSafePrivate => "113111",
+            Self::RetainInstitutionIdentity => "113112",
+        }
+    }
+
+    /// Code Meaning of the option in CID 7050.
+    pub fn meaning(self) -> &'static str {
+        match self {
+            Self::CleanPixelData => "Clean Pixel Data Option",
+            Self::CleanRecognizableVisualFeatures => "Clean Recognizable Visual Features Option",
+            Self::CleanGraphics => "Clean Graphics Option",
+            Self::CleanStructuredContent => "Clean Structured Content Option",
+            Self::CleanDescriptors => "Clean Descriptors Option",
+            Self::RetainLongitudinalFullDates => "Retain Longitudinal Temporal Information Full Dates Option",
+            Self::RetainLongitudinalModifiedDates => "Retain Longitudinal Temporal Information Modified Dates Option",
+            Self::RetainPatientCharacteristics => "Retain Patient Characteristics Option",
+            Self::RetainDeviceIdentity => "Retain Device Identity Option",
+            Self::RetainUids => "Retain UIDs Option",
+            Self::RetainSafePrivate => "Retain Safe Private Option",
+            Self::RetainInstitutionIdentity => "Retain Institution Identity Option",
+        }
+    }
+}
+
+const BASIC_PROFILE: (&str, &str) = ("113100", "Basic Application Confidentiality Profile");
+
+/// The Basic Profile column of Table E.1-1, compound actions included.
+#[derive(Debug, Clone, Copy)]
+enum Basic {
+    One(DeidentifyAction),
+    XorZ,
+    ZorD,
+    XorD,
+    XorZorD,
+    XorZorU,
+}
+
+impl Basic {
+    /// The action taken for a compound entry (see the module documentation).
+    fn resolve(self) -> DeidentifyAction {
+        match self {
+            Basic::One(action) => action,
+            Basic::XorZ => DeidentifyAction::Z,
+            Basic::ZorD | Basic::XorD | Basic::XorZorD => DeidentifyAction::D,
+            Basic::XorZorU => DeidentifyAction::U,
+        }
+    }
+}
+
+/// One row of Table E.1-1: the Basic Profile action and the options that
+/// override it. The date and UID options are not spelled out per row; they
+/// apply to every row by VR (see [`Deidentifier::actions`]).
+struct Rule {
+    key: TagKey,
+    basic: Basic,
+    options: &'static [(DeidentifyOption, DeidentifyAction)],
+}
+
+macro_rules! rules {
+    ($($keyword:ident: $basic:ident $(, $option:ident => $action:ident)*;)*) => {
+        #[allow(deprecated)]
+        static RULES: &[Rule] = &[$(Rule {
+            key: tags::$keyword.key,
+            basic: rules!(@basic $basic),
+            options: &[$((DeidentifyOption::$option, DeidentifyAction::$action)),*],
+        }),*];
+    };
+    (@basic XZ) => { Basic::XorZ };
+    (@basic ZD) => { Basic::ZorD };
+    (@basic XD) => { Basic::XorD };
+    (@basic XZD) => { Basic::XorZorD };
+    (@basic XZU) => { Basic::XorZorU };
+    (@basic $action:ident) => { Basic::One(DeidentifyAction::$action) };
+}
+
+rules! {
+    AccessionNumber: Z;
+    AcquisitionComments: X, CleanDescriptors => C;
+    AcquisitionContextSequence: X, CleanStructuredContent => C;
+    AcquisitionDate: XZ;
+    AcquisitionDateTime: XZD;
+    AcquisitionDeviceProcessingDescription: XD, RetainDeviceIdentity => K, CleanDescriptors => C;
+    AcquisitionProtocolDescription: X, CleanDescriptors => C;
+    AcquisitionTime: XZ;
+    AcquisitionUID: U;
+    ActualHumanPerformersSequence: X;
+    AdditionalPatientHistory: X, RetainPatientCharacteristics => C;
+    AdmissionID: X;
+    AdmittingDate: X;
+    AdmittingDiagnosesCodeSequence: X, RetainPatientCharacteristics => C;
+    AdmittingDiagnosesDescription: X, RetainPatientCharacteristics => C;
+    AdmittingTime: X;
+    AffectedSOPInstanceUID: X;
+    Allergies: X, RetainPatientCharacteristics => C;
+    AuthorObserverSequence: X;
+    BranchOfService: X;
+    CassetteID: X, RetainDeviceIdentity => K;
+    CommentsOnThePerformedProcedureStep: X, CleanDescriptors => C;
+    ConcatenationUID: U;
+    ConfidentialityConstraintOnPatientDataDescription: X;
+    ConsultingPhysicianName: X;
+    ContentCreatorIdentificationCodeSequence: X;
+    ContentCreatorName: Z;
+    ContentDate: ZD;
+    ContentSequence: X, CleanStructuredContent => C;
+    ContentTime: ZD;
+    ContextGroupExtensionCreatorUID: U;
+    ContrastBolusAgent: ZD, CleanDescriptors => C;
+    ContributionDescription: X, CleanDescriptors => C;
+    CountryOfResidence: X;
+    CreatorVersionUID: U;
+    CurrentPatientLocation: X;
+    CustodialOrganizationSequence: X;
+    DataSetTrailingPadding: X;
+    DerivationDescription: X, CleanDescriptors => C;
+    DetectorID: XD, RetainDeviceIdentity => K;
+    DeviceSerialNumber: XZD, RetainDeviceIdentity => K;
+    DeviceUID: U, RetainDeviceIdentity => K;
+    DigitalSignatureUID: X;
+    DigitalSignaturesSequence: X;
+    DimensionOrganizationUID: U;
+    DischargeDiagnosisDescription: X, RetainPatientCharacteristics => C;
+    DistributionAddress: X;
+    DistributionName: X;
+    DoseReferenceUID: U;
+    EncryptedAttributesSequence: X;
+    EthnicGroup: X, RetainPatientCharacteristics => K;
+    FailedSOPInstanceUIDList: U;
+    FiducialUID: U;
+    FillerOrderNumberImagingServiceRequest: Z;
+    FrameComments: X, CleanDescriptors => C;
+    FrameOfReferenceUID: U;
+    GantryID: X, RetainDeviceIdentity => K;
+    GeneratorID: X, RetainDeviceIdentity => K;
+    GraphicAnnotationSequence: D, CleanGraphics => C;
+    HumanPerformerName: X;
+    HumanPerformerOrganization: X, RetainInstitutionIdentity => K;
+    IconImageSequence: X;
+    IdentifyingComments: X, CleanDescriptors => C;
+    ImageComments: X, CleanDescriptors => C;
+    ImagePresentationComments: X;
+    ImagingServiceRequestComments: X, CleanDescriptors => C;
+    Impressions: X;
+    InstanceCreatorUID: U;
+    InstitutionAddress: X, RetainInstitutionIdentity => K;
+    InstitutionCodeSequence: XZD, RetainInstitutionIdentity => K;
+    InstitutionName: XZD, RetainInstitutionIdentity => K;
+    InstitutionalDepartmentName: X, RetainInstitutionIdentity => K;
+    InsurancePlanIdentification: X;
+    IntendedRecipientsOfResultsIdentificationSequence: X;
+    InterpretationApproverSequence: X;
+    InterpretationAuthor: X;
+    InterpretationDiagnosisDescription: X;
+    InterpretationIDIssuer: X;
+    InterpretationRecorder: X;
+    InterpretationText: X;
+    InterpretationTranscriber: X;
+    IrradiationEventUID: U;
+    IssuerOfAdmissionID: X;
+    IssuerOfPatientID: X;
+    IssuerOfServiceEpisodeID: X;
+    LargePaletteColorLookupTableUID: U;
+    LastMenstrualDate: X, RetainPatientCharacteristics => K;
+    MAC: X;
+    MedicalAlerts: X, RetainPatientCharacteristics => C;
+    MedicalRecordLocator: X;
+    MilitaryRank: X;
+    ModifiedAttributesSequence: X;
+    ModifiedImageDescription: X;
+    ModifyingDeviceID: X, RetainDeviceIdentity => K;
+    ModifyingDeviceManufacturer: X, RetainDeviceIdentity => K;
+    NameOfPhysiciansReadingStudy: X;
+    NamesOfIntendedRecipientsOfResults: X;
+    ObservationUID: U;
+    Occupation: X, RetainPatientCharacteristics => C;
+    OperatorIdentificationSequence: X;
+    OperatorsName: XZD;
+    OrderCallbackPhoneNumber: X;
+    OrderCallbackTelecomInformation: X;
+    OrderEnteredBy: X;
+    OrderEntererLocation: X;
+    OriginalAttributesSequence: X;
+    OtherPatientIDs: X;
+    OtherPatientIDsSequence: X;
+    OtherPatientNames: X;
+    PaletteColorLookupTableUID: U;
+    ParticipantSequence: X;
+    PatientAddress: X;
+    PatientAge: X, RetainPatientCharacteristics => K;
+    PatientBirthDate: Z;
+    PatientBirthName: X;
+    PatientBirthTime: X;
+    PatientComments: X, RetainPatientCharacteristics => C;
+    PatientID: Z;
+    PatientInstitutionResidence: X;
+    PatientInsurancePlanCodeSequence: X;
+    PatientMotherBirthName: X;
+    PatientName: Z;
+    PatientPrimaryLanguageCodeSequence: X;
+    PatientPrimaryLanguageModifierCodeSequence: X;
+    PatientReligiousPreference: X;
+    PatientSex: Z, RetainPatientCharacteristics => K;
+    PatientSexNeutered: XZ, RetainPatientCharacteristics => K;
+    PatientSize: X, RetainPatientCharacteristics => K;
+    PatientState: X, RetainPatientCharacteristics => C;
+    PatientTelephoneNumbers: X;
+    PatientTransportArrangements: X;
+    PatientWeight: X, RetainPatientCharacteristics => K;
+    PerformedLocation: X;
+    PerformedProcedureStepDescription: X, CleanDescriptors => C;
+    PerformedProcedureStepEndDate: X;
+    PerformedProcedureStepEndTime: X;
+    PerformedProcedureStepID: X;
+    PerformedProcedureStepStartDate: X;
+    PerformedProcedureStepStartTime: X;
+    PerformedStationAETitle: X, RetainDeviceIdentity => K;
+    PerformedStationGeographicLocationCodeSequence: X;
+    PerformedStationName: X, RetainDeviceIdentity => K;
+    PerformedStationNameCodeSequence: X, RetainDeviceIdentity => K;
+    PerformingPhysicianIdentificationSequence: X;
+    PerformingPhysicianName: X;
+    PersonAddress: X;
+    PersonIdentificationCodeSequence: D;
+    PersonName: D;
+    PersonTelephoneNumbers: X;
+    PhysicianApprovingInterpretation: X;
+    PhysiciansOfRecord: X;
+    PhysiciansOfRecordIdentificationSequence: X;
+    PhysiciansReadingStudyIdentificationSequence: X;
+    PlacerOrderNumberImagingServiceRequest: Z;
+    PlateID: X, RetainDeviceIdentity => K;
+    PreMedication: X, RetainPatientCharacteristics => C;
+    PregnancyStatus: X, RetainPatientCharacteristics => K;
+    ProtocolName: XD, CleanDescriptors => C;
+    ReasonForStudy: X, CleanDescriptors => C;
+    ReasonForTheImagingServiceRequest: X, CleanDescriptors => C;
+    ReferencedDigitalSignatureSequence: X;
+    ReferencedFrameOfReferenceUID: U;
+    ReferencedGeneralPurposeScheduledProcedureStepTransactionUID: U;
+    ReferencedImageSequence: XZU;
+    ReferencedPatientAliasSequence: X;
+    ReferencedPatientPhotoSequence: X;
+    ReferencedPatientSequence: X;
+    ReferencedPerformedProcedureStepSequence: XZD;
+    ReferencedSOPInstanceMACSequence: X;
+    ReferencedSOPInstanceUID: U;
+    ReferencedSOPInstanceUIDInFile: U;
+    ReferencedStudySequence: XZ;
+    ReferringPhysicianAddress: X;
+    ReferringPhysicianIdentificationSequence: X;
+    ReferringPhysicianName: Z;
+    ReferringPhysicianTelephoneNumbers: X;
+    RegionOfResidence: X;
+    RelatedFrameOfReferenceUID: U;
+    RequestAttributesSequence: X;
+    RequestedContrastAgent: X, CleanDescriptors => C;
+    RequestedProcedureComments: X;
+    RequestedProcedureDescription: XZ, CleanDescriptors => C;
+    RequestedProcedureID: X;
+    RequestedProcedureLocation: X;
+    RequestedSOPInstanceUID: U;
+    RequestingPhysician: X;
+    RequestingService: X;
+    ResponsibleOrganization: X;
+    ResponsiblePerson: X;
+    ResultsComments: X;
+    ResultsDistributionListSequence: X;
+    ResultsIDIssuer: X;
+    ReviewerName: XZ;
+    ScheduledHumanPerformersSequence: X;
+    ScheduledPatientInstitutionResidence: X;
+    ScheduledPerformingPhysicianIdentificationSequence: X;
+    ScheduledPerformingPhysicianName: X;
+    ScheduledProcedureStepDescription: X, CleanDescriptors => C;
+    ScheduledProcedureStepEndDate: X;
+    ScheduledProcedureStepEndTime: X;
+    ScheduledProcedureStepLocation: X;
+    ScheduledProcedureStepStartDate: X;
+    ScheduledProcedureStepStartTime: X;
+    ScheduledStationAETitle: X, RetainDeviceIdentity => K;
+    ScheduledStationGeographicLocationCodeSequence: X, RetainDeviceIdentity => K;
+    ScheduledStationName: X, RetainDeviceIdentity => K;
+    ScheduledStationNameCodeSequence: X, RetainDeviceIdentity => K;
+    ScheduledStudyLocation: X;
+    ScheduledStudyLocationAETitle: X;
+    SeriesDate: XD;
+    SeriesDescription: X, CleanDescriptors => C;
+    SeriesInstanceUID: U;
+    SeriesTime: XD;
+    ServiceEpisodeDescription: X;
+    ServiceEpisodeID: X;
+    SmokingStatus: X, RetainPatientCharacteristics => K;
+    SOPInstanceUID: U;
+    SourceImageSequence: XZU;
+    SpecialNeeds: X, RetainPatientCharacteristics => C;
+    SpecimenUID: U;
+    StationName: XZD, RetainDeviceIdentity => K;
+    StorageMediaFileSetUID: U;
+    StudyComments: X, CleanDescriptors => C;
+    StudyDate: Z;
+    StudyDescription: X, CleanDescriptors => C;
+    StudyID: Z;
+    StudyIDIssuer: X;
+    StudyInstanceUID: U;
+    StudyTime: Z;
+    SynchronizationFrameOfReferenceUID: U;
+    TemplateExtensionCreatorUID: U;
+    TemplateExtensionOrganizationUID: U;
+    TextComments: X, CleanDescriptors => C;
+    TextString: X, CleanDescriptors => C;
+    TextValue: X, CleanStructuredContent => C;
+    TimezoneOffsetFromUTC: X;
+    TopicAuthor: X;
+    TopicKeywords: X;
+    TopicSubject: X;
+    TopicTitle: X;
+    TrackingUID: U;
+    TransactionUID: U;
+    UID: U;
+    UnformattedTextValue: X, CleanGraphics => C;
+    VerifyingObserverIdentificationCodeSequence: Z;
+    VerifyingObserverName: D;
+    VerifyingObserverSequence: D;
+    VerifyingOrganization: X;
+    VisitComments: X, CleanDescriptors => C;
+}
+
+/// Table E.1-1 rows whose dates identify the patient rather than date the
+/// study, so the longitudinal temporal options do not retain them.
+const IDENTIFYING_DATES: [TagKey; 2] = [tags::PatientBirthDate.key, tags::PatientBirthTime.key];
+
+/// Replaces the text of a Clean (`C`) attribute. Given the attribute and its
+/// current text, returns text of similar meaning known not to identify.
+pub type Cleaner = Arc<dyn Fn(TagKey, &str) -> String + Send + Sync>;
+
+/// De-identifies data sets after the PS3.15 Basic Application Level
+/// Confidentiality Profile and its options.
+///
+/// One `Deidentifier` remembers the UIDs it has replaced, so instances of one
+/// study de-identified through it keep referencing each other.
+#[derive(Default)]
+pub struct Deidentifier {
+    options: Vec<DeidentifyOption>,
+    date_shift: i64,
+    overrides: Vec<(TagKey, DeidentifyAction)>,
+    cleaner: Option<Cleaner>,
+    uids: UidMapper,
+}
+
+impl Deidentifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces UIDs through `mapper` (default: random `2.25` UIDs).
+    pub fn uid_mapper(mut self, mapper: UidMapper) -> Self {
+        self.uids = mapper;
+        self
+    }
+
+    /// Enables a Profile Option.
+    pub fn option(mut self, option: DeidentifyOption) -> Self {
+        if !self.options.contains(&option) {
+            self.options.push(option);
+        }
+        self
+    }
+
+    /// Days dates are moved by under
+    /// [`RetainLongitudinalModifiedDates`](DeidentifyOption::RetainLongitudinalModifiedDates)
+    /// (default 0). Enables that option.
+    pub fn date_shift(mut self, days: i64) -> Self {
+        self.date_shift = days;
+        self.option(DeidentifyOption::RetainLongitudinalModifiedDates)
+    }
+
+    /// Applies `action` to `tag` instead of what the profile prescribes.
+    pub fn action(mut self, tag: &Tag, action: DeidentifyAction) -> Self {
+        self.overrides.retain(|(key, _)| *key != tag.key);
+        self.overrides.push((tag.key, action));
+        self
+    }
+
+    /// Cleans the text of `C` attributes (default: replaces it with an empty
+    /// value).
+    pub fn cleaner(mut self, cleaner: impl Fn(TagKey, &str) -> String + Send + Sync + 'static) -> Self {
+        self.cleaner = Some(Arc::new(cleaner));
+        self
+    }
+
+    /// The mapper holding every UID replaced so far.
+    pub fn uids(&self) -> &UidMapper {
+        &self.uids
+    }
+
+    /// De-identifies `ds` in place and records how.
+    pub fn deidentify(&mut self, ds: &mut DataSet) -> Result<()> {
+        let actions = self.actions();
+        let (shared, root) = ds.ctx_mut();
+        self.clean_item(shared, &actions, root)?;
+        ds.update_private_characteristics()?;
+        self.record(ds)
+    }
+
+    /// De-identifies the data set of a file and points the File Meta
+    /// Information at the replaced SOP Instance UID.
+    pub fn deidentify_file(&mut self, file: &mut ReadOutput) -> Result<()> {
+        let Some(ds) = file.dataset.as_mut() else { return Ok(()) };
+        self.deidentify(ds)?;
+        if let (Some(header), Some(uid)) = (file.header.as_mut(), ds.get_str_some(&tags::SOPInstanceUID)) {
+            header.set(&tags::MediaStorageSOPInstanceUID, uid.into_owned())?;
+        }
+        Ok(())
+    }
+
+    fn enabled(&self, option: DeidentifyOption) -> bool {
+        self.options.contains(&option)
+    }
+
+    /// The action on every attribute of Table E.1-1 under the enabled
+    /// options. Where several options apply the most retaining one wins:
+    /// keeping over cleaning over the Basic Profile action.
+    fn actions(&self) -> HashMap<TagKey, DeidentifyAction> {
+        let rank = |action: DeidentifyAction| match action {
+            DeidentifyAction::K => 2,
+            DeidentifyAction::C => 1,
+            _ => 0,
+        };
+        let mut actions: HashMap<_, _> = RULES
+            .iter()
+            .map(|rule| {
+                let basic = rule.basic.resolve();
+                let vr = Tag::new(rule.key, None).meta().map_or(Vr::Undefined, |m| m.vr.0);
+                let generic = [
+                    (DeidentifyOption::RetainUids, basic == DeidentifyAction::U, DeidentifyAction::K),
+                    (DeidentifyOption::RetainLongitudinalFullDates, is_temporal(vr, rule.key), DeidentifyAction::K),
+                    (DeidentifyOption::RetainLongitudinalModifiedDates, is_temporal(vr, rule.key), DeidentifyAction::C),
+                ];
+                let options = rule.options.iter().copied().chain(
+                    generic.into_iter().filter(|&(_, applies, _)| applies).map(|(option, _, action)| (option, action)),
+                );
+                let action = options
+                    .filter(|&(option, _)| self.enabled(option))
+                    .map(|(_, action)| action)
+                    .fold(basic, |best, action| if rank(action) > rank(best) { action } else { best });
+                (rule.key, action)
+            })
+            .collect();
+        actions.extend(self.overrides.iter().copied());
+        actions
+    }
+
+    /// The action on attribute `key` of `item`, for attributes outside
+    /// Table E.1-1 included.
+    fn action_of(
+        &self,
+        shared: &Shared,
+        actions: &HashMap<TagKey, DeidentifyAction>,
+        item: &Item,
+        key: TagKey,
+    ) -> DeidentifyAction {
+        if let Some(&action) = actions.get(&key) {
+            return action;
+        }
+        let (group, element) = (key.group(), key.element());
+        if key.is_private_attribute() {
+            if !self.enabled(DeidentifyOption::RetainSafePrivate) {
+                return DeidentifyAction::X;
+            }
+            let Some(creator) = item.private_creator(shared, key) else { return DeidentifyAction::X };
+            return match Tag::new_private_cow(group, element, creator).meta().map(|m| m.source) {
+                Some(Source::Vendored(PrivateIdentificationAction::None)) => DeidentifyAction::K,
+                Some(Source::Vendored(PrivateIdentificationAction::D)) => DeidentifyAction::D,
+                Some(Source::Vendored(PrivateIdentificationAction::Z)) => DeidentifyAction::Z,
+                Some(Source::Vendored(PrivateIdentificationAction::U)) => DeidentifyAction::U,
+                _ => DeidentifyAction::X,
+            };
+        }
+        if key.is_private() {
+            return DeidentifyAction::X;
+        }
+        // Curve Data (50xx,xxxx), Overlay Data (60xx,3000) and Overlay
+        // Comments (60xx,4000), repeating groups outside the tag table.
+        match (group & 0xFF01, element) {
+            (0x5000, _) => DeidentifyAction::X,
+            (0x6000, 0x3000 | 0x4000) if self.enabled(DeidentifyOption::CleanGraphics) => DeidentifyAction::K,
+            (0x6000, 0x3000 | 0x4000) => DeidentifyAction::X,
+            _ => DeidentifyAction::K,
+        }
+    }
+
+    fn clean_item(
+        &mut self,
+        shared: &Shared,
+        actions: &HashMap<TagKey, DeidentifyAction>,
+        item: &mut Item,
+    ) -> Result<()> {
+        let keys: Vec<TagKey> = item.map.entries().iter().map(|(key, _)| *key).collect();
+        for key in keys.iter().copied().filter(|key| !key.is_private_reservation()) {
+            let action = self.action_of(shared, actions, item, key);
+            self.apply(shared, actions, item, key, action)?;
+        }
+        // A Private Creator outlives its block only while the block keeps
+        // attributes.
+        let reserved: HashSet<TagKey> = item
+            .map
+            .entries()
+            .iter()
+            .filter(|(key, _)| key.is_private_attribute())
+            .map(|(key, _)| key.to_private_reservation())
+            .collect();
+        for key in keys.into_iter().filter(|key| key.is_private_reservation() && !reserved.contains(key)) {
+            item.map.remove(key);
+        }
+        Ok(())
+    }
+
+    fn apply(
+        &mut self,
+        shared: &Shared,
+        actions: &HashMap<TagKey, DeidentifyAction>,
+        item: &mut Item,
+        key: TagKey,
+        action: DeidentifyAction,
+    ) -> Result<()> {
+        let Some(el) = item.map.get(key) else { return Ok(()) };
+        let vr = el.vr;
+        if action == DeidentifyAction::X {
+            item.map.remove(key);
+            return Ok(());
+        }
+        if let Stored::Items(_) = el.value {
+            let Some(Element { value: Stored::Items(items), .. }) = item.map.get_mut(key) else { return Ok(()) };
+            if action == DeidentifyAction::Z {
+                items.clear();
+                return Ok(());
+            }
+            for nested in items.iter_mut() {
+                self.clean_item(shared, actions, nested)?;
+            }
+            return Ok(());
+        }
+        let value = match action {
+            DeidentifyAction::K => return Ok(()),
+            DeidentifyAction::Z => Stored::Owned(Bytes::new()),
+            DeidentifyAction::D if vr == Vr::UI => {
+                Stored::Native(Value::Str(self.uids.map_value(&item.element_str(shared, el)?)?))
+            }
+            DeidentifyAction::D => Stored::Native(dummy(vr)),
+            DeidentifyAction::U if vr == Vr::UI => {
+                Stored::Native(Value::Str(self.uids.map_value(&item.element_str(shared, el)?)?))
+            }
+            DeidentifyAction::U => return Ok(()),
+            DeidentifyAction::C if matches!(vr, Vr::DA | Vr::DT) => {
+                Stored::Native(Value::Str(shift_dates(&item.element_str(shared, el)?, self.date_shift)))
+            }
+            DeidentifyAction::C if matches!(vr.info().kind, Kind::Text { .. }) && vr != Vr::TM => {
+                let text = item.element_str(shared, el)?;
+                Stored::Native(Value::Str(self.cleaner.as_ref().map_or_else(String::new, |clean| clean(key, &text))))
+            }
+            DeidentifyAction::C => return Ok(()),
+            DeidentifyAction::X => unreachable!("removed above"),
+        };
+        item.map.insert(key, Element::new(vr, value));
+        Ok(())
+    }
+
+    /// Writes Patient Identity Removed, De-identification Method (Code
+    /// Sequence) and Longitudinal Temporal Information Modified.
+    fn record(&self, ds: &mut DataSet) -> Result<()> {
+        let methods: Vec<(&str, &str)> = std::iter::once(BASIC_PROFILE)
+            .chain(self.options.iter().map(|option| (option.code(), option.meaning())))
+            .collect();
+        ds.set(&tags::PatientIdentityRemoved, "YES")?;
+        ds.set(
+            &tags::DeidentificationMethod,
+            methods.iter().map(|(_, meaning)| *meaning).collect::<Vec<_>>().join("\\"),
+        )?;
+        let mut sequence = ds.sequence_mut(&tags::DeidentificationMethodCodeSequence)?;
+        sequence.clear();
+        for (code, meaning) in methods {
+            let mut item = sequence.new_item();
+            item.set(&tags::CodeValue, code)?;
+            item.set(&tags::CodingSchemeDesignator, "DCM")?;
+            item.set(&tags::CodeMeaning, meaning)?;
+        }
+        let temporal = if self.enabled(DeidentifyOption::RetainLongitudinalFullDates) {
+            "UNMODIFIED"
+        } else if self.enabled(DeidentifyOption::RetainLongitudinalModifiedDates) {
+            "MODIFIED"
+        } else {
+            "REMOVED"
+        };
+        ds.set(&tags::LongitudinalTemporalInformationModified, temporal)
+    }
+}
+
+/// `true` for a date or time attribute the longitudinal temporal options
+/// apply to.
+fn is_temporal(vr: Vr, key: TagKey) -> bool {
+    matches!(vr, Vr::DA | Vr::DT | Vr::TM) && !IDENTIFYING_DATES.contains(&key)
+}
+
+/// A non-zero length value valid for `vr`, standing in for a `D` attribute.
+fn dummy(vr: Vr) -> Value {
+    let text = |s: &str| Value::Str(s.to_string());
+    match vr {
+        Vr::DA => text("19000101"),
+        Vr::TM => text("000000"),
+        Vr::DT => text("19000101000000"),
+        Vr::AS => text("000D"),
+        Vr::DS | Vr::IS => text("0"),
+        Vr::UR => text("urn:anonymized"),
+        Vr::US | Vr::UL | Vr::UV => Value::UInt(OneOrMany::One(0)),
+        Vr::SS | Vr::SL | Vr::SV => Value::Int(OneOrMany::One(0)),
+        Vr::FL | Vr::FD => Value::Float(OneOrMany::One(0.0)),
+        _ if matches!(vr.info().kind, Kind::Text { .. }) => text("ANONYMIZED"),
+        _ => Value::Bytes(Bytes::from_static(&[0; 8])),
+    }
+}
+
+/// Moves the date of every value of a DA or DT attribute by `days`, keeping
+/// its precision and any time part. Values that do not start with a date (or
+/// would leave the calendar) are emptied.
+fn shift_dates(value: &str, days: i64) -> String {
+    let shift = |v: &str| -> Option<String> {
+        let v = v.trim_end_matches([' ', '\0']);
+        let digits = v.bytes().take_while(u8::is_ascii_digit).count().min(8);
+        if !matches!(digits, 4 | 6 | 8) {
+            return None;
+        }
+        let (date, rest) = v.split_at(digits);
+        let field = |at: usize| date.get(at..at + 2).and_then(|s| s.parse().ok());
+        let date = DicomDate { y: date[..4].parse().ok(), m: field(4), d: field(6) }.add_days(days)?;
+        let mut out = format!("{:04}", date.y?);
+        for part in [date.m, date.d].into_iter().flatten() {
+            out += &format!("{part:02}");
+        }
+        Some(out + rest)
+    };
+    value.split('\\').map(|v| shift(v).unwrap_or_default()).collect::<Vec<_>>().join("\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> DataSet {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PatientName, "Doe^John").unwrap();
+        ds.set(&tags::PatientID, "12345").unwrap();
+        ds.set(&tags::PatientSex, "M").unwrap();
+        ds.set(&tags::PatientAddress, "1 Main Street").unwrap();
+        ds.set(&tags::StudyDate, "20240301").unwrap();
+        ds.set(&tags::AcquisitionDate, "20240301").unwrap();
+        ds.set(&tags::InstitutionName, "General Hospital").unwrap();
+        ds.set(&tags::StudyDescription, "Chest of John Doe").unwrap();
+        ds.set(&tags::Modality, "CT").unwrap();
+        ds.set(&tags::StudyInstanceUID, "1.2.3.4").unwrap();
+        ds.set(&tags::SOPInstanceUID, "1.2.3.4.5").unwrap();
+        ds
+    }
+
+    fn text(ds: &DataSet, tag: &Tag) -> Option<String> {
+        ds.get_str_some(tag).map(|s| s.trim_end().to_string())
+    }
+
+    #[test]
+    fn basic_profile() {
+        let mut ds = sample();
+        let mut engine = Deidentifier::new().uid_mapper(UidMapper::new(crate::HashedUids::new(b"salt")));
+        engine.deidentify(&mut ds).unwrap();
+
+        assert_eq!(text(&ds, &tags::PatientName).as_deref(), Some(""));
+        assert_eq!(text(&ds, &tags::PatientSex).as_deref(), Some(""));
+        assert!(!ds.contains(&tags::PatientAddress));
+        assert!(!ds.contains(&tags::StudyDescription));
+        assert_eq!(text(&ds, &tags::AcquisitionDate).as_deref(), Some(""));
+        assert_eq!(text(&ds, &tags::InstitutionName).as_deref(), Some("ANONYMIZED"));
+        assert_eq!(text(&ds, &tags::Modality).as_deref(), Some("CT"));
+
+        let study = text(&ds, &tags::StudyInstanceUID).unwrap();
+        assert!(study.starts_with("2.25."), "{study}");
+        assert_eq!(engine.uids().mapping()["1.2.3.4"], study);
+
+        assert_eq!(text(&ds, &tags::PatientIdentityRemoved).as_deref(), Some("YES"));
+        assert_eq!(text(&ds, &tags::LongitudinalTemporalInformationModified).as_deref(), Some("REMOVED"));
+        let methods = ds.sequence(&tags::DeidentificationMethodCodeSequence).unwrap();
+        assert_eq!(methods.len(), 1);
+        let code = methods.item(0).unwrap();
+        assert_eq!(code.get_str(&tags::CodeValue).unwrap().trim_end(), "113100");
+    }
+
+    #[test]
+    fn options_retain_and_clean() {
+        let mut ds = sample();
+        Deidentifier::new()
+            .option(DeidentifyOption::RetainPatientCharacteristics)
+            .option(DeidentifyOption::RetainInstitutionIdentity)
+            .option(DeidentifyOption::RetainUids)
+            .option(DeidentifyOption::CleanDescriptors)
+            .cleaner(|_, text| text.replace("John Doe", "patient"))
+            .date_shift(-366)
+            .deidentify(&mut ds)
+            .unwrap();
+
+        assert_eq!(text(&ds, &tags::PatientSex).as_deref(), Some("M"));
+        assert_eq!(text(&ds, &tags::InstitutionName).as_deref(), Some("General Hospital"));
+        assert_eq!(text(&ds, &tags::StudyInstanceUID).as_deref(), Some("1.2.3.4"));
+        assert_eq!(text(&ds, &tags::StudyDescription).as_deref(), Some("Chest of patient"));
+        assert_eq!(text(&ds, &tags::AcquisitionDate).as_deref(), Some("20230301"));
+        assert_eq!(text(&ds, &tags::StudyDate).as_deref(), Some("20230301"));
+        assert_eq!(text(&ds, &tags::PatientName).as_deref(), Some(""));
+        assert_eq!(text(&ds, &tags::LongitudinalTemporalInformationModified).as_deref(), Some("MODIFIED"));
+        assert_eq!(ds.sequence(&tags::DeidentificationMethodCodeSequence).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn sequences_and_consistent_uids() {
+        let mut ds = sample();
+        let mut refs = ds.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        let mut item = refs.new_item();
+        item.set(&tags::ReferencedSOPInstanceUID, "1.2.3.4.5").unwrap();
+        item.set(&tags::PatientName, "Doe^John").unwrap();
+        let mut other = ds.sequence_mut(&tags::OtherPatientIDsSequence).unwrap();
+        other.new_item().set(&tags::PatientID, "999").unwrap();
+
+        let mut engine = Deidentifier::new();
+        engine.deidentify(&mut ds).unwrap();
+
+        assert!(!ds.contains(&tags::OtherPatientIDsSequence));
+        let sop = text(&ds, &tags::SOPInstanceUID).unwrap();
+        let refs = ds.sequence(&tags::ReferencedImageSequence).unwrap();
+        let item = refs.item(0).unwrap();
+        assert_eq!(item.get_str(&tags::ReferencedSOPInstanceUID).unwrap().trim_end(), sop);
+        assert_eq!(item.get_str(&tags::PatientName).unwrap().trim_end(), "");
+
+        // A second instance of the study maps to the same UIDs.
+        let mut second = sample();
+        engine.deidentify(&mut second).unwrap();
+        assert_eq!(text(&second, &tags::SOPInstanceUID).unwrap(), sop);
+    }
+
+    #[test]
+    fn private_attributes() {
+        let mut ds = sample();
+        let creator = Tag::new_standard(0x0009, 0x0010);
+        ds.set_with_vr(&creator, Vr::LO, Value::Str("ACME 1.0".into())).unwrap();
+        ds.set_with_vr(&Tag::new_standard(0x0009, 0x1001), Vr::LO, Value::Str("secret".into())).unwrap();
+        ds.set_with_vr(&Tag::new_standard(0x6000, 0x3000), Vr::OW, Value::Bytes(Bytes::from_static(&[1, 2]))).unwrap();
+
+        Deidentifier::new().option(DeidentifyOption::RetainSafePrivate).deidentify(&mut ds).unwrap();
+
+        // Unknown to the private dictionary, so not safe: removed with its
+        // now empty block's creator.
+        assert!(!ds.contains(&Tag::new_standard(0x0009, 0x1001)));
+        assert!(!ds.contains(&creator));
+        assert!(!ds.contains(&Tag::new_standard(0x6000, 0x3000)));
+    }
+
+    #[test]
+    fn date_shift_keeps_precision_and_time() {
+        assert_eq!(shift_dates("20240229\\2024", 1), "20240301\\2024");
+        assert_eq!(shift_dates("202402", 29), "202403");
+        assert_eq!(shift_dates("20240101120000.5+0100", -1), "20231231120000.5+0100");
+        assert_eq!(shift_dates("garbage", 1), "");
+    }
+}
@@ -0,0 +1,437 @@
+//! Structured comparison of two data sets, and its application as a patch.
+//!
+//! A [`Differ`] walks both trees in tag order, sequences Item by Item, and
+//! lists each attribute added, removed or changed as a [`Change`] telling
+//! whether the VR changed and whether the values differ once decoded or only
+//! in their encoding (padding, byte order, character set). The changes carry
+//! the values involved, detached from the data sets they come from, so that a
+//! [`Diff`] can be [applied](Diff::apply) to any other data set.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{Tag, TagKey, Vr, dicom_err, tags};
+
+use crate::adapt::{detach_element, detach_item};
+use crate::convert::{self, keyword_path};
+use crate::dataset::Shared;
+use crate::value::{Element, Stored, Value};
+use crate::{DataSet, Item};
+
+/// How an attribute differs between the two data sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// Only in the second data set.
+    Added,
+    /// Only in the first data set.
+    Removed,
+    /// In both, with a different VR or value. `decoded_equal` tells that the
+    /// values decode to the same, and only their encoding differs.
+    Changed { vr_changed: bool, decoded_equal: bool },
+    /// The Item at this index of the sequence is only in the second data set.
+    ItemAdded(usize),
+    /// The Item at this index of the sequence is only in the first data set.
+    ItemRemoved(usize),
+}
+
+/// One difference between two data sets.
+#[derive(Debug, Clone)]
+pub struct Change {
+    /// The sequences and Item indexes leading to the Item holding the
+    /// attribute; empty at the root.
+    pub parents: Vec<(TagKey, usize)>,
+    /// The attribute, or the sequence for Item changes.
+    pub key: TagKey,
+    pub kind: ChangeKind,
+    old: Option<Element>,
+    new: Option<Element>,
+}
+
+impl Change {
+    /// The attribute by keyword, with the sequences and Items leading to it:
+    /// `ReferencedImageSequence[0].ReferencedSOPInstanceUID`.
+    pub fn path(&self) -> String {
+        let parent =
+            self.parents.iter().fold(String::new(), |path, (key, i)| format!("{}[{i}]", keyword_path(&path, *key)));
+        keyword_path(&parent, self.key)
+    }
+
+    /// The VR in the first data set.
+    pub fn old_vr(&self) -> Option<Vr> {
+        self.old.as_ref().map(|el| el.vr)
+    }
+
+    /// The VR in the second data set.
+    pub fn new_vr(&self) -> Option<Vr> {
+        self.new.as_ref().map(|el| el.vr)
+    }
+
+    /// The decoded value in the first data set; `None` for sequences.
+    pub fn old_value(&self) -> Option<&Value> {
+        native(self.old.as_ref())
+    }
+
+    /// The decoded value in the second data set; `None` for sequences.
+    pub fn new_value(&self) -> Option<&Value> {
+        native(self.new.as_ref())
+    }
+}
+
+fn native(el: Option<&Element>) -> Option<&Value> {
+    match &el?.value {
+        Stored::Native(v) => Some(v),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path();
+        match self.kind {
+            ChangeKind::Added => write!(f, "+ {path} {}", self.new_vr().unwrap_or(Vr::Undefined)),
+            ChangeKind::Removed => write!(f, "- {path} {}", self.old_vr().unwrap_or(Vr::Undefined)),
+            ChangeKind::Changed { vr_changed: true, .. } => write!(
+                f,
+                "~ {path} {} -> {}",
+                self.old_vr().unwrap_or(Vr::Undefined),
+                self.new_vr().unwrap_or(Vr::Undefined)
+            ),
+            ChangeKind::Changed { decoded_equal: true, .. } => write!(f, "~ {path} (encoding only)"),
+            ChangeKind::Changed { .. } => write!(f, "~ {path}"),
+            ChangeKind::ItemAdded(i) => write!(f, "+ {path}[{i}]"),
+            ChangeKind::ItemRemoved(i) => write!(f, "- {path}[{i}]"),
+        }
+    }
+}
+
+/// The differences between two data sets, in tag order.
+#[derive(Debug, Clone, Default)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Makes the same changes to `ds`: attributes added or changed take the
+    /// VR and value of the second data set, removed ones are removed, and so
+    /// are Items. Fails when a sequence or Item leading to a change, or an
+    /// Item to remove, is missing from `ds`.
+    pub fn apply(&self, ds: &mut DataSet) -> Result<()> {
+        let mut context_changed = false;
+        for change in &self.changes {
+            let (_, root) = ds.ctx_mut();
+            let item = find_item(root, &change.parents).ok_or_else(|| {
+                dicom_err!(NotFound, "no Item for the change of {} in the patched data set", change.path())
+            })?;
+            match (change.kind, &change.new) {
+                (ChangeKind::Added | ChangeKind::Changed { .. }, Some(new)) => {
+                    item.map.insert(change.key, new.clone());
+                }
+                (ChangeKind::Removed, _) => {
+                    item.map.remove(change.key);
+                }
+                (ChangeKind::ItemAdded(i), Some(Element { value: Stored::Items(added), .. })) => {
+                    let el =
+                        item.map.get_or_insert_with(change.key, || Element::new(Vr::SQ, Stored::Items(Vec::new())));
+                    let Stored::Items(items) = &mut el.value else {
+                        return Err(dicom_err!(
+                            InvalidData,
+                            "{} is not a sequence in the patched data set",
+                            change.path()
+                        ));
+                    };
+                    items.splice(i.min(items.len())..i.min(items.len()), added.iter().cloned());
+                }
+                (ChangeKind::ItemRemoved(i), _) => match item.map.get_mut(change.key) {
+                    Some(Element { value: Stored::Items(items), .. }) if i < items.len() => {
+                        items.remove(i);
+                    }
+                    _ => {
+                        return Err(dicom_err!(
+                            NotFound,
+                            "no Item {i} to remove from {} in the patched data set",
+                            change.path()
+                        ));
+                    }
+                },
+                _ => return Err(dicom_err!(Internal, "change of {} has no value", change.path())),
+            }
+            if change.parents.is_empty() {
+                context_changed |=
+                    [tags::SpecificCharacterSet.key, tags::TimezoneOffsetFromUTC.key].contains(&change.key);
+                if let Some(Value::Str(s)) = change.new_value()
+                    && matches!(change.new_vr().map(|vr| vr.info().kind), Some(Kind::Text { translatable: true, .. }))
+                {
+                    ds.stamp_charset_for(s);
+                }
+            }
+        }
+        if context_changed {
+            ds.sync_context()?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+fn find_item<'a>(mut item: &'a mut Item, parents: &[(TagKey, usize)]) -> Option<&'a mut Item> {
+    for (key, i) in parents {
+        match &mut item.map.get_mut(*key)?.value {
+            Stored::Items(items) => item = items.get_mut(*i)?,
+            _ => return None,
+        }
+    }
+    Some(item)
+}
+
+/// Compares data sets; see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct Differ {
+    ignored: HashSet<TagKey>,
+}
+
+impl Differ {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves `tag` out of the comparison, at any depth.
+    pub fn ignore(mut self, tag: &Tag) -> Self {
+        self.ignored.insert(tag.key);
+        self
+    }
+
+    /// The changes turning `old` into `new`.
+    pub fn diff(&self, old: &DataSet, new: &DataSet) -> Diff {
+        let (old_shared, old_root) = old.context();
+        let (new_shared, new_root) = new.context();
+        let mut walk = Walk { old: old_shared, new: new_shared, ignored: &self.ignored, changes: Vec::new() };
+        walk.items(old_root, new_root, &[]);
+        Diff { changes: walk.changes }
+    }
+}
+
+struct Walk<'a> {
+    old: &'a Shared,
+    new: &'a Shared,
+    ignored: &'a HashSet<TagKey>,
+    changes: Vec<Change>,
+}
+
+impl Walk<'_> {
+    fn push(
+        &mut self,
+        parents: &[(TagKey, usize)],
+        key: TagKey,
+        kind: ChangeKind,
+        old: Option<Element>,
+        new: Option<Element>,
+    ) {
+        self.changes.push(Change { parents: parents.to_vec(), key, kind, old, new });
+    }
+
+    fn items(&mut self, old: &Item, new: &Item, parents: &[(TagKey, usize)]) {
+        let keys: BTreeSet<TagKey> = old.map.entries().iter().chain(new.map.entries()).map(|(key, _)| *key).collect();
+        for key in keys.into_iter().filter(|key| !self.ignored.contains(key)) {
+            match (old.map.get(key), new.map.get(key)) {
+                (Some(a), None) => {
+                    let removed = detach_element(self.old, old, a);
+                    self.push(parents, key, ChangeKind::Removed, Some(removed), None);
+                }
+                (None, Some(b)) => {
+                    let added = detach_element(self.new, new, b);
+                    self.push(parents, key, ChangeKind::Added, None, Some(added));
+                }
+                (Some(a), Some(b)) => self.element(old, new, a, b, parents, key),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn element(&mut self, old: &Item, new: &Item, a: &Element, b: &Element, parents: &[(TagKey, usize)], key: TagKey) {
+        if let (Stored::Items(a_items), Stored::Items(b_items)) = (&a.value, &b.value) {
+            let common = a_items.len().min(b_items.len());
+            for (i, (a_item, b_item)) in a_items.iter().zip(b_items).enumerate() {
+                let mut nested = parents.to_vec();
+                nested.push((key, i));
+                self.items(a_item, b_item, &nested);
+            }
+            for (i, b_item) in b_items.iter().enumerate().skip(common) {
+                let added = Element::new(Vr::SQ, Stored::Items(vec![detach_item(self.new, b_item)]));
+                self.push(parents, key, ChangeKind::ItemAdded(i), None, Some(added));
+            }
+            // Removed from the last, so that applying them keeps indexes valid.
+            for (i, a_item) in a_items.iter().enumerate().skip(common).rev() {
+                let removed = Element::new(Vr::SQ, Stored::Items(vec![detach_item(self.old, a_item)]));
+                self.push(parents, key, ChangeKind::ItemRemoved(i), Some(removed), None);
+            }
+            return;
+        }
+        let vr_changed = a.vr != b.vr;
+        // Text is the same in either byte order.
+        let same_order =
+            self.old.is_little_endian() == self.new.is_little_endian() || matches!(a.vr.info().kind, Kind::Text { .. });
+        let raw_equal = same_order
+            && matches!((old.element_bytes(self.old, a), new.element_bytes(self.new, b)), (Some(x), Some(y)) if x == y);
+        let decoded_equal = canonical(self.old, old, a) == canonical(self.new, new, b);
+        let both_raw = !matches!(a.value, Stored::Native(_)) && !matches!(b.value, Stored::Native(_));
+        if !vr_changed && (raw_equal || (decoded_equal && !both_raw)) {
+            return;
+        }
+        let kind = ChangeKind::Changed { vr_changed, decoded_equal };
+        let (a, b) = (detach_element(self.old, old, a), detach_element(self.new, new, b));
+        self.push(parents, key, kind, Some(a), Some(b));
+    }
+}
+
+/// A form of a value independent of its encoding: the trimmed values of text,
+/// little endian bytes of binary numbers.
+fn canonical(shared: &Shared, item: &Item, el: &Element) -> Option<Vec<u8>> {
+    if matches!(el.vr.info().kind, Kind::Text { .. }) {
+        let text = item.element_text(shared, el).ok()?;
+        let values: Vec<&str> = if convert::is_multi_valued_text(el.vr) {
+            text.split('\\').map(|v| convert::trim_text(el.vr, v)).collect()
+        } else {
+            vec![convert::trim_text(el.vr, &text)]
+        };
+        return Some(values.join("\\").into_bytes());
+    }
+    let mut out = Vec::new();
+    match item.element_value(shared, el) {
+        Ok(value) if convert::encode(shared, true, el.vr, &value, &mut out).is_ok() => Some(out),
+        _ => item.element_bytes(shared, el).map(<[u8]>::to_vec),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use dpx_dicom_core::TransferSyntax;
+
+    use super::*;
+    use crate::DcmWriter;
+    use crate::dcm_parser::{DcmReader, HeaderType};
+
+    fn sample() -> DataSet {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PatientName, "Doe^John").unwrap();
+        ds.set(&tags::PatientID, "ID-1").unwrap();
+        ds.set(&tags::Rows, 512u16).unwrap();
+        let mut refs = ds.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        refs.new_item().set(&tags::ReferencedSOPInstanceUID, "1.2.3").unwrap();
+        refs.new_item().set(&tags::ReferencedSOPInstanceUID, "1.2.4").unwrap();
+        ds
+    }
+
+    fn read(bytes: Bytes, ts: &'static TransferSyntax) -> DataSet {
+        let reader = DcmReader::new().header(HeaderType::NoHeader).transfer_syntax(ts);
+        reader.parse_bytes(bytes).unwrap().dataset.unwrap()
+    }
+
+    #[test]
+    fn reports_changes_at_any_depth() {
+        let old = sample();
+        let mut new = sample();
+        new.remove(&tags::PatientID);
+        new.set(&tags::PatientName, "Doe^Jane").unwrap();
+        new.set(&tags::StudyDescription, "Head").unwrap();
+        new.set_with_vr(&tags::Rows, Vr::SS, Value::Int(crate::OneOrMany::One(512))).unwrap();
+        let mut refs = new.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        refs.item_mut(1).unwrap().set(&tags::ReferencedSOPInstanceUID, "1.2.5").unwrap();
+        refs.new_item().set(&tags::ReferencedSOPInstanceUID, "1.2.6").unwrap();
+
+        let diff = Differ::new().ignore(&tags::StudyDescription).diff(&old, &new);
+        let found: Vec<_> = diff.changes.iter().map(|c| (c.path(), c.kind)).collect();
+        let changed = ChangeKind::Changed { vr_changed: false, decoded_equal: false };
+        assert_eq!(
+            found,
+            [
+                ("ReferencedImageSequence[1].ReferencedSOPInstanceUID".to_string(), changed),
+                ("ReferencedImageSequence".to_string(), ChangeKind::ItemAdded(2)),
+                ("PatientName".to_string(), changed),
+                ("PatientID".to_string(), ChangeKind::Removed),
+                ("Rows".to_string(), ChangeKind::Changed { vr_changed: true, decoded_equal: true }),
+            ]
+        );
+        let name = &diff.changes[2];
+        assert!(matches!(name.old_value(), Some(Value::Str(s)) if s == "Doe^John"));
+        assert!(matches!(name.new_value(), Some(Value::Str(s)) if s == "Doe^Jane"));
+        assert_eq!(diff.changes[4].to_string(), "~ Rows US -> SS");
+        assert!(Differ::new().diff(&old, &sample()).is_empty());
+    }
+
+    #[test]
+    fn tells_encoding_from_value_changes() {
+        let ds = sample();
+        let le = read(DcmWriter::new().to_bytes(&ds).unwrap(), &TransferSyntax::ExplicitVRLittleEndian);
+        let be = DcmWriter::new().transfer_syntax(&TransferSyntax::ExplicitVRBigEndian).to_bytes(&ds).unwrap();
+        let be = read(be, &TransferSyntax::ExplicitVRBigEndian);
+        // Native and raw values of the same content are equal.
+        assert!(Differ::new().diff(&ds, &le).is_empty());
+
+        let diff = Differ::new().diff(&le, &be);
+        let found: Vec<_> = diff.changes.iter().map(|c| (c.path(), c.kind)).collect();
+        assert_eq!(found, [("Rows".to_string(), ChangeKind::Changed { vr_changed: false, decoded_equal: true })]);
+    }
+
+    #[test]
+    fn applies_as_a_patch() {
+        let old = sample();
+        let mut new = sample();
+        new.remove(&tags::PatientID);
+        new.set(&tags::PatientName, "Müller^Hans").unwrap();
+        let mut refs = new.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        refs.clear();
+        let mut item = refs.new_item();
+        item.set(&tags::ReferencedSOPClassUID, "1.2.840.10008.5.1.4.1.1.2").unwrap();
+        item.set(&tags::ReferencedSOPInstanceUID, "1.2.3").unwrap();
+        let diff = Differ::new().diff(&old, &new);
+
+        // A third data set, read from a file: raw values in its own context.
+        let mut third = read(DcmWriter::new().to_bytes(&old).unwrap(), &TransferSyntax::ExplicitVRLittleEndian);
+        third.set(&tags::StudyDescription, "Kept").unwrap();
+        diff.apply(&mut third).unwrap();
+        assert_eq!(third.get_str(&tags::PatientName).unwrap(), "Müller^Hans");
+        assert!(third.contains(&tags::SpecificCharacterSet));
+        assert!(!third.contains(&tags::PatientID));
+        assert_eq!(third.get_str(&tags::StudyDescription).unwrap().trim_end(), "Kept");
+        assert_eq!(third.sequence(&tags::ReferencedImageSequence).unwrap().len(), 1);
+        assert!(Differ::new().ignore(&tags::StudyDescription).diff(&new, &third).is_empty());
+
+        let mut empty = DataSet::new();
+        let nested = diff.changes.iter().find(|c| !c.parents.is_empty()).cloned().unwrap();
+        assert!(Diff { changes: vec![nested] }.apply(&mut empty).is_err());
+    }
+
+    #[test]
+    fn removing_a_missing_item_fails() {
+        let old = sample();
+        let mut new = sample();
+        let mut refs = new.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        refs.clear();
+        refs.new_item().set(&tags::ReferencedSOPInstanceUID, "1.2.3").unwrap();
+        let diff = Differ::new().diff(&old, &new);
+        assert_eq!(diff.changes[0].kind, ChangeKind::ItemRemoved(1));
+
+        let mut patched = old.clone();
+        diff.apply(&mut patched).unwrap();
+        assert_eq!(patched.sequence(&tags::ReferencedImageSequence).unwrap().len(), 1);
+        let err = diff.apply(&mut patched).unwrap_err();
+        assert_eq!(err.kind, dpx_dicom_core::error::ErrorKind::NotFound);
+        patched.remove(&tags::ReferencedImageSequence);
+        assert!(diff.apply(&mut patched).is_err());
+    }
+}
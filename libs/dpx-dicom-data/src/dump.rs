@@ -0,0 +1,412 @@
+//! Human-readable listing of a data set, in the manner of DCMTK's `dcmdump`.
+//!
+//! One line per attribute: tag, VR, decoded value, then after `#` the value
+//! length, the number of values and the keyword. Sequence Items are listed
+//! under their sequence, indented. With the `file_offsets` feature, data sets
+//! read from a file also get their byte offsets in a leading column, and the
+//! delimiters and pixel data fragments found in the file get lines of their
+//! own.
+//!
+//! ```text
+//! (0008,0016) UI [1.2.840.10008.5.1.4.1.1.7]            #   26, 1 SOPClassUID
+//! (0008,1115) SQ (Sequence with 1 Item)                 #    -, 1 ReferencedSeriesSequence
+//!   (FFFE,E000) na (Item with 1 attribute)              #    -, 1 Item
+//!     (0020,000E) UI [1.2.3]                            #    6, 1 SeriesInstanceUID
+//! (0028,0010) US 512                                    #    2, 1 Rows
+//! ```
+
+use std::fmt::{self, Write as _};
+use std::io::Write;
+
+use dpx_dicom_core::error::{IntoDicomErr, Result};
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{Tag, TagKey, tags};
+
+use crate::DataSet;
+use crate::convert;
+use crate::dataset::Shared;
+use crate::dcm_parser::core::UNDEFINED_LENGTH;
+use crate::item::Item;
+use crate::value::{Element, PixelData, Stored, TagHeader, Value};
+
+const NO_VALUE: &str = "(no value available)";
+
+/// Lists a [`DataSet`] as text; see the [module documentation](self).
+/// `DataSet` implements [`Display`](fmt::Display) with the default options.
+#[derive(Debug, Clone)]
+pub struct Dumper {
+    max_width: usize,
+    private_creators: bool,
+    hex: bool,
+}
+
+impl Default for Dumper {
+    fn default() -> Self {
+        Self { max_width: 64, private_creators: false, hex: false }
+    }
+}
+
+impl Dumper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cuts values longer than `width` characters, marking the cut with
+    /// `...` (default: 64).
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = width;
+        self
+    }
+
+    /// Writes private attributes with their Private Creator:
+    /// `(0029,1010,"ACME 1.0")` (default: the bare tag).
+    pub fn private_creators(mut self, show: bool) -> Self {
+        self.private_creators = show;
+        self
+    }
+
+    /// Writes the raw bytes of the binary VRs (`OB`, `OD`, `OF`, `OL`, `OV`,
+    /// `OW`, `UN`) in hexadecimal, in file order (default: their length only).
+    pub fn hex(mut self, hex: bool) -> Self {
+        self.hex = hex;
+        self
+    }
+
+    /// Writes the listing of the data set.
+    pub fn write_dataset<W: Write>(&self, ds: &DataSet, mut w: W) -> Result<()> {
+        let text = self.to_string(ds);
+        w.write_all(text.as_bytes()).to_dicom_err_with(|| "writing data set dump".to_string())
+    }
+
+    /// The listing of the data set.
+    pub fn to_string(&self, ds: &DataSet) -> String {
+        let mut out = String::new();
+        let _ = self.write_fmt(ds, &mut out);
+        out
+    }
+
+    fn write_fmt(&self, ds: &DataSet, out: &mut impl fmt::Write) -> fmt::Result {
+        let (shared, root) = ds.context();
+        let offsets = root.map.entries().iter().any(|(_, el)| !headers(el).is_empty());
+        Listing { shared, options: self, offsets, out }.item(root, 0)
+    }
+}
+
+impl fmt::Display for DataSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Dumper::default().write_fmt(self, f)
+    }
+}
+
+fn headers(el: &Element) -> &[TagHeader] {
+    #[cfg(feature = "file_offsets")]
+    {
+        &el.header
+    }
+    #[cfg(not(feature = "file_offsets"))]
+    {
+        let _ = el;
+        &[]
+    }
+}
+
+/// One line of the listing.
+struct Line<'a> {
+    header: Option<&'a TagHeader>,
+    tag: String,
+    vr: &'a str,
+    value: String,
+    /// The value length when no header tells it.
+    length: Option<usize>,
+    vm: usize,
+    keyword: String,
+}
+
+struct Listing<'a, W> {
+    shared: &'a Shared,
+    options: &'a Dumper,
+    /// Whether the data set has file offsets to show.
+    offsets: bool,
+    out: &'a mut W,
+}
+
+impl<W: fmt::Write> Listing<'_, W> {
+    fn line(&mut self, depth: usize, line: Line) -> fmt::Result {
+        if self.offsets {
+            match line.header {
+                Some(header) => write!(self.out, "{:08X}  ", header.offset)?,
+                None => write!(self.out, "{:10}", "")?,
+            }
+        }
+        let length = match (line.header.map(|h| h.length.0), line.length) {
+            (Some(UNDEFINED_LENGTH), _) => "u/l".to_string(),
+            (Some(length), _) => length.to_string(),
+            (None, Some(length)) => length.to_string(),
+            (None, None) => "-".to_string(),
+        };
+        let head = format!("{:indent$}{} {} {}", "", line.tag, line.vr, line.value, indent = depth * 2);
+        let width = self.options.max_width + 20;
+        writeln!(self.out, "{head:<width$} # {length:>4}, {} {}", line.vm, line.keyword)
+    }
+
+    fn item(&mut self, item: &Item, depth: usize) -> fmt::Result {
+        for (key, el) in item.map.entries() {
+            self.attribute(item, *key, el, depth)?;
+        }
+        Ok(())
+    }
+
+    fn attribute(&mut self, item: &Item, key: TagKey, el: &Element, depth: usize) -> fmt::Result {
+        let creator = key.is_private_attribute().then(|| item.private_creator(self.shared, key)).flatten();
+        let tag = match creator {
+            Some(creator) => Tag::new_private_cow(key.group(), key.element(), creator),
+            None => Tag::new(key, None),
+        };
+        let keyword = keyword(&tag);
+        let shown = if self.options.private_creators { tag.to_string() } else { key.to_string() };
+        let headers = headers(el);
+        let vr = el.vr.keyword();
+
+        if let Stored::Items(items) = &el.value {
+            let value = format!("(Sequence with {} Item{})", items.len(), plural(items.len()));
+            self.line(depth, Line { header: headers.first(), tag: shown, vr, value, length: None, vm: 1, keyword })?;
+            for nested in items {
+                let item_headers = nested.item_headers();
+                let count = nested.map.len();
+                self.line(
+                    depth + 1,
+                    Line {
+                        header: item_headers.first(),
+                        tag: tags::Item.key.to_string(),
+                        vr: "na",
+                        value: format!("(Item with {count} attribute{})", plural(count)),
+                        length: None,
+                        vm: 1,
+                        keyword: "Item".to_string(),
+                    },
+                )?;
+                self.item(nested, depth + 2)?;
+                for header in item_headers.iter().skip(1) {
+                    self.delimiter(depth + 1, header)?;
+                }
+            }
+        } else {
+            let (value, vm, length) = self.value(item, el);
+            self.line(depth, Line { header: headers.first(), tag: shown, vr, value, length, vm, keyword })?;
+        }
+        // Pixel data fragments and the closing delimiter, as found in the file.
+        for header in headers.iter().skip(1) {
+            let depth = if header.tag == tags::SequenceDelimitationItem.key { depth } else { depth + 1 };
+            self.delimiter(depth, header)?;
+        }
+        Ok(())
+    }
+
+    fn delimiter(&mut self, depth: usize, header: &TagHeader) -> fmt::Result {
+        let keyword = keyword(&Tag::new(header.tag, None));
+        let value = match header.size {
+            Some(size) if header.tag == tags::Item.key => format!("(Item with {size} bytes)"),
+            _ => format!("({keyword})"),
+        };
+        let line =
+            Line { header: Some(header), tag: header.tag.to_string(), vr: "na", value, length: None, vm: 0, keyword };
+        self.line(depth, line)
+    }
+
+    /// The value text, the number of values and the encoded length of an
+    /// attribute other than a sequence.
+    fn value(&self, item: &Item, el: &Element) -> (String, usize, Option<usize>) {
+        let length = self.length(item, el);
+        if length == Some(0) {
+            return (NO_VALUE.to_string(), 0, length);
+        }
+        match el.vr.info().kind {
+            Kind::Text { .. } => {
+                let Ok(text) = item.element_text(self.shared, el) else {
+                    return ("(not decodable)".to_string(), 0, length);
+                };
+                let text = text.trim_end_matches([' ', '\0']);
+                let vm = match text {
+                    "" => 0,
+                    _ if convert::is_multi_valued_text(el.vr) => text.split('\\').count(),
+                    _ => 1,
+                };
+                let escaped: String = text.chars().flat_map(escape).collect();
+                (format!("[{}]", self.truncate(escaped)), vm, length)
+            }
+            Kind::Bytes => self.bytes(item, el, length),
+            _ => match item.element_value(self.shared, el) {
+                Ok(Value::Int(v)) => (self.truncate(join(v.iter())), v.len(), length),
+                Ok(Value::UInt(v)) => (self.truncate(join(v.iter())), v.len(), length),
+                Ok(Value::Float(v)) => (self.truncate(join(v.iter())), v.len(), length),
+                Ok(Value::Tags(v)) => (self.truncate(join(v.iter().map(|t| t.key))), v.len(), length),
+                _ => ("(not decodable)".to_string(), 0, length),
+            },
+        }
+    }
+
+    /// The value text of a binary VR: its length, or its bytes in hex.
+    fn bytes(&self, item: &Item, el: &Element, length: Option<usize>) -> (String, usize, Option<usize>) {
+        let bytes = match (&el.value, item.element_bytes(self.shared, el)) {
+            (_, Some(bytes)) => bytes,
+            (Stored::Native(Value::Bytes(bytes)), _) => bytes,
+            (Stored::Native(Value::Pixels(px)), _) => match px.as_ref() {
+                PixelData::Native(bytes) => bytes,
+                PixelData::Encapsulated { fragments, .. } => {
+                    let value = format!("(Encapsulated with {} fragment{})", fragments.len(), plural(fragments.len()));
+                    return (value, 1, length);
+                }
+            },
+            (Stored::Native(Value::BulkData(uri)), _) => return (format!("(BulkDataURI {uri})"), 1, length),
+            _ => return ("(not decodable)".to_string(), 0, length),
+        };
+        if !self.options.hex {
+            return (format!("({} bytes)", bytes.len()), 1, length);
+        }
+        // More bytes than fit the width, for `truncate` to mark the cut, but
+        // not the whole (maybe huge) value.
+        let shown = bytes.iter().take(self.options.max_width / 3 + 2).map(|b| format!("{b:02x}"));
+        let hex = shown.collect::<Vec<_>>().join("\\");
+        (self.truncate(hex), 1, length)
+    }
+
+    /// The length of the value as read, or as it would be written.
+    fn length(&self, item: &Item, el: &Element) -> Option<usize> {
+        let length = match &el.value {
+            Stored::Native(Value::Pixels(px)) => match px.as_ref() {
+                PixelData::Native(bytes) => bytes.len(),
+                PixelData::Encapsulated { .. } => return None,
+            },
+            Stored::Native(value) => {
+                let mut out = Vec::new();
+                let little_endian = self.shared.is_little_endian();
+                convert::encode(self.shared, little_endian, el.vr, value, &mut out).ok()?;
+                out.len()
+            }
+            _ => return item.element_bytes(self.shared, el).map(<[u8]>::len),
+        };
+        Some(length.next_multiple_of(2))
+    }
+
+    fn truncate(&self, text: String) -> String {
+        match text.char_indices().nth(self.options.max_width) {
+            Some((cut, _)) => format!("{}...", &text[..cut]),
+            None => text,
+        }
+    }
+}
+
+fn keyword(tag: &Tag) -> String {
+    tag.meta().map(|m| m.keyword.to_string()).filter(|k| !k.is_empty()).unwrap_or_else(|| "?".to_string())
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 { "" } else { "s" }
+}
+
+fn join<T: fmt::Display>(values: impl Iterator<Item = T>) -> String {
+    let mut out = String::new();
+    for (i, v) in values.enumerate() {
+        if i > 0 {
+            out.push('\\');
+        }
+        let _ = write!(out, "{v}");
+    }
+    out
+}
+
+/// Control characters as escapes, so that each attribute keeps to its line.
+fn escape(c: char) -> impl Iterator<Item = char> {
+    let escaped = c.is_control().then(|| c.escape_default());
+    let plain = (!c.is_control()).then_some(c);
+    escaped.into_iter().flatten().chain(plain)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use dpx_dicom_core::Vr;
+
+    use super::*;
+
+    fn sample() -> DataSet {
+        let mut ds = DataSet::new();
+        ds.set(&tags::ImageType, "ORIGINAL\\PRIMARY").unwrap();
+        ds.set(&tags::SOPClassUID, "1.2.840.10008.5.1.4.1.1.7").unwrap();
+        ds.set(&tags::StudyDescription, "").unwrap();
+        let mut series = ds.sequence_mut(&tags::ReferencedSeriesSequence).unwrap();
+        series.new_item().set(&tags::SeriesInstanceUID, "1.2.3").unwrap();
+        ds.set(&tags::Rows, 512u16).unwrap();
+        ds.set(&tags::PixelSpacing, "0.5\\0.25").unwrap();
+        ds.set_with_vr(&tags::EncapsulatedDocument, Vr::OB, Value::Bytes(Bytes::from_static(&[0, 1, 0xAB, 0xFF])))
+            .unwrap();
+        ds
+    }
+
+    #[test]
+    fn lists_attributes_and_items() {
+        let ds = sample();
+        let lines: Vec<String> =
+            ds.to_string().lines().map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+        assert_eq!(
+            lines,
+            [
+                "(0008,0008) CS [ORIGINAL\\PRIMARY] # 16, 2 ImageType",
+                "(0008,0016) UI [1.2.840.10008.5.1.4.1.1.7] # 26, 1 SOPClassUID",
+                "(0008,1030) LO (no value available) # 0, 0 StudyDescription",
+                "(0008,1115) SQ (Sequence with 1 Item) # -, 1 ReferencedSeriesSequence",
+                "(FFFE,E000) na (Item with 1 attribute) # -, 1 Item",
+                "(0020,000E) UI [1.2.3] # 6, 1 SeriesInstanceUID",
+                "(0028,0010) US 512 # 2, 1 Rows",
+                "(0028,0030) DS [0.5\\0.25] # 8, 2 PixelSpacing",
+                "(0042,0011) OB (4 bytes) # 4, 1 EncapsulatedDocument",
+            ]
+        );
+        let text = ds.to_string();
+        assert!(text.contains("\n  (FFFE,E000) na"));
+        assert!(text.contains("\n    (0020,000E) UI"));
+        // The `#` column is aligned.
+        let columns: Vec<_> = text.lines().map(|l| l.find(" # ")).collect();
+        assert!(columns.iter().all(|c| *c == columns[0]));
+    }
+
+    #[test]
+    fn options_change_values_and_tags() {
+        let mut ds = sample();
+        ds.set(&tags::StudyDescription, "A\nvery long description").unwrap();
+        ds.set(&Tag::new_private_cow(0x0029, 0x0010, ""), "ACME 1.0").unwrap();
+        ds.set_with_vr(&Tag::new_private(0x0029, 0x1010, "ACME 1.0"), Vr::LO, Value::Str("x".into())).unwrap();
+
+        let text = Dumper::new().max_width(12).hex(true).private_creators(true).to_string(&ds);
+        assert!(text.contains("[A\\nvery long...]"), "{text}");
+        assert!(text.contains("(0042,0011) OB 00\\01\\ab\\ff "), "{text}");
+        assert!(text.contains("(0029,1010,\"ACME 1.0\") LO [x]"), "{text}");
+        assert!(text.contains("(0029,0010) LO [ACME 1.0]"), "{text}");
+
+        let text = Dumper::new().to_string(&ds);
+        assert!(text.contains("(0029,1010) LO [x]"), "{text}");
+        assert!(text.contains("[A\\nvery long description]"), "{text}");
+    }
+
+    #[test]
+    fn shows_file_offsets() {
+        use dpx_dicom_core::TransferSyntax;
+
+        use crate::DcmWriter;
+        use crate::dcm_parser::{DcmReader, HeaderType};
+
+        let bytes = DcmWriter::new().to_bytes(&sample()).unwrap();
+        let reader =
+            DcmReader::new().header(HeaderType::NoHeader).transfer_syntax(&TransferSyntax::ExplicitVRLittleEndian);
+        let ds = reader.parse_bytes(bytes).unwrap().dataset.unwrap();
+        let text = ds.to_string();
+        if cfg!(feature = "file_offsets") {
+            assert!(text.starts_with("00000000  (0008,0008) CS"), "{text}");
+            assert!(text.contains("  (FFFE,E00D) na (ItemDelimitationItem) "), "{text}");
+            assert!(text.contains("  (FFFE,E0DD) na (SequenceDelimitationItem) "), "{text}");
+            assert!(text.lines().all(|l| l.as_bytes()[8] == b' ' || l.starts_with("          ")), "{text}");
+        } else {
+            assert!(text.starts_with("(0008,0008) CS"), "{text}");
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Export of frames as lossless PNG or Netpbm (PGM/PPM) images.
+//!
+//! [`FrameExporter`] decodes a frame with [`PixelDecoder`], renders grayscale
+//! frames through a [`GrayscaleRenderer`] and converts colour frames to RGB
+//! with a [`ColorConverter`], then encodes 8- or 16-bit gray or RGB samples.
+//! PNG stores 16-bit samples big-endian, as does Netpbm with a maxval of 65535.
+
+use dpx_dicom_core::dicom_err;
+use dpx_dicom_core::error::Result;
+
+use crate::DataSet;
+use crate::color::ColorConverter;
+use crate::lut::GrayscaleRenderer;
+use crate::pixels::{PixelDecoder, Samples};
+
+/// Renders the frames of one data set to image files.
+pub struct FrameExporter<'a> {
+    decoder: PixelDecoder<'a>,
+    /// Set for MONOCHROME1/MONOCHROME2 images.
+    gray: Option<GrayscaleRenderer>,
+    color: Option<ColorConverter>,
+    sixteen_bit: bool,
+}
+
+/// One rendered frame: `channels` samples per pixel, interleaved, each at most
+/// `max`.
+struct Rendered {
+    width: u32,
+    height: u32,
+    channels: u8,
+    max: u16,
+    samples: Vec<u16>,
+}
+
+impl<'a> FrameExporter<'a> {
+    /// Prepares to export the frames of `ds`: grayscale images through
+    /// [`GrayscaleRenderer::from_dataset`], colour images through
+    /// [`ColorConverter::from_dataset`]. Output is 8-bit.
+    pub fn new(ds: &'a DataSet) -> Result<Self> {
+        let decoder = PixelDecoder::new(ds)?;
+        let monochrome = decoder.info().photometric_interpretation.starts_with("MONOCHROME");
+        let (gray, color) = if monochrome {
+            (Some(GrayscaleRenderer::from_dataset(ds)?), None)
+        } else {
+            (None, Some(ColorConverter::from_dataset(ds)?))
+        };
+        Ok(FrameExporter { decoder, gray, color, sixteen_bit: false })
+    }
+
+    /// Replaces the grayscale pipeline, e.g. to pick another window.
+    pub fn renderer(mut self, renderer: GrayscaleRenderer) -> Self {
+        if self.gray.is_some() {
+            self.gray = Some(renderer);
+        }
+        self
+    }
+
+    /// Whether samples are written with 16 bits rather than 8.
+    pub fn sixteen_bit(mut self, sixteen_bit: bool) -> Self {
+        self.sixteen_bit = sixteen_bit;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.decoder.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decoder.is_empty()
+    }
+
+    /// Frame `index` as a PNG file.
+    pub fn png(&self, index: usize) -> Result<Vec<u8>> {
+        let image = self.render(index)?;
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, image.width, image.height);
+        encoder.set_color(if image.channels == 1 { png::ColorType::Grayscale } else { png::ColorType::Rgb });
+        encoder.set_depth(if image.max > 255 { png::BitDepth::Sixteen } else { png::BitDepth::Eight });
+        let encoding = |e: png::EncodingError| dicom_err!(Internal, "encoding PNG: {e}");
+        let mut writer = encoder.write_header().map_err(encoding)?;
+        writer.write_image_data(&image.bytes()).map_err(encoding)?;
+        writer.finish().map_err(encoding)?;
+        Ok(out)
+    }
+
+    /// Frame `index` as a binary Netpbm file: PGM (`P5`) for grayscale, PPM
+    /// (`P6`) for colour.
+    pub fn netpbm(&self, index: usize) -> Result<Vec<u8>> {
+        let image = self.render(index)?;
+        let magic = if image.channels == 1 { "P5" } else { "P6" };
+        let mut out = format!("{magic}\n{} {}\n{}\n", image.width, image.height, image.max).into_bytes();
+        out.extend(image.bytes());
+        Ok(out)
+    }
+
+    fn render(&self, index: usize) -> Result<Rendered> {
+        let frame = self.decoder.frame(index)?;
+        let (width, height) = (u32::from(frame.columns), u32::from(frame.rows));
+        let max = if self.sixteen_bit { u16::MAX } else { u16::from(u8::MAX) };
+        if let Some(gray) = &self.gray {
+            let samples = if self.sixteen_bit {
+                gray.render16(&frame)?
+            } else {
+                gray.render8(&frame)?.into_iter().map(u16::from).collect()
+            };
+            return Ok(Rendered { width, height, channels: 1, max, samples });
+        }
+        let color = self.color.as_ref().ok_or_else(|| dicom_err!(Internal, "no colour conversion prepared"))?;
+        let rgb = color.to_rgb(frame)?;
+        // Palette entries fill their sample type, which follows the entry size
+        // rather than the indices; other colour samples span Bits Stored.
+        let source_max = match (self.decoder.info().photometric_interpretation.as_str(), &rgb.samples) {
+            ("PALETTE COLOR", Samples::U8(_)) => f64::from(u8::MAX),
+            ("PALETTE COLOR", _) => f64::from(u16::MAX),
+            _ => ((1u64 << self.decoder.info().bits_stored.min(32)) - 1) as f64,
+        };
+        let scale = f64::from(max) / source_max;
+        let samples = (0..rgb.samples.len())
+            .map(|i| (rgb.samples.get(i).unwrap_or_default() * scale).round().clamp(0.0, f64::from(max)) as u16)
+            .collect();
+        Ok(Rendered { width, height, channels: 3, max, samples })
+    }
+}
+
+impl Rendered {
+    /// The samples as bytes, big-endian when wider than 8 bits.
+    fn bytes(&self) -> Vec<u8> {
+        if self.max > 255 {
+            self.samples.iter().flat_map(|s| s.to_be_bytes()).collect()
+        } else {
+            self.samples.iter().map(|&s| s as u8).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{Image, palette};
+    use dpx_dicom_core::tags;
+
+    fn decode_png(data: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut reader = png::Decoder::new(std::io::Cursor::new(data)).read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        buf.truncate(info.buffer_size());
+        (info, buf)
+    }
+
+    #[test]
+    fn grayscale_png_spans_the_frame() {
+        let pixels = [0u16, 50, 100].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let ds = Image::new(1, 3, 1, 16).photometric("MONOCHROME2").native(pixels);
+        let exporter = FrameExporter::new(&ds).unwrap();
+        let (info, pixels) = decode_png(&exporter.png(0).unwrap());
+        assert_eq!(
+            (info.width, info.height, info.color_type, info.bit_depth),
+            (3, 1, png::ColorType::Grayscale, png::BitDepth::Eight)
+        );
+        assert_eq!(pixels, vec![0, 128, 255]);
+
+        let (info, pixels) = decode_png(&exporter.sixteen_bit(true).png(0).unwrap());
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        assert_eq!(pixels, vec![0, 0, 0x80, 0x00, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn netpbm_headers() {
+        let ds = Image::new(1, 2, 1, 8).photometric("MONOCHROME2").native(vec![0, 255]);
+        assert_eq!(FrameExporter::new(&ds).unwrap().netpbm(0).unwrap(), b"P5\n2 1\n255\n\x00\xFF");
+
+        let ds = Image::new(1, 1, 3, 8).photometric("RGB").native(vec![1, 2, 3]);
+        let exporter = FrameExporter::new(&ds).unwrap().sixteen_bit(true);
+        assert_eq!(exporter.netpbm(0).unwrap(), b"P6\n1 1\n65535\n\x01\x01\x02\x02\x03\x03");
+    }
+
+    #[test]
+    fn palette_entries_scale_by_their_size() {
+        // 8-bit indices into 16-bit entries.
+        let mut ds = Image::new(1, 2, 1, 8).photometric("PALETTE COLOR").native(vec![0, 1]);
+        palette(&mut ds, [2, 0, 16], [&[0xFFFF, 0], &[0, 0x8000], &[0, 0xFFFF]]);
+        let exporter = FrameExporter::new(&ds).unwrap();
+        assert_eq!(exporter.netpbm(0).unwrap(), b"P6\n2 1\n255\n\xFF\x00\x00\x00\x80\xFF");
+        let (info, pixels) = decode_png(&exporter.sixteen_bit(true).png(0).unwrap());
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgb, png::BitDepth::Sixteen));
+        assert_eq!(pixels, vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0x80, 0x00, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn colour_png_is_rgb() {
+        let mut ds = Image::new(1, 2, 3, 8).photometric("YBR_FULL").native(vec![76, 128, 85, 128, 255, 128]);
+        ds.set(&tags::PlanarConfiguration, 1u16).unwrap();
+        let (info, pixels) = decode_png(&FrameExporter::new(&ds).unwrap().png(0).unwrap());
+        assert_eq!(info.color_type, png::ColorType::Rgb);
+        assert_eq!(pixels, vec![254, 0, 0, 128, 128, 128]);
+    }
+}
@@ -0,0 +1,378 @@
+//! Frame-level access to Pixel Data, native or encapsulated.
+//!
+//! Native frames are sliced from the value by the Image Pixel attributes;
+//! encapsulated frames are located by the Extended Offset Table, then the
+//! Basic Offset Table, then — when both are absent or do not fit the
+//! fragments — by fragment count and the start markers of the common
+//! compressed formats. A frame that is one contiguous slice (any byte-aligned
+//! native frame, any single-fragment encapsulated frame) is borrowed, so over
+//! a memory-mapped file it is zero-copy.
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+use bytes::Bytes;
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{Tag, TagKey, Vr, dicom_err, ensure, tags};
+
+use crate::DataSet;
+use crate::codec::PixelInfo;
+use crate::value::{Element, PixelData, Stored, Value};
+
+/// The frames of a data set's Pixel Data (7FE0,0010).
+pub struct Frames<'a> {
+    info: PixelInfo,
+    source: Source<'a>,
+}
+
+enum Source<'a> {
+    /// Contiguous frames in the data set's byte order.
+    Native(&'a [u8]),
+    /// The fragments each frame spans and, from the Extended Offset Table
+    /// Lengths, its length within them.
+    Encapsulated { fragments: &'a [Bytes], frames: Vec<(Range<usize>, Option<usize>)> },
+}
+
+impl<'a> Frames<'a> {
+    /// Locates the frames of `ds`. Fails when it has no Pixel Data, lacks the
+    /// Image Pixel attributes [`PixelInfo::from_dataset`] requires, or holds
+    /// fragments that cannot be grouped into Number of Frames frames.
+    pub fn new(ds: &'a DataSet) -> Result<Self> {
+        Self::of(ds, &tags::PixelData)
+    }
+
+    /// The frames of `tag`: Pixel Data, Float Pixel Data or Double Float Pixel
+    /// Data.
+    pub(crate) fn of(ds: &'a DataSet, tag: &Tag) -> Result<Self> {
+        let (shared, root) = ds.context();
+        let el = root.map.get(tag.key).ok_or_else(|| dicom_err!(NotFound, "the data set has no {tag}"))?;
+        let info = PixelInfo::from_dataset(ds)?;
+        let source = match &el.value {
+            Stored::Mapped(range) => Source::Native(
+                shared
+                    .master()
+                    .get(range.clone())
+                    .ok_or_else(|| dicom_err!(Internal, "mapped value range out of bounds"))?,
+            ),
+            Stored::Owned(b) | Stored::Native(Value::Bytes(b)) => Source::Native(b),
+            Stored::Native(Value::Pixels(px)) => match &**px {
+                PixelData::Native(b) => Source::Native(b),
+                PixelData::Encapsulated { bot, fragments, eot } => {
+                    let extended = extended_table(ds, eot);
+                    let frames = locate(bot, extended, fragments, info.number_of_frames as usize)?;
+                    Source::Encapsulated { fragments, frames }
+                }
+            },
+            _ => return Err(dicom_err!(InvalidData, "Pixel Data holds no pixel value")),
+        };
+        Ok(Frames { info, source })
+    }
+
+    /// The Image Pixel attributes describing each frame.
+    pub fn info(&self) -> &PixelInfo {
+        &self.info
+    }
+
+    /// Whether the frames are compressed (encapsulated) data.
+    pub fn is_encapsulated(&self) -> bool {
+        matches!(self.source, Source::Encapsulated { .. })
+    }
+
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        match &self.source {
+            Source::Native(_) => self.info.number_of_frames as usize,
+            Source::Encapsulated { frames, .. } => frames.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Frame `index` (from 0): native samples in the data set's byte order, or
+    /// the compressed bytes of an encapsulated frame.
+    pub fn frame(&self, index: usize) -> Result<Cow<'a, [u8]>> {
+        ensure!(index < self.len(), NotFound, "frame {index} requested of {}", self.len());
+        match &self.source {
+            Source::Native(data) => {
+                let info = &self.info;
+                let bits = info.frame_samples() * info.bits_allocated as usize;
+                let (start, end) = (index * bits, (index + 1) * bits);
+                ensure!(
+                    end.div_ceil(8) <= data.len(),
+                    InvalidData,
+                    "Pixel Data holds {} bytes, frame {index} ends at {}",
+                    data.len(),
+                    end.div_ceil(8)
+                );
+                if start % 8 == 0 && end % 8 == 0 {
+                    return Ok(Cow::Borrowed(&data[start / 8..end / 8]));
+                }
+                // Packed 1-bit frames need not start or end on a byte boundary.
+                let mut out = vec![0u8; bits.div_ceil(8)];
+                for bit in 0..bits {
+                    let src = start + bit;
+                    out[bit / 8] |= ((data[src / 8] >> (src % 8)) & 1) << (bit % 8);
+                }
+                Ok(Cow::Owned(out))
+            }
+            Source::Encapsulated { fragments, frames } => {
+                let (range, length) = &frames[index];
+                let parts = &fragments[range.clone()];
+                let frame = match parts {
+                    [one] => Cow::Borrowed(&one[..]),
+                    _ => Cow::Owned(parts.concat()),
+                };
+                Ok(match (frame, length) {
+                    (Cow::Borrowed(b), Some(n)) => Cow::Borrowed(&b[..(*n).min(b.len())]),
+                    (Cow::Owned(mut v), Some(n)) => {
+                        v.truncate(*n);
+                        Cow::Owned(v)
+                    }
+                    (frame, None) => frame,
+                })
+            }
+        }
+    }
+
+    /// Every frame, in order.
+    pub fn iter(&self) -> impl Iterator<Item = Result<Cow<'a, [u8]>>> + '_ {
+        (0..self.len()).map(|i| self.frame(i))
+    }
+}
+
+/// The Extended Offset Table of encapsulated Pixel Data and its Lengths: the
+/// model's `eot`, else (7FE0,0001)/(7FE0,0002) elements of `ds` (as a data set
+/// built by hand or read from JSON / XML carries them).
+fn extended_table(ds: &DataSet, eot: &[(u64, u64)]) -> Option<(Vec<u64>, Option<Vec<u64>>)> {
+    if !eot.is_empty() {
+        return Some(eot.iter().copied().unzip()).map(|(offsets, lengths)| (offsets, Some(lengths)));
+    }
+    // Encapsulated transfer syntaxes are little endian.
+    let table = |tag| {
+        ds.get_bytes_some(tag)
+            .map(|b| b.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().expect("8-byte chunk"))).collect())
+    };
+    table(&tags::ExtendedOffsetTable).map(|offsets| (offsets, table(&tags::ExtendedOffsetTableLengths)))
+}
+
+/// Groups `fragments` into `count` frames, returning the fragment range of
+/// each and its length when the Extended Offset Table gives one.
+fn locate(
+    bot: &[u32],
+    extended: Option<(Vec<u64>, Option<Vec<u64>>)>,
+    fragments: &[Bytes],
+    count: usize,
+) -> Result<Vec<(Range<usize>, Option<usize>)>> {
+    // Offsets count from the first fragment's Item tag, 8 header bytes each.
+    let positions: Vec<u64> = fragments
+        .iter()
+        .scan(0u64, |pos, f| {
+            let at = *pos;
+            *pos += 8 + f.len() as u64;
+            Some(at)
+        })
+        .collect();
+    let ranges = |starts: Vec<usize>| -> Vec<Range<usize>> {
+        let ends = starts.iter().skip(1).copied().chain([fragments.len()]);
+        starts.iter().zip(ends).map(|(&s, e)| s..e).collect()
+    };
+    let by_offsets = |offsets: &[u64]| -> Option<Vec<usize>> {
+        let starts: Option<Vec<usize>> = offsets.iter().map(|o| positions.binary_search(o).ok()).collect();
+        starts.filter(|s| s.len() == count && s.first() == Some(&0) && s.is_sorted_by(|a, b| a < b))
+    };
+
+    if let Some((offsets, lengths)) = extended
+        && let Some(starts) = by_offsets(&offsets)
+    {
+        let lengths = lengths.filter(|l| l.len() == count);
+        let frames = ranges(starts).into_iter().enumerate();
+        return Ok(frames.map(|(i, r)| (r, lengths.as_ref().map(|l| l[i] as usize))).collect());
+    }
+    let bot: Vec<u64> = bot.iter().map(|&o| u64::from(o)).collect();
+    if let Some(starts) = by_offsets(&bot) {
+        return Ok(ranges(starts).into_iter().map(|r| (r, None)).collect());
+    }
+    if fragments.len() == count {
+        return Ok((0..count).map(|i| (i..i + 1, None)).collect());
+    }
+    if count == 1 {
+        return Ok(vec![(0..fragments.len(), None)]);
+    }
+    let starts: Vec<usize> = (0..fragments.len()).filter(|&i| starts_frame(&fragments[i])).collect();
+    ensure!(
+        starts.len() == count && starts.first() == Some(&0),
+        InvalidData,
+        "cannot split {} fragments into {count} frames without an offset table",
+        fragments.len()
+    );
+    Ok(ranges(starts).into_iter().map(|r| (r, None)).collect())
+}
+
+/// Adjusts the root `overrides` [`DcmWriter`](crate::DcmWriter) writes so that
+/// encapsulated Pixel Data carries an Extended Offset Table — with the Basic
+/// Offset Table empty and each frame in a single fragment — when `force`d, when
+/// the data set already has one, or when frame offsets may overflow 32 bits.
+/// Otherwise any (7FE0,0001)/(7FE0,0002) elements are dropped, so stale tables
+/// never reach the output. `overrides` stays sorted by tag.
+pub(crate) fn offset_tables(ds: &DataSet, overrides: &mut Vec<(TagKey, Option<Element>)>, force: bool) -> Result<()> {
+    let (_, root) = ds.context();
+    let keys = [tags::ExtendedOffsetTable.key, tags::ExtendedOffsetTableLengths.key];
+    let pixels = match overrides.iter().find(|(k, _)| *k == tags::PixelData.key) {
+        Some((_, el)) => el.as_ref(),
+        None => root.map.get(tags::PixelData.key),
+    };
+    let encapsulated = match pixels.map(|el| (el.vr, &el.value)) {
+        Some((vr, Stored::Native(Value::Pixels(px)))) => match &**px {
+            PixelData::Encapsulated { bot, fragments, eot } => Some((vr, bot.clone(), fragments.clone(), eot.clone())),
+            PixelData::Native(_) => None,
+        },
+        _ => None,
+    };
+    let stale = keys.iter().any(|k| root.map.contains_key(*k));
+    let wanted = encapsulated.filter(|(_, _, fragments, eot)| {
+        let size: u64 = fragments.iter().map(|f| 8 + f.len() as u64).sum();
+        force || stale || !eot.is_empty() || size > u64::from(u32::MAX)
+    });
+    let mut set = |key: TagKey, el: Option<Element>| match overrides.iter_mut().find(|(k, _)| *k == key) {
+        Some(entry) => entry.1 = el,
+        None => overrides.push((key, el)),
+    };
+    let Some((vr, bot, fragments, eot)) = wanted else {
+        for key in keys.into_iter().filter(|k| root.map.contains_key(*k)) {
+            set(key, None);
+        }
+        overrides.sort_by_key(|(k, _)| k.0);
+        return Ok(());
+    };
+
+    let count = ds.get_some::<u32>(&tags::NumberOfFrames).unwrap_or(1).max(1) as usize;
+    let frames = locate(&bot, extended_table(ds, &eot), &fragments, count)?;
+    let mut merged = Vec::with_capacity(frames.len());
+    let mut table = Vec::with_capacity(frames.len());
+    let mut offset = 0u64;
+    for (range, length) in frames {
+        let fragment = match &fragments[range] {
+            [one] => one.clone(),
+            parts => Bytes::from(parts.concat()),
+        };
+        let length = length.map_or(fragment.len(), |n| n.min(fragment.len()));
+        table.push((offset, length as u64));
+        offset += 8 + fragment.len() as u64;
+        merged.push(fragment);
+    }
+    let words = |values: Vec<u64>| {
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        Some(Element::new(Vr::OV, Stored::Native(Value::Bytes(Bytes::from(bytes)))))
+    };
+    let (offsets, lengths): (Vec<u64>, Vec<u64>) = table.iter().copied().unzip();
+    let px = PixelData::Encapsulated { bot: Vec::new(), fragments: merged, eot: table };
+    set(tags::ExtendedOffsetTable.key, words(offsets));
+    set(tags::ExtendedOffsetTableLengths.key, words(lengths));
+    set(tags::PixelData.key, Some(Element::new(vr, Stored::Native(Value::Pixels(Box::new(px))))));
+    overrides.sort_by_key(|(k, _)| k.0);
+    Ok(())
+}
+
+/// Whether `fragment` opens a compressed image: a JPEG SOI marker, a JPEG 2000
+/// codestream SOC marker or a JP2 signature box.
+fn starts_frame(fragment: &[u8]) -> bool {
+    fragment.starts_with(&[0xFF, 0xD8, 0xFF])
+        || fragment.starts_with(&[0xFF, 0x4F, 0xFF, 0x51])
+        || fragment.starts_with(&[0, 0, 0, 0x0C, b'j', b'P', b' ', b' '])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Image;
+    use dpx_dicom_core::TransferSyntax;
+
+    #[test]
+    fn native_frames_borrow_the_mapped_value() {
+        let mut b = Vec::new();
+        let mut el = |g: u16, e: u16, vr: &[u8; 2], val: &[u8]| {
+            b.extend_from_slice(&g.to_le_bytes());
+            b.extend_from_slice(&e.to_le_bytes());
+            b.extend_from_slice(vr);
+            if vr == b"OW" {
+                b.extend_from_slice(&[0, 0]);
+                b.extend_from_slice(&(val.len() as u32).to_le_bytes());
+            } else {
+                b.extend_from_slice(&(val.len() as u16).to_le_bytes());
+            }
+            b.extend_from_slice(val);
+        };
+        el(0x0028, 0x0008, b"IS", b"3 ");
+        el(0x0028, 0x0010, b"US", &1u16.to_le_bytes());
+        el(0x0028, 0x0011, b"US", &2u16.to_le_bytes());
+        el(0x0028, 0x0100, b"US", &16u16.to_le_bytes());
+        el(0x7FE0, 0x0010, b"OW", &(0u8..12).collect::<Vec<_>>());
+        let ds = crate::DcmReader::new()
+            .header(crate::HeaderType::NoHeader)
+            .transfer_syntax(&TransferSyntax::ExplicitVRLittleEndian)
+            .parse_bytes(Bytes::from(b))
+            .unwrap()
+            .dataset
+            .unwrap();
+
+        let frames = Frames::new(&ds).unwrap();
+        assert!(!frames.is_encapsulated());
+        assert_eq!(frames.len(), 3);
+        let frame = frames.frame(1).unwrap();
+        assert!(matches!(frame, Cow::Borrowed(_)));
+        assert_eq!(&frame[..], [4, 5, 6, 7]);
+        assert!(frames.frame(3).is_err());
+    }
+
+    #[test]
+    fn single_bit_frames_are_unpacked_across_bytes() {
+        // 18 bits: frame 0 all set, frame 1 alternating from its first pixel.
+        let ds = Image::new(3, 3, 1, 1).frames(2).native(vec![0xFF, 0b1010_1011, 0b0000_0010]);
+        let frames = Frames::new(&ds).unwrap();
+        assert_eq!(&frames.frame(0).unwrap()[..], [0xFF, 0x01]);
+        assert_eq!(&frames.frame(1).unwrap()[..], [0b0101_0101, 0x01]);
+    }
+
+    #[test]
+    fn fragments_group_by_offset_table() {
+        // Frame 1 starts at the third fragment: 2 * (8 + 2) bytes in.
+        let ds = Image::new(1, 1, 1, 8).frames(2).encapsulated(vec![0, 20], &[b"AB", b"CD", b"EF"]);
+        let frames = Frames::new(&ds).unwrap();
+        assert!(frames.is_encapsulated());
+        assert_eq!(frames.frame(0).unwrap(), &b"ABCD"[..]);
+        assert!(matches!(frames.frame(1).unwrap(), Cow::Borrowed(b"EF")));
+
+        let ds = Image::new(1, 1, 1, 8).frames(1).encapsulated(vec![], &[b"AB", b"CD", b"EF"]);
+        assert_eq!(Frames::new(&ds).unwrap().frame(0).unwrap(), &b"ABCDEF"[..]);
+
+        let ds = Image::new(1, 1, 1, 8).frames(3).encapsulated(vec![], &[b"AB", b"CD", b"EF"]);
+        assert_eq!(Frames::new(&ds).unwrap().frame(2).unwrap(), &b"EF"[..]);
+    }
+
+    #[test]
+    fn extended_offset_table_trims_padding() {
+        let mut ds = Image::new(1, 1, 1, 8).frames(2).encapsulated(vec![], &[b"ABC\0", b"DE"]);
+        let offsets: Vec<u8> = [0u64, 12].iter().flat_map(|o| o.to_le_bytes()).collect();
+        let lengths: Vec<u8> = [3u64, 2].iter().flat_map(|o| o.to_le_bytes()).collect();
+        ds.set_with_vr(&tags::ExtendedOffsetTable, Vr::OV, Value::Bytes(offsets.into())).unwrap();
+        ds.set_with_vr(&tags::ExtendedOffsetTableLengths, Vr::OV, Value::Bytes(lengths.into())).unwrap();
+        let frames = Frames::new(&ds).unwrap();
+        assert_eq!(frames.frame(0).unwrap(), &b"ABC"[..]);
+        assert_eq!(frames.frame(1).unwrap(), &b"DE"[..]);
+    }
+
+    #[test]
+    fn empty_offset_table_falls_back_to_markers() {
+        let ds = Image::new(1, 1, 1, 8)
+            .frames(2)
+            .encapsulated(vec![], &[b"\xFF\xD8\xFF\xE0", b"xx", b"\xFF\xD8\xFF\xDB", b"yy", b"zz"]);
+        let frames = Frames::new(&ds).unwrap();
+        assert_eq!(frames.frame(0).unwrap(), &b"\xFF\xD8\xFF\xE0xx"[..]);
+        assert_eq!(frames.frame(1).unwrap(), &b"\xFF\xD8\xFF\xDByyzz"[..]);
+
+        let ds = Image::new(1, 1, 1, 8).frames(2).encapsulated(vec![], &[b"AB", b"CD", b"EF"]);
+        assert!(Frames::new(&ds).is_err());
+    }
+}
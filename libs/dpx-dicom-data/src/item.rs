@@ -174,6 +174,30 @@ pub(crate) fn vr_for_write(tag: &Tag) -> Result<Vr> {
     }
 }
 
+/// Resolves the VR of an attribute read without one (DICOM JSON / XML) from
+/// the active tag dictionary, looking a private attribute up under `creator`.
+/// Unknown tags become `UN`.
+pub(crate) fn dictionary_vr(key: TagKey, creator: Option<&str>) -> Vr {
+    let tag = match creator {
+        Some(c) if key.is_private_attribute() => Tag::new_private_cow(key.group(), key.element(), c.trim()),
+        _ => Tag::new(key, None),
+    };
+    match tag.meta() {
+        Some(m) if m.vr.0 != Vr::Undefined => m.vr.0,
+        _ => Vr::UN,
+    }
+}
+
+/// Byte width of one word of a binary VR, for byte-order conversion.
+fn binary_word(vr: Vr) -> usize {
+    match vr {
+        Vr::OW => 2,
+        Vr::OF | Vr::OL => 4,
+        Vr::OD | Vr::OV => 8,
+        _ => 1,
+    }
+}
+
 impl Item {
     /// Borrows the raw bytes of an attribute when byte-backed, resolving
     /// `Mapped` slices against `master`. Used by `DataSet::sync_context`.
@@ -196,7 +220,7 @@ impl Item {
         Ok(tag.key)
     }
 
-    fn element_bytes<'a>(&'a self, shared: &'a Shared, el: &'a Element) -> Option<&'a [u8]> {
+    pub(crate) fn element_bytes<'a>(&'a self, shared: &'a Shared, el: &'a Element) -> Option<&'a [u8]> {
         match &el.value {
             Stored::Mapped(range) => shared.master().get(range.clone()),
             Stored::Owned(bytes) => Some(&bytes[..]),
@@ -206,7 +230,7 @@ impl Item {
         }
     }
 
-    fn element_value(&self, shared: &Shared, el: &Element) -> Result<Value> {
+    pub(crate) fn element_value(&self, shared: &Shared, el: &Element) -> Result<Value> {
         match &el.value {
             Stored::Native(v) => Ok(v.clone()),
             Stored::Owned(bytes) => convert::decode(shared, el.vr, bytes),
@@ -221,7 +245,7 @@ impl Item {
         }
     }
 
-    fn element_str<'a>(&'a self, shared: &'a Shared, el: &'a Element) -> Result<Cow<'a, str>> {
+    pub(crate) fn element_str<'a>(&'a self, shared: &'a Shared, el: &'a Element) -> Result<Cow<'a, str>> {
         if let Stored::Native(Value::Str(s)) = &el.value {
             return Ok(Cow::Borrowed(s.as_str()));
         }
@@ -231,6 +255,41 @@ impl Item {
         Ok(convert::decode_str(shared, el.vr, bytes))
     }
 
+    /// The text form of a primitive, non-binary value for the text formats
+    /// (JSON / XML): decoded through the charset when raw or a native string,
+    /// rendered in DICOM syntax for other typed natives.
+    pub(crate) fn element_text<'a>(&'a self, shared: &'a Shared, el: &'a Element) -> Result<Cow<'a, str>> {
+        match &el.value {
+            Stored::Native(v) if !matches!(v, Value::Str(_)) => {
+                let mut buf = Vec::new();
+                convert::encode(shared, true, el.vr, v, &mut buf)?;
+                Ok(Cow::Owned(String::from_utf8_lossy(&buf).into_owned()))
+            }
+            _ => self.element_str(shared, el),
+        }
+    }
+
+    /// The bytes of a binary-VR value in little-endian word order, as the text
+    /// formats carry them. `None` for a value with no byte form.
+    pub(crate) fn element_bytes_le<'a>(&'a self, shared: &'a Shared, el: &'a Element) -> Option<Cow<'a, [u8]>> {
+        let raw = self.element_bytes(shared, el)?;
+        let width = binary_word(el.vr);
+        if shared.is_little_endian() || width == 1 {
+            return Some(Cow::Borrowed(raw));
+        }
+        let mut v = raw.to_vec();
+        v.chunks_exact_mut(width).for_each(<[u8]>::reverse);
+        Some(Cow::Owned(v))
+    }
+
+    /// The creator string of the Private Creator element reserving the block
+    /// of private attribute `key`, if present in this item.
+    pub(crate) fn private_creator(&self, shared: &Shared, key: TagKey) -> Option<String> {
+        let el = self.map.get(key.to_private_reservation())?;
+        let text = self.element_text(shared, el).ok()?;
+        Some(convert::trim_text(Vr::LO, &text).to_owned())
+    }
+
     pub(crate) fn value(&self, shared: &Shared, tag: &Tag) -> Result<Value> {
         let key = self.resolve_read_key(shared, tag)?;
         let el = self.map.get(key).ok_or_else(|| dicom_err!(NotFound, "attribute {tag} not found"))?;
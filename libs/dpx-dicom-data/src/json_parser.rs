@@ -0,0 +1,454 @@
+//! DICOM JSON Model (PS3.18 Annex F) parser.
+//!
+//! The JSON sibling of [`dcm_parser`](crate::dcm_parser). Attributes map to the
+//! same storage forms the binary parser produces: ASCII-only VRs become raw
+//! bytes, translatable text becomes a native Unicode string (encoded with the
+//! root charset on write, with (0008,0005) auto-recorded as for
+//! [`DataSet::set`]), numbers become typed values, and `"InlineBinary"` is
+//! decoded to little-endian bytes. A `"BulkDataURI"` is kept as a
+//! [`Value::BulkData`] placeholder for the caller to resolve.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::Bytes;
+use serde_json::{Map, Number, Value as Json};
+
+use dpx_dicom_core::error::{ErrContext, IntoDicomErr, Result};
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{Tag, TagKey, TransferSyntax, Vr, dicom_err, ensure, tags};
+
+use crate::dataset::{DataSet, DatasetKind};
+use crate::convert::{self, parse_tag_hex};
+use crate::item::{ElementMap, Item, dictionary_vr};
+use crate::value::{Element, OneOrMany, PixelData, Stored, Value};
+
+/// Configurable DICOM JSON reader. Set parameters with the builder methods,
+/// then call one of the `parse_*` entry points.
+#[derive(Debug, Clone, Default)]
+pub struct JsonReader {
+    xfer: Option<&'static TransferSyntax>,
+}
+
+impl JsonReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Transfer syntax the JSON was produced from. Only decides whether an
+    /// inline Pixel Data value is encapsulated; by default it is taken from
+    /// (0002,0010) when present, else native. Binary values in the JSON model
+    /// are always little endian, so a big-endian syntax is rejected.
+    pub fn transfer_syntax(mut self, ts: &'static TransferSyntax) -> Self {
+        self.xfer = Some(ts);
+        self
+    }
+
+    // --- Entry points ------------------------------------------------------
+
+    /// Parses one data set from a JSON object.
+    pub fn parse_str(&self, json: &str) -> Result<DataSet> {
+        self.parse_slice(json.as_bytes())
+    }
+
+    /// Parses one data set from a UTF-8 JSON object.
+    pub fn parse_slice(&self, json: &[u8]) -> Result<DataSet> {
+        let tree = serde_json::from_slice(json).map_err(|e| dicom_err!(InvalidData, "invalid DICOM JSON: {e}"))?;
+        self.dataset(&tree)
+    }
+
+    /// Reads one data set from a JSON object in any reader.
+    pub fn parse_bufreader<R: Read>(&self, reader: R) -> Result<DataSet> {
+        let tree = serde_json::from_reader(reader).map_err(|e| dicom_err!(InvalidData, "invalid DICOM JSON: {e}"))?;
+        self.dataset(&tree)
+    }
+
+    /// Opens a file and reads one data set from it.
+    pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<DataSet> {
+        let path = path.as_ref();
+        let file = File::open(path).to_dicom_err_with(|| format!("opening {}", path.display()))?;
+        self.parse_bufreader(BufReader::new(file))
+    }
+
+    /// Reads a list of data sets: a JSON array (a DICOMweb metadata response)
+    /// or a single object.
+    pub fn parse_list<R: Read>(&self, reader: R) -> Result<Vec<DataSet>> {
+        let tree: Json =
+            serde_json::from_reader(reader).map_err(|e| dicom_err!(InvalidData, "invalid DICOM JSON: {e}"))?;
+        match &tree {
+            Json::Array(list) => list.iter().map(|ds| self.dataset(ds)).collect(),
+            _ => Ok(vec![self.dataset(&tree)?]),
+        }
+    }
+
+    // --- Orchestration -----------------------------------------------------
+
+    fn dataset(&self, tree: &Json) -> Result<DataSet> {
+        let obj = tree.as_object().ok_or_else(|| dicom_err!(InvalidData, "DICOM JSON data set is not an object"))?;
+        let ts = match self.xfer {
+            Some(ts) => ts,
+            None => declared_transfer_syntax(obj).unwrap_or(&TransferSyntax::ExplicitVRLittleEndian),
+        };
+        ensure!(
+            ts.is_little_endian,
+            UnsupportedFeature,
+            "DICOM JSON binary values are little endian; cannot read under {}",
+            ts.uid
+        );
+        let mut decoder = Decoder { encapsulated: ts.is_encapsulated, non_ascii: None };
+        let root = decoder.item(obj)?;
+
+        let mut ds = DataSet::parsed(Bytes::new(), ts, DatasetKind::Dataset);
+        *ds.root_mut() = root;
+        ds.sync_context()?;
+        if let Some(text) = decoder.non_ascii {
+            ds.stamp_charset_for(&text);
+        }
+        Ok(ds)
+    }
+}
+
+/// The transfer syntax named by a (0002,0010) attribute in the JSON, if any.
+fn declared_transfer_syntax(obj: &Map<String, Json>) -> Option<&'static TransferSyntax> {
+    let uid = obj.get("00020010")?.get("Value")?.get(0)?.as_str()?;
+    TransferSyntax::from_uid(uid.trim_end_matches(['\0', ' ']))
+}
+
+/// Per-data-set decoding state.
+struct Decoder {
+    /// Pixel Data `InlineBinary` carries an encapsulated Value Field.
+    encapsulated: bool,
+    /// The first non-ASCII translatable text seen, for (0008,0005) stamping.
+    non_ascii: Option<String>,
+}
+
+impl Decoder {
+    fn item(&mut self, obj: &Map<String, Json>) -> Result<Item> {
+        let mut map = ElementMap::with_capacity(obj.len());
+        for (hex, attr) in obj {
+            let key = parse_tag_hex(hex)?;
+            let attr = attr
+                .as_object()
+                .ok_or_else(|| dicom_err!(InvalidData, "DICOM JSON attribute {hex} is not an object"))?;
+            let vr = match attr.get("vr") {
+                Some(Json::String(vr)) => Vr::try_from(vr.as_str())?,
+                Some(_) => {
+                    return Err(dicom_err!(InvalidData, "DICOM JSON attribute {hex} has a non-string \"vr\""));
+                }
+                None => {
+                    let creator = key.is_private_attribute().then(|| private_creator(obj, key)).flatten();
+                    dictionary_vr(key, creator)
+                }
+            };
+            let value = self.attribute(key, vr, attr).err_context_with(|| format!("DICOM JSON attribute {hex}"))?;
+            map.insert(key, Element::new(vr, value));
+        }
+        Ok(Item::from_map(map))
+    }
+
+    fn attribute(&mut self, key: TagKey, vr: Vr, attr: &Map<String, Json>) -> Result<Stored> {
+        if let Some(uri) = attr.get("BulkDataURI") {
+            let uri = uri.as_str().ok_or_else(|| dicom_err!(InvalidData, "\"BulkDataURI\" is not a string"))?;
+            return Ok(Stored::Native(Value::BulkData(uri.to_owned())));
+        }
+        if let Some(b64) = attr.get("InlineBinary") {
+            let b64 = b64.as_str().ok_or_else(|| dicom_err!(InvalidData, "\"InlineBinary\" is not a string"))?;
+            let bytes = Bytes::from(BASE64.decode(b64).map_err(|e| dicom_err!(InvalidData, "invalid base64: {e}"))?);
+            if key == tags::PixelData.key && self.encapsulated {
+                let px = PixelData::from_encapsulated_field(bytes)?;
+                return Ok(Stored::Native(Value::Pixels(Box::new(px))));
+            }
+            return Ok(Stored::Owned(bytes));
+        }
+        match attr.get("Value") {
+            None | Some(Json::Null) => {
+                Ok(if vr == Vr::SQ { Stored::Items(Vec::new()) } else { Stored::Owned(Bytes::new()) })
+            }
+            Some(Json::Array(values)) if values.is_empty() => {
+                Ok(if vr == Vr::SQ { Stored::Items(Vec::new()) } else { Stored::Owned(Bytes::new()) })
+            }
+            Some(Json::Array(values)) => self.values(vr, values),
+            Some(_) => Err(dicom_err!(InvalidData, "\"Value\" is not an array")),
+        }
+    }
+
+    fn values(&mut self, vr: Vr, values: &[Json]) -> Result<Stored> {
+        match vr.info().kind {
+            Kind::Items => {
+                let mut items = Vec::with_capacity(values.len());
+                for v in values {
+                    items.push(match v {
+                        Json::Object(obj) => self.item(obj)?,
+                        Json::Null => Item::default(),
+                        _ => return Err(dicom_err!(InvalidData, "sequence item is not an object")),
+                    });
+                }
+                Ok(Stored::Items(items))
+            }
+            _ if vr == Vr::AT => {
+                let tags = values
+                    .iter()
+                    .map(|v| {
+                        let hex = v.as_str().ok_or_else(|| dicom_err!(InvalidData, "AT value is not a string"))?;
+                        Ok(Tag::new(parse_tag_hex(hex)?, None))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Stored::Native(Value::Tags(one_or_many(tags))))
+            }
+            Kind::Text { translatable, .. } => {
+                ensure!(
+                    values.len() == 1 || convert::is_multi_valued_text(vr),
+                    InvalidData,
+                    "VR {vr} holds a single value, got {}",
+                    values.len()
+                );
+                let tokens = values.iter().map(|v| text_token(vr, v)).collect::<Result<Vec<_>>>()?;
+                let text = tokens.join("\\");
+                if translatable {
+                    if self.non_ascii.is_none() && !text.is_ascii() {
+                        self.non_ascii = Some(text.clone());
+                    }
+                    Ok(Stored::Native(Value::Str(text)))
+                } else {
+                    Ok(Stored::Owned(Bytes::from(text)))
+                }
+            }
+            Kind::U16 | Kind::U32 | Kind::U64 => {
+                let v = values.iter().map(|v| number(v, Json::as_u64, vr)).collect::<Result<Vec<_>>>()?;
+                Ok(Stored::Native(Value::UInt(one_or_many(v))))
+            }
+            Kind::I16 | Kind::I32 | Kind::I64 => {
+                let v = values.iter().map(|v| number(v, Json::as_i64, vr)).collect::<Result<Vec<_>>>()?;
+                Ok(Stored::Native(Value::Int(one_or_many(v))))
+            }
+            Kind::F32 | Kind::F64 => {
+                let v = values.iter().map(|v| number(v, Json::as_f64, vr)).collect::<Result<Vec<_>>>()?;
+                Ok(Stored::Native(Value::Float(one_or_many(v))))
+            }
+            Kind::Bytes | Kind::Invalid => {
+                Err(dicom_err!(InvalidData, "VR {vr} must use \"InlineBinary\" or \"BulkDataURI\""))
+            }
+        }
+    }
+}
+
+/// The value of the Private Creator element reserving `key`'s block, looked up
+/// among the sibling attributes of the same JSON object.
+fn private_creator(obj: &Map<String, Json>, key: TagKey) -> Option<&str> {
+    let hex = convert::tag_hex(key.to_private_reservation());
+    obj.get(&hex)?.get("Value")?.get(0)?.as_str()
+}
+
+/// One text value in DICOM syntax: `null` is empty, `IS`/`DS` accept numbers,
+/// `PN` joins its component groups with `'='`.
+fn text_token(vr: Vr, v: &Json) -> Result<String> {
+    match v {
+        Json::Null => Ok(String::new()),
+        Json::String(s) => Ok(s.clone()),
+        Json::Number(n) if vr == Vr::IS => integer_string(n),
+        Json::Number(n) if vr == Vr::DS => Ok(decimal_string(n)),
+        Json::Object(pn) if vr == Vr::PN => {
+            let groups =
+                ["Alphabetic", "Ideographic", "Phonetic"].map(|g| pn.get(g).and_then(Json::as_str).unwrap_or(""));
+            let used = groups.iter().rposition(|g| !g.is_empty()).map_or(0, |i| i + 1);
+            Ok(groups[..used].join("="))
+        }
+        _ => Err(dicom_err!(InvalidData, "unexpected JSON value {v} for VR {vr}")),
+    }
+}
+
+/// An `IS` number as an integer: `4.0` and `1e3` are integral, `4.5` is not.
+fn integer_string(n: &Number) -> Result<String> {
+    n.as_i64()
+        .or_else(|| n.as_f64().filter(|v| v.fract() == 0.0 && v.abs() < i64::MAX as f64).map(|v| v as i64))
+        .map(|v| v.to_string())
+        .ok_or_else(|| dicom_err!(InvalidData, "IS value {n} is not an integer"))
+}
+
+/// A `DS` number in its shortest form, or rounded to the most significant
+/// digits that fit the 16 bytes of `DS`, in decimal or exponent notation.
+fn decimal_string(n: &Number) -> String {
+    let text = n.to_string();
+    let Some(v) = n.as_f64().filter(|_| text.len() > 16) else {
+        return text;
+    };
+    (1..=17)
+        .rev()
+        .flat_map(|digits| {
+            let exponent = format!("{v:.*e}", digits - 1);
+            let (mantissa, power) = exponent.split_once('e').unwrap_or((&exponent, "0"));
+            // `v` is not zero, so a one-digit mantissa has no zero to trim.
+            let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+            let decimal = exponent.parse::<f64>().map(|v| v.to_string()).unwrap_or_default();
+            [decimal, format!("{mantissa}e{power}")]
+        })
+        .find(|s| !s.is_empty() && s.len() <= 16)
+        .unwrap_or(text)
+}
+
+/// One binary numeric value; `SV`/`UV` beyond the exact JSON range arrive as
+/// strings.
+fn number<T: std::str::FromStr>(v: &Json, get: fn(&Json) -> Option<T>, vr: Vr) -> Result<T> {
+    match v {
+        Json::String(s) => s.trim().parse().ok(),
+        _ => get(v),
+    }
+    .ok_or_else(|| dicom_err!(InvalidData, "invalid {vr} value {v}"))
+}
+
+fn one_or_many<T>(mut v: Vec<T>) -> OneOrMany<T> {
+    if v.len() == 1 { OneOrMany::One(v.swap_remove(0)) } else { OneOrMany::Many(v) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DcmReader, DcmWriter, HeaderType, JsonWriter};
+    use dpx_dicom_core::DicomDate;
+
+    #[test]
+    fn reads_standard_example() {
+        let ds = JsonReader::new()
+            .parse_str(
+                r#"{
+                "00080005": {"vr": "CS", "Value": ["ISO_IR 192"]},
+                "00080020": {"vr": "DA", "Value": ["20130409"]},
+                "00080061": {"vr": "CS", "Value": ["CT", "PET"]},
+                "00100010": {"vr": "PN", "Value": [{"Alphabetic": "Wang^XiaoDong", "Ideographic": "王^小東"}]},
+                "00201206": {"vr": "IS", "Value": [4]},
+                "00280030": {"vr": "DS", "Value": [0.5, "0.25"]},
+                "00280010": {"vr": "US", "Value": [512]},
+                "00280009": {"vr": "AT", "Value": ["00181063"]},
+                "00081115": {"vr": "SQ", "Value": [{"0020000E": {"vr": "UI", "Value": ["1.2.3"]}}]}
+            }"#,
+            )
+            .expect("parse");
+        assert_eq!(ds.charset().specific_character_set(), "ISO_IR 192");
+        assert_eq!(ds.get::<DicomDate>(&tags::StudyDate).unwrap(), DicomDate::from_dicom(b"20130409").unwrap());
+        assert_eq!(ds.get_all::<String>(&tags::ModalitiesInStudy).unwrap(), ["CT", "PET"]);
+        assert_eq!(ds.get::<String>(&tags::PatientName).unwrap(), "Wang^XiaoDong=王^小東");
+        assert_eq!(ds.get::<i64>(&tags::NumberOfStudyRelatedSeries).unwrap(), 4);
+        assert_eq!(ds.get_all::<f64>(&tags::PixelSpacing).unwrap(), [0.5, 0.25]);
+        assert_eq!(ds.get::<u16>(&tags::Rows).unwrap(), 512);
+        assert_eq!(ds.vr(&tags::FrameIncrementPointer), Some(Vr::AT));
+        let seq = ds.sequence(&tags::ReferencedSeriesSequence).expect("sequence");
+        assert_eq!(seq.item(0).unwrap().get::<String>(&tags::SeriesInstanceUID).unwrap(), "1.2.3");
+    }
+
+    #[test]
+    fn numbers_fit_integer_and_decimal_strings() {
+        let ds = JsonReader::new()
+            .parse_str(
+                r#"{
+                "00201206": {"vr": "IS", "Value": [4.0]},
+                "00201208": {"vr": "IS", "Value": [1e3]},
+                "00280030": {"vr": "DS", "Value": [0.30000000000000004, -1.2345678901234567e-300]},
+                "00281050": {"vr": "DS", "Value": [123456789012345678]}
+            }"#,
+            )
+            .expect("parse");
+        let text = |tag| ds.get_bytes(tag).unwrap();
+        assert_eq!(text(&tags::NumberOfStudyRelatedSeries), b"4");
+        assert_eq!(text(&tags::NumberOfStudyRelatedInstances), b"1000");
+        assert_eq!(text(&tags::PixelSpacing), b"0.3\\-1.23456789e-300");
+        assert_eq!(text(&tags::WindowCenter), b"1.23456789012e17");
+        assert!(ds.validate_values().is_valid(), "{}", ds.validate_values());
+    }
+
+    #[test]
+    fn missing_vr_resolves_through_dictionary() {
+        let ds = JsonReader::new()
+            .parse_str(r#"{"00100020": {"Value": ["ID-1"]}, "00280011": {"Value": [256]}, "00091001": {}}"#)
+            .expect("parse");
+        assert_eq!(ds.vr(&tags::PatientID), Some(Vr::LO));
+        assert_eq!(ds.vr(&tags::Columns), Some(Vr::US));
+        assert_eq!(ds.vr(&Tag::new_standard(0x0009, 0x1001)), Some(Vr::UN));
+    }
+
+    #[test]
+    fn inline_binary_and_bulk_data() {
+        let ds = JsonReader::new()
+            .parse_str(
+                r#"{
+                "00420011": {"vr": "OB", "InlineBinary": "JVBERg=="},
+                "7FE00010": {"vr": "OW", "BulkDataURI": "https://pacs/bulk/1"}
+            }"#,
+            )
+            .expect("parse");
+        assert_eq!(ds.get_bytes(&tags::EncapsulatedDocument).unwrap(), b"%PDF");
+        assert!(matches!(ds.value(&tags::PixelData).unwrap(), Value::BulkData(uri) if uri == "https://pacs/bulk/1"));
+        // An unresolved reference cannot be serialized to the binary form.
+        assert!(DcmWriter::new().to_bytes(&ds).is_err());
+    }
+
+    #[test]
+    fn encapsulated_pixel_data_roundtrip() {
+        let mut ds = DataSet::new();
+        let px = PixelData::Encapsulated {
+            bot: vec![0],
+            fragments: vec![Bytes::from_static(&[0xFF, 0xD8, 0xFF, 0xD9])],
+            eot: vec![],
+        };
+        ds.set_with_vr(&tags::PixelData, Vr::OB, Value::Pixels(Box::new(px))).unwrap();
+        let json = JsonWriter::new().to_string(&ds).unwrap();
+        let back = JsonReader::new().transfer_syntax(&TransferSyntax::JPEGBaseline8Bit).parse_str(&json).unwrap();
+        match back.value(&tags::PixelData).unwrap() {
+            Value::Pixels(px) => match *px {
+                PixelData::Encapsulated { bot, fragments, .. } => {
+                    assert_eq!(bot, [0]);
+                    assert_eq!(&fragments[0][..], &[0xFF, 0xD8, 0xFF, 0xD9]);
+                }
+                PixelData::Native(_) => panic!("expected encapsulated pixel data"),
+            },
+            other => panic!("expected pixel data, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn binary_roundtrip_through_json() {
+        let mut b = Vec::new();
+        for (tag, vr, val) in [
+            ([0x10u8, 0x00, 0x10, 0x00], b"PN", &b"Doe^John"[..]),
+            ([0x10, 0x00, 0x30, 0x00], b"DA", b"19700101"),
+            ([0x28, 0x00, 0x10, 0x00], b"US", &512u16.to_le_bytes()),
+            ([0x28, 0x00, 0x30, 0x00], b"DS", b"0.5\\0.5 "),
+        ] {
+            b.extend_from_slice(&tag);
+            b.extend_from_slice(vr);
+            b.extend_from_slice(&(val.len() as u16).to_le_bytes());
+            b.extend_from_slice(val);
+        }
+        let ds =
+            DcmReader::new().header(HeaderType::NoHeader).parse_bytes(Bytes::from(b.clone())).unwrap().dataset.unwrap();
+        let json = JsonWriter::new().to_string(&ds).unwrap();
+        let back = JsonReader::new().parse_str(&json).unwrap();
+        assert_eq!(back.get::<String>(&tags::PatientName).unwrap(), "Doe^John");
+        assert_eq!(back.get_str(&tags::PatientBirthDate).unwrap(), "19700101");
+        assert_eq!(back.get::<u16>(&tags::Rows).unwrap(), 512);
+        assert_eq!(&DcmWriter::new().to_bytes(&back).unwrap()[..], &b[..]);
+    }
+
+    #[test]
+    fn non_ascii_text_records_the_charset() {
+        let ds =
+            JsonReader::new().parse_str(r#"{"00100010": {"vr": "PN", "Value": [{"Alphabetic": "Müller"}]}}"#).unwrap();
+        assert_eq!(ds.get::<String>(&tags::PatientName).unwrap(), "Müller");
+        assert_eq!(ds.get::<String>(&tags::SpecificCharacterSet).unwrap(), "ISO_IR 192");
+    }
+
+    #[test]
+    fn lists_and_errors() {
+        let list =
+            JsonReader::new().parse_list(&br#"[{"00100020": {"vr": "LO", "Value": ["A"]}}, {}]"#[..]).expect("list");
+        assert_eq!(list.len(), 2);
+        assert!(list[1].is_empty());
+        assert!(JsonReader::new().parse_str(r#"{"0010": {}}"#).is_err());
+        assert!(JsonReader::new().parse_str(r#"{"00420011": {"vr": "OB", "Value": [1]}}"#).is_err());
+        assert!(JsonReader::new().parse_str(r#"{"00280010": {"vr": "US", "Value": [null]}}"#).is_err());
+        assert!(JsonReader::new().parse_str(r#"{"00201206": {"vr": "IS", "Value": [4.5]}}"#).is_err());
+        assert!(JsonReader::new().transfer_syntax(&TransferSyntax::ExplicitVRBigEndian).parse_str("{}").is_err());
+    }
+}
@@ -0,0 +1,346 @@
+//! DICOM JSON Model (PS3.18 Annex F) serializer.
+//!
+//! The JSON sibling of [`dcm_writer`](crate::dcm_writer): a data set becomes one
+//! object keyed by `"GGGGEEEE"`, each attribute carrying its `"vr"` plus a
+//! `"Value"` array, an `"InlineBinary"` base64 string or a `"BulkDataURI"`.
+//! Text is decoded through the root charset, so the output is always UTF-8;
+//! binary values are written in little-endian byte order as the model requires.
+
+use std::io::Write;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{Map, Number, Value as Json};
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{Vr, dicom_err};
+
+use crate::DataSet;
+use crate::convert;
+use crate::dataset::Shared;
+use crate::item::Item;
+use crate::value::{Element, Stored, Value};
+
+/// Largest magnitude a JSON number carries exactly (IEEE-754 double mantissa).
+/// `SV`/`UV` values beyond it are written as strings, as PS3.18 F.2.3 allows.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Serializes a [`DataSet`] to the DICOM JSON Model.
+///
+/// Binary values (`OB OD OF OL OV OW UN` and pixel data) are inlined as base64
+/// unless [`bulk_data_uri`](Self::bulk_data_uri) moves large ones out of line.
+/// A [`Value::BulkData`] placeholder read from JSON is written back as its URI.
+#[derive(Debug, Clone, Default)]
+pub struct JsonWriter {
+    pretty: bool,
+    /// URI prefix and the byte length above which a binary value is referenced
+    /// rather than inlined.
+    bulk_data: Option<(String, usize)>,
+}
+
+impl JsonWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indent the output for humans (default: compact).
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Writes binary values longer than `threshold` bytes as a `"BulkDataURI"`
+    /// of `prefix` followed by the attribute path (`7FE00010`, or
+    /// `00081115/0/00081150` inside a sequence) instead of inline base64. The
+    /// caller serves the bytes at those URIs.
+    pub fn bulk_data_uri(mut self, prefix: impl Into<String>, threshold: usize) -> Self {
+        self.bulk_data = Some((prefix.into(), threshold));
+        self
+    }
+
+    /// Writes one data set as a JSON object.
+    pub fn write_dataset<W: Write>(&self, ds: &DataSet, w: W) -> Result<()> {
+        let json = Json::Object(self.dataset(ds)?);
+        self.emit(&json, w)
+    }
+
+    /// Writes several data sets as a JSON array, the shape of a DICOMweb
+    /// QIDO-RS / WADO-RS metadata response.
+    pub fn write_datasets<'a, W: Write>(&self, datasets: impl IntoIterator<Item = &'a DataSet>, w: W) -> Result<()> {
+        let list = datasets.into_iter().map(|ds| self.dataset(ds).map(Json::Object)).collect::<Result<Vec<_>>>()?;
+        self.emit(&Json::Array(list), w)
+    }
+
+    /// Serializes one data set to a JSON string.
+    pub fn to_string(&self, ds: &DataSet) -> Result<String> {
+        let mut buf = Vec::new();
+        self.write_dataset(ds, &mut buf)?;
+        String::from_utf8(buf).map_err(|_| dicom_err!(Internal, "JSON output is not UTF-8"))
+    }
+
+    fn dataset(&self, ds: &DataSet) -> Result<Map<String, Json>> {
+        let (shared, root) = ds.context();
+        let encoder = Encoder { shared, bulk_data: self.bulk_data.as_ref().map(|(p, t)| (p.as_str(), *t)) };
+        encoder.item(root, "")
+    }
+
+    fn emit<W: Write>(&self, json: &Json, w: W) -> Result<()> {
+        let written = if self.pretty { serde_json::to_writer_pretty(w, json) } else { serde_json::to_writer(w, json) };
+        written.map_err(|e| dicom_err!(Io, "writing DICOM JSON: {e}"))
+    }
+}
+
+/// Per-data-set encoding state: the root context the values are read under.
+struct Encoder<'a> {
+    shared: &'a Shared,
+    bulk_data: Option<(&'a str, usize)>,
+}
+
+impl Encoder<'_> {
+    /// Encodes `item`; `path` is the bulk-data path of its parent item, either
+    /// empty (root) or ending with `/`.
+    fn item(&self, item: &Item, path: &str) -> Result<Map<String, Json>> {
+        let mut obj = Map::with_capacity(item.map.len());
+        for (key, el) in item.map.entries() {
+            let hex = convert::tag_hex(*key);
+            let attr = self.attribute(item, el, &format!("{path}{hex}"))?;
+            obj.insert(hex, Json::Object(attr));
+        }
+        Ok(obj)
+    }
+
+    fn attribute(&self, item: &Item, el: &Element, path: &str) -> Result<Map<String, Json>> {
+        let mut obj = Map::new();
+        obj.insert("vr".into(), Json::String(el.vr.keyword().into()));
+        match &el.value {
+            Stored::Items(items) => {
+                let values = items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, it)| self.item(it, &format!("{path}/{i}/")).map(Json::Object))
+                    .collect::<Result<Vec<_>>>()?;
+                if !values.is_empty() {
+                    obj.insert("Value".into(), Json::Array(values));
+                }
+            }
+            Stored::Native(Value::BulkData(uri)) => {
+                obj.insert("BulkDataURI".into(), Json::String(uri.clone()));
+            }
+            Stored::Native(Value::Pixels(px)) => self.binary(&mut obj, path, &px.value_field()),
+            _ if matches!(el.vr.info().kind, Kind::Bytes) => {
+                let bytes = item
+                    .element_bytes_le(self.shared, el)
+                    .ok_or_else(|| dicom_err!(InvalidData, "binary attribute {path} holds a non-binary value"))?;
+                self.binary(&mut obj, path, &bytes);
+            }
+            _ => {
+                let values = self.values(item, el)?;
+                if !values.is_empty() {
+                    obj.insert("Value".into(), Json::Array(values));
+                }
+            }
+        }
+        Ok(obj)
+    }
+
+    fn binary(&self, obj: &mut Map<String, Json>, path: &str, bytes: &[u8]) {
+        match self.bulk_data {
+            Some((prefix, threshold)) if bytes.len() > threshold => {
+                obj.insert("BulkDataURI".into(), Json::String(format!("{prefix}{path}")));
+            }
+            _ if bytes.is_empty() => {}
+            _ => {
+                obj.insert("InlineBinary".into(), Json::String(BASE64.encode(bytes)));
+            }
+        }
+    }
+
+    /// The `"Value"` array of a primitive attribute; empty for a zero-length value.
+    fn values(&self, item: &Item, el: &Element) -> Result<Vec<Json>> {
+        let vr = el.vr;
+        if item.element_bytes(self.shared, el).is_some_and(<[u8]>::is_empty) {
+            return Ok(Vec::new());
+        }
+        match vr.info().kind {
+            _ if vr == Vr::AT => match item.element_value(self.shared, el)? {
+                Value::Tags(tags) => Ok(tags.iter().map(|t| Json::String(convert::tag_hex(t.key))).collect()),
+                _ => Err(dicom_err!(InvalidData, "AT value is not an attribute tag")),
+            },
+            Kind::Text { .. } => {
+                let text = item.element_text(self.shared, el)?;
+                if !convert::is_multi_valued_text(vr) {
+                    return Ok(vec![Json::String(convert::trim_text(vr, &text).to_owned())]);
+                }
+                text.split('\\').map(|token| text_value(vr, convert::trim_text(vr, token))).collect()
+            }
+            Kind::U16 | Kind::U32 | Kind::U64 => {
+                let value = item.element_value(self.shared, el)?;
+                Ok(convert::numbers::<u64>(&value)
+                    .map(|v| if v > MAX_SAFE_INTEGER { Json::String(v.to_string()) } else { Json::from(v) })
+                    .collect())
+            }
+            Kind::I16 | Kind::I32 | Kind::I64 => {
+                let value = item.element_value(self.shared, el)?;
+                Ok(convert::numbers::<i64>(&value)
+                    .map(
+                        |v| {
+                            if v.unsigned_abs() > MAX_SAFE_INTEGER {
+                                Json::String(v.to_string())
+                            } else {
+                                Json::from(v)
+                            }
+                        },
+                    )
+                    .collect())
+            }
+            Kind::F32 | Kind::F64 => {
+                let value = item.element_value(self.shared, el)?;
+                convert::numbers::<f64>(&value)
+                    .map(|v| {
+                        Number::from_f64(v)
+                            .map(Json::Number)
+                            .ok_or_else(|| dicom_err!(InvalidData, "non-finite {vr} value {v}"))
+                    })
+                    .collect()
+            }
+            Kind::Bytes | Kind::Items | Kind::Invalid => Err(dicom_err!(InvalidData, "VR {vr} has no JSON Value form")),
+        }
+    }
+}
+
+/// One value of a multi-valued text attribute: a string, a number for `IS`/`DS`,
+/// a Person Name object for `PN`, and `null` when empty.
+fn text_value(vr: Vr, token: &str) -> Result<Json> {
+    if token.is_empty() {
+        return Ok(Json::Null);
+    }
+    Ok(match vr {
+        Vr::IS => Json::from(token.parse::<i64>().map_err(|_| dicom_err!(InvalidData, "invalid IS value {token:?}"))?),
+        Vr::DS => {
+            let v = token.parse::<f64>().map_err(|_| dicom_err!(InvalidData, "invalid DS value {token:?}"))?;
+            Json::Number(Number::from_f64(v).ok_or_else(|| dicom_err!(InvalidData, "non-finite DS value {token:?}"))?)
+        }
+        Vr::PN => {
+            let mut obj = Map::new();
+            for (group, name) in token.split('=').zip(["Alphabetic", "Ideographic", "Phonetic"]) {
+                if !group.is_empty() {
+                    obj.insert(name.into(), Json::String(group.to_owned()));
+                }
+            }
+            if obj.is_empty() { Json::Null } else { Json::Object(obj) }
+        }
+        _ => Json::String(token.to_owned()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use dpx_dicom_core::{Tag, tags};
+
+    use crate::dataset::DatasetKind;
+    use crate::value::{OneOrMany, PixelData};
+    use dpx_dicom_core::TransferSyntax;
+
+    fn json(ds: &DataSet) -> Json {
+        serde_json::from_str(&JsonWriter::new().to_string(ds).expect("write")).expect("valid JSON")
+    }
+
+    #[test]
+    fn writes_text_numbers_and_tags() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PatientID, "ID-1").unwrap();
+        ds.set(&tags::ImageType, "ORIGINAL\\\\AXIAL").unwrap();
+        ds.set(&tags::Rows, 512u16).unwrap();
+        ds.set_value(&tags::PixelSpacing, Value::Float(OneOrMany::Many(vec![0.5, 0.25]))).unwrap();
+        ds.set_value(&tags::FrameIncrementPointer, Value::Tags(OneOrMany::One(tags::FrameTime.clone()))).unwrap();
+        let j = json(&ds);
+        assert_eq!(j["00100020"], serde_json::json!({"vr": "LO", "Value": ["ID-1"]}));
+        assert_eq!(j["00080008"]["Value"], serde_json::json!(["ORIGINAL", null, "AXIAL"]));
+        assert_eq!(j["00280010"]["Value"], serde_json::json!([512]));
+        assert_eq!(j["00280030"]["Value"], serde_json::json!([0.5, 0.25]));
+        assert_eq!(j["00280009"]["Value"], serde_json::json!(["00181063"]));
+
+        // JSON has no NaN or infinity to write.
+        ds.set_value(&tags::RecommendedDisplayFrameRateInFloat, Value::Float(OneOrMany::One(f64::NAN))).unwrap();
+        assert!(JsonWriter::new().to_string(&ds).is_err());
+    }
+
+    #[test]
+    fn writes_person_name_groups() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PatientName, "Yamada^Tarou=山田^太郎=やまだ^たろう\\Doe^John").unwrap();
+        let j = json(&ds);
+        assert_eq!(
+            j["00100010"]["Value"],
+            serde_json::json!([
+                {"Alphabetic": "Yamada^Tarou", "Ideographic": "山田^太郎", "Phonetic": "やまだ^たろう"},
+                {"Alphabetic": "Doe^John"}
+            ])
+        );
+    }
+
+    #[test]
+    fn writes_sequences_and_empty_values() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::AccessionNumber, "").unwrap();
+        {
+            let mut seq = ds.sequence_mut(&tags::ReferencedSeriesSequence).unwrap();
+            seq.new_item().set(&tags::SeriesInstanceUID, "1.2.3").unwrap();
+        }
+        let j = json(&ds);
+        assert_eq!(j["00080050"], serde_json::json!({"vr": "SH"}));
+        assert_eq!(
+            j["00081115"],
+            serde_json::json!({"vr": "SQ", "Value": [{"0020000E": {"vr": "UI", "Value": ["1.2.3"]}}]})
+        );
+    }
+
+    #[test]
+    fn writes_inline_binary_and_bulk_data() {
+        let mut ds = DataSet::new();
+        ds.set_with_vr(
+            &tags::PixelData,
+            Vr::OW,
+            Value::Pixels(Box::new(PixelData::Native(Bytes::from_static(&[1, 2, 3, 4])))),
+        )
+        .unwrap();
+        ds.set_with_vr(&tags::EncapsulatedDocument, Vr::OB, Value::Bytes(Bytes::from_static(b"%PDF"))).unwrap();
+        let j = json(&ds);
+        assert_eq!(j["7FE00010"], serde_json::json!({"vr": "OW", "InlineBinary": "AQIDBA=="}));
+
+        let out = JsonWriter::new().bulk_data_uri("https://pacs/bulk/", 3).to_string(&ds).unwrap();
+        let j: Json = serde_json::from_str(&out).unwrap();
+        assert_eq!(j["7FE00010"]["BulkDataURI"], "https://pacs/bulk/7FE00010");
+        assert_eq!(j["00420011"]["BulkDataURI"], "https://pacs/bulk/00420011");
+    }
+
+    #[test]
+    fn big_endian_binary_is_written_little_endian() {
+        let mut ds = DataSet::parsed(Bytes::new(), &TransferSyntax::ExplicitVRBigEndian, DatasetKind::Dataset);
+        ds.root_mut().map.insert(
+            tags::RedPaletteColorLookupTableData.key,
+            Element::new(Vr::OW, Stored::Owned(Bytes::from_static(&[0x01, 0x02]))),
+        );
+        ds.root_mut()
+            .map
+            .insert(tags::Columns.key, Element::new(Vr::US, Stored::Owned(Bytes::from_static(&[0x01, 0x02]))));
+        let j = json(&ds);
+        assert_eq!(j["00281201"]["InlineBinary"], "AgE=");
+        assert_eq!(j["00280011"]["Value"], serde_json::json!([258]));
+    }
+
+    #[test]
+    fn private_tags_and_dataset_lists() {
+        let mut ds = DataSet::new();
+        ds.set_with_vr(&Tag::new_standard(0x0009, 0x0010), Vr::LO, Value::Str("ACME".into())).unwrap();
+        ds.set_with_vr(&Tag::new_private(0x0009, 0x1001, "ACME"), Vr::DS, Value::Str("1.5".into())).unwrap();
+        let mut buf = Vec::new();
+        JsonWriter::new().write_datasets([&ds, &ds], &mut buf).unwrap();
+        let j: Json = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(j.as_array().map(Vec::len), Some(2));
+        assert_eq!(j[1]["00091001"], serde_json::json!({"vr": "DS", "Value": [1.5]}));
+    }
+}
@@ -9,19 +9,58 @@
 //! context-free data governed by their owning root.
 
 mod adapt;
+mod capture;
+mod codec;
+mod color;
 mod convert;
 mod dataset;
 mod dcm_parser;
 mod dcm_writer;
+mod deidentify;
+mod diff;
+mod dump;
+mod export;
+mod frames;
 mod item;
+mod json_parser;
+mod json_writer;
+mod lut;
+mod path;
+mod pixels;
+mod private_blocks;
 mod sequence;
+#[cfg(test)]
+mod testing;
+mod uid_map;
+mod validate;
 mod value;
+mod xml_parser;
+mod xml_writer;
 
+pub use capture::SecondaryCapture;
+pub use codec::{CodecEntry, PixelCodec, PixelInfo, find_codec};
+pub use color::ColorConverter;
 pub use convert::{FromNumber, FromValue, IntoValue};
 pub use dataset::{DataSet, DatasetKind, DatasetRole};
-pub use dcm_parser::{DcmReader, HeaderType, ReadMode, ReadOutput};
+pub use dcm_parser::{
+    DcmReader, DcmVisitor, ElementHeader, HeaderType, LazyValue, ReadMode, ReadOutput, StreamOutput, Visit,
+};
 pub use dcm_writer::DcmWriter;
+pub use deidentify::{Cleaner, DeidentifyAction, DeidentifyOption, Deidentifier};
+pub use diff::{Change, ChangeKind, Diff, Differ};
 pub use dpx_dicom_core::TransferSyntax;
+pub use dump::Dumper;
+pub use export::FrameExporter;
+pub use frames::Frames;
 pub use item::Item;
+pub use json_parser::JsonReader;
+pub use json_writer::JsonWriter;
+pub use lut::{GrayscaleRenderer, Lut, VoiFunction, Window};
+pub use path::{AttributePath, ItemIndex};
+pub use pixels::{PixelBuffer, PixelDecoder, Samples};
 pub use sequence::{ItemMut, ItemRef, Sequence, SequenceRef};
+pub use uid_map::{HashedUids, RandomUids, UidMapper, UidStrategy, UidTable};
+pub use validate::{AttributeType, Finding, IodDictionary, Problem, Severity, ValidationReport};
 pub use value::{OneOrMany, PixelData, TagHeader, Value};
+pub use xml_parser::XmlReader;
+pub use xml_writer::XmlWriter;
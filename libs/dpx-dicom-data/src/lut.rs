@@ -0,0 +1,499 @@
+//! Grayscale presentation: the Modality LUT, VOI LUT and Presentation LUT
+//! stages of PS3.3 C.11 applied to a decoded [`PixelBuffer`].
+//!
+//! [`GrayscaleRenderer`] maps stored values to modality values through Rescale
+//! Slope/Intercept or the Modality LUT Sequence, to a normalized display range
+//! through a [`Window`] or the VOI LUT Sequence, inverts for MONOCHROME1 or an
+//! INVERSE Presentation LUT Shape, and scales the result to 8- or 16-bit
+//! display values.
+//!
+//! ```ignore
+//! let decoder = PixelDecoder::new(&ds)?;
+//! let display: Vec<u8> = GrayscaleRenderer::from_dataset(&ds)?.render8(&decoder.frame(0)?)?;
+//! ```
+
+use dpx_dicom_core::error::{ErrContext, Result};
+use dpx_dicom_core::{Tag, dicom_err, ensure, tags};
+
+use crate::convert::FromNumber;
+use crate::pixels::PixelBuffer;
+use crate::{DataSet, ItemRef, Value};
+
+/// A lookup table read from a LUT Descriptor / LUT Data pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lut {
+    /// The input value mapped by the first entry.
+    pub first_mapped: i64,
+    /// Significant bits of each entry.
+    pub bits: u16,
+    pub data: Vec<u16>,
+}
+
+impl Lut {
+    /// Reads the LUT Descriptor and LUT Data of a Modality or VOI LUT Sequence
+    /// item. A first mapped value above 0x7FFF is negative when the stored
+    /// pixels are signed (`signed`), as PS3.3 C.11.1.1 allows for a US
+    /// descriptor.
+    pub fn from_item(item: &ItemRef, signed: bool) -> Result<Self> {
+        let descriptor: Vec<i64> = item.get_iter::<i64>(&tags::LUTDescriptor)?.collect();
+        let data = item.value(&tags::LUTData)?;
+        Self::from_values(&descriptor, &data, item.shared.is_little_endian(), signed)
+            .err_context("reading a LUT Sequence item")
+    }
+
+    /// Builds the table from the three LUT Descriptor values and the decoded
+    /// LUT Data: US values, or OW bytes in the `little_endian` byte order.
+    /// 8-bit tables packed two entries per word are unpacked.
+    pub(crate) fn from_values(descriptor: &[i64], data: &Value, little_endian: bool, signed: bool) -> Result<Self> {
+        let (entries, first_mapped, bits) = parse_descriptor(descriptor, signed)?;
+        let mut words = lut_words(data, little_endian);
+        if bits == 8 && words.len() == entries.div_ceil(2) && entries > 1 {
+            // Legacy 8-bit tables: two entries per word, low byte first.
+            words = words.iter().flat_map(|w| [w & 0xFF, w >> 8]).take(entries).collect();
+        }
+        ensure!(words.len() >= entries, InvalidData, "LUT Data holds {} of {entries} entries", words.len());
+        words.truncate(entries);
+        Ok(Lut { first_mapped, bits, data: words })
+    }
+
+    /// Builds the table from a LUT Descriptor and Segmented LUT Data (PS3.3
+    /// C.7.9.2): discrete, linear and indirect segments of 16-bit words.
+    pub(crate) fn from_segmented(descriptor: &[i64], data: &Value, little_endian: bool, signed: bool) -> Result<Self> {
+        let (entries, first_mapped, bits) = parse_descriptor(descriptor, signed)?;
+        let words = lut_words(data, little_endian);
+        let mut out = Vec::with_capacity(entries);
+        expand_segments(&words, 0, usize::MAX, entries, &mut out, 0)?;
+        ensure!(out.len() == entries, InvalidData, "Segmented LUT Data expands to {} of {entries} entries", out.len());
+        Ok(Lut { first_mapped, bits, data: out })
+    }
+
+    /// The entry for `input`; inputs outside the table take its first or last
+    /// entry.
+    pub fn lookup(&self, input: f64) -> u16 {
+        let index = (input.round() as i64).saturating_sub(self.first_mapped);
+        self.data[index.clamp(0, self.data.len() as i64 - 1) as usize]
+    }
+
+    /// The largest value an entry can hold.
+    pub fn max_value(&self) -> u16 {
+        (((1u32 << self.bits) - 1) & 0xFFFF) as u16
+    }
+}
+
+/// Number of entries, first mapped value and bits per entry.
+fn parse_descriptor(descriptor: &[i64], signed: bool) -> Result<(usize, i64, u16)> {
+    let &[entries, first, bits] = descriptor else {
+        return Err(dicom_err!(InvalidData, "LUT Descriptor has {} values, 3 expected", descriptor.len()));
+    };
+    let entries = if entries == 0 { 65536 } else { entries as usize };
+    let first_mapped = if signed && first > 0x7FFF { first - 0x10000 } else { first };
+    ensure!((1..=16).contains(&bits), InvalidData, "LUT Descriptor declares {bits}-bit entries");
+    Ok((entries, first_mapped, bits as u16))
+}
+
+/// LUT Data as 16-bit words: US values, or OW bytes in the given byte order.
+fn lut_words(data: &Value, little_endian: bool) -> Vec<u16> {
+    match data {
+        Value::Bytes(b) => b
+            .chunks_exact(2)
+            .map(|w| if little_endian { u16::from_le_bytes([w[0], w[1]]) } else { u16::from_be_bytes([w[0], w[1]]) })
+            .collect(),
+        other => crate::convert::numbers::<u16>(other).collect(),
+    }
+}
+
+/// Appends the entries of up to `count` segments of `words`, from word `start`,
+/// to `out`, stopping once it holds `entries`. Indirect segments recurse into
+/// earlier segments, `depth` guarding against offsets that loop. Empty
+/// segments are rejected, so that every segment visited adds an entry.
+fn expand_segments(
+    words: &[u16],
+    start: usize,
+    count: usize,
+    entries: usize,
+    out: &mut Vec<u16>,
+    depth: usize,
+) -> Result<()> {
+    ensure!(depth < 8, InvalidData, "Segmented LUT Data nests indirect segments too deeply");
+    let mut pos = start;
+    let mut done = 0;
+    while done < count && out.len() < entries && pos + 1 < words.len() {
+        let (opcode, length) = (words[pos], words[pos + 1] as usize);
+        ensure!(length > 0, InvalidData, "empty segment at word {pos} of Segmented LUT Data");
+        let room = entries - out.len();
+        match opcode {
+            0 => {
+                let values = words.get(pos + 2..pos + 2 + length);
+                let values = values.ok_or_else(|| dicom_err!(InvalidData, "discrete segment overruns LUT Data"))?;
+                out.extend(values.iter().take(room));
+                pos += 2 + length;
+            }
+            1 => {
+                let from =
+                    f64::from(*out.last().ok_or_else(|| dicom_err!(InvalidData, "linear segment starts LUT Data"))?);
+                let end =
+                    f64::from(*words.get(pos + 2).ok_or_else(|| dicom_err!(InvalidData, "truncated linear segment"))?);
+                out.extend(
+                    (1..=length.min(room)).map(|i| (from + (end - from) * i as f64 / length as f64).round() as u16),
+                );
+                pos += 3;
+            }
+            2 => {
+                ensure!(!out.is_empty(), InvalidData, "indirect segment starts LUT Data");
+                let offset =
+                    words.get(pos + 2..pos + 4).ok_or_else(|| dicom_err!(InvalidData, "truncated indirect segment"))?;
+                // A 32-bit word offset, least significant word first.
+                let offset = (offset[0] as usize) | ((offset[1] as usize) << 16);
+                ensure!(offset < pos, InvalidData, "indirect segment points forward to word {offset}");
+                expand_segments(words, offset, length, entries, out, depth + 1)?;
+                pos += 4;
+            }
+            other => return Err(dicom_err!(InvalidData, "unknown Segmented LUT Data opcode {other}")),
+        }
+        done += 1;
+    }
+    Ok(())
+}
+
+/// The VOI LUT Function of a window (PS3.3 C.11.2.1.3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VoiFunction {
+    #[default]
+    Linear,
+    LinearExact,
+    Sigmoid,
+}
+
+/// A Window Center / Window Width pair and its VOI LUT Function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub center: f64,
+    pub width: f64,
+    pub function: VoiFunction,
+}
+
+impl Window {
+    pub fn new(center: f64, width: f64) -> Self {
+        Window { center, width, function: VoiFunction::Linear }
+    }
+
+    /// The windows of `ds`, one per Window Center / Window Width value pair,
+    /// all sharing its VOI LUT Function.
+    pub fn from_dataset(ds: &DataSet) -> Result<Vec<Self>> {
+        if !ds.contains(&tags::WindowCenter) || !ds.contains(&tags::WindowWidth) {
+            return Ok(Vec::new());
+        }
+        let function = match ds.get_some::<String>(&tags::VOILUTFunction).as_deref().map(str::trim) {
+            None | Some("" | "LINEAR") => VoiFunction::Linear,
+            Some("LINEAR_EXACT") => VoiFunction::LinearExact,
+            Some("SIGMOID") => VoiFunction::Sigmoid,
+            Some(other) => return Err(dicom_err!(UnsupportedFeature, "unknown VOI LUT Function {other}")),
+        };
+        let centers = ds.get_iter::<f64>(&tags::WindowCenter)?;
+        let widths = ds.get_iter::<f64>(&tags::WindowWidth)?;
+        Ok(centers.zip(widths).map(|(center, width)| Window { center, width, function }).collect())
+    }
+
+    /// Maps a modality value to `0.0..=1.0` (PS3.3 C.11.2.1.2).
+    pub fn apply(&self, x: f64) -> f64 {
+        let (c, w) = (self.center, self.width);
+        match self.function {
+            VoiFunction::Linear => {
+                // Width 1 is a threshold at the center.
+                let (low, high) = (c - 0.5 - (w - 1.0) / 2.0, c - 0.5 + (w - 1.0) / 2.0);
+                if x <= low {
+                    0.0
+                } else if x > high {
+                    1.0
+                } else {
+                    (x - (c - 0.5)) / (w - 1.0) + 0.5
+                }
+            }
+            VoiFunction::LinearExact => ((x - c) / w + 0.5).clamp(0.0, 1.0),
+            VoiFunction::Sigmoid => 1.0 / (1.0 + (-4.0 * (x - c) / w).exp()),
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        let min = if self.function == VoiFunction::Linear { 1.0 } else { f64::MIN_POSITIVE };
+        ensure!(self.width >= min, InvalidData, "Window Width {} is out of range for {:?}", self.width, self.function);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Modality {
+    Rescale { slope: f64, intercept: f64 },
+    Lut(Lut),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Voi {
+    Window(Window),
+    Lut(Lut),
+    /// A linear window over the frame's own range of modality values.
+    Auto,
+}
+
+/// Renders single-sample frames to display values through the grayscale
+/// pipeline; [`from_dataset`](Self::from_dataset) takes every stage from the
+/// data set and the builder methods override them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrayscaleRenderer {
+    modality: Modality,
+    voi: Voi,
+    invert: bool,
+}
+
+impl Default for GrayscaleRenderer {
+    /// No rescale, a window over each frame's range, no inversion.
+    fn default() -> Self {
+        GrayscaleRenderer { modality: Modality::Rescale { slope: 1.0, intercept: 0.0 }, voi: Voi::Auto, invert: false }
+    }
+}
+
+impl GrayscaleRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pipeline `ds` describes: its first Modality LUT Sequence item, else
+    /// Rescale Slope / Intercept; its first window, else its first VOI LUT
+    /// Sequence item, else the frame's range; inversion for an INVERSE
+    /// Presentation LUT Shape, or for MONOCHROME1 when the shape is absent.
+    pub fn from_dataset(ds: &DataSet) -> Result<Self> {
+        // ponytail: per-frame values in the Functional Group Sequences
+        // (Pixel Value Transformation, Frame VOI LUT) are not read.
+        let signed = ds.get_some::<u16>(&tags::PixelRepresentation) == Some(1);
+        let first_item =
+            |tag: &Tag| ds.sequence(tag).filter(|s| !s.is_empty()).map(|s| Lut::from_item(&s.item(0).unwrap(), signed));
+        let modality = match first_item(&tags::ModalityLUTSequence) {
+            Some(lut) => Modality::Lut(lut?),
+            None => Modality::Rescale {
+                slope: first_number(ds, &tags::RescaleSlope).unwrap_or(1.0),
+                intercept: first_number(ds, &tags::RescaleIntercept).unwrap_or(0.0),
+            },
+        };
+        // The VOI LUT Sequence indexes modality values, which a Modality LUT leaves unsigned.
+        let voi_signed = signed && matches!(modality, Modality::Rescale { .. });
+        let voi = match Window::from_dataset(ds)?.into_iter().next() {
+            Some(window) => Voi::Window(window),
+            None => match ds.sequence(&tags::VOILUTSequence).filter(|s| !s.is_empty()) {
+                Some(seq) => Voi::Lut(Lut::from_item(&seq.item(0).unwrap(), voi_signed)?),
+                None => Voi::Auto,
+            },
+        };
+        let invert = match ds.get_some::<String>(&tags::PresentationLUTShape).as_deref().map(str::trim) {
+            Some("INVERSE") => true,
+            Some("IDENTITY") => false,
+            _ => {
+                ds.get_some::<String>(&tags::PhotometricInterpretation).as_deref().map(str::trim) == Some("MONOCHROME1")
+            }
+        };
+        Ok(GrayscaleRenderer { modality, voi, invert })
+    }
+
+    /// Maps stored values linearly: `slope * stored + intercept`.
+    pub fn rescale(mut self, slope: f64, intercept: f64) -> Self {
+        self.modality = Modality::Rescale { slope, intercept };
+        self
+    }
+
+    /// Maps stored values through a Modality LUT.
+    pub fn modality_lut(mut self, lut: Lut) -> Self {
+        self.modality = Modality::Lut(lut);
+        self
+    }
+
+    /// Selects modality values through `window`.
+    pub fn window(mut self, window: Window) -> Self {
+        self.voi = Voi::Window(window);
+        self
+    }
+
+    /// Selects modality values through a VOI LUT.
+    pub fn voi_lut(mut self, lut: Lut) -> Self {
+        self.voi = Voi::Lut(lut);
+        self
+    }
+
+    /// Windows each frame over its own minimum and maximum modality values.
+    pub fn auto_window(mut self) -> Self {
+        self.voi = Voi::Auto;
+        self
+    }
+
+    /// Whether the lowest values display brightest.
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Display values in `0..=255`, one per pixel.
+    pub fn render8(&self, frame: &PixelBuffer) -> Result<Vec<u8>> {
+        Ok(self.render(frame)?.map(|y| (y * 255.0).round() as u8).collect())
+    }
+
+    /// Display values in `0..=65535`, one per pixel.
+    pub fn render16(&self, frame: &PixelBuffer) -> Result<Vec<u16>> {
+        Ok(self.render(frame)?.map(|y| (y * 65535.0).round() as u16).collect())
+    }
+
+    /// Each pixel's presentation value in `0.0..=1.0`.
+    fn render(&self, frame: &PixelBuffer) -> Result<impl Iterator<Item = f64> + '_> {
+        ensure!(
+            frame.samples_per_pixel == 1,
+            UnsupportedFeature,
+            "grayscale rendering needs one sample per pixel, the frame has {}",
+            frame.samples_per_pixel
+        );
+        if let Voi::Window(window) = &self.voi {
+            window.check()?;
+        }
+        let modality: Vec<f64> = (0..frame.samples.len())
+            .map(|i| {
+                let stored = frame.samples.get(i).unwrap_or_default();
+                match &self.modality {
+                    Modality::Rescale { slope, intercept } => stored * slope + intercept,
+                    Modality::Lut(lut) => f64::from(lut.lookup(stored)),
+                }
+            })
+            .collect();
+        let auto = match self.voi {
+            Voi::Auto => {
+                let (min, max) =
+                    modality.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+                Some((min, (max - min).max(f64::MIN_POSITIVE)))
+            }
+            _ => None,
+        };
+        let invert = self.invert;
+        Ok(modality.into_iter().map(move |x| {
+            let y = match (&self.voi, auto) {
+                (Voi::Window(window), _) => window.apply(x),
+                (Voi::Lut(lut), _) => f64::from(lut.lookup(x)) / f64::from(lut.max_value()),
+                (Voi::Auto, Some((min, range))) => (x - min) / range,
+                (Voi::Auto, None) => unreachable!("range computed above"),
+            };
+            let y = y.clamp(0.0, 1.0);
+            if invert { 1.0 - y } else { y }
+        }))
+    }
+}
+
+/// The first numeric value of `tag`, whether stored as text or binary.
+fn first_number<T: FromNumber>(ds: &DataSet, tag: &Tag) -> Option<T> {
+    ds.get_iter::<T>(tag).ok()?.next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OneOrMany;
+    use crate::pixels::Samples;
+    use crate::testing::Image;
+    use bytes::Bytes;
+    use dpx_dicom_core::Vr;
+
+    fn frame(samples: Samples) -> PixelBuffer {
+        PixelBuffer {
+            rows: 1,
+            columns: samples.len() as u16,
+            samples_per_pixel: 1,
+            planar: false,
+            photometric_interpretation: "MONOCHROME2".into(),
+            samples,
+        }
+    }
+
+    fn lut_item(ds: &mut DataSet, seq: &Tag, descriptor: [u64; 3], data: Value) {
+        let mut seq = ds.sequence_mut(seq).unwrap();
+        let mut item = seq.new_item();
+        item.set_with_vr(&tags::LUTDescriptor, Vr::US, Value::UInt(OneOrMany::Many(descriptor.to_vec()))).unwrap();
+        item.set_with_vr(&tags::LUTData, Vr::OW, data).unwrap();
+    }
+
+    #[test]
+    fn rescale_then_linear_window() {
+        // CT: stored 0..=3 become -1024, -24, 976, 1976 HU.
+        let renderer = GrayscaleRenderer::new().rescale(1000.0, -1024.0).window(Window::new(-24.0, 2001.0));
+        let out = renderer.render8(&frame(Samples::U16(vec![0, 1, 2, 3]))).unwrap();
+        assert_eq!(out, vec![0, 128, 255, 255]);
+        let exact = Window { center: -24.0, width: 2000.0, function: VoiFunction::LinearExact };
+        let out = renderer.window(exact).render16(&frame(Samples::U16(vec![0, 1, 2]))).unwrap();
+        assert_eq!(out, vec![0, 32768, 65535]);
+        // Width 1 thresholds at the center.
+        let threshold = GrayscaleRenderer::new().window(Window::new(10.0, 1.0));
+        assert_eq!(threshold.render8(&frame(Samples::I16(vec![9, 10]))).unwrap(), vec![0, 255]);
+        assert!(GrayscaleRenderer::new().window(Window::new(0.0, 0.5)).render8(&frame(Samples::U8(vec![0]))).is_err());
+    }
+
+    #[test]
+    fn linear_exact_and_sigmoid_windows() {
+        let exact = Window { center: 100.0, width: 50.0, function: VoiFunction::LinearExact };
+        assert_eq!([exact.apply(75.0), exact.apply(100.0), exact.apply(125.0)], [0.0, 0.5, 1.0]);
+        let sigmoid = Window { function: VoiFunction::Sigmoid, ..exact };
+        assert_eq!(sigmoid.apply(100.0), 0.5);
+        assert!(sigmoid.apply(75.0) < 0.5 && sigmoid.apply(0.0) > 0.0);
+
+        let mut ds = DataSet::new();
+        ds.set(&tags::WindowCenter, "40\\300").unwrap();
+        ds.set(&tags::WindowWidth, "400\\1500").unwrap();
+        ds.set(&tags::VOILUTFunction, "SIGMOID").unwrap();
+        let windows = Window::from_dataset(&ds).unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[1], Window { center: 300.0, width: 1500.0, function: VoiFunction::Sigmoid });
+    }
+
+    #[test]
+    fn modality_lut_clamps_and_unpacks_8_bit_tables() {
+        // Four 8-bit entries packed two per word, starting at stored value 10.
+        let packed = Value::Bytes(Bytes::from_static(&[1, 2, 3, 4]));
+        let lut = Lut::from_values(&[4, 10, 8], &packed, true, false).unwrap();
+        assert_eq!(lut.data, vec![1, 2, 3, 4]);
+        assert_eq!([lut.lookup(0.0), lut.lookup(12.0), lut.lookup(99.0)], [1, 3, 4]);
+        // A signed first mapped value written as US.
+        let lut = Lut::from_values(&[2, 0xFFFF, 16], &Value::UInt(OneOrMany::Many(vec![7, 9])), true, true).unwrap();
+        assert_eq!((lut.first_mapped, lut.lookup(0.0)), (-1, 9));
+        assert!(Lut::from_values(&[3, 0, 16], &Value::UInt(OneOrMany::One(1)), true, false).is_err());
+    }
+
+    #[test]
+    fn lut_sequences_from_dataset() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PhotometricInterpretation, "MONOCHROME2").unwrap();
+        let words: Vec<u8> = [0u16, 100, 200].iter().flat_map(|w| w.to_le_bytes()).collect();
+        lut_item(&mut ds, &tags::ModalityLUTSequence, [3, 0, 16], Value::Bytes(Bytes::from(words)));
+        let voi = Value::UInt(OneOrMany::Many((0..=200).map(|v| if v < 100 { 0 } else { 255 }).collect()));
+        lut_item(&mut ds, &tags::VOILUTSequence, [201, 0, 8], voi);
+        // Present but ignored: the Modality LUT wins over Rescale Slope.
+        ds.set(&tags::RescaleSlope, "5").unwrap();
+        let renderer = GrayscaleRenderer::from_dataset(&ds).unwrap();
+        assert_eq!(renderer.render8(&frame(Samples::U8(vec![0, 1, 2, 9]))).unwrap(), vec![0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn monochrome1_inverts_unless_identity() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PhotometricInterpretation, "MONOCHROME1").unwrap();
+        ds.set(&tags::WindowCenter, "128").unwrap();
+        ds.set(&tags::WindowWidth, "256").unwrap();
+        let pixels = frame(Samples::U8(vec![0, 255]));
+        assert_eq!(GrayscaleRenderer::from_dataset(&ds).unwrap().render8(&pixels).unwrap(), vec![255, 0]);
+        ds.set(&tags::PresentationLUTShape, "IDENTITY").unwrap();
+        assert_eq!(GrayscaleRenderer::from_dataset(&ds).unwrap().render8(&pixels).unwrap(), vec![0, 255]);
+    }
+
+    #[test]
+    fn auto_window_spans_the_frame() {
+        let words: Vec<u8> = [-500i16, 0, 500].iter().flat_map(|w| w.to_le_bytes()).collect();
+        let mut ds = Image::new(1, 3, 1, 16).native(words);
+        ds.set(&tags::PixelRepresentation, 1u16).unwrap();
+        let frame = crate::PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+        let renderer = GrayscaleRenderer::from_dataset(&ds).unwrap();
+        assert_eq!(renderer.render8(&frame).unwrap(), vec![0, 128, 255]);
+        let mut rgb = frame.clone();
+        rgb.samples_per_pixel = 3;
+        assert!(renderer.render8(&rgb).is_err());
+    }
+}
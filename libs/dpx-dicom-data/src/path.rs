@@ -0,0 +1,297 @@
+//! Attribute paths: the sequences and Items leading to a nested attribute,
+//! written as `ReferencedSeriesSequence[0].ReferencedInstanceSequence[*].ReferencedSOPInstanceUID`
+//! or `(0008,1115)[0].(0008,114A)[*].(0008,1155)`.
+//!
+//! Each sequence of the path takes an Item index, or `*` for every Item. The
+//! last attribute takes none. Attributes are named by dictionary keyword or by
+//! tag, with the private creator for private ones: `(0029,1010,"ACME 1.0")`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use dpx_dicom_core::error::{DicomError, Result};
+use dpx_dicom_core::{Tag, dicom_err, ensure};
+
+use crate::dataset::Shared;
+use crate::item::Item;
+use crate::sequence::Sequence;
+
+/// Which Items of a sequence a path goes through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemIndex {
+    /// `[n]`: the Item at this index.
+    At(usize),
+    /// `[*]`: every Item.
+    All,
+}
+
+/// A parsed attribute path; see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributePath {
+    sequences: Vec<(Tag, ItemIndex)>,
+    tag: Tag,
+}
+
+impl AttributePath {
+    /// The path of `tag` in the Item reached through `sequences`; at the root
+    /// if there are none.
+    pub fn new(sequences: Vec<(Tag, ItemIndex)>, tag: Tag) -> Self {
+        Self { sequences, tag }
+    }
+
+    /// The sequences leading to the attribute, with the Items taken in each.
+    pub fn sequences(&self) -> &[(Tag, ItemIndex)] {
+        &self.sequences
+    }
+
+    /// The attribute the path leads to.
+    pub fn tag(&self) -> &Tag {
+        &self.tag
+    }
+
+    /// Whether the path may match more than one attribute.
+    pub fn has_wildcard(&self) -> bool {
+        self.sequences.iter().any(|(_, index)| *index == ItemIndex::All)
+    }
+}
+
+impl FromStr for AttributePath {
+    type Err = DicomError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut sequences = Vec::new();
+        let mut rest = s;
+        loop {
+            let (tag, after) = parse_tag(s, rest)?;
+            let (index, after) = parse_index(s, after)?;
+            if let Some(next) = after.strip_prefix('.') {
+                let index = index.ok_or_else(|| dicom_err!(InvalidData, "no Item index after {tag} in path {s:?}"))?;
+                sequences.push((tag, index));
+                rest = next;
+                continue;
+            }
+            ensure!(after.is_empty(), InvalidData, "unexpected {after:?} in path {s:?}");
+            ensure!(index.is_none(), InvalidData, "path {s:?} ends with an Item index, not an attribute");
+            return Ok(Self { sequences, tag });
+        }
+    }
+}
+
+impl TryFrom<&str> for AttributePath {
+    type Error = DicomError;
+
+    fn try_from(s: &str) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Keyword or parenthesized tag at the start of `s`.
+fn parse_tag<'a>(path: &str, s: &'a str) -> Result<(Tag, &'a str)> {
+    if s.starts_with('(') {
+        // The first parenthesis outside of a quoted private creator.
+        let (mut quoted, mut escaped) = (false, false);
+        let end = s
+            .char_indices()
+            .find(|&(_, c)| {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' if quoted => escaped = true,
+                    '"' => quoted = !quoted,
+                    ')' if !quoted => return true,
+                    _ => {}
+                }
+                false
+            })
+            .map(|(i, _)| i + 1)
+            .ok_or_else(|| dicom_err!(InvalidData, "unclosed tag in path {path:?}"))?;
+        return Ok((s[..end].parse()?, &s[end..]));
+    }
+    let end = s.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(s.len());
+    let keyword = &s[..end];
+    ensure!(!keyword.is_empty(), InvalidData, "missing attribute in path {path:?}");
+    let tag = Tag::from_keyword(keyword)
+        .ok_or_else(|| dicom_err!(NotFound, "unknown keyword {keyword:?} in path {path:?}"))?;
+    Ok((tag, &s[end..]))
+}
+
+/// Optional `[n]` or `[*]` at the start of `s`.
+fn parse_index<'a>(path: &str, s: &'a str) -> Result<(Option<ItemIndex>, &'a str)> {
+    let Some(rest) = s.strip_prefix('[') else { return Ok((None, s)) };
+    let (index, rest) =
+        rest.split_once(']').ok_or_else(|| dicom_err!(InvalidData, "unclosed Item index in path {path:?}"))?;
+    let index = match index {
+        "*" => ItemIndex::All,
+        n => {
+            ItemIndex::At(n.parse().map_err(|_| dicom_err!(InvalidData, "invalid Item index {n:?} in path {path:?}"))?)
+        }
+    };
+    Ok((Some(index), rest))
+}
+
+impl fmt::Display for AttributePath {
+    /// Writes keywords where they name the same tag back, tags otherwise.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (tag, index) in &self.sequences {
+            write_tag(f, tag)?;
+            match index {
+                ItemIndex::At(i) => write!(f, "[{i}].")?,
+                ItemIndex::All => write!(f, "[*].")?,
+            }
+        }
+        write_tag(f, &self.tag)
+    }
+}
+
+fn write_tag(f: &mut fmt::Formatter<'_>, tag: &Tag) -> fmt::Result {
+    match tag.meta().filter(|m| Tag::from_keyword(&m.keyword).as_ref() == Some(tag)) {
+        Some(meta) => write!(f, "{}", meta.keyword),
+        None => write!(f, "{tag}"),
+    }
+}
+
+/// The existing Items holding the attribute at `path`.
+pub(crate) fn items<'a>(shared: &'a Shared, root: &'a Item, path: &AttributePath) -> Vec<&'a Item> {
+    let mut current = vec![root];
+    for (tag, index) in &path.sequences {
+        let mut next = Vec::new();
+        for item in current {
+            let Some(sequence) = item.sequence(shared, tag) else { continue };
+            match index {
+                ItemIndex::At(i) => next.extend(sequence.items.get(*i)),
+                ItemIndex::All => next.extend(sequence.items),
+            }
+        }
+        current = next;
+    }
+    current
+}
+
+/// The Items holding the attribute at `path`, for editing. With `create`,
+/// missing sequences are created, and an index one past the last Item appends
+/// a new one; a later index is an error, and `[*]` still only takes the
+/// existing Items and creates nothing.
+pub(crate) fn items_mut<'a>(
+    shared: &'a Shared,
+    root: &'a mut Item,
+    path: &AttributePath,
+    create: bool,
+) -> Result<Vec<&'a mut Item>> {
+    let mut current = vec![root];
+    for (tag, index) in &path.sequences {
+        let mut next = Vec::new();
+        for item in current {
+            let len = item.sequence(shared, tag).map(|sequence| sequence.items.len());
+            match (index, len) {
+                (ItemIndex::All, None) => continue,
+                (ItemIndex::At(i), len) if !create && *i >= len.unwrap_or(0) => continue,
+                (ItemIndex::At(i), len) => {
+                    let len = len.unwrap_or(0);
+                    ensure!(*i <= len, NotFound, "{tag} has {len} Items, so only Item {len} can be created, not {i}");
+                }
+                (ItemIndex::All, Some(_)) => {}
+            }
+            let Sequence { items, .. } = item.sequence_mut(shared, tag)?;
+            match index {
+                ItemIndex::At(i) => {
+                    if *i == items.len() {
+                        items.push(Item::default());
+                    }
+                    next.push(&mut items[*i]);
+                }
+                ItemIndex::All => next.extend(items.iter_mut()),
+            }
+        }
+        current = next;
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use dpx_dicom_core::tags;
+
+    use super::*;
+    use crate::{DataSet, FromValue, Value};
+
+    fn path(s: &str) -> AttributePath {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_keywords_and_tags() {
+        let by_keyword = path("ReferencedSeriesSequence[0].ReferencedInstanceSequence[*].ReferencedSOPInstanceUID");
+        let by_tag = path("(0008,1115)[0].(0008,114A)[*].(0008,1155)");
+        assert_eq!(by_keyword, by_tag);
+        assert!(by_tag.has_wildcard());
+        assert_eq!(by_tag.tag(), &tags::ReferencedSOPInstanceUID);
+        assert_eq!(
+            by_tag.to_string(),
+            "ReferencedSeriesSequence[0].ReferencedInstanceSequence[*].ReferencedSOPInstanceUID"
+        );
+
+        let private = path(r#"(0029,1010,"ACME 1.0")[2].(0029,1011,"ACME 1.0")"#);
+        assert_eq!(private.sequences()[0], (Tag::new_private(0x0029, 0x1010, "ACME 1.0"), ItemIndex::At(2)));
+        assert_eq!(private.to_string(), r#"(0029,1010,"ACME 1.0")[2].(0029,1011,"ACME 1.0")"#);
+        assert_eq!(path("PatientName").to_string(), "PatientName");
+
+        for invalid in [
+            "",
+            "NoSuchKeyword",
+            "ReferencedSeriesSequence.SeriesInstanceUID",
+            "ReferencedSeriesSequence[0]",
+            "ReferencedSeriesSequence[x].SeriesInstanceUID",
+            "ReferencedSeriesSequence[0.SeriesInstanceUID",
+            "(0008,1115[0].(0020,000E)",
+            "PatientName ",
+            "ReferencedSeriesSequence[0].",
+        ] {
+            assert!(invalid.parse::<AttributePath>().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn edits_nested_attributes() {
+        let mut ds = DataSet::new();
+        let uid = path("ReferencedSeriesSequence[0].ReferencedInstanceSequence[0].ReferencedSOPInstanceUID");
+        // Intermediate sequences and Items are created.
+        assert_eq!(ds.set_at(&uid, "1.2.3").unwrap(), 1);
+        assert_eq!(ds.sequence(&tags::ReferencedSeriesSequence).unwrap().len(), 1);
+        let second = path("ReferencedSeriesSequence[1].ReferencedInstanceSequence[0].ReferencedSOPInstanceUID");
+        ds.set_at(&second, "1.2.4").unwrap();
+        assert_eq!(ds.sequence(&tags::ReferencedSeriesSequence).unwrap().len(), 2);
+        // Only the next Item can be created.
+        let skipped = path("ReferencedSeriesSequence[3].ReferencedInstanceSequence[0].ReferencedSOPInstanceUID");
+        assert_eq!(ds.set_at(&skipped, "1.2.5").unwrap_err().kind, dpx_dicom_core::error::ErrorKind::NotFound);
+        assert_eq!(ds.sequence(&tags::ReferencedSeriesSequence).unwrap().len(), 2);
+
+        let all = path("ReferencedSeriesSequence[*].ReferencedInstanceSequence[*].ReferencedSOPInstanceUID");
+        let found: Vec<String> = ds.get_at(&all).unwrap().iter().map(|v| String::from_value(v).unwrap()).collect();
+        assert_eq!(found, ["1.2.3", "1.2.4"]);
+        assert_eq!(ds.items_at(&all).len(), 2);
+        assert!(matches!(&ds.get_at(&uid).unwrap()[..], [Value::Str(s)] if s == "1.2.3"));
+
+        // Wildcards take existing Items only.
+        let class = path("ReferencedSeriesSequence[*].ReferencedInstanceSequence[*].ReferencedSOPClassUID");
+        assert_eq!(ds.set_at(&class, "1.2.840.10008.5.1.4.1.1.2").unwrap(), 2);
+        assert_eq!(ds.set_at(&path("ReferencedImageSequence[*].ReferencedSOPClassUID"), "1.2").unwrap(), 0);
+        assert!(!ds.contains(&tags::ReferencedImageSequence));
+
+        // Replacing touches present attributes only.
+        let third = path("ReferencedSeriesSequence[2].ReferencedInstanceSequence[0].ReferencedSOPInstanceUID");
+        assert_eq!(ds.replace_value_at(&all, Value::Str("1.2.5".into())).unwrap(), 2);
+        assert_eq!(ds.replace_value_at(&third, Value::Str("1.2.6".into())).unwrap(), 0);
+        assert_eq!(ds.sequence(&tags::ReferencedSeriesSequence).unwrap().len(), 2);
+
+        assert_eq!(ds.remove_at(&all), 2);
+        assert_eq!(ds.remove_at(&all), 0);
+        assert!(ds.get_at(&all).unwrap().is_empty());
+        assert_eq!(ds.get_at(&class).unwrap().len(), 2);
+        assert_eq!(ds.remove_at(&path("StudyDescription")), 0);
+        assert!(ds.items_at(&path("ReferencedImageSequence[0].ReferencedSOPClassUID")).is_empty());
+
+        // A non-sequence on the way is an error for edits that create.
+        ds.set(&tags::PatientName, "Doe^John").unwrap();
+        assert!(ds.set_at(&path("PatientName[0].PatientID"), "1").is_err());
+        assert_eq!(ds.remove_at(&path("PatientName[0].PatientID")), 0);
+    }
+}
@@ -0,0 +1,422 @@
+//! Typed pixel samples decoded from Pixel Data, Float Pixel Data or Double
+//! Float Pixel Data per the Image Pixel module.
+//!
+//! [`PixelDecoder`] reads a frame through [`Frames`] — decompressing it with
+//! the registered [`PixelCodec`](crate::PixelCodec) when encapsulated — and
+//! turns its bytes into a [`PixelBuffer`] of [`Samples`]: words in the data
+//! set's byte order, Bits Stored extracted below High Bit and sign-extended
+//! for Pixel Representation 1, 1-bit data unpacked to one `0`/`1` byte per
+//! sample, YBR_FULL_422 chroma expanded to three samples per pixel.
+
+use std::borrow::Cow;
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{Tag, TransferSyntax, Vr, dicom_err, ensure, tags};
+
+use crate::DataSet;
+use crate::codec::{PixelCodec, PixelInfo, find_codec};
+use crate::frames::Frames;
+
+/// The samples of one frame, typed by Bits Allocated and Pixel Representation
+/// (or the float Pixel Data attribute they came from).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Samples {
+    U8(Vec<u8>),
+    I8(Vec<i8>),
+    U16(Vec<u16>),
+    I16(Vec<i16>),
+    U32(Vec<u32>),
+    I32(Vec<i32>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// Applies `$body` to the vector inside any [`Samples`] variant; the `wrap`
+/// form puts the result back in the same variant.
+macro_rules! each_samples {
+    ($samples:expr, $v:ident => $body:expr) => {
+        match $samples {
+            Samples::U8($v) => $body,
+            Samples::I8($v) => $body,
+            Samples::U16($v) => $body,
+            Samples::I16($v) => $body,
+            Samples::U32($v) => $body,
+            Samples::I32($v) => $body,
+            Samples::F32($v) => $body,
+            Samples::F64($v) => $body,
+        }
+    };
+    (wrap $samples:expr, $v:ident => $body:expr) => {
+        match $samples {
+            Samples::U8($v) => Samples::U8($body),
+            Samples::I8($v) => Samples::I8($body),
+            Samples::U16($v) => Samples::U16($body),
+            Samples::I16($v) => Samples::I16($body),
+            Samples::U32($v) => Samples::U32($body),
+            Samples::I32($v) => Samples::I32($body),
+            Samples::F32($v) => Samples::F32($body),
+            Samples::F64($v) => Samples::F64($body),
+        }
+    };
+}
+
+impl Samples {
+    pub fn len(&self) -> usize {
+        each_samples!(self, v => v.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sample `index` widened to `f64`.
+    pub fn get(&self, index: usize) -> Option<f64> {
+        each_samples!(self, v => widen(v, index))
+    }
+}
+
+fn widen<T: Copy + Into<f64>>(v: &[T], index: usize) -> Option<f64> {
+    v.get(index).map(|&s| s.into())
+}
+
+/// One decoded frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelBuffer {
+    pub rows: u16,
+    pub columns: u16,
+    pub samples_per_pixel: u16,
+    /// Samples laid out `RR…GG…BB…` rather than `RGBRGB…`.
+    pub planar: bool,
+    /// The colour model of the samples; YBR_FULL_422 frames are expanded and
+    /// reported as YBR_FULL.
+    pub photometric_interpretation: String,
+    pub samples: Samples,
+}
+
+impl PixelBuffer {
+    /// Sample `sample` of the pixel at `row`, `column`, widened to `f64`.
+    pub fn value(&self, row: usize, column: usize, sample: usize) -> Option<f64> {
+        let (rows, columns, spp) = (self.rows as usize, self.columns as usize, self.samples_per_pixel as usize);
+        if row >= rows || column >= columns || sample >= spp {
+            return None;
+        }
+        let pixel = row * columns + column;
+        self.samples.get(if self.planar { sample * rows * columns + pixel } else { pixel * spp + sample })
+    }
+
+    /// The same frame with samples interleaved (`RGBRGB…`).
+    pub fn into_interleaved(self) -> Self {
+        if !self.planar { self } else { self.reordered(false) }
+    }
+
+    /// The same frame with samples in planes (`RR…GG…BB…`).
+    pub fn into_planar(self) -> Self {
+        if self.planar { self } else { self.reordered(true) }
+    }
+
+    fn reordered(self, planar: bool) -> Self {
+        let pixels = self.rows as usize * self.columns as usize;
+        let spp = self.samples_per_pixel as usize;
+        let samples = each_samples!(wrap self.samples, v => reorder(&v, pixels, spp, planar));
+        PixelBuffer { planar, samples, ..self }
+    }
+}
+
+fn reorder<T: Copy>(v: &[T], pixels: usize, spp: usize, to_planar: bool) -> Vec<T> {
+    if spp < 2 {
+        return v.to_vec();
+    }
+    let mut out = Vec::with_capacity(v.len());
+    if to_planar {
+        out.extend((0..spp).flat_map(|s| (0..pixels).map(move |p| v[p * spp + s])));
+    } else {
+        out.extend((0..pixels).flat_map(|p| (0..spp).map(move |s| v[s * pixels + p])));
+    }
+    out
+}
+
+/// Expands YBR_FULL_422 `Y1 Y2 Cb Cr` groups (Columns is even) to
+/// `Y1 Cb Cr Y2 Cb Cr`.
+fn expand_422<T: Copy>(v: &[T]) -> Vec<T> {
+    let mut out = Vec::with_capacity(v.len() / 2 * 3);
+    for group in v.chunks_exact(4) {
+        let [y1, y2, cb, cr] = [group[0], group[1], group[2], group[3]];
+        out.extend([y1, cb, cr, y2, cb, cr]);
+    }
+    out
+}
+
+/// Which attribute holds the pixels, deciding how samples are typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Integer,
+    Float,
+    Double,
+}
+
+/// Decodes the frames of a data set's pixel data into [`PixelBuffer`]s.
+pub struct PixelDecoder<'a> {
+    frames: Frames<'a>,
+    /// The codec decompressing encapsulated frames.
+    codec: Option<&'static dyn PixelCodec>,
+    /// Layout of a native (or decompressed) frame.
+    info: PixelInfo,
+    encoding: Encoding,
+    little_endian: bool,
+    /// 8-bit or 1-bit samples stored as big-endian OW words: each byte pair is
+    /// swapped on the wire.
+    swap_bytes: bool,
+}
+
+impl<'a> PixelDecoder<'a> {
+    /// Prepares to decode the Pixel Data of `ds`, else its Float or Double
+    /// Float Pixel Data. Encapsulated frames need a registered codec for the
+    /// data set's transfer syntax.
+    pub fn new(ds: &'a DataSet) -> Result<Self> {
+        let (tag, encoding) = [
+            (&tags::PixelData, Encoding::Integer),
+            (&tags::FloatPixelData, Encoding::Float),
+            (&tags::DoubleFloatPixelData, Encoding::Double),
+        ]
+        .into_iter()
+        .find(|(tag, _)| ds.contains(tag))
+        .ok_or_else(|| dicom_err!(NotFound, "the data set has no pixel data"))?;
+        let frames = Frames::of(ds, tag)?;
+        let ts: &TransferSyntax = ds.transfer_syntax();
+        let (codec, info) = if frames.is_encapsulated() {
+            let codec = find_codec(ts)
+                .ok_or_else(|| dicom_err!(UnsupportedFeature, "no pixel data codec registered for {}", ts.uid))?;
+            (Some(codec), codec.decoded_info(frames.info()))
+        } else {
+            (None, frames.info().clone())
+        };
+        check(&info, encoding, tag)?;
+        let little_endian = codec.is_some() || ds.is_little_endian();
+        let swap_bytes = !little_endian && info.bits_allocated <= 8 && ds.vr(tag) == Some(Vr::OW);
+        Ok(PixelDecoder { frames, codec, info, encoding, little_endian, swap_bytes })
+    }
+
+    /// The Image Pixel attributes of a decoded frame (before YBR_FULL_422
+    /// expansion).
+    pub fn info(&self) -> &PixelInfo {
+        &self.info
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Decodes frame `index` (from 0).
+    pub fn frame(&self, index: usize) -> Result<PixelBuffer> {
+        let raw = self.frames.frame(index)?;
+        let mut bytes: Cow<[u8]> = match self.codec {
+            Some(codec) => codec.decode_frame(&raw, self.frames.info())?.into(),
+            None => raw,
+        };
+        if self.swap_bytes {
+            for pair in bytes.to_mut().chunks_exact_mut(2) {
+                pair.swap(0, 1);
+            }
+        }
+        let info = &self.info;
+        let count = info.frame_samples();
+        let needed = (count * info.bits_allocated as usize).div_ceil(8);
+        ensure!(bytes.len() >= needed, InvalidData, "frame {index} holds {} of {needed} bytes", bytes.len());
+        let mut samples = self.samples(&bytes, count);
+        let mut photometric = info.photometric_interpretation.clone();
+        if photometric == "YBR_FULL_422" {
+            samples = each_samples!(wrap samples, v => expand_422(&v));
+            photometric = "YBR_FULL".to_string();
+        }
+        Ok(PixelBuffer {
+            rows: info.rows,
+            columns: info.columns,
+            samples_per_pixel: info.samples_per_pixel,
+            planar: info.planar_configuration == 1 && info.samples_per_pixel > 1,
+            photometric_interpretation: photometric,
+            samples,
+        })
+    }
+
+    /// Every frame, decoded in order.
+    pub fn iter(&self) -> impl Iterator<Item = Result<PixelBuffer>> + '_ {
+        (0..self.len()).map(|i| self.frame(i))
+    }
+
+    fn samples(&self, bytes: &[u8], count: usize) -> Samples {
+        let info = &self.info;
+        let le = self.little_endian;
+        let signed = info.pixel_representation == 1;
+        let words = |width: usize| {
+            bytes.chunks_exact(width).take(count).map(move |c| {
+                let mut w = [0u8; 8];
+                if le {
+                    w[..width].copy_from_slice(c);
+                } else {
+                    w[8 - width..].copy_from_slice(c);
+                }
+                if le { u64::from_le_bytes(w) } else { u64::from_be_bytes(w) }
+            })
+        };
+        // Bits Stored below High Bit, sign-extended for two's complement.
+        let shift = u32::from(info.high_bit + 1 - info.bits_stored);
+        let stored = u32::from(info.bits_stored);
+        let mask = if stored >= 64 { u64::MAX } else { (1u64 << stored) - 1 };
+        let unsigned = move |w: u64| (w >> shift) & mask;
+        let extend = move |w: u64| {
+            let v = unsigned(w);
+            if stored < 64 && (v >> (stored - 1)) & 1 == 1 { (v | !mask) as i64 } else { v as i64 }
+        };
+        match (self.encoding, info.bits_allocated) {
+            (Encoding::Float, _) => Samples::F32(words(4).map(|w| f32::from_bits(w as u32)).collect()),
+            (Encoding::Double, _) => Samples::F64(words(8).map(f64::from_bits).collect()),
+            (_, 1) => Samples::U8((0..count).map(|i| (bytes[i / 8] >> (i % 8)) & 1).collect()),
+            (_, 8) if signed => Samples::I8(words(1).map(|w| extend(w) as i8).collect()),
+            (_, 8) => Samples::U8(words(1).map(|w| unsigned(w) as u8).collect()),
+            (_, 16) if signed => Samples::I16(words(2).map(|w| extend(w) as i16).collect()),
+            (_, 16) => Samples::U16(words(2).map(|w| unsigned(w) as u16).collect()),
+            (_, _) if signed => Samples::I32(words(4).map(|w| extend(w) as i32).collect()),
+            (_, _) => Samples::U32(words(4).map(|w| unsigned(w) as u32).collect()),
+        }
+    }
+}
+
+/// Rejects Image Pixel attributes the decoder cannot honour.
+fn check(info: &PixelInfo, encoding: Encoding, tag: &Tag) -> Result<()> {
+    match encoding {
+        Encoding::Float => ensure!(info.bits_allocated == 32, InvalidData, "{tag} needs Bits Allocated 32"),
+        Encoding::Double => ensure!(info.bits_allocated == 64, InvalidData, "{tag} needs Bits Allocated 64"),
+        Encoding::Integer => {
+            ensure!(
+                matches!(info.bits_allocated, 1 | 8 | 16 | 32),
+                UnsupportedFeature,
+                "cannot decode {}-bit samples",
+                info.bits_allocated
+            );
+            ensure!(
+                info.bits_stored >= 1 && info.bits_stored <= info.bits_allocated,
+                InvalidData,
+                "Bits Stored {} does not fit Bits Allocated {}",
+                info.bits_stored,
+                info.bits_allocated
+            );
+            ensure!(
+                info.high_bit + 1 >= info.bits_stored && info.high_bit < info.bits_allocated,
+                InvalidData,
+                "High Bit {} does not fit Bits Stored {} in Bits Allocated {}",
+                info.high_bit,
+                info.bits_stored,
+                info.bits_allocated
+            );
+        }
+    }
+    if info.photometric_interpretation == "YBR_FULL_422" {
+        ensure!(
+            info.samples_per_pixel == 3 && info.planar_configuration == 0 && info.columns.is_multiple_of(2),
+            InvalidData,
+            "YBR_FULL_422 needs 3 interleaved samples per pixel and an even number of columns"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::Image;
+    use crate::{DcmReader, DcmWriter, HeaderType, Value};
+    use bytes::Bytes;
+
+    fn reread(ds: &DataSet, ts: &'static TransferSyntax) -> DataSet {
+        let out = DcmWriter::new().transfer_syntax(ts).to_bytes(ds).unwrap();
+        let read = DcmReader::new().header(HeaderType::NoHeader).transfer_syntax(ts).parse_bytes(out).unwrap();
+        read.dataset.unwrap()
+    }
+
+    #[test]
+    fn bits_stored_below_high_bit_are_sign_extended() {
+        // 0x0FFF is -1 in 12 bits; the overlay bit 0xF000 is ignored.
+        let words: [u16; 3] = [0xFFFF, 0x07FF, 0x0800];
+        let mut ds = Image::new(1, 3, 1, 16).native(words.iter().flat_map(|w| w.to_le_bytes()).collect());
+        ds.set(&tags::BitsStored, 12u16).unwrap();
+        ds.set(&tags::HighBit, 11u16).unwrap();
+        ds.set(&tags::PixelRepresentation, 1u16).unwrap();
+        let frame = PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+        assert_eq!(frame.samples, Samples::I16(vec![-1, 2047, -2048]));
+
+        ds.set(&tags::HighBit, 15u16).unwrap();
+        ds.set(&tags::PixelRepresentation, 0u16).unwrap();
+        let frame = PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+        assert_eq!(frame.samples, Samples::U16(vec![0xFFF, 0x07F, 0x080]));
+    }
+
+    #[test]
+    fn big_endian_matches_little_endian() {
+        for (bits, vr) in [(16u16, Vr::OW), (8, Vr::OW), (8, Vr::OB)] {
+            let ds = Image::new(2, 2, 1, bits).native_as(vr, (1u8..=8).take(4 * bits as usize / 8).collect());
+            let le = PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+            let be = reread(&ds, &TransferSyntax::ExplicitVRBigEndian);
+            assert!(!be.is_little_endian());
+            assert_eq!(PixelDecoder::new(&be).unwrap().frame(0).unwrap(), le, "{bits}-bit {vr}");
+        }
+    }
+
+    #[test]
+    fn single_bit_frames_unpack() {
+        let ds = Image::new(3, 3, 1, 1).frames(2).native(vec![0xFF, 0b1010_1011, 0b0000_0010]);
+        let decoder = PixelDecoder::new(&ds).unwrap();
+        assert_eq!(decoder.len(), 2);
+        assert_eq!(decoder.frame(0).unwrap().samples, Samples::U8(vec![1; 9]));
+        assert_eq!(decoder.frame(1).unwrap().samples, Samples::U8(vec![1, 0, 1, 0, 1, 0, 1, 0, 1]));
+    }
+
+    #[test]
+    fn planar_and_interleaved_convert() {
+        let mut ds = Image::new(1, 2, 3, 8).photometric("RGB").native(vec![10, 11, 20, 21, 30, 31]);
+        ds.set(&tags::PlanarConfiguration, 1u16).unwrap();
+        let frame = PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+        assert!(frame.planar);
+        assert_eq!(frame.value(0, 1, 2), Some(31.0));
+        let interleaved = frame.clone().into_interleaved();
+        assert_eq!(interleaved.samples, Samples::U8(vec![10, 20, 30, 11, 21, 31]));
+        assert_eq!(interleaved.value(0, 1, 2), Some(31.0));
+        assert_eq!(interleaved.into_planar(), frame);
+    }
+
+    #[test]
+    fn ybr_full_422_expands() {
+        let ds = Image::new(1, 2, 3, 8).photometric("YBR_FULL_422").native(vec![100, 110, 128, 129]);
+        let frame = PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+        assert_eq!(frame.photometric_interpretation, "YBR_FULL");
+        assert_eq!(frame.samples, Samples::U8(vec![100, 128, 129, 110, 128, 129]));
+    }
+
+    #[test]
+    fn float_pixel_data() {
+        let mut ds = Image::new(1, 2, 1, 32).build();
+        let floats: Vec<u8> = [1.5f32, -2.25].iter().flat_map(|f| f.to_le_bytes()).collect();
+        ds.set_with_vr(&tags::FloatPixelData, Vr::OF, Value::Bytes(Bytes::from(floats))).unwrap();
+        let frame = PixelDecoder::new(&ds).unwrap().frame(0).unwrap();
+        assert_eq!(frame.samples, Samples::F32(vec![1.5, -2.25]));
+
+        let mut ds = Image::new(1, 1, 1, 64).build();
+        ds.set_with_vr(&tags::DoubleFloatPixelData, Vr::OD, Value::Bytes(Bytes::from(0.1f64.to_le_bytes().to_vec())))
+            .unwrap();
+        assert_eq!(PixelDecoder::new(&ds).unwrap().frame(0).unwrap().samples, Samples::F64(vec![0.1]));
+    }
+
+    #[test]
+    fn encapsulated_frames_go_through_the_codec() {
+        let ds = Image::new(2, 2, 1, 16).frames(2).native((0..8u16).flat_map(|v| (v * 1000).to_le_bytes()).collect());
+        let rle = reread(&ds, &TransferSyntax::RLELossless);
+        let decoder = PixelDecoder::new(&rle).unwrap();
+        assert_eq!(decoder.frame(1).unwrap().samples, Samples::U16(vec![4000, 5000, 6000, 7000]));
+
+        let ds = Image::new(1, 1, 1, 12).native(vec![0, 0]);
+        assert!(PixelDecoder::new(&ds).is_err());
+    }
+}
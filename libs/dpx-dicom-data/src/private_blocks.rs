@@ -0,0 +1,191 @@
+//! Private Data Element Characteristics (PS3.3 C.12.1.1.7): a description of
+//! each private block of a data set telling de-identifiers which of its
+//! attributes are safe to keep and what to do with the others, derived from
+//! the private tag dictionary ([`PrivateIdentificationAction`]).
+
+use std::collections::BTreeMap;
+
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::tag::{PrivateIdentificationAction, Source};
+use dpx_dicom_core::{Tag, Vr, tags};
+
+use crate::convert;
+use crate::dataset::Shared;
+use crate::{DataSet, Item, OneOrMany, Value};
+
+/// What the dictionary says about the attributes of one private block.
+struct Block {
+    group: u16,
+    creator: String,
+    /// Element numbers (the low byte) of the attributes the dictionary calls
+    /// nonidentifying.
+    safe: Vec<u16>,
+    /// Element numbers by recommended action, for identifying attributes.
+    actions: BTreeMap<&'static str, Vec<u16>>,
+    /// Attributes in the block, unknown ones included.
+    len: usize,
+}
+
+impl Block {
+    /// Block Identifying Information Status (0008,0303). Attributes unknown
+    /// to the dictionary count as identifying.
+    fn status(&self) -> &'static str {
+        if self.safe.len() == self.len {
+            "SAFE"
+        } else if self.safe.is_empty() {
+            "UNSAFE"
+        } else {
+            "MIXED"
+        }
+    }
+}
+
+/// The blocks reserved in `item` that hold attributes, in tag order.
+fn blocks(shared: &Shared, item: &Item) -> Vec<Block> {
+    let entries = item.map.entries();
+    let mut blocks: Vec<Block> = entries
+        .iter()
+        .filter(|(key, _)| key.is_private_reservation())
+        .filter_map(|(reservation, el)| {
+            let creator = convert::trim_text(Vr::LO, &item.element_text(shared, el).ok()?).to_string();
+            let mut block =
+                Block { group: reservation.group(), creator, safe: Vec::new(), actions: BTreeMap::new(), len: 0 };
+            let members = entries
+                .iter()
+                .map(|(key, _)| *key)
+                .filter(|key| key.is_private_attribute() && key.to_private_reservation() == *reservation);
+            for key in members {
+                block.len += 1;
+                let element = key.element() & 0xFF;
+                let tag = Tag::new_private_cow(key.group(), key.element(), block.creator.clone());
+                let code = match tag.meta().map(|m| m.source) {
+                    Some(Source::Vendored(PrivateIdentificationAction::None)) => {
+                        block.safe.push(element);
+                        continue;
+                    }
+                    Some(Source::Vendored(PrivateIdentificationAction::D)) => "D",
+                    Some(Source::Vendored(PrivateIdentificationAction::Z)) => "Z",
+                    Some(Source::Vendored(PrivateIdentificationAction::X)) => "X",
+                    Some(Source::Vendored(PrivateIdentificationAction::U)) => "U",
+                    _ => continue,
+                };
+                block.actions.entry(code).or_default().push(element);
+            }
+            (block.len > 0).then_some(block)
+        })
+        .collect();
+    blocks.sort_by_key(|block| block.group);
+    blocks
+}
+
+fn elements(list: Vec<u16>) -> Value {
+    match <[u16; 1]>::try_from(list) {
+        Ok([one]) => Value::UInt(OneOrMany::One(u64::from(one))),
+        Err(list) => Value::UInt(OneOrMany::Many(list.into_iter().map(u64::from).collect())),
+    }
+}
+
+/// Rebuilds (0008,0300) from the private blocks at the root of `ds`, or
+/// removes it when there are none.
+pub(crate) fn update_characteristics(ds: &mut DataSet) -> Result<()> {
+    let blocks = {
+        let (shared, root) = ds.context();
+        blocks(shared, root)
+    };
+    if blocks.is_empty() {
+        ds.remove(&tags::PrivateDataElementCharacteristicsSequence);
+        return Ok(());
+    }
+    let mut sequence = ds.sequence_mut(&tags::PrivateDataElementCharacteristicsSequence)?;
+    sequence.clear();
+    for block in blocks {
+        let status = block.status();
+        let mut item = sequence.new_item();
+        item.set(&tags::PrivateGroupReference, block.group)?;
+        item.set(&tags::PrivateCreatorReference, block.creator)?;
+        item.set(&tags::BlockIdentifyingInformationStatus, status)?;
+        if status == "MIXED" {
+            item.set_value(&tags::NonidentifyingPrivateElements, elements(block.safe))?;
+        }
+        if !block.actions.is_empty() {
+            let mut actions = item.sequence_mut(&tags::DeidentificationActionSequence)?;
+            for (code, list) in block.actions {
+                let mut action = actions.new_item();
+                action.set_value(&tags::IdentifyingPrivateElements, elements(list))?;
+                action.set(&tags::DeidentificationAction, code)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    dpx_dicom_core::declare_tags! {
+        const TEST_TAGS = [
+            SafeOne: { (0x4321, 0x1000, "DPX TEST"), LO, 1, "Safe One", Vendored(None) },
+            SafeTwo: { (0x4321, 0x1001, "DPX TEST"), LO, 1, "Safe Two", Vendored(None) },
+            Removed: { (0x4321, 0x1002, "DPX TEST"), LO, 1, "Removed", Vendored(X) },
+            Dummied: { (0x4321, 0x1003, "DPX TEST"), LO, 1, "Dummied", Vendored(D) },
+            AlsoRemoved: { (0x4321, 0x1004, "DPX TEST"), LO, 1, "Also Removed", Vendored(X) },
+        ];
+    }
+    inventory::submit!(TEST_TAGS);
+
+    fn block(ds: &mut DataSet, group: u16, reservation: u16, creator: &str, elements: &[u16]) {
+        ds.set_with_vr(&Tag::new_standard(group, reservation), Vr::LO, Value::Str(creator.into())).unwrap();
+        for &e in elements {
+            ds.set_with_vr(&Tag::new_standard(group, reservation << 8 | e), Vr::LO, Value::Str("x".into())).unwrap();
+        }
+    }
+
+    fn us(item: &crate::ItemRef<'_>, tag: &Tag) -> Vec<u16> {
+        item.get_iter::<u16>(tag).unwrap().collect()
+    }
+
+    #[test]
+    fn describes_blocks_from_the_dictionary() {
+        let mut ds = DataSet::new();
+        block(&mut ds, 0x4321, 0x11, "DPX TEST", &[0, 1, 2, 3, 4]);
+        block(&mut ds, 0x4321, 0x12, "DPX TEST", &[0, 1]);
+        block(&mut ds, 0x4323, 0x11, "UNKNOWN", &[0]);
+        ds.update_private_characteristics().unwrap();
+
+        let sequence = ds.sequence(&tags::PrivateDataElementCharacteristicsSequence).unwrap();
+        assert_eq!(sequence.len(), 3);
+
+        let mixed = sequence.item(0).unwrap();
+        assert_eq!(mixed.get::<u16>(&tags::PrivateGroupReference).unwrap(), 0x4321);
+        assert_eq!(mixed.get_str(&tags::PrivateCreatorReference).unwrap().trim_end(), "DPX TEST");
+        assert_eq!(mixed.get_str(&tags::BlockIdentifyingInformationStatus).unwrap().trim_end(), "MIXED");
+        assert_eq!(us(&mixed, &tags::NonidentifyingPrivateElements), [0, 1]);
+        let actions = mixed.sequence(&tags::DeidentificationActionSequence).unwrap();
+        assert_eq!(actions.len(), 2);
+        let dummied = actions.item(0).unwrap();
+        assert_eq!(dummied.get_str(&tags::DeidentificationAction).unwrap().trim_end(), "D");
+        assert_eq!(us(&dummied, &tags::IdentifyingPrivateElements), [3]);
+        assert_eq!(us(&actions.item(1).unwrap(), &tags::IdentifyingPrivateElements), [2, 4]);
+
+        let safe = sequence.item(1).unwrap();
+        assert_eq!(safe.get_str(&tags::BlockIdentifyingInformationStatus).unwrap().trim_end(), "SAFE");
+        assert!(!safe.contains(&tags::NonidentifyingPrivateElements));
+        assert!(safe.sequence(&tags::DeidentificationActionSequence).is_none());
+
+        let unknown = sequence.item(2).unwrap();
+        assert_eq!(unknown.get_str(&tags::BlockIdentifyingInformationStatus).unwrap().trim_end(), "UNSAFE");
+        assert!(unknown.sequence(&tags::DeidentificationActionSequence).is_none());
+    }
+
+    #[test]
+    fn removed_without_private_blocks() {
+        let mut ds = DataSet::new();
+        block(&mut ds, 0x4321, 0x11, "DPX TEST", &[0]);
+        ds.update_private_characteristics().unwrap();
+        assert!(ds.contains(&tags::PrivateDataElementCharacteristicsSequence));
+        ds.remove(&Tag::new_standard(0x4321, 0x1100));
+        ds.update_private_characteristics().unwrap();
+        assert!(!ds.contains(&tags::PrivateDataElementCharacteristicsSequence));
+    }
+}
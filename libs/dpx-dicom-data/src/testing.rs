@@ -0,0 +1,99 @@
+//! Fixtures shared by the pixel data tests: test images and their
+//! [`PixelInfo`].
+
+use bytes::Bytes;
+use dpx_dicom_core::{Vr, tags};
+
+use crate::codec::PixelInfo;
+use crate::{DataSet, OneOrMany, PixelData, Value};
+
+/// A data set holding the Image Pixel attributes of a test image, finished
+/// with its Pixel Data or without.
+pub(crate) struct Image {
+    ds: DataSet,
+}
+
+impl Image {
+    /// A `rows` × `columns` image of `samples` samples per pixel, each in
+    /// `bits` bits allocated.
+    pub(crate) fn new(rows: u16, columns: u16, samples: u16, bits: u16) -> Self {
+        let mut ds = DataSet::new();
+        ds.set(&tags::SamplesPerPixel, samples).unwrap();
+        ds.set(&tags::Rows, rows).unwrap();
+        ds.set(&tags::Columns, columns).unwrap();
+        ds.set(&tags::BitsAllocated, bits).unwrap();
+        Image { ds }
+    }
+
+    pub(crate) fn photometric(mut self, photometric: &str) -> Self {
+        self.ds.set(&tags::PhotometricInterpretation, photometric).unwrap();
+        self
+    }
+
+    pub(crate) fn frames(mut self, frames: u32) -> Self {
+        self.ds.set(&tags::NumberOfFrames, frames).unwrap();
+        self
+    }
+
+    /// The image without Pixel Data.
+    pub(crate) fn build(self) -> DataSet {
+        self.ds
+    }
+
+    /// The image with native Pixel Data: OW when wider than 8 bits, OB
+    /// otherwise.
+    pub(crate) fn native(self, bytes: Vec<u8>) -> DataSet {
+        let vr = if self.ds.get::<u16>(&tags::BitsAllocated).unwrap() > 8 { Vr::OW } else { Vr::OB };
+        self.native_as(vr, bytes)
+    }
+
+    pub(crate) fn native_as(self, vr: Vr, bytes: Vec<u8>) -> DataSet {
+        self.pixel_data(vr, PixelData::Native(Bytes::from(bytes)))
+    }
+
+    /// The image with encapsulated Pixel Data of `fragments`, after a Basic
+    /// Offset Table of `bot`.
+    pub(crate) fn encapsulated(self, bot: Vec<u32>, fragments: &[&'static [u8]]) -> DataSet {
+        let fragments = fragments.iter().map(|f| Bytes::from_static(f)).collect();
+        self.pixel_data(Vr::OB, PixelData::Encapsulated { bot, fragments, eot: Vec::new() })
+    }
+
+    fn pixel_data(mut self, vr: Vr, pixels: PixelData) -> DataSet {
+        self.ds.set_with_vr(&tags::PixelData, vr, Value::Pixels(Box::new(pixels))).unwrap();
+        self.ds
+    }
+}
+
+/// The [`PixelInfo`] of a single-frame, unsigned, interleaved image storing
+/// all of its `bits`.
+pub(crate) fn pixel_info(rows: u16, columns: u16, samples: u16, bits: u16, photometric: &str) -> PixelInfo {
+    PixelInfo {
+        rows,
+        columns,
+        samples_per_pixel: samples,
+        bits_allocated: bits,
+        bits_stored: bits,
+        high_bit: bits - 1,
+        pixel_representation: 0,
+        planar_configuration: 0,
+        number_of_frames: 1,
+        photometric_interpretation: photometric.to_string(),
+    }
+}
+
+/// 16-bit words as an OW value.
+pub(crate) fn words(values: &[u16]) -> Value {
+    Value::UInt(OneOrMany::Many(values.iter().map(|&v| u64::from(v)).collect()))
+}
+
+/// Red, green and blue Palette Color Lookup Tables sharing `descriptor`.
+pub(crate) fn palette(ds: &mut DataSet, descriptor: [u64; 3], [red, green, blue]: [&[u16]; 3]) {
+    for (descriptor_tag, data, entries) in [
+        (&tags::RedPaletteColorLookupTableDescriptor, &tags::RedPaletteColorLookupTableData, red),
+        (&tags::GreenPaletteColorLookupTableDescriptor, &tags::GreenPaletteColorLookupTableData, green),
+        (&tags::BluePaletteColorLookupTableDescriptor, &tags::BluePaletteColorLookupTableData, blue),
+    ] {
+        ds.set_with_vr(descriptor_tag, Vr::US, Value::UInt(OneOrMany::Many(descriptor.to_vec()))).unwrap();
+        ds.set_with_vr(data, Vr::OW, words(entries)).unwrap();
+    }
+}
@@ -0,0 +1,265 @@
+//! Consistent replacement of UIDs across a set of data sets.
+//!
+//! A [`UidMapper`] replaces the value of every `UI` attribute of the data sets
+//! it is given, nested references included, remembering each replacement so
+//! that a UID maps to the same new UID wherever and whenever it appears. UIDs
+//! known to the active [`uid::Dictionary`](dpx_dicom_core::uid::Dictionary)
+//! (SOP Classes, Transfer Syntaxes, ...) are left untouched.
+//!
+//! The new UIDs come from a pluggable [`UidStrategy`]: [`RandomUids`],
+//! [`HashedUids`] or [`UidTable`].
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+
+use dpx_dicom_core::error::{IntoDicomErr, Result};
+use dpx_dicom_core::{Uid, Vr, dicom_err, tags};
+use uuid::Uuid;
+
+use crate::dataset::Shared;
+use crate::value::{Stored, Value};
+use crate::{DataSet, Item, ReadOutput};
+
+/// Makes the replacement of a UID seen for the first time.
+pub trait UidStrategy: Send {
+    fn replace(&mut self, uid: &str) -> Result<String>;
+}
+
+/// The `2.25` form of a UUID (PS3.5 B.2): its 128 bits as one decimal
+/// component.
+fn uuid_uid(uuid: Uuid) -> String {
+    format!("2.25.{}", uuid.as_u128())
+}
+
+/// Replaces each UID with a `2.25` UID derived from a random UUID.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomUids;
+
+impl UidStrategy for RandomUids {
+    fn replace(&mut self, _uid: &str) -> Result<String> {
+        Ok(uuid_uid(Uuid::new_v4()))
+    }
+}
+
+/// Replaces each UID with a `2.25` UID derived from a name-based (SHA-1) UUID
+/// of the original UID, in a namespace derived from a secret salt. The same
+/// salt gives the same replacements on every run and machine, without a
+/// table; without the salt they cannot be traced back.
+#[derive(Debug, Clone, Copy)]
+pub struct HashedUids {
+    namespace: Uuid,
+}
+
+impl HashedUids {
+    pub fn new(salt: &[u8]) -> Self {
+        Self { namespace: Uuid::new_v5(&Uuid::NAMESPACE_OID, salt) }
+    }
+}
+
+impl UidStrategy for HashedUids {
+    fn replace(&mut self, uid: &str) -> Result<String> {
+        Ok(uuid_uid(Uuid::new_v5(&self.namespace, uid.as_bytes())))
+    }
+}
+
+/// Replaces UIDs through a mapping table kept in a file, one
+/// `original<TAB>replacement` line per UID. UIDs missing from the table get a
+/// random `2.25` UID, appended to the file at once so that later runs reuse
+/// it.
+#[derive(Debug)]
+pub struct UidTable {
+    map: HashMap<String, String>,
+    file: LineWriter<File>,
+}
+
+impl UidTable {
+    /// Loads the table at `path`, creating an empty one if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let ctx = || format!("opening UID table {}", path.display());
+        let file = OpenOptions::new().read(true).append(true).create(true).open(path).to_dicom_err_with(ctx)?;
+        let mut map = HashMap::new();
+        for (n, line) in BufReader::new(&file).lines().enumerate() {
+            let line = line.to_dicom_err_with(ctx)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (original, replacement) = line.split_once('\t').ok_or_else(|| {
+                dicom_err!(InvalidData, "{}:{}: expected two tab-separated UIDs", path.display(), n + 1)
+            })?;
+            map.insert(original.trim().to_string(), replacement.trim().to_string());
+        }
+        Ok(Self { map, file: LineWriter::new(file) })
+    }
+}
+
+impl UidStrategy for UidTable {
+    fn replace(&mut self, uid: &str) -> Result<String> {
+        if let Some(known) = self.map.get(uid) {
+            return Ok(known.clone());
+        }
+        let replacement = uuid_uid(Uuid::new_v4());
+        writeln!(self.file, "{uid}\t{replacement}").to_dicom_err_with(|| "appending to the UID table".to_string())?;
+        self.map.insert(uid.to_string(), replacement.clone());
+        Ok(replacement)
+    }
+}
+
+/// Replaces the UIDs of many data sets consistently.
+pub struct UidMapper {
+    strategy: Box<dyn UidStrategy>,
+    map: HashMap<String, String>,
+}
+
+impl Default for UidMapper {
+    fn default() -> Self {
+        Self::new(RandomUids)
+    }
+}
+
+impl UidMapper {
+    pub fn new(strategy: impl UidStrategy + 'static) -> Self {
+        Self { strategy: Box::new(strategy), map: HashMap::new() }
+    }
+
+    /// The replacement of every UID replaced so far, by original UID.
+    pub fn mapping(&self) -> &HashMap<String, String> {
+        &self.map
+    }
+
+    /// The replacement of `uid`: the one made earlier, a new one, or `uid`
+    /// itself when empty or known to the UID dictionary.
+    pub fn map_uid(&mut self, uid: &str) -> Result<String> {
+        let uid = uid.trim_end_matches([' ', '\0']);
+        if uid.is_empty() || Uid::new(Cow::Borrowed(uid)).meta().is_some() {
+            return Ok(uid.to_string());
+        }
+        if let Some(known) = self.map.get(uid) {
+            return Ok(known.clone());
+        }
+        let replacement = self.strategy.replace(uid)?;
+        self.map.insert(uid.to_string(), replacement.clone());
+        Ok(replacement)
+    }
+
+    /// [`map_uid`](Self::map_uid) over each value of a multi-valued `UI`
+    /// value.
+    pub fn map_value(&mut self, value: &str) -> Result<String> {
+        let mapped = value.split('\\').map(|uid| self.map_uid(uid)).collect::<Result<Vec<_>>>()?;
+        Ok(mapped.join("\\"))
+    }
+
+    /// Replaces the UIDs of every `UI` attribute of `ds`, at any depth.
+    pub fn map_dataset(&mut self, ds: &mut DataSet) -> Result<()> {
+        let (shared, root) = ds.ctx_mut();
+        self.map_item(shared, root)
+    }
+
+    /// Replaces the UIDs of a file's data set, and the Media Storage SOP
+    /// Instance UID of its File Meta Information.
+    pub fn map_file(&mut self, file: &mut ReadOutput) -> Result<()> {
+        if let Some(ds) = file.dataset.as_mut() {
+            self.map_dataset(ds)?;
+        }
+        if let Some(header) = file.header.as_mut()
+            && let Some(uid) = header.get_str_some(&tags::MediaStorageSOPInstanceUID)
+        {
+            let mapped = self.map_value(&uid)?;
+            header.set(&tags::MediaStorageSOPInstanceUID, mapped)?;
+        }
+        Ok(())
+    }
+
+    fn map_item(&mut self, shared: &Shared, item: &mut Item) -> Result<()> {
+        let keys: Vec<_> = item.map.entries().iter().map(|(key, _)| *key).collect();
+        for key in keys {
+            let Some(el) = item.map.get(key) else { continue };
+            if el.vr != Vr::UI {
+                if let Some(el) = item.map.get_mut(key)
+                    && let Stored::Items(items) = &mut el.value
+                {
+                    for nested in items {
+                        self.map_item(shared, nested)?;
+                    }
+                }
+                continue;
+            }
+            let mapped = self.map_value(&item.element_str(shared, el)?)?;
+            if let Some(el) = item.map.get_mut(key) {
+                el.value = Stored::Native(Value::Str(mapped));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dpx_dicom_core::uids::svc_storage::CTImageStorage;
+
+    use super::*;
+
+    fn sample() -> DataSet {
+        let mut ds = DataSet::new();
+        ds.set(&tags::SOPClassUID, CTImageStorage).unwrap();
+        ds.set(&tags::SOPInstanceUID, "1.2.3.4.5").unwrap();
+        ds.set(&tags::StudyInstanceUID, "1.2.3.4").unwrap();
+        let mut refs = ds.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        let mut item = refs.new_item();
+        item.set(&tags::ReferencedSOPClassUID, CTImageStorage).unwrap();
+        item.set(&tags::ReferencedSOPInstanceUID, "1.2.3.4.6").unwrap();
+        ds
+    }
+
+    fn uid(ds: &DataSet, tag: &dpx_dicom_core::Tag) -> String {
+        ds.get_str(tag).unwrap().trim_end_matches('\0').to_string()
+    }
+
+    #[test]
+    fn maps_nested_and_skips_known_uids() {
+        let mut ds = sample();
+        let mut mapper = UidMapper::default();
+        mapper.map_dataset(&mut ds).unwrap();
+
+        assert_eq!(uid(&ds, &tags::SOPClassUID), CTImageStorage);
+        let sop = uid(&ds, &tags::SOPInstanceUID);
+        assert!(sop.starts_with("2.25.") && sop.len() <= 64, "{sop}");
+        let refs = ds.sequence(&tags::ReferencedImageSequence).unwrap();
+        let item = refs.item(0).unwrap();
+        assert_eq!(item.get_str(&tags::ReferencedSOPClassUID).unwrap().trim_end_matches('\0'), CTImageStorage);
+        let referenced = item.get_str(&tags::ReferencedSOPInstanceUID).unwrap().into_owned();
+        assert_eq!(mapper.mapping()["1.2.3.4.6"], referenced);
+
+        // Another instance referencing the first maps onto its new UID.
+        let mut other = DataSet::new();
+        other.set(&tags::ReferencedSOPInstanceUID, "1.2.3.4.5\\1.2.3.4.6").unwrap();
+        mapper.map_dataset(&mut other).unwrap();
+        assert_eq!(uid(&other, &tags::ReferencedSOPInstanceUID), format!("{sop}\\{referenced}"));
+    }
+
+    #[test]
+    fn hashed_uids_are_deterministic() {
+        let mut a = UidMapper::new(HashedUids::new(b"secret"));
+        let mut b = UidMapper::new(HashedUids::new(b"secret"));
+        let mut c = UidMapper::new(HashedUids::new(b"other"));
+        let mapped = a.map_uid("1.2.3.4").unwrap();
+        assert_eq!(b.map_uid("1.2.3.4").unwrap(), mapped);
+        assert_ne!(c.map_uid("1.2.3.4").unwrap(), mapped);
+        assert_ne!(a.map_uid("1.2.3.5").unwrap(), mapped);
+    }
+
+    #[test]
+    fn table_persists_across_runs() {
+        let path = std::env::temp_dir().join(format!("dpx-uid-table-{}.tsv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let first = UidMapper::new(UidTable::open(&path).unwrap()).map_uid("1.2.3.4").unwrap();
+        let second = UidMapper::new(UidTable::open(&path).unwrap()).map_uid("1.2.3.4").unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(text, format!("1.2.3.4\t{first}\n"));
+    }
+}
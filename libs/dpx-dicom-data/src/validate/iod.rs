@@ -0,0 +1,486 @@
+//! Validation against the IOD of a data set's SOP Class (PS3.3): presence of
+//! the modules the IOD is made of, the Type 1/1C/2/2C/3 rules of their
+//! attributes, the Enumerated Values and Defined Terms of `CS` attributes and
+//! the number of Items of sequences.
+//!
+//! The definitions come from a TSV file in the format written by `mk-dicom-tsv
+//! --iod-output` from the PS3.3 and PS3.4 docbook; see
+//! `utils/mk-dicom-tsv/iod_header.txt` for its format. The built-in copy,
+//! `etc/iod.tsv`, is a hand-written seed covering only the CT Image and
+//! Secondary Capture Image IODs: any other SOP Class is reported as
+//! [`Problem::UnknownSopClass`] unless its definitions are loaded into an
+//! [`IodDictionary`] from a generated file.
+//!
+//! Conditions of `C` modules and of Type 1C/2C attributes are free text in the
+//! standard and are not evaluated: a conditional module is checked only when
+//! present, and a conditional attribute only for being empty (1C). An absent
+//! conditional attribute is reported at [`Severity::Info`] as
+//! [`Problem::ConditionNotEvaluated`].
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use dpx_dicom_core::error::{IntoDicomErr, Result};
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{TagKey, dicom_err, tags};
+
+use super::{Finding, Problem, Severity, ValidationReport};
+use crate::convert::{self, keyword_path};
+use crate::dataset::Shared;
+use crate::value::{Element, Stored, Value};
+use crate::{DataSet, Item};
+
+static BUILT_IN: LazyLock<IodDictionary> = LazyLock::new(|| {
+    let mut dict = IodDictionary::new_empty();
+    dict.add_from_memory(include_str!("../../etc/iod.tsv").as_bytes()).expect("built-in IOD definitions are valid");
+    dict
+});
+
+/// The Attribute Type of PS3.5 7.4: whether an attribute is required and
+/// whether it may be empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeType {
+    Type1,
+    Type1C,
+    Type2,
+    Type2C,
+    Type3,
+}
+
+impl fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Type1 => "1",
+            Self::Type1C => "1C",
+            Self::Type2 => "2",
+            Self::Type2C => "2C",
+            Self::Type3 => "3",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Usage {
+    Mandatory,
+    Conditional,
+    UserOption,
+}
+
+#[derive(Debug, Clone)]
+struct ModuleUse {
+    module: String,
+    usage: Usage,
+}
+
+#[derive(Debug, Clone)]
+enum Terms {
+    Enumerated(Vec<String>),
+    Defined(Vec<String>),
+}
+
+/// The rule of one attribute of a module, with those of its Items.
+#[derive(Debug, Clone)]
+struct Rule {
+    key: TagKey,
+    kind: AttributeType,
+    items: Option<(usize, Option<usize>)>,
+    terms: Option<Terms>,
+    children: Vec<Rule>,
+}
+
+/// IOD and module definitions: which IOD each SOP Class conforms to, which
+/// modules each IOD is made of and the attribute rules of each module.
+#[derive(Debug, Clone, Default)]
+pub struct IodDictionary {
+    sop_classes: HashMap<String, String>,
+    iods: HashMap<String, Vec<ModuleUse>>,
+    modules: HashMap<String, Vec<Rule>>,
+}
+
+impl IodDictionary {
+    /// The built-in definitions.
+    pub fn new() -> Self {
+        BUILT_IN.clone()
+    }
+
+    pub fn new_empty() -> Self {
+        Self::default()
+    }
+
+    /// The IOD instances of `sop_class_uid` conform to.
+    pub fn iod_of(&self, sop_class_uid: &str) -> Option<&str> {
+        self.sop_classes.get(sop_class_uid).map(String::as_str)
+    }
+
+    /// Adds definitions in the `mk-dicom-tsv --iod-output` format. An IOD or
+    /// module defined again replaces the existing definition.
+    pub fn add_from_memory(&mut self, buf: impl io::Read) -> Result<()> {
+        let mut redefined = HashSet::new();
+        for (n, line) in io::BufReader::new(buf).lines().enumerate() {
+            let line = line.to_dicom_err_with(|| "reading IOD definitions".to_string())?;
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            self.add_line(&line, &mut redefined)
+                .map_err(|e| dicom_err!(InvalidData, "line {} of IOD definitions: {e}", n + 1))?;
+        }
+        Ok(())
+    }
+
+    pub fn add_from_file(&mut self, file_name: impl AsRef<Path>) -> Result<()> {
+        let file_name = file_name.as_ref();
+        let file = std::fs::File::open(file_name)
+            .to_dicom_err_with(|| format!("unable to open IOD definitions \"{}\"", file_name.display()))?;
+        self.add_from_memory(file)
+    }
+
+    fn add_line(&mut self, line: &str, redefined: &mut HashSet<String>) -> Result<()> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+        match field(0) {
+            "IOD" => {
+                self.sop_classes.insert(field(1).to_string(), field(2).to_string());
+            }
+            "MODULE" => {
+                let usage = match field(4) {
+                    "M" => Usage::Mandatory,
+                    "C" => Usage::Conditional,
+                    "U" => Usage::UserOption,
+                    other => return Err(dicom_err!(InvalidData, "unknown module usage \"{other}\"")),
+                };
+                let modules = self.iods.entry(field(1).to_string()).or_default();
+                if redefined.insert(format!("IOD {}", field(1))) {
+                    modules.clear();
+                }
+                modules.push(ModuleUse { module: field(3).to_string(), usage });
+            }
+            "ATTR" => {
+                let depth: usize =
+                    field(2).parse().map_err(|_| dicom_err!(InvalidData, "invalid depth \"{}\"", field(2)))?;
+                let rule = Rule {
+                    key: field(3).parse()?,
+                    kind: match field(4) {
+                        "1" => AttributeType::Type1,
+                        "1C" => AttributeType::Type1C,
+                        "2" => AttributeType::Type2,
+                        "2C" => AttributeType::Type2C,
+                        "3" => AttributeType::Type3,
+                        other => return Err(dicom_err!(InvalidData, "unknown attribute type \"{other}\"")),
+                    },
+                    items: parse_items(field(5))?,
+                    terms: parse_terms(field(6))?,
+                    children: Vec::new(),
+                };
+                let mut rules = self.modules.entry(field(1).to_string()).or_default();
+                if redefined.insert(format!("MODULE {}", field(1))) {
+                    rules.clear();
+                }
+                for _ in 0..depth {
+                    let parent = rules.last_mut().ok_or_else(|| dicom_err!(InvalidData, "no parent sequence"))?;
+                    rules = &mut parent.children;
+                }
+                rules.push(rule);
+            }
+            other => return Err(dicom_err!(InvalidData, "unknown record \"{other}\"")),
+        }
+        Ok(())
+    }
+
+    /// Checks `ds` against the IOD of its SOP Class UID.
+    pub fn validate(&self, ds: &DataSet) -> ValidationReport {
+        let (shared, root) = ds.context();
+        let mut checker = Checker { shared, module: "SOP Common", findings: Vec::new() };
+        let uid = root
+            .map
+            .get(tags::SOPClassUID.key)
+            .and_then(|el| root.element_str(shared, el).ok())
+            .map(|uid| uid.trim_end_matches([' ', '\0']).to_string())
+            .filter(|uid| !uid.is_empty());
+        let Some(uid) = uid else {
+            checker.check_item(root, "", &[Rule::required(tags::SOPClassUID.key)]);
+            return ValidationReport { iod: None, findings: checker.findings };
+        };
+        let Some(iod) = self.sop_classes.get(&uid) else {
+            let finding = Finding {
+                severity: Severity::Error,
+                problem: Problem::UnknownSopClass(uid),
+                module: None,
+                path: String::new(),
+            };
+            return ValidationReport { iod: None, findings: vec![finding] };
+        };
+
+        let modules = self.iods.get(iod).map(Vec::as_slice).unwrap_or_default();
+        let mandatory: HashSet<TagKey> = modules
+            .iter()
+            .filter(|m| m.usage == Usage::Mandatory)
+            .filter_map(|m| self.modules.get(&m.module))
+            .flatten()
+            .map(|rule| rule.key)
+            .collect();
+        for m in modules {
+            let Some(rules) = self.modules.get(&m.module) else { continue };
+            checker.module = &m.module;
+            // Attributes shared with a mandatory module do not tell that an
+            // optional one is present.
+            let present = rules
+                .iter()
+                .any(|r| root.map.contains_key(r.key) && (m.usage == Usage::Mandatory || !mandatory.contains(&r.key)));
+            if present {
+                checker.check_item(root, "", rules);
+            } else if m.usage == Usage::Mandatory {
+                checker.push(Severity::Error, Problem::MissingModule, String::new());
+            }
+        }
+        ValidationReport { iod: Some(iod.clone()), findings: checker.findings }
+    }
+}
+
+impl Rule {
+    fn required(key: TagKey) -> Self {
+        Self { key, kind: AttributeType::Type1, items: None, terms: None, children: Vec::new() }
+    }
+}
+
+/// `1`, `0-1`, `1-n`, ...: the bounds of the number of Items.
+fn parse_items(s: &str) -> Result<Option<(usize, Option<usize>)>> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || dicom_err!(InvalidData, "invalid Item count \"{s}\"");
+    let (min, max) = s.split_once('-').unwrap_or((s, s));
+    let min = min.parse().map_err(|_| invalid())?;
+    let max = if max == "n" { None } else { Some(max.parse().map_err(|_| invalid())?) };
+    Ok(Some((min, max)))
+}
+
+/// `E:A\B` or `D:A\B`.
+fn parse_terms(s: &str) -> Result<Option<Terms>> {
+    if s.is_empty() {
+        return Ok(None);
+    }
+    let list = |values: &str| values.split('\\').map(str::to_string).collect();
+    match s.split_once(':') {
+        Some(("E", values)) => Ok(Some(Terms::Enumerated(list(values)))),
+        Some(("D", values)) => Ok(Some(Terms::Defined(list(values)))),
+        _ => Err(dicom_err!(InvalidData, "invalid terms \"{s}\"")),
+    }
+}
+
+/// Whether an attribute has no value: zero length, only padding, or no Items.
+fn is_empty(shared: &Shared, item: &Item, el: &Element) -> bool {
+    match &el.value {
+        Stored::Items(items) => items.is_empty(),
+        Stored::Native(Value::Str(_) | Value::Bytes(_)) | Stored::Mapped(_) | Stored::Owned(_) => {
+            match item.element_bytes(shared, el) {
+                Some(bytes) if matches!(el.vr.info().kind, Kind::Text { .. }) => {
+                    bytes.iter().all(|b| matches!(b, b' ' | 0))
+                }
+                Some(bytes) => bytes.is_empty(),
+                None => true,
+            }
+        }
+        // Typed values always hold at least one value.
+        Stored::Native(_) => false,
+    }
+}
+
+struct Checker<'a> {
+    shared: &'a Shared,
+    module: &'a str,
+    findings: Vec<Finding>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, severity: Severity, problem: Problem, path: String) {
+        self.findings.push(Finding { severity, problem, module: Some(self.module.to_string()), path });
+    }
+
+    fn check_item(&mut self, item: &Item, parent: &str, rules: &[Rule]) {
+        for rule in rules {
+            let path = keyword_path(parent, rule.key);
+            let Some(el) = item.map.get(rule.key) else {
+                match rule.kind {
+                    AttributeType::Type1 | AttributeType::Type2 => {
+                        self.push(Severity::Error, Problem::MissingAttribute(rule.kind), path)
+                    }
+                    AttributeType::Type1C | AttributeType::Type2C => {
+                        self.push(Severity::Info, Problem::ConditionNotEvaluated(rule.kind), path)
+                    }
+                    AttributeType::Type3 => (),
+                }
+                continue;
+            };
+            if is_empty(self.shared, item, el) {
+                if matches!(rule.kind, AttributeType::Type1 | AttributeType::Type1C) {
+                    self.push(Severity::Error, Problem::EmptyAttribute(rule.kind), path);
+                }
+                continue;
+            }
+            if let Stored::Items(items) = &el.value {
+                if let Some((min, max)) = rule.items
+                    && (items.len() < min || max.is_some_and(|max| items.len() > max))
+                {
+                    self.push(Severity::Error, Problem::ItemCount { found: items.len(), min, max }, path.clone());
+                }
+                for (i, nested) in items.iter().enumerate() {
+                    self.check_item(nested, &format!("{path}[{i}]"), &rule.children);
+                }
+            } else if let Some(terms) = &rule.terms
+                && let Ok(text) = item.element_str(self.shared, el)
+            {
+                for value in text.split('\\').map(|v| convert::trim_text(el.vr, v)).filter(|v| !v.is_empty()) {
+                    match terms {
+                        Terms::Enumerated(list) if !list.iter().any(|t| t == value) => {
+                            self.push(Severity::Error, Problem::NotEnumerated(value.to_string()), path.clone())
+                        }
+                        Terms::Defined(list) if !list.iter().any(|t| t == value) => {
+                            self.push(Severity::Warning, Problem::NotDefined(value.to_string()), path.clone())
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks `ds` against the built-in IOD definitions.
+pub(crate) fn validate(ds: &DataSet) -> ValidationReport {
+    BUILT_IN.validate(ds)
+}
+
+#[cfg(test)]
+mod tests {
+    use dpx_dicom_core::uids::svc_storage::{CTImageStorage, SecondaryCaptureImageStorage};
+
+    use super::*;
+    use dpx_dicom_core::Vr;
+
+    /// A Secondary Capture image with every required attribute.
+    fn secondary_capture() -> DataSet {
+        let mut ds = DataSet::new();
+        ds.set(&tags::SOPClassUID, SecondaryCaptureImageStorage).unwrap();
+        ds.set(&tags::SOPInstanceUID, "1.2.3.4.5").unwrap();
+        for tag in [&tags::PatientName, &tags::PatientID, &tags::PatientBirthDate, &tags::StudyDate] {
+            ds.set(tag, "").unwrap();
+        }
+        for tag in [&tags::StudyTime, &tags::ReferringPhysicianName, &tags::StudyID, &tags::AccessionNumber] {
+            ds.set(tag, "").unwrap();
+        }
+        ds.set(&tags::PatientSex, "O").unwrap();
+        ds.set(&tags::StudyInstanceUID, "1.2.3").unwrap();
+        ds.set(&tags::SeriesInstanceUID, "1.2.3.4").unwrap();
+        ds.set(&tags::Modality, "OT").unwrap();
+        ds.set(&tags::SeriesNumber, "").unwrap();
+        ds.set(&tags::ConversionType, "WSD").unwrap();
+        ds.set(&tags::InstanceNumber, "1").unwrap();
+        ds.set(&tags::SamplesPerPixel, 1u16).unwrap();
+        ds.set(&tags::PhotometricInterpretation, "MONOCHROME2").unwrap();
+        for tag in [&tags::Rows, &tags::Columns, &tags::BitsAllocated, &tags::BitsStored] {
+            ds.set(tag, 8u16).unwrap();
+        }
+        ds.set(&tags::HighBit, 7u16).unwrap();
+        ds.set(&tags::PixelRepresentation, 0u16).unwrap();
+        ds.set_value(&tags::PixelData, Value::Bytes(vec![0; 64].into())).unwrap();
+        ds
+    }
+
+    fn problems(report: &ValidationReport) -> Vec<(&str, &Problem)> {
+        report.findings.iter().map(|f| (f.path.as_str(), &f.problem)).collect()
+    }
+
+    #[test]
+    fn conformant_instance_has_no_errors_or_warnings() {
+        let report = secondary_capture().validate();
+        assert_eq!(report.iod.as_deref(), Some("Secondary Capture Image"));
+        assert!(report.findings.iter().all(|f| f.severity == Severity::Info), "{report}");
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn reports_conditional_attributes_as_not_evaluated() {
+        let report = secondary_capture().validate();
+        let found = problems(&report);
+        let planar = ("PlanarConfiguration", &Problem::ConditionNotEvaluated(AttributeType::Type1C));
+        assert!(found.contains(&planar), "{report}");
+        assert!(found.contains(&("Laterality", &Problem::ConditionNotEvaluated(AttributeType::Type2C))), "{report}");
+        // Present conditional attributes are checked as usual.
+        assert!(!found.iter().any(|(path, _)| *path == "PixelData"), "{report}");
+        assert!(report.to_string().contains("Type 1C attribute missing, condition not evaluated"), "{report}");
+    }
+
+    #[test]
+    fn reports_attribute_types_and_terms() {
+        let mut ds = secondary_capture();
+        ds.remove(&tags::PatientID);
+        ds.set(&tags::SeriesInstanceUID, "").unwrap();
+        ds.set(&tags::PatientSex, "X").unwrap();
+        ds.set(&tags::ConversionType, "XYZ").unwrap();
+        ds.remove(&tags::Rows);
+
+        let report = ds.validate();
+        assert!(!report.is_valid());
+        let found = problems(&report);
+        assert!(found.contains(&("PatientID", &Problem::MissingAttribute(AttributeType::Type2))), "{report}");
+        assert!(found.contains(&("PatientSex", &Problem::NotEnumerated("X".into()))), "{report}");
+        assert!(found.contains(&("SeriesInstanceUID", &Problem::EmptyAttribute(AttributeType::Type1))), "{report}");
+        assert!(found.contains(&("Rows", &Problem::MissingAttribute(AttributeType::Type1))), "{report}");
+        let warning = report.warnings().next().unwrap();
+        assert_eq!((warning.path.as_str(), &warning.problem), ("ConversionType", &Problem::NotDefined("XYZ".into())));
+        assert_eq!(report.errors().count(), 4, "{report}");
+    }
+
+    #[test]
+    fn checks_modules_and_sequences() {
+        let mut ds = secondary_capture();
+        ds.set(&tags::SOPClassUID, CTImageStorage).unwrap();
+        let mut refs = ds.sequence_mut(&tags::ReferencedPerformedProcedureStepSequence).unwrap();
+        refs.new_item().set(&tags::ReferencedSOPClassUID, "1.2.3").unwrap();
+        refs.new_item().set(&tags::ReferencedSOPInstanceUID, "1.2.4").unwrap();
+
+        let report = ds.validate();
+        assert_eq!(report.iod.as_deref(), Some("CT Image"));
+        let missing: Vec<_> = report
+            .findings
+            .iter()
+            .filter(|f| f.problem == Problem::MissingModule)
+            .map(|f| f.module.as_deref().unwrap())
+            .collect();
+        assert_eq!(missing, ["Frame of Reference", "General Equipment", "Image Plane"]);
+        let found = problems(&report);
+        let sequence = "ReferencedPerformedProcedureStepSequence";
+        assert!(found.contains(&(sequence, &Problem::ItemCount { found: 2, min: 1, max: Some(1) })), "{report}");
+        let missing = Problem::MissingAttribute(AttributeType::Type1);
+        assert!(found.contains(&(&format!("{sequence}[0].ReferencedSOPInstanceUID"), &missing)), "{report}");
+        assert!(found.contains(&(&format!("{sequence}[1].ReferencedSOPClassUID"), &missing)), "{report}");
+        // The CT Image module requires Rescale Slope and Intercept.
+        assert!(found.contains(&("RescaleSlope", &missing)), "{report}");
+    }
+
+    #[test]
+    fn unknown_sop_class_and_definition_overrides() {
+        let mut ds = secondary_capture();
+        ds.set(&tags::SOPClassUID, "1.2.3.4").unwrap();
+        let report = ds.validate();
+        assert_eq!(problems(&report), [("", &Problem::UnknownSopClass("1.2.3.4".into()))]);
+
+        let mut dict = IodDictionary::new_empty();
+        let tsv = "IOD\t1.2.3.4\tTest\nMODULE\tTest\tImage\tTest\tM\nATTR\tTest\t0\t(0028,0010)\t1\n";
+        dict.add_from_memory(tsv.as_bytes()).unwrap();
+        assert!(dict.validate(&ds).is_valid());
+        ds.set_with_vr(&tags::Rows, Vr::US, Value::Bytes(Default::default())).unwrap();
+        let report = dict.validate(&ds);
+        assert_eq!(problems(&report), [("Rows", &Problem::EmptyAttribute(AttributeType::Type1))]);
+
+        dict.add_from_memory("ATTR\tTest\t0\t(0028,0008)\t1\n".as_bytes()).unwrap();
+        let report = dict.validate(&ds);
+        // The module now only has Number of Frames, absent.
+        assert_eq!(problems(&report), [("", &Problem::MissingModule)]);
+        assert!(dict.add_from_memory("ATTR\tOther\t1\t(0028,0010)\t1\n".as_bytes()).is_err());
+        assert!(dict.add_from_memory("MODULE\tTest\tImage\tTest\tQ\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Conformance checks of data sets, reported as a [`ValidationReport`]: a list
+//! of [`Finding`]s, each naming the offending attribute by its path from the
+//! root of the data set. [`iod`] checks a data set against the IOD of its SOP
+//! Class, [`values`] checks each value against its VR and VM.
+
+mod iod;
+mod values;
+
+use std::fmt;
+
+use dpx_dicom_core::Vr;
+
+pub(crate) use iod::validate;
+pub use iod::{AttributeType, IodDictionary};
+pub(crate) use values::validate_values;
+
+/// How badly a [`Finding`] breaks conformance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Not checked, e.g. a conditional attribute whose condition is not
+    /// evaluated.
+    Info,
+    /// Allowed by the standard but suspicious, e.g. a value outside the
+    /// Defined Terms.
+    Warning,
+    /// Not conformant.
+    Error,
+}
+
+/// What is wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The SOP Class UID is not known to the [`IodDictionary`], so nothing
+    /// else was checked.
+    UnknownSopClass(String),
+    /// None of the attributes of a mandatory module is present.
+    MissingModule,
+    /// An attribute of this type is absent.
+    MissingAttribute(AttributeType),
+    /// An attribute of this type is present with no value.
+    EmptyAttribute(AttributeType),
+    /// A Type 1C or 2C attribute is absent; whether its condition holds is
+    /// not evaluated.
+    ConditionNotEvaluated(AttributeType),
+    /// A value outside the Enumerated Values.
+    NotEnumerated(String),
+    /// A value outside the Defined Terms.
+    NotDefined(String),
+    /// The number of Items of a sequence is out of the allowed range.
+    ItemCount { found: usize, min: usize, max: Option<usize> },
+    /// The number of values does not satisfy the Value Multiplicity of the
+    /// dictionary, `(min, max, stride)` as in
+    /// [`Meta::vm`](dpx_dicom_core::tag::Meta::vm).
+    ValueMultiplicity { found: usize, vm: (u8, u8, u8) },
+    /// A value longer than its VR allows, in bytes or, for `SH`, `LO`, `ST`,
+    /// `LT` and each component group of `PN`, in characters.
+    ValueTooLong { length: usize, max: usize },
+    /// A character outside the repertoire of the VR.
+    InvalidCharacter(char),
+    /// A value not matching the syntax of its VR, or binary data of a length
+    /// its VR can not have.
+    InvalidValue { vr: Vr, value: String },
+}
+
+/// One conformance problem of a data set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub problem: Problem,
+    /// The module whose rule is broken, if any.
+    pub module: Option<String>,
+    /// The attribute, by keyword, prefixed with the sequences and Item indexes
+    /// leading to it: `ReferencedImageSequence[0].ReferencedSOPClassUID`.
+    /// Empty for the data set as a whole.
+    pub path: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}: ")?;
+        if let Some(module) = &self.module {
+            write!(f, "{module}: ")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        match &self.problem {
+            Problem::UnknownSopClass(uid) => write!(f, "no IOD known for SOP Class \"{uid}\""),
+            Problem::MissingModule => write!(f, "mandatory module missing"),
+            Problem::MissingAttribute(kind) => write!(f, "Type {kind} attribute missing"),
+            Problem::EmptyAttribute(kind) => write!(f, "Type {kind} attribute empty"),
+            Problem::ConditionNotEvaluated(kind) => {
+                write!(f, "Type {kind} attribute missing, condition not evaluated")
+            }
+            Problem::NotEnumerated(value) => write!(f, "\"{value}\" is not an Enumerated Value"),
+            Problem::NotDefined(value) => write!(f, "\"{value}\" is not a Defined Term"),
+            Problem::ItemCount { found, min, max: Some(max) } => {
+                write!(f, "{found} Items where {min} to {max} are allowed")
+            }
+            Problem::ItemCount { found, min, max: None } => {
+                write!(f, "{found} Items where at least {min} are required")
+            }
+            Problem::ValueMultiplicity { found, vm: (min, max, stride) } => {
+                write!(f, "{found} values where VM is {min}")?;
+                match (max, stride) {
+                    (0, 0 | 1) => write!(f, "-n"),
+                    (0, stride) => write!(f, "-{stride}n"),
+                    (max, _) if max == min => Ok(()),
+                    (max, _) => write!(f, "-{max}"),
+                }
+            }
+            Problem::ValueTooLong { length, max } => write!(f, "value length {length} exceeds {max}"),
+            Problem::InvalidCharacter(c) => write!(f, "invalid character {:?} (U+{:04X})", c, u32::from(*c)),
+            Problem::InvalidValue { vr, value } => write!(f, "\"{value}\" is not a valid {vr} value"),
+        }
+    }
+}
+
+/// The outcome of validating a data set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// The IOD the data set was checked against.
+    pub iod: Option<String>,
+    pub findings: Vec<Finding>,
+}
+
+impl ValidationReport {
+    /// Whether no [`Severity::Error`] was found.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(|f| f.severity == Severity::Warning)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            writeln!(f, "{finding}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+//! Value-level checks of every attribute of a data set (PS3.5 6.2 and 6.4):
+//! the Value Multiplicity of the dictionary, the maximum length of each VR,
+//! the characters its repertoire allows and the syntax of `AS`, `DA`, `DS`,
+//! `DT`, `IS`, `TM` and `UI` values.
+
+use dpx_dicom_charset::char_class::{self, CharClass};
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{DicomDate, DicomDateTime, DicomTime, Tag, Vr, tags};
+
+use super::{Finding, Problem, Severity, ValidationReport};
+use crate::convert::{self, keyword_path};
+use crate::dataset::Shared;
+use crate::value::{Element, Stored, Value};
+use crate::{DataSet, Item};
+
+/// Maximum length of one value, and whether it counts characters rather than
+/// bytes. `PN` limits each component group.
+fn max_length(vr: Vr) -> Option<(usize, bool)> {
+    match vr {
+        Vr::AE | Vr::CS | Vr::DS => Some((16, false)),
+        Vr::AS => Some((4, false)),
+        Vr::DA => Some((8, false)),
+        Vr::DT => Some((26, false)),
+        Vr::IS => Some((12, false)),
+        Vr::TM => Some((14, false)),
+        Vr::UI => Some((64, false)),
+        Vr::SH => Some((16, true)),
+        Vr::LO | Vr::PN => Some((64, true)),
+        Vr::ST => Some((1024, true)),
+        Vr::LT => Some((10240, true)),
+        _ => None,
+    }
+}
+
+/// Whether `c` may appear in a value of `vr`. `extended` tells whether the
+/// Specific Character Set extends the repertoire of translatable VRs.
+fn allowed(vr: Vr, c: char, extended: bool) -> bool {
+    match vr {
+        Vr::AS => c.is_ascii_digit() || matches!(c, 'D' | 'W' | 'M' | 'Y'),
+        Vr::CS => c.is_ascii_uppercase() || c.is_ascii_digit() || matches!(c, ' ' | '_'),
+        Vr::DA => c.is_ascii_digit(),
+        Vr::DS => c.is_ascii_digit() || matches!(c, '+' | '-' | 'E' | 'e' | '.' | ' '),
+        Vr::DT => c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | ' '),
+        Vr::IS => c.is_ascii_digit() || matches!(c, '+' | '-' | ' '),
+        Vr::TM => c.is_ascii_digit() || matches!(c, '.' | ' '),
+        Vr::UI => c.is_ascii_digit() || c == '.',
+        _ => {
+            let class = if extended && matches!(vr.info().kind, Kind::Text { translatable: true, .. }) {
+                char_class::in_extended_repertoire(c, 0)
+            } else {
+                char_class::in_default_repertoire(c, 0)
+            };
+            match class {
+                CharClass::Default | CharClass::Delimiter => true,
+                CharClass::Control => matches!(vr, Vr::LT | Vr::ST | Vr::UT),
+                CharClass::Invalid => false,
+            }
+        }
+    }
+}
+
+/// Whether a value of a VR with a syntax of its own is well formed.
+fn well_formed(vr: Vr, value: &str) -> bool {
+    match vr {
+        Vr::AS => {
+            let b = value.as_bytes();
+            b.len() == 4 && b[..3].iter().all(u8::is_ascii_digit) && matches!(b[3], b'D' | b'W' | b'M' | b'Y')
+        }
+        Vr::DA => DicomDate::from_dicom(value.as_bytes()).is_ok(),
+        Vr::TM => DicomTime::from_dicom(value.as_bytes()).is_ok(),
+        Vr::DT => DicomDateTime::from_dicom(value.as_bytes(), false, None).is_ok(),
+        Vr::DS => value.trim().parse::<f64>().is_ok_and(f64::is_finite),
+        Vr::IS => value.trim().parse::<i64>().is_ok_and(|n| i32::try_from(n).is_ok()),
+        Vr::UI => value.split('.').all(|c| !c.is_empty() && (c == "0" || !c.starts_with('0'))),
+        _ => true,
+    }
+}
+
+/// Whether `count` values satisfy the `(min, max, stride)` of
+/// [`Meta::vm`](dpx_dicom_core::tag::Meta::vm).
+fn vm_allows((min, max, stride): (u8, u8, u8), count: usize) -> bool {
+    count >= usize::from(min)
+        && (max == 0 || count <= usize::from(max))
+        && (stride <= 1 || count.is_multiple_of(usize::from(stride)))
+}
+
+/// Whether the Specific Character Set of `item` goes beyond the default
+/// repertoire.
+fn extends_repertoire(shared: &Shared, item: &Item) -> bool {
+    item.map
+        .get(tags::SpecificCharacterSet.key)
+        .and_then(|el| item.element_str(shared, el).ok())
+        .is_some_and(|s| s.split('\\').any(|term| !matches!(term.trim(), "" | "ISO_IR 6" | "ISO 2022 IR 6")))
+}
+
+struct Checker<'a> {
+    shared: &'a Shared,
+    findings: Vec<Finding>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, problem: Problem, path: &str) {
+        self.findings.push(Finding { severity: Severity::Error, problem, module: None, path: path.to_string() });
+    }
+
+    fn check_item(&mut self, item: &Item, parent: &str, extended: bool) {
+        let extended = extended || extends_repertoire(self.shared, item);
+        for (key, el) in item.map.entries() {
+            let path = keyword_path(parent, *key);
+            if let Stored::Items(items) = &el.value {
+                for (i, nested) in items.iter().enumerate() {
+                    self.check_item(nested, &format!("{path}[{i}]"), extended);
+                }
+                continue;
+            }
+            let count = if matches!(el.vr.info().kind, Kind::Text { .. }) {
+                self.check_text(item, el, &path, extended)
+            } else {
+                self.count_binary(item, el, &path)
+            };
+            let creator = key.is_private_attribute().then(|| item.private_creator(self.shared, *key)).flatten();
+            let meta = Tag::new(*key, creator.map(Into::into)).meta();
+            if let Some(meta) = meta
+                && count > 0
+                && [meta.vr.0, meta.vr.1, meta.vr.2].contains(&el.vr)
+                && !vm_allows(meta.vm, count)
+            {
+                self.push(Problem::ValueMultiplicity { found: count, vm: meta.vm }, &path);
+            }
+        }
+    }
+
+    /// Checks each value of a text attribute, returning their number.
+    fn check_text(&mut self, item: &Item, el: &Element, path: &str, extended: bool) -> usize {
+        let Ok(text) = item.element_text(self.shared, el) else {
+            self.push(Problem::InvalidValue { vr: el.vr, value: "<not textual>".to_string() }, path);
+            return 0;
+        };
+        if text.trim_end_matches([' ', '\0']).is_empty() {
+            return 0;
+        }
+        let values: Vec<&str> =
+            if convert::is_multi_valued_text(el.vr) { text.split('\\').collect() } else { vec![&text] };
+        for value in &values {
+            let value = value.trim_end_matches([' ', '\0']);
+            if let Some((max, chars)) = max_length(el.vr) {
+                let groups: Vec<&str> = if el.vr == Vr::PN { value.split('=').collect() } else { vec![value] };
+                for group in groups {
+                    let length = if chars { group.chars().count() } else { group.len() };
+                    if length > max {
+                        self.push(Problem::ValueTooLong { length, max }, path);
+                    }
+                }
+            }
+            if let Some(c) = value.chars().find(|c| !allowed(el.vr, *c, extended)) {
+                self.push(Problem::InvalidCharacter(c), path);
+            } else if !value.is_empty() && !well_formed(el.vr, value) {
+                self.push(Problem::InvalidValue { vr: el.vr, value: value.to_string() }, path);
+            }
+        }
+        values.len()
+    }
+
+    /// The number of values of a binary attribute.
+    fn count_binary(&mut self, item: &Item, el: &Element, path: &str) -> usize {
+        let width = match el.vr.info().kind {
+            Kind::I16 | Kind::U16 => 2,
+            Kind::I32 | Kind::U32 | Kind::F32 => 4,
+            Kind::I64 | Kind::U64 | Kind::F64 => 8,
+            _ => 0,
+        };
+        match (&el.value, item.element_bytes(self.shared, el)) {
+            (Stored::Native(Value::Int(v)), _) => v.len(),
+            (Stored::Native(Value::UInt(v)), _) => v.len(),
+            (Stored::Native(Value::Float(v)), _) => v.len(),
+            (Stored::Native(Value::Tags(v)), _) => v.len(),
+            (_, Some(bytes)) if width > 0 => {
+                if !bytes.len().is_multiple_of(width) {
+                    let value = format!("<{} bytes>", bytes.len());
+                    self.push(Problem::InvalidValue { vr: el.vr, value }, path);
+                }
+                bytes.len() / width
+            }
+            (_, Some([])) => 0,
+            _ => 1,
+        }
+    }
+}
+
+/// Checks every attribute of `ds`, at any depth.
+pub(crate) fn validate_values(ds: &DataSet) -> ValidationReport {
+    let (shared, root) = ds.context();
+    let mut checker = Checker { shared, findings: Vec::new() };
+    checker.check_item(root, "", false);
+    ValidationReport { iod: None, findings: checker.findings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(ds: &DataSet) -> Vec<(String, Problem)> {
+        ds.validate_values().findings.into_iter().map(|f| (f.path, f.problem)).collect()
+    }
+
+    #[test]
+    fn conformant_values_pass() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PatientName, "Doe^John").unwrap();
+        ds.set(&tags::PatientAge, "042Y").unwrap();
+        ds.set(&tags::StudyDate, "20240229").unwrap();
+        ds.set(&tags::StudyTime, "101530.25").unwrap();
+        ds.set(&tags::AcquisitionDateTime, "20240229101530+0100").unwrap();
+        ds.set(&tags::SOPInstanceUID, "1.2.840.10008.0.1").unwrap();
+        ds.set(&tags::PixelSpacing, "0.5\\-1.25E-1").unwrap();
+        ds.set(&tags::ImageType, "ORIGINAL\\PRIMARY\\AXIAL").unwrap();
+        ds.set(&tags::InstanceNumber, " 12").unwrap();
+        ds.set(&tags::Rows, 512u16).unwrap();
+        ds.set(&tags::ImageComments, "line one\r\nline two").unwrap();
+        let mut refs = ds.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        refs.new_item().set(&tags::ReferencedSOPInstanceUID, "1.2.3").unwrap();
+        assert_eq!(problems(&ds), []);
+    }
+
+    #[test]
+    fn reports_multiplicity_length_characters_and_syntax() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PixelSpacing, "0.5").unwrap();
+        ds.set(&tags::PatientID, "X".repeat(65)).unwrap();
+        ds.set(&tags::Modality, "ct").unwrap();
+        ds.set(&tags::StudyDate, "20241301").unwrap();
+        ds.set(&tags::PatientAge, "42Y").unwrap();
+        ds.set(&tags::InstanceNumber, "3000000000").unwrap();
+        ds.set(&tags::PatientName, "Doe\rJohn").unwrap();
+        ds.set_with_vr(&tags::Rows, Vr::US, Value::Bytes(vec![1, 2, 3].into())).unwrap();
+        let mut refs = ds.sequence_mut(&tags::ReferencedImageSequence).unwrap();
+        refs.new_item().set(&tags::ReferencedSOPInstanceUID, "1.02.3").unwrap();
+
+        let found = problems(&ds);
+        let expect = |path: &str, problem: Problem| {
+            assert!(found.contains(&(path.to_string(), problem.clone())), "{path}: {problem:?} not in {found:?}")
+        };
+        expect("PixelSpacing", Problem::ValueMultiplicity { found: 1, vm: (2, 2, 1) });
+        expect("PatientID", Problem::ValueTooLong { length: 65, max: 64 });
+        expect("Modality", Problem::InvalidCharacter('c'));
+        expect("StudyDate", Problem::InvalidValue { vr: Vr::DA, value: "20241301".into() });
+        expect("PatientAge", Problem::InvalidValue { vr: Vr::AS, value: "42Y".into() });
+        expect("InstanceNumber", Problem::InvalidValue { vr: Vr::IS, value: "3000000000".into() });
+        expect("PatientName", Problem::InvalidCharacter('\r'));
+        expect("Rows", Problem::InvalidValue { vr: Vr::US, value: "<3 bytes>".into() });
+        expect(
+            "ReferencedImageSequence[0].ReferencedSOPInstanceUID",
+            Problem::InvalidValue { vr: Vr::UI, value: "1.02.3".into() },
+        );
+        assert_eq!(found.len(), 9, "{found:?}");
+    }
+
+    #[test]
+    fn repertoire_follows_specific_character_set() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PatientName, "Müller").unwrap();
+        ds.remove(&tags::SpecificCharacterSet);
+        assert_eq!(problems(&ds), [("PatientName".to_string(), Problem::InvalidCharacter('ü'))]);
+        ds.set(&tags::SpecificCharacterSet, "ISO_IR 192").unwrap();
+        assert_eq!(problems(&ds), []);
+    }
+}
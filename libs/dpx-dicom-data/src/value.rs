@@ -1,7 +1,8 @@
 use std::ops::Range;
 
 use bytes::Bytes;
-use dpx_dicom_core::{DicomDate, DicomDateTime, DicomTime, Tag, TagKey, Vr};
+use dpx_dicom_core::error::Result;
+use dpx_dicom_core::{DicomDate, DicomDateTime, DicomTime, Tag, TagKey, Vr, ensure, tags};
 
 use crate::item::Item;
 
@@ -99,6 +100,9 @@ pub enum Value {
     Bytes(Bytes),
     /// Pixel data (7FE0,0010); boxed to keep [`Value`] small.
     Pixels(Box<PixelData>),
+    /// A value held elsewhere and not retrieved: the BulkDataURI of a DICOM
+    /// JSON attribute. Must be resolved (replaced) before binary encoding.
+    BulkData(String),
 }
 
 /// Pixel data, native (contiguous) or encapsulated (offset table + fragments).
@@ -106,8 +110,75 @@ pub enum Value {
 pub enum PixelData {
     /// Uncompressed, single contiguous blob.
     Native(Bytes),
-    /// Encapsulated: Basic Offset Table plus per-fragment slices.
-    Encapsulated { bot: Vec<u32>, fragments: Vec<Bytes> },
+    /// Encapsulated: Basic Offset Table plus per-fragment slices, and the
+    /// Extended Offset Table (7FE0,0001) with its Lengths (7FE0,0002) as one
+    /// `(offset, length)` pair per frame — empty unless the data set has one,
+    /// in which case the Basic Offset Table is empty.
+    Encapsulated { bot: Vec<u32>, fragments: Vec<Bytes>, eot: Vec<(u64, u64)> },
+}
+
+impl PixelData {
+    /// The little-endian Value Field as the text formats (JSON / XML
+    /// `InlineBinary`) carry it: the bytes as-is for native data; for
+    /// encapsulated data the Basic Offset Table item, one item per fragment and
+    /// the Sequence Delimitation Item.
+    pub(crate) fn value_field(&self) -> Bytes {
+        match self {
+            PixelData::Native(b) => b.clone(),
+            PixelData::Encapsulated { bot, fragments, .. } => {
+                let item = |out: &mut Vec<u8>, len: usize| {
+                    out.extend_from_slice(&[0xFE, 0xFF, 0x00, 0xE0]);
+                    out.extend_from_slice(&(len as u32).to_le_bytes());
+                };
+                let size = 8 + bot.len() * 4 + fragments.iter().map(|f| 8 + f.len()).sum::<usize>() + 8;
+                let mut out = Vec::with_capacity(size);
+                item(&mut out, bot.len() * 4);
+                for offset in bot {
+                    out.extend_from_slice(&offset.to_le_bytes());
+                }
+                for fragment in fragments {
+                    item(&mut out, fragment.len());
+                    out.extend_from_slice(fragment);
+                }
+                out.extend_from_slice(&[0xFE, 0xFF, 0xDD, 0xE0, 0, 0, 0, 0]);
+                Bytes::from(out)
+            }
+        }
+    }
+
+    /// Splits an encapsulated little-endian Value Field (the inverse of
+    /// [`value_field`](Self::value_field)) into its offset table and fragments,
+    /// slicing `field` zero-copy. Strict, unlike the binary parser: the text
+    /// formats have no legacy writers to tolerate.
+    pub(crate) fn from_encapsulated_field(field: Bytes) -> Result<Self> {
+        let mut pos = 0;
+        let mut items = Vec::new();
+        loop {
+            ensure!(pos + 8 <= field.len(), InvalidData, "encapsulated pixel data lacks a Sequence Delimitation Item");
+            let tag = TagKey::new(
+                u16::from_le_bytes([field[pos], field[pos + 1]]),
+                u16::from_le_bytes([field[pos + 2], field[pos + 3]]),
+            );
+            let len = u32::from_le_bytes([field[pos + 4], field[pos + 5], field[pos + 6], field[pos + 7]]) as usize;
+            pos += 8;
+            if tag == tags::SequenceDelimitationItem.key {
+                break;
+            }
+            ensure!(
+                tag == tags::Item.key && pos + len <= field.len(),
+                InvalidData,
+                "malformed encapsulated pixel data item at offset {}",
+                pos - 8
+            );
+            items.push(field.slice(pos..pos + len));
+            pos += len;
+        }
+        ensure!(!items.is_empty(), InvalidData, "encapsulated pixel data lacks a Basic Offset Table");
+        let table = items.remove(0);
+        ensure!(table.len().is_multiple_of(4), InvalidData, "Basic Offset Table length is not a multiple of 4");
+        let bot = table.chunks_exact(4).map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect();
+        Ok(PixelData::Encapsulated { bot, fragments: items, eot: Vec::new() })
+    }
 }
 
 /// A single value inline, or many on the heap. Avoids a `Vec` allocation for
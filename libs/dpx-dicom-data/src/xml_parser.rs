@@ -0,0 +1,493 @@
+//! Native DICOM Model (PS3.19 Annex A) XML parser.
+//!
+//! The XML sibling of [`dcm_parser`](crate::dcm_parser) and
+//! [`json_parser`](crate::json_parser), producing the same storage forms as
+//! the JSON reader. Private attributes are placed by their `privateCreator`:
+//! the block named in the tag is used when its reservation matches (or is
+//! absent), otherwise the attribute moves to the block already reserved for
+//! that creator, or to a newly reserved free block. A tag whose block byte is
+//! `00` or `xx` (as some writers emit) is always placed this way.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use bytes::Bytes;
+use roxmltree::{Document, Node, ParsingOptions};
+
+use dpx_dicom_core::error::{ErrContext, IntoDicomErr, Result};
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{Tag, TagKey, TransferSyntax, Vr, dicom_err, ensure, tags};
+
+use crate::convert::{self, parse_tag_hex};
+use crate::dataset::{DataSet, DatasetKind};
+use crate::item::{ElementMap, Item, dictionary_vr};
+use crate::value::{Element, OneOrMany, PixelData, Stored, Value};
+use crate::xml_writer::{PN_COMPONENTS, PN_GROUPS};
+
+/// Configurable PS3.19 XML reader. Set parameters with the builder methods,
+/// then call one of the `parse_*` entry points.
+#[derive(Debug, Clone, Default)]
+pub struct XmlReader {
+    xfer: Option<&'static TransferSyntax>,
+}
+
+impl XmlReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Transfer syntax the document was produced from. Only decides whether an
+    /// inline Pixel Data value is encapsulated; by default it is taken from
+    /// (0002,0010) when present, else native. Binary values in the XML model
+    /// are little endian, so a big-endian syntax is rejected.
+    pub fn transfer_syntax(mut self, ts: &'static TransferSyntax) -> Self {
+        self.xfer = Some(ts);
+        self
+    }
+
+    // --- Entry points ------------------------------------------------------
+
+    /// Parses a `<NativeDicomModel>` document.
+    pub fn parse_str(&self, xml: &str) -> Result<DataSet> {
+        // PS3.19 documents may carry a DOCTYPE; entities are not expanded.
+        let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+        let doc = Document::parse_with_options(xml, options)
+            .map_err(|e| dicom_err!(InvalidData, "invalid DICOM XML: {e}"))?;
+        self.dataset(doc.root_element())
+    }
+
+    /// Reads a UTF-8 document from any reader.
+    pub fn parse_bufreader<R: Read>(&self, mut reader: R) -> Result<DataSet> {
+        let mut xml = String::new();
+        reader.read_to_string(&mut xml).to_dicom_err_with(|| "reading DICOM XML".to_string())?;
+        self.parse_str(&xml)
+    }
+
+    /// Reads a document from a file.
+    pub fn parse_file(&self, path: impl AsRef<Path>) -> Result<DataSet> {
+        let path = path.as_ref();
+        let xml = fs::read_to_string(path).to_dicom_err_with(|| format!("reading {}", path.display()))?;
+        self.parse_str(&xml)
+    }
+
+    // --- Orchestration -----------------------------------------------------
+
+    fn dataset(&self, root: Node) -> Result<DataSet> {
+        ensure!(
+            root.has_tag_name("NativeDicomModel"),
+            InvalidData,
+            "DICOM XML root is <{}>, expected <NativeDicomModel>",
+            root.tag_name().name()
+        );
+        let ts = match self.xfer {
+            Some(ts) => ts,
+            None => declared_transfer_syntax(root).unwrap_or(&TransferSyntax::ExplicitVRLittleEndian),
+        };
+        ensure!(
+            ts.is_little_endian,
+            UnsupportedFeature,
+            "DICOM XML binary values are little endian; cannot read under {}",
+            ts.uid
+        );
+        let mut decoder = Decoder { encapsulated: ts.is_encapsulated, non_ascii: None };
+        let item = decoder.item(root)?;
+
+        let mut ds = DataSet::parsed(Bytes::new(), ts, DatasetKind::Dataset);
+        *ds.root_mut() = item;
+        ds.sync_context()?;
+        if let Some(text) = decoder.non_ascii {
+            ds.stamp_charset_for(&text);
+        }
+        Ok(ds)
+    }
+}
+
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |n| n.is_element() && n.has_tag_name(name))
+}
+
+/// The highest `number` a list with fewer children may use, leaving the
+/// values in between empty.
+const MAX_SPARSE_NUMBER: usize = 64;
+
+/// The `name` children of `node` placed by their 1-based `number` attribute;
+/// missing numbers are `None` (empty values). Unnumbered children follow in
+/// document order. A number may not exceed the count of `name` children or
+/// [`MAX_SPARSE_NUMBER`], whichever is larger, so that it can not make the
+/// list grow out of proportion to the document.
+fn numbered<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Result<Vec<Option<Node<'a, 'input>>>> {
+    let max = children(node, name).count().max(MAX_SPARSE_NUMBER);
+    let mut out: Vec<Option<Node>> = Vec::new();
+    for child in children(node, name) {
+        let index = match child.attribute("number") {
+            Some(n) => {
+                let n: usize =
+                    n.trim().parse().map_err(|_| dicom_err!(InvalidData, "invalid <{name}> number {n:?}"))?;
+                ensure!(n >= 1, InvalidData, "<{name}> numbers start at 1");
+                ensure!(n <= max, InvalidData, "<{name}> number {n} is out of range");
+                n - 1
+            }
+            None => out.len(),
+        };
+        if out.len() <= index {
+            out.resize(index + 1, None);
+        }
+        out[index] = Some(child);
+    }
+    Ok(out)
+}
+
+/// The transfer syntax named by a (0002,0010) attribute in the document, if any.
+fn declared_transfer_syntax(root: Node) -> Option<&'static TransferSyntax> {
+    let attr = children(root, "DicomAttribute").find(|n| n.attribute("tag") == Some("00020010"))?;
+    let uid = children(attr, "Value").next()?.text()?;
+    TransferSyntax::from_uid(uid.trim_end_matches(['\0', ' ']))
+}
+
+/// Parses the `tag` attribute; the second value is `true` when the private
+/// block byte is unspecified: `xx`, or `00` outside of the Private Creator
+/// elements (gggg,0010-00FF), which keep their tag.
+fn parse_tag(s: &str) -> Result<(TagKey, bool)> {
+    if s.len() == 8 && s.is_char_boundary(4) && s.is_char_boundary(6) && s[4..6].eq_ignore_ascii_case("xx") {
+        let key = parse_tag_hex(&format!("{}00{}", &s[..4], &s[6..]))?;
+        return Ok((key, true));
+    }
+    let key = parse_tag_hex(s)?;
+    let unspecified =
+        key.is_private() && key.element() >> 8 == 0 && key.element() & 0xFF != 0 && !key.is_private_reservation();
+    Ok((key, unspecified))
+}
+
+/// A private attribute awaiting block placement by its creator.
+struct PendingPrivate {
+    key: TagKey,
+    unspecified_block: bool,
+    creator: String,
+    element: Element,
+}
+
+/// Per-document decoding state.
+struct Decoder {
+    /// Pixel Data `InlineBinary` carries an encapsulated Value Field.
+    encapsulated: bool,
+    /// The first non-ASCII translatable text seen, for (0008,0005) stamping.
+    non_ascii: Option<String>,
+}
+
+impl Decoder {
+    fn item(&mut self, node: Node) -> Result<Item> {
+        let mut map = ElementMap::default();
+        let mut pending = Vec::new();
+        for attr in children(node, "DicomAttribute") {
+            let hex = attr.attribute("tag").ok_or_else(|| dicom_err!(InvalidData, "<DicomAttribute> without a tag"))?;
+            let (key, unspecified_block) = parse_tag(hex)?;
+            let creator = attr.attribute("privateCreator").map(str::trim).filter(|c| !c.is_empty());
+            let placed_key = if unspecified_block { TagKey::new(key.group(), 0x1000 | key.element()) } else { key };
+            let vr = match attr.attribute("vr") {
+                Some(vr) => Vr::try_from(vr)?,
+                None => dictionary_vr(placed_key, creator),
+            };
+            let value = self.attribute(key, vr, attr).err_context_with(|| format!("DICOM XML attribute {hex}"))?;
+            let element = Element::new(vr, value);
+            match creator {
+                Some(creator) if placed_key.is_private_attribute() => {
+                    pending.push(PendingPrivate { key, unspecified_block, creator: creator.to_owned(), element });
+                }
+                _ => {
+                    map.insert(key, element);
+                }
+            }
+        }
+        for p in pending {
+            let key = place_private(&mut map, &p)?;
+            map.insert(key, p.element);
+        }
+        Ok(Item::from_map(map))
+    }
+
+    fn attribute(&mut self, key: TagKey, vr: Vr, node: Node) -> Result<Stored> {
+        if let Some(bulk) = children(node, "BulkData").next() {
+            let uri = match (bulk.attribute("uri"), bulk.attribute("uuid")) {
+                (Some(uri), _) => uri.to_owned(),
+                (None, Some(uuid)) => format!("urn:uuid:{uuid}"),
+                (None, None) => return Err(dicom_err!(InvalidData, "<BulkData> has neither uri nor uuid")),
+            };
+            return Ok(Stored::Native(Value::BulkData(uri)));
+        }
+        if let Some(inline) = children(node, "InlineBinary").next() {
+            let b64: String = inline.text().unwrap_or("").chars().filter(|c| !c.is_ascii_whitespace()).collect();
+            let bytes = Bytes::from(BASE64.decode(b64).map_err(|e| dicom_err!(InvalidData, "invalid base64: {e}"))?);
+            if key == tags::PixelData.key && self.encapsulated {
+                let px = PixelData::from_encapsulated_field(bytes)?;
+                return Ok(Stored::Native(Value::Pixels(Box::new(px))));
+            }
+            return Ok(Stored::Owned(bytes));
+        }
+        if vr == Vr::SQ {
+            let mut items = Vec::new();
+            for item in numbered(node, "Item")? {
+                items.push(match item {
+                    Some(item) => self.item(item)?,
+                    None => Item::default(),
+                });
+            }
+            return Ok(Stored::Items(items));
+        }
+        let tokens = if vr == Vr::PN {
+            numbered(node, "PersonName")?.into_iter().map(|n| n.map(person_name).unwrap_or_default()).collect()
+        } else {
+            numbered(node, "Value")?.into_iter().map(|n| n.and_then(|n| n.text()).unwrap_or("").to_owned()).collect()
+        };
+        self.values(vr, tokens)
+    }
+
+    fn values(&mut self, vr: Vr, tokens: Vec<String>) -> Result<Stored> {
+        if tokens.is_empty() {
+            return Ok(Stored::Owned(Bytes::new()));
+        }
+        match vr.info().kind {
+            _ if vr == Vr::AT => {
+                let tags =
+                    tokens.iter().map(|t| Ok(Tag::new(parse_tag_hex(t.trim())?, None))).collect::<Result<Vec<_>>>()?;
+                Ok(Stored::Native(Value::Tags(one_or_many(tags))))
+            }
+            Kind::Text { translatable, .. } => {
+                ensure!(
+                    tokens.len() == 1 || convert::is_multi_valued_text(vr),
+                    InvalidData,
+                    "VR {vr} holds a single value, got {}",
+                    tokens.len()
+                );
+                let text = tokens.join("\\");
+                if translatable {
+                    if self.non_ascii.is_none() && !text.is_ascii() {
+                        self.non_ascii = Some(text.clone());
+                    }
+                    Ok(Stored::Native(Value::Str(text)))
+                } else {
+                    Ok(Stored::Owned(Bytes::from(text)))
+                }
+            }
+            Kind::U16 | Kind::U32 | Kind::U64 => {
+                let v = tokens.iter().map(|t| t.trim().parse().ok()).collect::<Option<Vec<u64>>>();
+                let v = v.ok_or_else(|| dicom_err!(InvalidData, "invalid {vr} value"))?;
+                Ok(Stored::Native(Value::UInt(one_or_many(v))))
+            }
+            Kind::I16 | Kind::I32 | Kind::I64 => {
+                let v = tokens.iter().map(|t| t.trim().parse().ok()).collect::<Option<Vec<i64>>>();
+                let v = v.ok_or_else(|| dicom_err!(InvalidData, "invalid {vr} value"))?;
+                Ok(Stored::Native(Value::Int(one_or_many(v))))
+            }
+            Kind::F32 | Kind::F64 => {
+                let v = tokens.iter().map(|t| t.trim().parse().ok()).collect::<Option<Vec<f64>>>();
+                let v = v.ok_or_else(|| dicom_err!(InvalidData, "invalid {vr} value"))?;
+                Ok(Stored::Native(Value::Float(one_or_many(v))))
+            }
+            Kind::Bytes | Kind::Items | Kind::Invalid => {
+                Err(dicom_err!(InvalidData, "VR {vr} must use <InlineBinary> or <BulkData>"))
+            }
+        }
+    }
+}
+
+/// One Person Name value in DICOM syntax: components joined with `'^'`, groups
+/// with `'='`, trailing empty components and groups dropped.
+fn person_name(node: Node) -> String {
+    let join = |parts: Vec<&str>, sep: &str| {
+        let used = parts.iter().rposition(|p| !p.is_empty()).map_or(0, |i| i + 1);
+        parts[..used].join(sep)
+    };
+    let groups: Vec<String> = PN_GROUPS
+        .iter()
+        .map(|g| match children(node, g).next() {
+            Some(group) => join(
+                PN_COMPONENTS.iter().map(|c| children(group, c).next().and_then(|n| n.text()).unwrap_or("")).collect(),
+                "^",
+            ),
+            None => String::new(),
+        })
+        .collect();
+    join(groups.iter().map(String::as_str).collect(), "=")
+}
+
+/// The creator string held by a Private Creator element.
+fn creator_of(el: &Element) -> Option<&str> {
+    match &el.value {
+        Stored::Native(Value::Str(s)) => Some(convert::trim_text(Vr::LO, s)),
+        Stored::Owned(b) => std::str::from_utf8(b).ok().map(|s| convert::trim_text(Vr::LO, s)),
+        _ => None,
+    }
+}
+
+/// Chooses the key of a private attribute from its creator, reserving a block
+/// in `map` when none is reserved for that creator yet.
+fn place_private(map: &mut ElementMap, p: &PendingPrivate) -> Result<TagKey> {
+    let group = p.key.group();
+    let with_block = |block: u16| TagKey::new(group, (block << 8) | (p.key.element() & 0xFF));
+    let reservation = |block: u16| TagKey::new(group, block);
+
+    if !p.unspecified_block {
+        let block = p.key.element() >> 8;
+        match map.get(reservation(block)) {
+            Some(el) if creator_of(el) == Some(p.creator.as_str()) => return Ok(p.key),
+            Some(_) => {}
+            None => {
+                map.insert(reservation(block), Element::new(Vr::LO, Stored::Native(Value::Str(p.creator.clone()))));
+                return Ok(p.key);
+            }
+        }
+    }
+    let reserved = (0x10..=0xFF).find(|&b| map.get(reservation(b)).and_then(creator_of) == Some(p.creator.as_str()));
+    if let Some(block) = reserved {
+        return Ok(with_block(block));
+    }
+    let free = (0x10..=0xFF)
+        .find(|&b| !map.contains_key(reservation(b)))
+        .ok_or_else(|| dicom_err!(InvalidData, "no free private block in group {group:04X}"))?;
+    map.insert(reservation(free), Element::new(Vr::LO, Stored::Native(Value::Str(p.creator.clone()))));
+    Ok(with_block(free))
+}
+
+fn one_or_many<T>(mut v: Vec<T>) -> OneOrMany<T> {
+    if v.len() == 1 { OneOrMany::One(v.swap_remove(0)) } else { OneOrMany::Many(v) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DcmWriter, XmlWriter};
+    use dpx_dicom_core::DicomDate;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<NativeDicomModel xml:space="preserve">
+  <DicomAttribute tag="00080020" vr="DA" keyword="StudyDate"><Value number="1">20130409</Value></DicomAttribute>
+  <DicomAttribute tag="00080061" vr="CS"><Value number="1">CT</Value><Value number="3">PET</Value></DicomAttribute>
+  <DicomAttribute tag="00100010" vr="PN">
+    <PersonName number="1">
+      <Alphabetic><FamilyName>Wang</FamilyName><GivenName>XiaoDong</GivenName></Alphabetic>
+      <Ideographic><FamilyName>王</FamilyName><GivenName>小東</GivenName></Ideographic>
+    </PersonName>
+  </DicomAttribute>
+  <DicomAttribute tag="00100020"><Value number="1">ID-1</Value></DicomAttribute>
+  <DicomAttribute tag="00280010" vr="US"><Value number="1">512</Value></DicomAttribute>
+  <DicomAttribute tag="00280030" vr="DS"><Value number="1">0.5</Value><Value number="2">0.25</Value></DicomAttribute>
+  <DicomAttribute tag="00081115" vr="SQ">
+    <Item number="1"><DicomAttribute tag="0020000E" vr="UI"><Value number="1">1.2.3</Value></DicomAttribute></Item>
+  </DicomAttribute>
+  <DicomAttribute tag="00420011" vr="OB"><InlineBinary>JVBE
+Rg==</InlineBinary></DicomAttribute>
+  <DicomAttribute tag="7FE00010" vr="OW"><BulkData uri="https://pacs/bulk/1"/></DicomAttribute>
+</NativeDicomModel>"#;
+
+    #[test]
+    fn reads_native_model() {
+        let ds = XmlReader::new().parse_str(SAMPLE).expect("parse");
+        assert_eq!(ds.get::<DicomDate>(&tags::StudyDate).unwrap(), DicomDate::from_dicom(b"20130409").unwrap());
+        assert_eq!(ds.get_all::<String>(&tags::ModalitiesInStudy).unwrap(), ["CT", "", "PET"]);
+        assert_eq!(ds.get::<String>(&tags::PatientName).unwrap(), "Wang^XiaoDong=王^小東");
+        assert_eq!(ds.get::<String>(&tags::SpecificCharacterSet).unwrap(), "ISO_IR 192");
+        assert_eq!(ds.vr(&tags::PatientID), Some(Vr::LO));
+        assert_eq!(ds.get::<u16>(&tags::Rows).unwrap(), 512);
+        assert_eq!(ds.get_all::<f64>(&tags::PixelSpacing).unwrap(), [0.5, 0.25]);
+        let seq = ds.sequence(&tags::ReferencedSeriesSequence).expect("sequence");
+        assert_eq!(seq.item(0).unwrap().get::<String>(&tags::SeriesInstanceUID).unwrap(), "1.2.3");
+        assert_eq!(ds.get_bytes(&tags::EncapsulatedDocument).unwrap(), b"%PDF");
+        assert!(matches!(ds.value(&tags::PixelData).unwrap(), Value::BulkData(uri) if uri == "https://pacs/bulk/1"));
+    }
+
+    #[test]
+    fn roundtrips_through_writer() {
+        let mut ds = XmlReader::new().parse_str(SAMPLE).expect("parse");
+        ds.set_with_vr(&tags::PixelData, Vr::OW, Value::Bytes(Bytes::from_static(&[1, 0, 2, 0]))).unwrap();
+        ds.set_value(&tags::FrameIncrementPointer, Value::Tags(OneOrMany::One(tags::FrameTime.clone()))).unwrap();
+        let xml = XmlWriter::new().pretty(true).to_string(&ds).unwrap();
+        let back = XmlReader::new().parse_str(&xml).expect("reparse");
+        assert_eq!(DcmWriter::new().to_bytes(&back).unwrap(), DcmWriter::new().to_bytes(&ds).unwrap());
+    }
+
+    #[test]
+    fn places_private_attributes_by_creator() {
+        let ds = XmlReader::new()
+            .parse_str(
+                r#"<NativeDicomModel>
+                <DicomAttribute tag="00090010" vr="LO"><Value number="1">OTHER</Value></DicomAttribute>
+                <DicomAttribute tag="00091001" vr="LO" privateCreator="ACME"><Value number="1">a</Value></DicomAttribute>
+                <DicomAttribute tag="0009xx02" vr="LO" privateCreator="ACME"><Value number="1">b</Value></DicomAttribute>
+                <DicomAttribute tag="00190010" vr="LO"><Value number="1">GEMS</Value></DicomAttribute>
+                <DicomAttribute tag="00191001" vr="LO" privateCreator="GEMS"><Value number="1">c</Value></DicomAttribute>
+            </NativeDicomModel>"#,
+            )
+            .expect("parse");
+        // Block 10 of group 0009 belongs to OTHER, so ACME gets block 11.
+        assert_eq!(ds.get::<String>(&Tag::new_standard(0x0009, 0x0011)).unwrap(), "ACME");
+        assert_eq!(ds.get::<String>(&Tag::new_standard(0x0009, 0x1101)).unwrap(), "a");
+        assert_eq!(ds.get::<String>(&Tag::new_standard(0x0009, 0x1102)).unwrap(), "b");
+        assert_eq!(ds.get::<String>(&Tag::new_standard(0x0019, 0x1001)).unwrap(), "c");
+        assert!(!ds.contains(&Tag::new_standard(0x0009, 0x1001)));
+    }
+
+    #[test]
+    fn keeps_private_creator_elements() {
+        let ds = XmlReader::new()
+            .parse_str(
+                r#"<NativeDicomModel>
+                <DicomAttribute tag="00090010"><Value number="1">ACME</Value></DicomAttribute>
+                <DicomAttribute tag="00090011" privateCreator="ACME"><Value number="1">OTHER</Value></DicomAttribute>
+                <DicomAttribute tag="00090002" vr="LO" privateCreator="ACME"><Value number="1">a</Value></DicomAttribute>
+            </NativeDicomModel>"#,
+            )
+            .expect("parse");
+        // Creator elements are LO without a `vr` and stay where they are.
+        assert_eq!(ds.vr(&Tag::new_standard(0x0009, 0x0010)), Some(Vr::LO));
+        assert_eq!(ds.get::<String>(&Tag::new_standard(0x0009, 0x0010)).unwrap(), "ACME");
+        assert_eq!(ds.get::<String>(&Tag::new_standard(0x0009, 0x0011)).unwrap(), "OTHER");
+        assert!(!ds.contains(&Tag::new_standard(0x0009, 0x1010)));
+        // A `00` block below the creator range is still placed by creator.
+        assert_eq!(ds.get::<String>(&Tag::new_standard(0x0009, 0x1002)).unwrap(), "a");
+    }
+
+    #[test]
+    fn encapsulated_pixel_data_and_errors() {
+        let xml = r#"<NativeDicomModel>
+            <DicomAttribute tag="00020010" vr="UI"><Value number="1">1.2.840.10008.1.2.4.50</Value></DicomAttribute>
+            <DicomAttribute tag="7FE00010" vr="OB"><InlineBinary>/v8A4AAAAAD+/wDgAgAAAP/Y/v/d4AAAAAA=</InlineBinary></DicomAttribute>
+        </NativeDicomModel>"#;
+        let ds = XmlReader::new().parse_str(xml).expect("parse");
+        assert_eq!(ds.transfer_syntax().uid, TransferSyntax::JPEGBaseline8Bit.uid);
+        match ds.value(&tags::PixelData).unwrap() {
+            Value::Pixels(px) => assert!(
+                matches!(*px, PixelData::Encapsulated { ref fragments, .. } if fragments[0][..] == [0xFF, 0xD8])
+            ),
+            other => panic!("expected pixel data, got {other:?}"),
+        }
+        assert!(XmlReader::new().parse_str("<Other/>").is_err());
+        assert!(
+            XmlReader::new().parse_str(r#"<NativeDicomModel><DicomAttribute vr="LO"/></NativeDicomModel>"#).is_err()
+        );
+        assert!(
+            XmlReader::new()
+                .parse_str(r#"<NativeDicomModel><DicomAttribute tag="00280010" vr="US"><Value number="1">x</Value></DicomAttribute></NativeDicomModel>"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn bounds_value_numbers() {
+        fn modalities(numbers: impl IntoIterator<Item = String>) -> Result<usize> {
+            let values: String = numbers.into_iter().map(|n| format!(r#"<Value number="{n}">CT</Value>"#)).collect();
+            let xml = format!(
+                r#"<NativeDicomModel><DicomAttribute tag="00080061" vr="CS">{values}</DicomAttribute></NativeDicomModel>"#
+            );
+            Ok(XmlReader::new().parse_str(&xml)?.get_all::<String>(&tags::ModalitiesInStudy)?.len())
+        }
+        assert_eq!(modalities(["64".into()]).unwrap(), 64);
+        for number in ["65", "4000000000", "18446744073709551615", "18446744073709551616"] {
+            assert!(modalities([number.into()]).is_err(), "{number}");
+        }
+        // Dense lists may be longer.
+        assert_eq!(modalities((1..=100).rev().map(|n: u32| n.to_string())).unwrap(), 100);
+    }
+}
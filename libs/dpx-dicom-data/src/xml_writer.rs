@@ -0,0 +1,366 @@
+//! Native DICOM Model (PS3.19 Annex A) XML serializer.
+//!
+//! The XML sibling of [`dcm_writer`](crate::dcm_writer) and
+//! [`json_writer`](crate::json_writer): a `<NativeDicomModel>` holding one
+//! `<DicomAttribute>` per attribute, with `<Value>`, `<PersonName>`, `<Item>`,
+//! `<InlineBinary>` or `<BulkData>` children. Private attributes carry their
+//! `privateCreator`. Text is decoded through the root charset, so the document
+//! is always UTF-8; binary values are little endian.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::Write;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+use dpx_dicom_core::error::{IntoDicomErr, Result};
+use dpx_dicom_core::tag::{Meta, Source};
+use dpx_dicom_core::vr::Kind;
+use dpx_dicom_core::{Tag, TagKey, Vr, dicom_err};
+
+use crate::DataSet;
+use crate::convert;
+use crate::dataset::Shared;
+use crate::item::Item;
+use crate::value::{Element, Stored, Value};
+
+/// Person Name component groups, in `'='` order.
+pub(crate) const PN_GROUPS: [&str; 3] = ["Alphabetic", "Ideographic", "Phonetic"];
+/// Person Name components of one group, in `'^'` order.
+pub(crate) const PN_COMPONENTS: [&str; 5] = ["FamilyName", "GivenName", "MiddleName", "NamePrefix", "NameSuffix"];
+
+/// Serializes a [`DataSet`] to the PS3.19 Native DICOM Model XML.
+///
+/// Binary values are inlined as base64 unless
+/// [`bulk_data_uri`](Self::bulk_data_uri) moves large ones out of line; a
+/// [`Value::BulkData`] placeholder is written back as `<BulkData uri=…/>`.
+#[derive(Debug, Clone, Default)]
+pub struct XmlWriter {
+    pretty: bool,
+    /// URI prefix and the byte length above which a binary value is referenced
+    /// rather than inlined.
+    bulk_data: Option<(String, usize)>,
+}
+
+impl XmlWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indent the output for humans (default: compact).
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Writes binary values longer than `threshold` bytes as `<BulkData>` with
+    /// a `uri` of `prefix` followed by the attribute path (`7FE00010`, or
+    /// `00081115/1/00081150` inside a sequence, items numbered from 1 as in
+    /// the document) instead of inline base64.
+    pub fn bulk_data_uri(mut self, prefix: impl Into<String>, threshold: usize) -> Self {
+        self.bulk_data = Some((prefix.into(), threshold));
+        self
+    }
+
+    /// Writes the data set as an XML document.
+    pub fn write_dataset<W: Write>(&self, ds: &DataSet, mut w: W) -> Result<()> {
+        let doc = self.to_string(ds)?;
+        w.write_all(doc.as_bytes()).to_dicom_err_with(|| "writing DICOM XML".to_string())
+    }
+
+    /// Serializes the data set to an XML string.
+    pub fn to_string(&self, ds: &DataSet) -> Result<String> {
+        let (shared, root) = ds.context();
+        let mut enc = Encoder {
+            shared,
+            bulk_data: self.bulk_data.as_ref().map(|(p, t)| (p.as_str(), *t)),
+            pretty: self.pretty,
+            out: String::new(),
+        };
+        enc.out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        enc.newline(0);
+        enc.out.push_str("<NativeDicomModel xml:space=\"preserve\">");
+        enc.item(root, "", 1)?;
+        enc.newline(0);
+        enc.out.push_str("</NativeDicomModel>");
+        enc.newline(0);
+        Ok(enc.out)
+    }
+}
+
+/// Escapes XML markup characters in text and attribute values.
+fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Per-document encoding state: the root context and the output buffer.
+struct Encoder<'a> {
+    shared: &'a Shared,
+    bulk_data: Option<(&'a str, usize)>,
+    pretty: bool,
+    out: String,
+}
+
+impl Encoder<'_> {
+    fn newline(&mut self, depth: usize) {
+        if self.pretty {
+            self.out.push('\n');
+            self.out.extend(std::iter::repeat_n("  ", depth));
+        }
+    }
+
+    /// `<name number="n">text</name>`, or `<name number="n"/>` when empty.
+    fn numbered(&mut self, name: &str, number: usize, text: &str, depth: usize) {
+        self.newline(depth);
+        if text.is_empty() {
+            let _ = write!(self.out, "<{name} number=\"{number}\"/>");
+        } else {
+            let _ = write!(self.out, "<{name} number=\"{number}\">{}</{name}>", escape(text));
+        }
+    }
+
+    /// Writes the attributes of `item`; `path` is the bulk-data path of the
+    /// item, either empty (root) or ending with `/`.
+    fn item(&mut self, item: &Item, path: &str, depth: usize) -> Result<()> {
+        for (key, el) in item.map.entries() {
+            self.attribute(item, *key, el, path, depth)?;
+        }
+        Ok(())
+    }
+
+    fn attribute(&mut self, item: &Item, key: TagKey, el: &Element, path: &str, depth: usize) -> Result<()> {
+        let hex = convert::tag_hex(key);
+        let path = format!("{path}{hex}");
+        let creator = if key.is_private_attribute() { item.private_creator(self.shared, key) } else { None };
+        let tag = match &creator {
+            Some(c) => Tag::new_private_cow(key.group(), key.element(), c.clone()),
+            None => Tag::new(key, None),
+        };
+
+        self.newline(depth);
+        let _ = write!(self.out, "<DicomAttribute tag=\"{hex}\" vr=\"{}\"", el.vr.keyword());
+        // Masked dictionary matches (repeating groups, generic reservations)
+        // may not name this exact attribute, and `keyword` is optional.
+        let exact = |m: &Meta| m.source != Source::Invalid && (m.mask == u32::MAX || m.tag.creator.is_some());
+        if let Some(meta) = tag.meta().filter(|m| exact(m) && !m.keyword.is_empty()) {
+            let _ = write!(self.out, " keyword=\"{}\"", escape(&meta.keyword));
+        }
+        if let Some(c) = &creator {
+            let _ = write!(self.out, " privateCreator=\"{}\"", escape(c));
+        }
+        let open_end = self.out.len();
+        self.out.push('>');
+
+        let inner = depth + 1;
+        match &el.value {
+            Stored::Items(items) => {
+                for (i, it) in items.iter().enumerate() {
+                    self.newline(inner);
+                    let _ = write!(self.out, "<Item number=\"{}\">", i + 1);
+                    self.item(it, &format!("{path}/{}/", i + 1), inner + 1)?;
+                    self.newline(inner);
+                    self.out.push_str("</Item>");
+                }
+            }
+            Stored::Native(Value::BulkData(uri)) => {
+                self.newline(inner);
+                let _ = write!(self.out, "<BulkData uri=\"{}\"/>", escape(uri));
+            }
+            Stored::Native(Value::Pixels(px)) => self.binary(&path, &px.value_field(), inner),
+            _ if matches!(el.vr.info().kind, Kind::Bytes) => {
+                let bytes = item
+                    .element_bytes_le(self.shared, el)
+                    .ok_or_else(|| dicom_err!(InvalidData, "binary attribute {path} holds a non-binary value"))?;
+                self.binary(&path, &bytes, inner);
+            }
+            _ => self.values(item, el, inner)?,
+        }
+
+        if self.out.len() == open_end + 1 {
+            self.out.truncate(open_end);
+            self.out.push_str("/>");
+        } else {
+            self.newline(depth);
+            self.out.push_str("</DicomAttribute>");
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, path: &str, bytes: &[u8], depth: usize) {
+        match self.bulk_data {
+            Some((prefix, threshold)) if bytes.len() > threshold => {
+                self.newline(depth);
+                let _ = write!(self.out, "<BulkData uri=\"{}{}\"/>", escape(prefix), path);
+            }
+            _ if bytes.is_empty() => {}
+            _ => {
+                self.newline(depth);
+                let _ = write!(self.out, "<InlineBinary>{}</InlineBinary>", BASE64.encode(bytes));
+            }
+        }
+    }
+
+    /// The `<Value>` / `<PersonName>` children of a primitive attribute; none
+    /// for a zero-length value.
+    fn values(&mut self, item: &Item, el: &Element, depth: usize) -> Result<()> {
+        let shared = self.shared;
+        let vr = el.vr;
+        if item.element_bytes(shared, el).is_some_and(<[u8]>::is_empty) {
+            return Ok(());
+        }
+        match vr.info().kind {
+            _ if vr == Vr::AT => match item.element_value(shared, el)? {
+                Value::Tags(tags) => {
+                    for (i, t) in tags.iter().enumerate() {
+                        self.numbered("Value", i + 1, &convert::tag_hex(t.key), depth);
+                    }
+                }
+                _ => return Err(dicom_err!(InvalidData, "AT value is not an attribute tag")),
+            },
+            Kind::Text { .. } => {
+                let text = item.element_text(shared, el)?;
+                if !convert::is_multi_valued_text(vr) {
+                    self.numbered("Value", 1, convert::trim_text(vr, &text), depth);
+                } else if vr == Vr::PN {
+                    for (i, token) in text.split('\\').enumerate() {
+                        self.person_name(i + 1, convert::trim_text(vr, token), depth);
+                    }
+                } else {
+                    for (i, token) in text.split('\\').enumerate() {
+                        self.numbered("Value", i + 1, convert::trim_text(vr, token), depth);
+                    }
+                }
+            }
+            Kind::U16 | Kind::U32 | Kind::U64 => {
+                let value = item.element_value(shared, el)?;
+                for (i, v) in convert::numbers::<u64>(&value).enumerate() {
+                    self.numbered("Value", i + 1, &v.to_string(), depth);
+                }
+            }
+            Kind::I16 | Kind::I32 | Kind::I64 => {
+                let value = item.element_value(shared, el)?;
+                for (i, v) in convert::numbers::<i64>(&value).enumerate() {
+                    self.numbered("Value", i + 1, &v.to_string(), depth);
+                }
+            }
+            Kind::F32 | Kind::F64 => {
+                let value = item.element_value(shared, el)?;
+                for (i, v) in convert::numbers::<f64>(&value).enumerate() {
+                    self.numbered("Value", i + 1, &v.to_string(), depth);
+                }
+            }
+            Kind::Bytes | Kind::Items | Kind::Invalid => {
+                return Err(dicom_err!(InvalidData, "VR {vr} has no XML Value form"));
+            }
+        }
+        Ok(())
+    }
+
+    /// `<PersonName>` with one element per non-empty component group, each
+    /// split into its named components.
+    fn person_name(&mut self, number: usize, name: &str, depth: usize) {
+        if name.is_empty() {
+            self.numbered("PersonName", number, "", depth);
+            return;
+        }
+        self.newline(depth);
+        let _ = write!(self.out, "<PersonName number=\"{number}\">");
+        for (group, label) in name.split('=').zip(PN_GROUPS) {
+            if group.is_empty() {
+                continue;
+            }
+            self.newline(depth + 1);
+            let _ = write!(self.out, "<{label}>");
+            for (component, element) in group.split('^').zip(PN_COMPONENTS) {
+                if !component.is_empty() {
+                    self.newline(depth + 2);
+                    let _ = write!(self.out, "<{element}>{}</{element}>", escape(component));
+                }
+            }
+            self.newline(depth + 1);
+            let _ = write!(self.out, "</{label}>");
+        }
+        self.newline(depth);
+        self.out.push_str("</PersonName>");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use dpx_dicom_core::tags;
+
+    use crate::value::{OneOrMany, PixelData};
+
+    #[test]
+    fn writes_values_and_person_names() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::ImageType, "ORIGINAL\\\\AXIAL").unwrap();
+        ds.set(&tags::PatientName, "Doe^John=山田^太郎").unwrap();
+        ds.set(&tags::Rows, 512u16).unwrap();
+        ds.set_value(&tags::FrameIncrementPointer, Value::Tags(OneOrMany::One(tags::FrameTime.clone()))).unwrap();
+        let xml = XmlWriter::new().to_string(&ds).unwrap();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?><NativeDicomModel xml:space=\"preserve\">"));
+        assert!(xml.contains(
+            "<DicomAttribute tag=\"00080008\" vr=\"CS\" keyword=\"ImageType\">\
+             <Value number=\"1\">ORIGINAL</Value><Value number=\"2\"/><Value number=\"3\">AXIAL</Value></DicomAttribute>"
+        ));
+        assert!(xml.contains(
+            "<PersonName number=\"1\"><Alphabetic><FamilyName>Doe</FamilyName><GivenName>John</GivenName></Alphabetic>\
+             <Ideographic><FamilyName>山田</FamilyName><GivenName>太郎</GivenName></Ideographic></PersonName>"
+        ));
+        assert!(xml.contains("keyword=\"Rows\"><Value number=\"1\">512</Value>"));
+        assert!(xml.contains("<Value number=\"1\">00181063</Value>"));
+    }
+
+    #[test]
+    fn writes_items_binary_and_private_creator() {
+        let mut ds = DataSet::new();
+        ds.set_with_vr(&Tag::new_standard(0x0009, 0x0010), Vr::LO, Value::Str("ACME & Co".into())).unwrap();
+        ds.set_with_vr(&Tag::new_private(0x0009, 0x1001, "ACME & Co"), Vr::LO, Value::Str("<x>".into())).unwrap();
+        ds.set_with_vr(&tags::PixelData, Vr::OB, Value::Pixels(Box::new(PixelData::Native(Bytes::from_static(b"ab")))))
+            .unwrap();
+        ds.set(&tags::AccessionNumber, "").unwrap();
+        ds.sequence_mut(&tags::ReferencedSeriesSequence)
+            .unwrap()
+            .new_item()
+            .set(&tags::SeriesInstanceUID, "1.2")
+            .unwrap();
+
+        let xml = XmlWriter::new().to_string(&ds).unwrap();
+        assert!(xml.contains("<DicomAttribute tag=\"00080050\" vr=\"SH\" keyword=\"AccessionNumber\"/>"));
+        assert!(xml.contains(
+            "<DicomAttribute tag=\"00091001\" vr=\"LO\" privateCreator=\"ACME &amp; Co\"><Value number=\"1\">&lt;x&gt;</Value>"
+        ));
+        assert!(xml.contains("<Item number=\"1\"><DicomAttribute tag=\"0020000E\" vr=\"UI\""));
+        assert!(xml.contains("<InlineBinary>YWI=</InlineBinary>"));
+
+        let xml = XmlWriter::new().bulk_data_uri("http://s/", 1).to_string(&ds).unwrap();
+        assert!(xml.contains("<BulkData uri=\"http://s/7FE00010\"/>"));
+    }
+
+    #[test]
+    fn pretty_output_indents() {
+        let mut ds = DataSet::new();
+        ds.set(&tags::PatientID, "1").unwrap();
+        let xml = XmlWriter::new().pretty(true).to_string(&ds).unwrap();
+        assert!(xml.contains(
+            "\n  <DicomAttribute tag=\"00100020\" vr=\"LO\" keyword=\"PatientID\">\n    <Value number=\"1\">1</Value>\n  </DicomAttribute>\n"
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+use clap::{Parser, ValueEnum};
+use dpx_dicom_core::{ErrContext, IntoDicomErr, Tag, TagKey, TransferSyntax, dicom_err};
+use dpx_dicom_data::{DataSet, DcmReader, Dumper, HeaderType, JsonWriter, ReadMode};
+use log::debug;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use dpx_dicom_core::error::Result;
+
+#[derive(Parser, Debug)]
+#[command(about, long_about = None)]
+#[rustfmt::skip]
+struct Cli {
+    /// DICOM file(s) or, with --recursive, directories to dump
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Walk directories recursively and dump every file found
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Read and dump the File Meta Information header only
+    #[arg(short = 'H', long)]
+    header_only: bool,
+
+    /// Keep only this attribute, by keyword or as (gggg,eeee); may be repeated
+    #[arg(short, long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Write DICOM JSON instead of a text dump, one object per file: the data
+    /// set, or the header with --header-only
+    #[arg(short, long)]
+    json: bool,
+
+    /// Whether the files start with a File Meta Information header
+    #[arg(long, value_enum, default_value_t = HeaderArg::Auto)]
+    header: HeaderArg,
+
+    /// Transfer syntax UID to read the data set with, instead of detecting it
+    #[arg(short = 'x', long, value_name = "UID")]
+    transfer_syntax: Option<String>,
+
+    /// Maximum number of characters of a value shown in the text dump
+    #[arg(short = 'w', long, default_value_t = 64)]
+    max_width: usize,
+
+    /// Show private creator elements in the text dump
+    #[arg(short, long)]
+    private_creators: bool,
+
+    /// Show binary values as hex bytes in the text dump
+    #[arg(long)]
+    hex: bool,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum HeaderArg {
+    /// Detect the header by its preamble and "DICM" prefix
+    Auto,
+    /// The files hold a bare data set
+    No,
+    /// The files must have a header
+    With,
+}
+
+impl From<HeaderArg> for HeaderType {
+    fn from(value: HeaderArg) -> Self {
+        match value {
+            HeaderArg::Auto => HeaderType::Auto,
+            HeaderArg::No => HeaderType::NoHeader,
+            HeaderArg::With => HeaderType::WithHeader,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    if std::env::var_os("RUST_LOG").is_none() {
+        // SAFETY: single-threaded at this point, no concurrent env access
+        unsafe { std::env::set_var("RUST_LOG", "dpx_dump=warn") };
+    }
+    if let Err(e) = pretty_env_logger::try_init() {
+        eprintln!("Warning: could not initialize logger: {e}");
+    }
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Dumps every file, reporting the ones that fail and going on with the rest.
+/// Returns whether all of them were dumped.
+fn run() -> Result<bool> {
+    let cli = Cli::parse();
+    let reader = make_reader(&cli)?;
+
+    let mut files = Vec::new();
+    for path in &cli.paths {
+        if cli.recursive && path.is_dir() {
+            walk(path, &mut files)?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let mut out = io::stdout().lock();
+    let mut ok = true;
+    for (i, file) in files.iter().enumerate() {
+        debug!("Reading {} ...", file.display());
+        let dumped = reader
+            .parse_mmap(file)
+            .and_then(|output| {
+                if files.len() > 1 && !cli.json {
+                    if i > 0 {
+                        writeln!(out).to_dicom_err("writing output")?;
+                    }
+                    writeln!(out, "# File: {}", file.display()).to_dicom_err("writing output")?;
+                }
+                if cli.header_only {
+                    match &output.header {
+                        Some(header) => write(&cli, &mut out, None, header),
+                        None => Err(dicom_err!(NotFound, "no File Meta Information header")),
+                    }
+                } else {
+                    // The DICOM JSON model has no place for the header.
+                    if let Some(header) = output.header.as_ref().filter(|_| !cli.json) {
+                        write(&cli, &mut out, Some("File Meta Information"), header)?;
+                    }
+                    match &output.dataset {
+                        Some(dataset) => write(&cli, &mut out, Some("Data Set"), dataset),
+                        None => Ok(()),
+                    }
+                }
+            })
+            .err_context_with(|| file.display().to_string());
+        if let Err(e) = dumped {
+            eprintln!("Error: {e}");
+            ok = false;
+        }
+    }
+    Ok(ok)
+}
+
+/// The reader configured by the command line.
+fn make_reader(cli: &Cli) -> Result<DcmReader> {
+    let mut reader = DcmReader::new().header(cli.header.into());
+    if cli.header_only {
+        reader = reader.mode(ReadMode::HeaderOnly);
+    }
+    if let Some(uid) = &cli.transfer_syntax {
+        let ts =
+            TransferSyntax::from_uid(uid).ok_or_else(|| dicom_err!(NotFound, "unknown transfer syntax UID {uid:?}"))?;
+        reader = reader.transfer_syntax(ts);
+    }
+    if !cli.tags.is_empty() {
+        let keys = cli.tags.iter().map(|s| parse_tag(s)).collect::<Result<Vec<_>>>()?;
+        reader = reader.tag_whitelist(keys);
+    }
+    Ok(reader)
+}
+
+/// A tag given by keyword or as `(gggg,eeee)`.
+fn parse_tag(s: &str) -> Result<TagKey> {
+    if s.starts_with('(') {
+        return Ok(s.parse::<Tag>()?.key);
+    }
+    Tag::from_keyword(s)
+        .map(|tag| tag.key)
+        .ok_or_else(|| dicom_err!(NotFound, "unknown keyword {s:?}"))
+}
+
+/// Writes one data set as JSON or as a text dump under a `# title` line.
+fn write(cli: &Cli, out: &mut impl Write, title: Option<&str>, ds: &DataSet) -> Result<()> {
+    if cli.json {
+        JsonWriter::new().pretty(true).write_dataset(ds, &mut *out)?;
+        return writeln!(out).to_dicom_err("writing output");
+    }
+    if let Some(title) = title {
+        writeln!(out, "# {title}").to_dicom_err("writing output")?;
+    }
+    Dumper::new()
+        .max_width(cli.max_width)
+        .private_creators(cli.private_creators)
+        .hex(cli.hex)
+        .write_dataset(ds, &mut *out)
+}
+
+/// Collects the files under `dir`, sorted by name in each directory.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.map(|e| e.map(|e| e.path())).collect::<io::Result<Vec<_>>>())
+        .to_dicom_err_with(|| format!("reading directory {}", dir.display()))?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,402 @@
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
+use dpx_dicom_core::{ErrContext, IntoDicomErr, Tag, TransferSyntax, Uid, Vr, dicom_err, ensure, tags};
+use dpx_dicom_data::{AttributePath, DataSet, DcmReader, DcmWriter, ReadOutput, UidMapper, Value};
+use log::{info, warn};
+use std::{
+    borrow::Cow,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use dpx_dicom_core::error::Result;
+
+// cSpell:ignore dcmodify
+
+#[derive(Parser, Debug)]
+#[command(about, long_about = None)]
+#[rustfmt::skip]
+struct Cli {
+    /// DICOM file(s) to modify in place
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Set an attribute, creating it and the sequence Items leading to it.
+    /// PATH is a keyword or tag path such as `ReferencedSeriesSequence[0].SeriesInstanceUID`
+    /// or `(0029,1010,"ACME 1.0")`; `PATH:VR=VALUE` gives the VR of an attribute
+    /// unknown to the dictionary
+    #[arg(short, long, value_name = "PATH=VALUE", value_parser = parse_assignment)]
+    insert: Vec<Assignment>,
+
+    /// Set an attribute where it is present, keeping its VR
+    #[arg(short, long, value_name = "PATH=VALUE", value_parser = parse_assignment)]
+    modify: Vec<Assignment>,
+
+    /// Remove an attribute
+    #[arg(short, long, value_name = "PATH")]
+    erase: Vec<AttributePath>,
+
+    /// Only warn when an attribute to modify or erase is missing
+    #[arg(short = 'M', long)]
+    ignore_missing: bool,
+
+    /// Replace the Study Instance UID; files sharing one get the same new one
+    #[arg(long)]
+    gen_study_uid: bool,
+
+    /// Replace the Series Instance UID; files sharing one get the same new one
+    #[arg(long)]
+    gen_series_uid: bool,
+
+    /// Replace the SOP Instance UID
+    #[arg(short, long)]
+    gen_instance_uid: bool,
+
+    /// Transfer syntax UID to write the files with (default: the one they were read with)
+    #[arg(short = 'x', long, value_name = "UID")]
+    transfer_syntax: Option<String>,
+
+    /// Do not keep the original file as a backup
+    #[arg(short, long)]
+    no_backup: bool,
+
+    /// Suffix appended to the name of the original file kept as a backup
+    #[arg(long, default_value = ".bak")]
+    backup_suffix: String,
+}
+
+/// `PATH=VALUE` or `PATH:VR=VALUE`.
+#[derive(Debug, Clone)]
+struct Assignment {
+    path: AttributePath,
+    vr: Option<Vr>,
+    value: String,
+}
+
+/// One change, in command-line order.
+enum Edit<'a> {
+    Insert(&'a Assignment),
+    Modify(&'a Assignment),
+    Erase(&'a AttributePath),
+}
+
+fn main() -> ExitCode {
+    if std::env::var_os("RUST_LOG").is_none() {
+        // SAFETY: single-threaded at this point, no concurrent env access
+        unsafe { std::env::set_var("RUST_LOG", "dpx_modify=info") };
+    }
+    if let Err(e) = pretty_env_logger::try_init() {
+        eprintln!("Warning: could not initialize logger: {e}");
+    }
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Modifies every file, reporting the ones that fail and going on with the
+/// rest. Returns whether all of them were modified.
+fn run() -> Result<bool> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let edits = edits(&cli, &matches);
+    let xfer = match &cli.transfer_syntax {
+        Some(uid) => Some(
+            TransferSyntax::from_uid(uid).ok_or_else(|| dicom_err!(NotFound, "unknown transfer syntax UID {uid:?}"))?,
+        ),
+        None => None,
+    };
+
+    // One mapper for all files, so that they keep sharing their study and series.
+    let mut uids = UidMapper::default();
+    let mut ok = true;
+    for file in &cli.files {
+        let modified = modify(&cli, &edits, xfer, &mut uids, file).err_context_with(|| file.display().to_string());
+        match modified {
+            Ok(()) => info!("Modified {}", file.display()),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// The edits of the command line, in the order given.
+fn edits<'a>(cli: &'a Cli, matches: &ArgMatches) -> Vec<Edit<'a>> {
+    let indices = |id| matches.indices_of(id).into_iter().flatten();
+    let mut edits: Vec<_> = indices("insert")
+        .zip(cli.insert.iter().map(Edit::Insert))
+        .chain(indices("modify").zip(cli.modify.iter().map(Edit::Modify)))
+        .chain(indices("erase").zip(cli.erase.iter().map(Edit::Erase)))
+        .collect();
+    edits.sort_by_key(|(index, _)| *index);
+    edits.into_iter().map(|(_, edit)| edit).collect()
+}
+
+fn parse_assignment(s: &str) -> Result<Assignment> {
+    // The first `=` outside of a quoted private creator.
+    let mut quoted = false;
+    let eq = s
+        .char_indices()
+        .find(|&(_, c)| {
+            quoted ^= c == '"';
+            c == '=' && !quoted
+        })
+        .map(|(i, _)| i)
+        .ok_or_else(|| dicom_err!(InvalidData, "expected PATH=VALUE, got {s:?}"))?;
+    let (path, value) = (&s[..eq], &s[eq + 1..]);
+    let (path, vr) = match path.rsplit_once(':') {
+        Some((path, vr)) if !vr.contains([')', '"']) => (path, Some(vr.parse()?)),
+        _ => (path, None),
+    };
+    Ok(Assignment {
+        path: path.parse()?,
+        vr,
+        value: value.to_string(),
+    })
+}
+
+/// Reads `file`, applies the command line to it and replaces it with the
+/// result, keeping the original as a backup unless told not to.
+fn modify(
+    cli: &Cli,
+    edits: &[Edit],
+    xfer: Option<&'static TransferSyntax>,
+    uids: &mut UidMapper,
+    file: &Path,
+) -> Result<()> {
+    let mut output = DcmReader::new().parse_file(file)?;
+    let ReadOutput {
+        header,
+        dataset: Some(ds),
+    } = &mut output
+    else {
+        return Err(dicom_err!(InvalidData, "no data set to modify"));
+    };
+    for edit in edits {
+        apply(cli, header.as_mut(), ds, edit)?;
+    }
+
+    for (enabled, tag) in [
+        (cli.gen_study_uid, &tags::StudyInstanceUID),
+        (cli.gen_series_uid, &tags::SeriesInstanceUID),
+        (cli.gen_instance_uid, &tags::SOPInstanceUID),
+    ] {
+        if enabled {
+            regenerate(uids, ds, tag)?;
+        }
+    }
+
+    let xfer = xfer.unwrap_or_else(|| ds.transfer_syntax());
+    let writer = DcmWriter::new().transfer_syntax(xfer);
+    let bytes = match header {
+        Some(header) => {
+            sync_header(header, ds, xfer)?;
+            writer.to_file_bytes(header, ds)?
+        }
+        None => writer.to_bytes(ds)?,
+    };
+
+    let with_suffix = |suffix: &str| {
+        let mut name = OsString::from(file);
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+    let temp = with_suffix(".tmp");
+    fs::write(&temp, &bytes).to_dicom_err_with(|| format!("writing {}", temp.display()))?;
+    if !cli.no_backup {
+        let backup = with_suffix(&cli.backup_suffix);
+        fs::rename(file, &backup).to_dicom_err_with(|| format!("renaming to {}", backup.display()))?;
+    }
+    fs::rename(&temp, file).to_dicom_err_with(|| format!("renaming {}", temp.display()))
+}
+
+/// Applies one edit: to the File Meta Information for a root group 0002
+/// attribute, to the data set otherwise.
+fn apply(cli: &Cli, header: Option<&mut DataSet>, ds: &mut DataSet, edit: &Edit) -> Result<()> {
+    let path = match edit {
+        Edit::Insert(a) | Edit::Modify(a) => &a.path,
+        Edit::Erase(path) => path,
+    };
+    let ds = match header {
+        Some(header) if path.sequences().is_empty() && path.tag().key.group() == 0x0002 => header,
+        None if path.tag().key.group() == 0x0002 => {
+            return Err(dicom_err!(NotFound, "no File Meta Information header for {path}"));
+        }
+        _ => ds,
+    };
+    let tag = path.tag();
+
+    let found = match edit {
+        Edit::Insert(a) => {
+            let vr =
+                a.vr.or_else(|| tag.meta().map(|m| m.vr.0).filter(|vr| *vr != Vr::Undefined));
+            let mut found = 0;
+            for mut item in ds.items_mut_at(path)? {
+                if let Some((reservation, creator)) = reservation(tag) {
+                    match item.get_str_some(&reservation) {
+                        Some(reserved) => check_creator(&reservation, reserved, creator)?,
+                        None => item.set_with_vr(&reservation, Vr::LO, Value::Str(creator.to_string()))?,
+                    }
+                }
+                let vr = vr
+                    .or_else(|| item.vr(tag))
+                    .ok_or_else(|| dicom_err!(NotFound, "no VR known for {tag}; give one as {path}:VR=VALUE"))?;
+                item.set_with_vr(tag, vr, Value::Str(a.value.clone()))?;
+                found += 1;
+            }
+            if found == 0 {
+                warn!("No Item matches {path}");
+            }
+            return Ok(());
+        }
+        Edit::Modify(a) => {
+            ensure!(
+                a.vr.is_none(),
+                InvalidData,
+                "{path}: the VR of a modified attribute is kept"
+            );
+            check_creators(ds, path)?;
+            ds.replace_value_at(path, Value::Str(a.value.clone()))?
+        }
+        Edit::Erase(path) => {
+            check_creators(ds, path)?;
+            ds.remove_at(path)
+        }
+    };
+    if found == 0 {
+        ensure!(cli.ignore_missing, NotFound, "attribute {path} not found");
+        warn!("Attribute {path} not found");
+    }
+    Ok(())
+}
+
+/// The Private Creator element reserving the block of a private attribute
+/// named with its creator, and that creator.
+fn reservation(tag: &Tag) -> Option<(Tag, &str)> {
+    let creator = tag.creator.as_deref()?;
+    tag.key
+        .is_private_attribute()
+        .then(|| (Tag::new(tag.key.to_private_reservation(), None), creator))
+}
+
+fn check_creator(reservation: &Tag, reserved: Cow<str>, creator: &str) -> Result<()> {
+    ensure!(
+        reserved.trim() == creator,
+        InvalidData,
+        "private block {reservation} is reserved by {reserved:?}, not {creator:?}"
+    );
+    Ok(())
+}
+
+/// Checks that the present attributes at `path` belong to the private
+/// creator they are named with, if any.
+fn check_creators(ds: &DataSet, path: &AttributePath) -> Result<()> {
+    let Some((reservation, creator)) = reservation(path.tag()) else {
+        return Ok(());
+    };
+    for item in ds.items_at(path).iter().filter(|item| item.contains(path.tag())) {
+        let reserved = item.get_str_some(&reservation).unwrap_or_default();
+        check_creator(&reservation, reserved, creator)?;
+    }
+    Ok(())
+}
+
+/// Replaces the UID of `tag` through `uids`, or gives a new one when absent.
+fn regenerate(uids: &mut UidMapper, ds: &mut DataSet, tag: &Tag) -> Result<()> {
+    let uid = match ds.get_str_some(tag) {
+        Some(old) => uids.map_uid(&old)?,
+        None => String::new(),
+    };
+    let uid = if uid.is_empty() {
+        Uid::generate_unique(None).to_string()
+    } else {
+        uid
+    };
+    ds.set(tag, uid)
+}
+
+/// Points the File Meta Information at the data set as written.
+fn sync_header(header: &mut DataSet, ds: &DataSet, xfer: &TransferSyntax) -> Result<()> {
+    for (meta, tag) in [
+        (&tags::MediaStorageSOPClassUID, &tags::SOPClassUID),
+        (&tags::MediaStorageSOPInstanceUID, &tags::SOPInstanceUID),
+    ] {
+        if let Some(uid) = ds.get_str_some(tag) {
+            header.set(meta, uid.into_owned())?;
+        }
+    }
+    header.set(&tags::TransferSyntaxUID, xfer.uid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(s: &str) -> (String, Option<Vr>, String) {
+        let a = parse_assignment(s).unwrap();
+        (a.path.to_string(), a.vr, a.value)
+    }
+
+    #[test]
+    fn splits_assignments_outside_creators() {
+        assert_eq!(
+            assignment("PatientName=Doe^John"),
+            ("PatientName".into(), None, "Doe^John".into())
+        );
+        assert_eq!(
+            assignment("PatientComments=a=b:c"),
+            ("PatientComments".into(), None, "a=b:c".into())
+        );
+        assert_eq!(
+            assignment("PatientName:PN="),
+            ("PatientName".into(), Some(Vr::PN), String::new())
+        );
+
+        // Neither `=` nor `:` splits inside a quoted private creator.
+        let (path, vr, value) = assignment(r#"(0029,1010,"ACME=1:2")=x"#);
+        assert_eq!(
+            (path.as_str(), vr, value.as_str()),
+            (r#"(0029,1010,"ACME=1:2")"#, None, "x")
+        );
+        let a = parse_assignment(r#"(0029,1010,"A:B")[0].(0029,1011,"A:B"):LO=y=z"#).unwrap();
+        assert_eq!(a.path.tag().creator.as_deref(), Some("A:B"));
+        assert_eq!(a.path.sequences().len(), 1);
+        assert_eq!((a.vr, a.value.as_str()), (Some(Vr::LO), "y=z"));
+
+        for invalid in ["PatientName", "PatientName:XY=1", r#"(0029,1010,"ACME=1")"#, "=1"] {
+            assert!(parse_assignment(invalid).is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn edits_keep_command_line_order() {
+        let args = "dpx-modify -e PatientID -i PatientName=a -m StudyID=1 -i PatientAge=042Y file.dcm";
+        let matches = Cli::command().try_get_matches_from(args.split(' ')).unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        let found: Vec<_> = edits(&cli, &matches)
+            .into_iter()
+            .map(|edit| match edit {
+                Edit::Insert(a) => format!("insert {}", a.path),
+                Edit::Modify(a) => format!("modify {}", a.path),
+                Edit::Erase(path) => format!("erase {path}"),
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "erase PatientID",
+                "insert PatientName",
+                "modify StudyID",
+                "insert PatientAge"
+            ]
+        );
+    }
+}
@@ -0,0 +1,429 @@
+//! Extraction of IOD module tables (PS3.3 Annex A), module attribute tables
+//! (PS3.3 Annexes C and 10) and the SOP Class to IOD mapping (PS3.4 Table
+//! B.5-1) into the `iod.tsv` used by the `dpx-dicom-data` validator.
+
+use dpx_dicom_core::{TagKey, Vr, tag::Dictionary};
+use log::{info, trace, warn};
+use snafu::{Whatever, prelude::*};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+};
+
+type Result<T, E = Whatever> = std::result::Result<T, E>;
+
+/// Guards against include cycles between macro tables.
+const MAX_DEPTH: usize = 16;
+
+/// Elements of a docbook document by their `xml:id`.
+pub struct Index<'a, 'input> {
+    ids: HashMap<&'a str, roxmltree::Node<'a, 'input>>,
+}
+
+impl<'a, 'input> Index<'a, 'input> {
+    pub fn new(doc: &'a roxmltree::Document<'input>) -> Self {
+        let ids = doc
+            .descendants()
+            .filter_map(|n| n.attribute((roxmltree::NS_XML_URI, "id")).map(|id| (id, n)))
+            .collect();
+        Self { ids }
+    }
+
+    fn get(&self, id: &str) -> Option<roxmltree::Node<'a, 'input>> {
+        self.ids.get(id).copied()
+    }
+}
+
+struct Iod {
+    uid: String,
+    name: String,
+    modules: Vec<ModuleRef>,
+}
+
+struct ModuleRef {
+    ie: String,
+    module: String,
+    usage: char,
+    condition: String,
+}
+
+struct Attribute {
+    depth: usize,
+    tag: String,
+    kind: String,
+    items: &'static str,
+    terms: String,
+}
+
+pub struct Iods {
+    /// The data dictionary of the same edition, to tell `CS` attributes.
+    dict: Dictionary,
+    iods: Vec<Iod>,
+    modules: BTreeMap<String, Vec<Attribute>>,
+}
+
+impl Iods {
+    pub fn new(mut dict: Dictionary) -> Self {
+        dict.rebuild_cache();
+        Self {
+            dict,
+            iods: Vec::new(),
+            modules: BTreeMap::new(),
+        }
+    }
+
+    /// Collects every IOD referenced from PS3.4 Table B.5-1 along with the
+    /// modules it is made of.
+    pub fn parse(&mut self, part03: &Index, part04: &Index) -> Result<()> {
+        info!("Processing table B.5-1...");
+        let table = part04
+            .get("table_B.5-1")
+            .whatever_context("could not find table B.5-1")?;
+        for tr in rows(table) {
+            let cells: Vec<_> = tr.children().filter(|c| c.is_element()).collect();
+            let [_, uid, spec, ..] = cells[..] else { continue };
+            let uid = all_text(uid);
+            let Some(sect) = spec.descendants().find_map(|n| n.attribute("targetptr")) else {
+                trace!("skipping {uid} without an IOD reference");
+                continue;
+            };
+            match self.parse_iod(part03, sect) {
+                Ok((name, modules)) => self.iods.push(Iod { uid, name, modules }),
+                Err(e) => warn!("skipping {uid}: {e}"),
+            }
+        }
+        info!(
+            "... processed {} SOP Classes and {} modules",
+            self.iods.len(),
+            self.modules.len()
+        );
+        Ok(())
+    }
+
+    fn parse_iod(&mut self, part03: &Index, sect: &str) -> Result<(String, Vec<ModuleRef>)> {
+        let section = part03
+            .get(sect)
+            .with_whatever_context(|| format!("could not find {sect}"))?;
+        let name = title(section).trim_end_matches(" IOD").to_string();
+        let table = section
+            .descendants()
+            .filter(|n| n.has_tag_name("table"))
+            .find(|t| title(*t).ends_with("IOD Modules"))
+            .with_whatever_context(|| format!("could not find the module table of {sect}"))?;
+
+        let mut modules = Vec::new();
+        let mut ie = String::new();
+        for tr in rows(table) {
+            let cells: Vec<_> = tr.children().filter(|c| c.is_element()).collect();
+            let (module, reference, usage) = match cells[..] {
+                [first, module, reference, usage] => {
+                    ie = all_text(first);
+                    (module, reference, usage)
+                }
+                [module, reference, usage] => (module, reference, usage),
+                _ => continue,
+            };
+            let Some(link) = reference.descendants().find_map(|n| n.attribute("linkend")) else {
+                continue;
+            };
+            let module_name = self.parse_module(part03, link, &all_text(module))?;
+            let usage = all_text(usage);
+            let condition = usage
+                .get(1..)
+                .unwrap_or_default()
+                .trim_start_matches([' ', '-'])
+                .to_string();
+            modules.push(ModuleRef {
+                ie: ie.clone(),
+                module: module_name,
+                usage: usage.chars().next().unwrap_or('U'),
+                condition,
+            });
+        }
+        Ok((name, modules))
+    }
+
+    /// Parses the attribute table of a module once, returning the module name.
+    fn parse_module(&mut self, part03: &Index, sect: &str, name: &str) -> Result<String> {
+        let name = name.trim_end_matches(" Module").to_string();
+        if !self.modules.contains_key(&name) {
+            let section = part03
+                .get(sect)
+                .with_whatever_context(|| format!("could not find {sect}"))?;
+            let table = section
+                .descendants()
+                .find(|n| n.has_tag_name("table"))
+                .with_whatever_context(|| format!("could not find the attribute table of {sect}"))?;
+            let mut attributes = Vec::new();
+            parse_attributes(&mut attributes, &self.dict, part03, table, 0, 0)?;
+            self.modules.insert(name.clone(), attributes);
+        }
+        Ok(name)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        for iod in &self.iods {
+            writeln!(writer, "IOD\t{}\t{}", iod.uid, iod.name)?;
+        }
+        let mut written = std::collections::HashSet::new();
+        for iod in self.iods.iter().filter(|iod| written.insert(&iod.name)) {
+            for m in &iod.modules {
+                writeln!(
+                    writer,
+                    "MODULE\t{}\t{}\t{}\t{}\t{}",
+                    iod.name, m.ie, m.module, m.usage, m.condition
+                )?;
+            }
+        }
+        for (module, attributes) in &self.modules {
+            for a in attributes {
+                writeln!(
+                    writer,
+                    "ATTR\t{module}\t{}\t{}\t{}\t{}\t{}",
+                    a.depth, a.tag, a.kind, a.items, a.terms
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the rows of a module or macro attribute table, expanding `Include`
+/// rows in place at their nesting level.
+fn parse_attributes(
+    output: &mut Vec<Attribute>,
+    dict: &Dictionary,
+    part03: &Index,
+    table: roxmltree::Node,
+    depth: usize,
+    recursion: usize,
+) -> Result<()> {
+    ensure_whatever!(recursion < MAX_DEPTH, "macro tables include each other too deep");
+    for tr in rows(table) {
+        let cells: Vec<_> = tr.children().filter(|c| c.is_element()).collect();
+        let Some(&first) = cells.first() else { continue };
+        let name = all_text(first);
+        let level = depth + name.chars().take_while(|c| *c == '>').count();
+        if cells.len() == 1 {
+            if name.trim_start_matches('>').starts_with("Include")
+                && let Some(link) = first.descendants().find_map(|n| n.attribute("linkend"))
+            {
+                let macro_table = part03
+                    .get(link)
+                    .with_whatever_context(|| format!("could not find {link}"))?;
+                parse_attributes(output, dict, part03, macro_table, level, recursion + 1)?;
+            }
+            continue;
+        }
+        let (tag, kind, description) = match cells[..] {
+            [_, tag, kind, description] => (all_text(tag), all_text(kind), description),
+            [_, tag, description] => (all_text(tag), "3".to_string(), description),
+            _ => continue,
+        };
+        // Repeating groups and ranges can not be matched against a data set.
+        if !tag.starts_with('(') || tag.contains(['x', 'X']) {
+            trace!("skipping {tag}");
+            continue;
+        }
+        let items = cardinality(&all_text(description));
+        let terms = if is_cs(dict, &tag) {
+            terms(part03, description)
+        } else {
+            String::new()
+        };
+        output.push(Attribute {
+            depth: level,
+            tag,
+            kind,
+            items,
+            terms,
+        });
+    }
+    Ok(())
+}
+
+fn is_cs(dict: &Dictionary, tag: &str) -> bool {
+    let hex = |s: &str| u16::from_str_radix(s, 16).ok();
+    let Some((g, e)) = tag.trim_matches(['(', ')']).split_once(',') else {
+        return false;
+    };
+    let (Some(g), Some(e)) = (hex(g), hex(e)) else {
+        return false;
+    };
+    dict.search_by_key(TagKey::new(g, e)).is_some_and(|m| m.vr.0 == Vr::CS)
+}
+
+/// Number of Items allowed in a sequence, as stated by its description.
+fn cardinality(description: &str) -> &'static str {
+    const FORMS: [(&str, &str); 6] = [
+        ("Only a single Item", "1"),
+        ("Only one Item", "1"),
+        ("One or more Items", "1-n"),
+        ("Zero or more Items", "0-n"),
+        ("Zero or one Item", "0-1"),
+        ("One or two Items", "1-2"),
+    ];
+    FORMS
+        .iter()
+        .find(|(form, _)| description.contains(form))
+        .map(|(_, items)| *items)
+        .unwrap_or_default()
+}
+
+/// Enumerated Values (`E:`) or Defined Terms (`D:`) of a `CS` attribute,
+/// listed in its description or in the section it refers to.
+fn terms(part03: &Index, description: roxmltree::Node) -> String {
+    let referenced = description
+        .descendants()
+        .filter_map(|n| n.attribute("linkend"))
+        .filter(|link| link.starts_with("sect_"))
+        .filter_map(|link| part03.get(link));
+    for scope in std::iter::once(description).chain(referenced) {
+        for list in scope.descendants().filter(|n| n.has_tag_name("variablelist")) {
+            let prefix = match title(list).as_str() {
+                t if t.starts_with("Enumerated Value") => "E",
+                t if t.starts_with("Defined Term") => "D",
+                _ => continue,
+            };
+            let values: Vec<_> = list
+                .children()
+                .filter(|n| n.has_tag_name("varlistentry"))
+                .filter_map(|e| e.children().find(|n| n.has_tag_name("term")))
+                .map(all_text)
+                .filter(|t| !t.is_empty())
+                .collect();
+            if !values.is_empty() {
+                return format!("{prefix}:{}", values.join("\\"));
+            }
+        }
+    }
+    String::new()
+}
+
+fn rows<'a, 'input>(table: roxmltree::Node<'a, 'input>) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    table
+        .children()
+        .filter(|c| c.has_tag_name("tbody"))
+        .flat_map(|b| b.children().filter(|c| c.has_tag_name("tr")))
+}
+
+fn title(node: roxmltree::Node) -> String {
+    node.children()
+        .find(|c| c.has_tag_name("title") || c.has_tag_name("caption"))
+        .map(all_text)
+        .unwrap_or_default()
+}
+
+/// The text of a node and its descendants, with white space collapsed and
+/// non-ASCII characters dropped.
+fn all_text(node: roxmltree::Node) -> String {
+    let text: String = node
+        .descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect();
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| c.is_ascii())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PART03: &str = r#"<book xmlns="http://docbook.org/ns/docbook">
+<section xml:id="sect_A.3"><title>CT Image IOD</title>
+<table xml:id="table_A.3-1"><caption>CT Image IOD Modules</caption><tbody>
+<tr><td rowspan="2"><para>Patient</para></td><td><para>Patient</para></td>
+<td><para><xref linkend="sect_C.7.1.1"/></para></td><td><para>M</para></td></tr>
+<tr><td><para>Clinical Trial Subject</para></td><td><para><xref linkend="sect_C.7.1.3"/></para></td>
+<td><para>U</para></td></tr>
+<tr><td><para>Image</para></td><td><para>Contrast/Bolus</para></td><td><para><xref linkend="sect_C.7.6.4"/></para></td>
+<td><para>C - Required if contrast media was used in this image.</para></td></tr>
+</tbody></table></section>
+<section xml:id="sect_C.7.1.1"><title>Patient Module</title>
+<table xml:id="table_C.7-1"><caption>Patient Module Attributes</caption><tbody>
+<tr><td><para>Patient's Name</para></td><td><para>(0010,0010)</para></td><td><para>2</para></td>
+<td><para>Patient's full name.</para></td></tr>
+<tr><td colspan="4"><para>Include <xref linkend="table_10-18"/></para></td></tr>
+<tr><td><para>Patient's Sex</para></td><td><para>(0010,0040)</para></td><td><para>2</para></td>
+<td><para>Sex of the named patient.</para><variablelist><title>Enumerated Values:</title>
+<varlistentry><term>M</term><listitem><para>male</para></listitem></varlistentry>
+<varlistentry><term>F</term><listitem><para>female</para></listitem></varlistentry>
+</variablelist></td></tr>
+<tr><td><para>Referenced Patient Sequence</para></td><td><para>(0008,1120)</para></td><td><para>3</para></td>
+<td><para>Only a single Item is permitted in this Sequence.</para></td></tr>
+<tr><td><para>&gt;Referenced SOP Class UID</para></td><td><para>(0008,1150)</para></td><td><para>1</para></td>
+<td><para>Uniquely identifies the referenced SOP Class.</para></td></tr>
+<tr><td><para>Overlay Rows</para></td><td><para>(60xx,0010)</para></td><td><para>1</para></td>
+<td><para>Not a single attribute.</para></td></tr>
+</tbody></table></section>
+<table xml:id="table_10-18"><caption>Issuer of Patient ID Macro Attributes</caption><tbody>
+<tr><td><para>Issuer of Patient ID</para></td><td><para>(0010,0021)</para></td><td><para>3</para></td>
+<td><para>Identifier of the Assigning Authority.</para></td></tr>
+</tbody></table>
+<section xml:id="sect_C.7.1.3"><title>Clinical Trial Subject Module</title>
+<table xml:id="table_C.7-2b"><caption>Clinical Trial Subject Module Attributes</caption><tbody>
+<tr><td><para>Clinical Trial Sponsor Name</para></td><td><para>(0012,0010)</para></td><td><para>1</para></td>
+<td><para>The name of the clinical trial sponsor.</para></td></tr>
+</tbody></table></section>
+<section xml:id="sect_C.7.6.4"><title>Contrast/Bolus Module</title>
+<table xml:id="table_C.7-14"><caption>Contrast/Bolus Module Attributes</caption><tbody>
+<tr><td><para>Contrast/Bolus Agent</para></td><td><para>(0018,0010)</para></td><td><para>2</para></td>
+<td><para>Contrast or bolus agent.</para></td></tr>
+<tr><td><para>Contrast/Bolus Ingredient</para></td><td><para>(0018,1048)</para></td><td><para>3</para></td>
+<td><para>Active ingredient of agent. See <xref linkend="sect_C.7.6.4.1"/>.</para></td></tr>
+</tbody></table>
+<section xml:id="sect_C.7.6.4.1"><title>Contrast/Bolus Ingredient</title>
+<variablelist><title>Defined Terms:</title>
+<varlistentry><term>IODINE</term><listitem><para/></listitem></varlistentry>
+<varlistentry><term>GADOLINIUM</term><listitem><para/></listitem></varlistentry>
+</variablelist></section></section>
+</book>"#;
+
+    const PART04: &str = r#"<book xmlns="http://docbook.org/ns/docbook">
+<table xml:id="table_B.5-1"><caption>Standard SOP Classes</caption>
+<thead><tr><th>SOP Class Name</th><th>SOP Class UID</th><th>IOD Specification</th></tr></thead><tbody>
+<tr><td><para>CT Image Storage</para></td><td><para>1.2.840.10008.5.1.4.1.1.2</para></td>
+<td><para><olink targetdoc="PS3.3" targetptr="sect_A.3" xrefstyle="select: labelnumber"/></para></td></tr>
+<tr><td><para>Enhanced CT Image Storage</para></td><td><para>1.2.840.10008.5.1.4.1.1.2.1</para></td>
+<td><para><olink targetdoc="PS3.3" targetptr="sect_A.38" xrefstyle="select: labelnumber"/></para></td></tr>
+<tr><td><para>Verification SOP Class</para></td><td><para>1.2.840.10008.1.1</para></td><td><para>N/A</para></td></tr>
+</tbody></table>
+</book>"#;
+
+    const DICTIONARY: &str = "(0010,0010)\tPN\tPatient's Name\tPatientName\t1\tDicom\n\
+        (0010,0040)\tCS\tPatient's Sex\tPatientSex\t1\tDicom\n\
+        (0018,1048)\tCS\tContrast/Bolus Ingredient\tContrastBolusIngredient\t1-n\tDicom\n";
+
+    #[test]
+    fn extracts_iods_modules_and_attributes() {
+        let mut dict = Dictionary::new_empty();
+        dict.add_from_memory(DICTIONARY.as_bytes()).unwrap();
+        let part03 = roxmltree::Document::parse(PART03).unwrap();
+        let part04 = roxmltree::Document::parse(PART04).unwrap();
+
+        let mut iods = Iods::new(dict);
+        iods.parse(&Index::new(&part03), &Index::new(&part04)).unwrap();
+        let mut out = Vec::new();
+        iods.write(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "IOD\t1.2.840.10008.5.1.4.1.1.2\tCT Image\n\
+             MODULE\tCT Image\tPatient\tPatient\tM\t\n\
+             MODULE\tCT Image\tPatient\tClinical Trial Subject\tU\t\n\
+             MODULE\tCT Image\tImage\tContrast/Bolus\tC\tRequired if contrast media was used in this image.\n\
+             ATTR\tClinical Trial Subject\t0\t(0012,0010)\t1\t\t\n\
+             ATTR\tContrast/Bolus\t0\t(0018,0010)\t2\t\t\n\
+             ATTR\tContrast/Bolus\t0\t(0018,1048)\t3\t\tD:IODINE\\GADOLINIUM\n\
+             ATTR\tPatient\t0\t(0010,0010)\t2\t\t\n\
+             ATTR\tPatient\t0\t(0010,0021)\t3\t\t\n\
+             ATTR\tPatient\t0\t(0010,0040)\t2\t\tE:M\\F\n\
+             ATTR\tPatient\t0\t(0008,1120)\t3\t1\t\n\
+             ATTR\tPatient\t1\t(0008,1150)\t1\t\t\n"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use clap::Parser;
-use dpx_dicom_core::tag::Source;
+use dpx_dicom_core::tag::{Dictionary, Source};
 use log::{info, trace};
 use snafu::{Whatever, prelude::*};
 use std::{
@@ -9,6 +9,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
+mod iod;
+
 type Result<T, E = Whatever> = std::result::Result<T, E>;
 
 // cSpell:ignore tbody canonicalize
@@ -26,6 +28,15 @@ struct Cli {
     /// Header file name(s)
     #[arg(short='e', long, default_values_os_t = vec![PathBuf::from("utils/mk-dicom-tsv/header.txt")], num_args(0..))]
     headers: Vec<PathBuf>,
+
+    /// Also extract IOD and module definitions from PS3.3 and PS3.4 into this
+    /// file
+    #[arg(long)]
+    iod_output: Option<PathBuf>,
+
+    /// Header file name(s) of the IOD definitions file
+    #[arg(long, default_values_os_t = vec![PathBuf::from("utils/mk-dicom-tsv/iod_header.txt")], num_args(0..))]
+    iod_headers: Vec<PathBuf>,
 }
 
 #[snafu::report]
@@ -76,24 +87,7 @@ fn main() -> Result<()> {
         fs::File::create(&output_file_name).with_whatever_context(|e| format!("Unable to open output file({e})"))?;
     let mut writer = std::io::BufWriter::new(file);
 
-    for header_file_name in cli.headers {
-        let header = std::fs::read_to_string(&header_file_name)
-            .with_whatever_context(|e| format!("couldn't open the file {}: {e}", header_file_name.to_string_lossy()))?;
-        let header = header
-            .replacen("${VERSION}", format!("{version_06} and {version_07}").as_str(), 1)
-            .replacen("${DATE}", chrono::Local::now().to_rfc2822().as_str(), 1)
-            .replacen("${USER}", whoami::username().unwrap_or_default().as_str(), 1)
-            .replacen("${HOST}", whoami::hostname().unwrap_or_default().as_str(), 1)
-            .replacen(
-                "${CMD_LINE}",
-                env::args().collect::<Vec<String>>().join(" ").as_str(),
-                1,
-            );
-
-        writer
-            .write(header.as_bytes())
-            .with_whatever_context(|e| format!("Unable to write file: {e}"))?;
-    }
+    write_headers(&mut writer, &cli.headers, &format!("{version_06} and {version_07}"))?;
 
     for f in tags.iter() {
         use dpx_dicom_core::tag::PrivateIdentificationAction as V;
@@ -127,7 +121,7 @@ fn main() -> Result<()> {
     drop(writer);
 
     info!("Verifying ...");
-    let mut dict = dpx_dicom_core::tag::Dictionary::new_empty();
+    let mut dict = Dictionary::new_empty();
     dict.add_from_file(&output_file_name)
         .with_whatever_context(|_| "Could not load dictionary".to_string())?;
     let metrics = dict.metrics();
@@ -139,6 +133,69 @@ fn main() -> Result<()> {
 
     info!("Done. Total {} tags found.", tags.len());
 
+    if let Some(iod_output) = &cli.iod_output {
+        make_iod_tsv(&cli.docbook_path, iod_output, &cli.iod_headers, dict)?;
+    }
+
+    Ok(())
+}
+
+fn make_iod_tsv(docbook_path: &Path, output: &Path, headers: &[PathBuf], dict: Dictionary) -> Result<()> {
+    let output_file_name = abs_path(output)?;
+
+    let file_name = &docbook_path.join("part03").join("part03.xml");
+    info!("Reading {} ...", file_name.to_string_lossy());
+    let content_03 = std::fs::read_to_string(file_name)
+        .with_whatever_context(|e| format!("couldn't open the file {}: {e}", file_name.to_string_lossy()))?;
+    let xml_03 = roxmltree::Document::parse(&content_03)
+        .with_whatever_context(|e| format!("couldn't parse xml file {}: {e}", file_name.to_string_lossy()))?;
+    let version_03 =
+        extract_version(xml_03.root_element()).with_whatever_context(|| "unable to extract version string")?;
+
+    let file_name = &docbook_path.join("part04").join("part04.xml");
+    info!("Reading {} ...", file_name.to_string_lossy());
+    let content_04 = std::fs::read_to_string(file_name)
+        .with_whatever_context(|e| format!("couldn't open the file {}: {e}", file_name.to_string_lossy()))?;
+    let xml_04 = roxmltree::Document::parse(&content_04)
+        .with_whatever_context(|e| format!("couldn't parse xml file {}: {e}", file_name.to_string_lossy()))?;
+    let version_04 =
+        extract_version(xml_04.root_element()).with_whatever_context(|| "unable to extract version string")?;
+
+    let mut iods = iod::Iods::new(dict);
+    iods.parse(&iod::Index::new(&xml_03), &iod::Index::new(&xml_04))?;
+
+    info!("Writing {} ...", output_file_name.to_string_lossy());
+    let file =
+        fs::File::create(&output_file_name).with_whatever_context(|e| format!("Unable to open output file({e})"))?;
+    let mut writer = std::io::BufWriter::new(file);
+    write_headers(&mut writer, headers, &format!("{version_03} and {version_04}"))?;
+    iods.write(&mut writer)
+        .and_then(|_| writer.flush())
+        .with_whatever_context(|_| format!("unable to write output file {output_file_name:?}"))?;
+
+    info!("Done.");
+    Ok(())
+}
+
+fn write_headers(writer: &mut impl Write, headers: &[PathBuf], version: &str) -> Result<()> {
+    for header_file_name in headers {
+        let header = std::fs::read_to_string(header_file_name)
+            .with_whatever_context(|e| format!("couldn't open the file {}: {e}", header_file_name.to_string_lossy()))?;
+        let header = header
+            .replacen("${VERSION}", version, 1)
+            .replacen("${DATE}", chrono::Local::now().to_rfc2822().as_str(), 1)
+            .replacen("${USER}", whoami::username().unwrap_or_default().as_str(), 1)
+            .replacen("${HOST}", whoami::hostname().unwrap_or_default().as_str(), 1)
+            .replacen(
+                "${CMD_LINE}",
+                env::args().collect::<Vec<String>>().join(" ").as_str(),
+                1,
+            );
+
+        writer
+            .write(header.as_bytes())
+            .with_whatever_context(|e| format!("Unable to write file: {e}"))?;
+    }
     Ok(())
 }
 